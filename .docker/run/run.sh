@@ -12,8 +12,11 @@ export CARGO_TARGET_DIR="/paste/.docker/run/target"
 
 diesel migration --migration-dir=webserver/migrations run
 
+cargo build -p worker_change_visibility "$@"
 cargo build -p worker_email "$@"
+cargo build -p worker_dedupe_store "$@"
 cargo build -p worker_delete_directory "$@"
 cargo build -p worker_expire_paste "$@"
+cargo build -p worker_export_data "$@"
 
 cargo run "$@" -p webserver config.toml
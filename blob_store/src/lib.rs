@@ -0,0 +1,306 @@
+//! The content-addressed store of file blobs shared by every paste, used by the webserver and by
+//! the workers that delete pastes or move old pastes into the store.
+
+use anyhow::{anyhow, Error};
+
+use git2::{ObjectType, Oid, Repository, Tree};
+
+use uuid::Uuid;
+
+use std::{
+  fs,
+  io::{self, Write},
+  os::unix::fs::MetadataExt,
+  path::{Path, PathBuf},
+};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The content-addressed store of file blobs shared by every paste.
+///
+/// Blobs are keyed by the ID git gives them. `content` holds each blob's raw content, which pastes
+/// use as their working files, and `objects` holds the loose git object for each blob, which paste
+/// repositories use in their object databases. Both are shared by hard links, so a blob's reference
+/// count is its link count minus the store's own link, and deleting a paste's directory drops its
+/// references without any bookkeeping. Blobs left with only the store's link are removed by
+/// [`Blobs::release`] and [`Blobs::sweep`].
+///
+/// `encoded` caches compressed copies of blobs' content, which are removed along with the blobs.
+///
+/// Shared files must never be written to in place, since every paste linking them would change.
+/// Replace them with [`Blobs::write`] instead.
+pub struct Blobs {
+  directory: PathBuf,
+}
+
+impl Blobs {
+  pub fn new(directory: PathBuf) -> Self {
+    Blobs { directory }
+  }
+
+  pub fn directory(&self) -> &Path {
+    &self.directory
+  }
+
+  fn content_path(&self, id: Oid) -> PathBuf {
+    shard(&self.directory.join("content"), id)
+  }
+
+  fn object_path(&self, id: Oid) -> PathBuf {
+    shard(&self.directory.join("objects"), id)
+  }
+
+  fn encoded_directory(&self, id: Oid) -> PathBuf {
+    shard(&self.directory.join("encoded"), id).parent().expect("blob path has a parent").to_path_buf()
+  }
+
+  /// Where the copy of a blob's content compressed with `encoding` is cached.
+  ///
+  /// Cached copies should be written via a file in the same directory starting with `.tmp-`, so
+  /// they're never removed half-written.
+  pub fn encoded_path(&self, id: Oid, encoding: &str) -> PathBuf {
+    self.encoded_directory(id).join(format!("{}.{}", &id.to_string()[2..], encoding))
+  }
+
+  /// Writes `content` to `dest` as a link to the shared blob with that content, adding the blob if
+  /// it isn't stored yet.
+  ///
+  /// Anything already at `dest` is replaced rather than written over.
+  pub fn write(&self, content: &[u8], dest: &Path) -> Result<Oid> {
+    let id = Oid::hash_object(ObjectType::Blob, content)?;
+    let blob = self.content_path(id);
+
+    // the blob may be swept between checking for it and linking it, so try again if it vanishes
+    for _ in 0..2 {
+      if !blob.exists() {
+        write_new(&blob, content)?;
+      }
+
+      match replace_with_link(&blob, dest) {
+        Ok(()) => return Ok(id),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+        Err(e) => return Err(e.into()),
+      }
+    }
+
+    // fall back to an unshared copy, which a later commit will share
+    write_new(dest, content)?;
+    Ok(id)
+  }
+
+  /// Shares the working files and blob objects of a paste's repository for every blob in `tree`.
+  ///
+  /// Files already linked to the store are left alone, so this is cheap to call after every commit.
+  pub fn share(&self, repo: &Repository, tree: &Tree) -> Result<()> {
+    let workdir = repo.workdir().ok_or_else(|| anyhow!("paste repository is bare"))?;
+
+    for entry in tree.iter() {
+      if entry.kind() != Some(ObjectType::Blob) {
+        continue;
+      }
+      let name = match entry.name() {
+        Some(n) => n,
+        None => continue,
+      };
+
+      // the working file may have changed since the tree was written
+      adopt(&self.content_path(entry.id()), &workdir.join(name), Some(entry.id()))?;
+
+      // objects from pushed packs aren't loose, so there's nothing to share for them
+      adopt(&self.object_path(entry.id()), &shard(&repo.path().join("objects"), entry.id()), None)?;
+    }
+
+    Ok(())
+  }
+
+  /// Shares every loose blob object in a paste's repository, including those of older revisions.
+  pub fn share_objects(&self, repo: &Repository) -> Result<()> {
+    let odb = repo.odb()?;
+    let mut ids = Vec::new();
+    odb.foreach(|id| {
+      ids.push(*id);
+      true
+    })?;
+
+    for id in ids {
+      let loose = shard(&repo.path().join("objects"), id);
+      if loose.exists() && odb.read_header(id)?.1 == ObjectType::Blob {
+        adopt(&self.object_path(id), &loose, None)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Removes the blobs in `ids` that no paste refers to any more.
+  pub fn release<I>(&self, ids: I) -> Result<()>
+    where I: IntoIterator<Item = Oid>,
+  {
+    for id in ids {
+      remove_if_unused(&self.content_path(id))?;
+      remove_if_unused(&self.object_path(id))?;
+      if !self.content_path(id).exists() {
+        self.remove_encoded(id)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Removes every cached copy of a blob's content.
+  fn remove_encoded(&self, id: Oid) -> Result<()> {
+    let directory = self.encoded_directory(id);
+    let prefix = format!("{}.", &id.to_string()[2..]);
+
+    let entries = match fs::read_dir(directory) {
+      Ok(e) => e,
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+      Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+      let entry = entry?;
+      if entry.file_name().to_string_lossy().starts_with(&prefix) {
+        remove_file(&entry.path())?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Removes every blob that no paste refers to any more.
+  pub fn sweep(&self) -> Result<()> {
+    for kind in &["content", "objects"] {
+      let kind_dir = self.directory.join(kind);
+      if !kind_dir.exists() {
+        continue;
+      }
+
+      for shard in fs::read_dir(kind_dir)? {
+        for blob in fs::read_dir(shard?.path())? {
+          let blob = blob?;
+          // blobs still being written
+          if blob.file_name().to_string_lossy().starts_with(".tmp-") {
+            continue;
+          }
+          remove_if_unused(&blob.path())?;
+        }
+      }
+    }
+
+    // cached copies of blobs that are gone
+    let encoded_dir = self.directory.join("encoded");
+    if encoded_dir.exists() {
+      for shard in fs::read_dir(encoded_dir)? {
+        let shard = shard?;
+        let shard_name = shard.file_name().to_string_lossy().into_owned();
+        for encoded in fs::read_dir(shard.path())? {
+          let encoded = encoded?;
+          let name = encoded.file_name().to_string_lossy().into_owned();
+          if name.starts_with(".tmp-") {
+            continue;
+          }
+
+          let id = name.split('.').next().unwrap_or_default();
+          let content = self.directory.join("content").join(&shard_name).join(id);
+          if !content.exists() {
+            remove_file(&encoded.path())?;
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Splits on the first two characters of the ID, like git's object directories.
+fn shard(directory: &Path, id: Oid) -> PathBuf {
+  let id = id.to_string();
+  directory.join(&id[..2]).join(&id[2..])
+}
+
+/// Writes a file that doesn't exist yet, via a temporary file so it never appears half-written.
+fn write_new(path: &Path, content: &[u8]) -> Result<()> {
+  let parent = path.parent().expect("blob path has a parent");
+  fs::create_dir_all(parent)?;
+
+  let temp = parent.join(format!(".tmp-{}", Uuid::new_v4().to_simple()));
+  let result = fs::File::create(&temp)
+    .and_then(|mut f| f.write_all(content))
+    .and_then(|_| fs::rename(&temp, path));
+  if result.is_err() {
+    fs::remove_file(&temp).ok();
+  }
+
+  Ok(result?)
+}
+
+/// Replaces `dest` with a hard link to `source` without touching whatever `dest` linked to before.
+fn replace_with_link(source: &Path, dest: &Path) -> io::Result<()> {
+  let parent = dest.parent().expect("destination has a parent");
+  let temp = parent.join(format!(".tmp-{}", Uuid::new_v4().to_simple()));
+
+  fs::hard_link(source, &temp)?;
+  if let Err(e) = fs::rename(&temp, dest) {
+    fs::remove_file(&temp).ok();
+    return Err(e);
+  }
+
+  Ok(())
+}
+
+/// Makes `path` share the blob at `shared`, moving it into the store if the store doesn't have it.
+///
+/// If `expected` is given, `path` is only shared if it really has that content.
+fn adopt(shared: &Path, path: &Path, expected: Option<Oid>) -> Result<()> {
+  let meta = match fs::metadata(path) {
+    Ok(m) => m,
+    Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+    Err(e) => return Err(e.into()),
+  };
+
+  let existing = match fs::metadata(shared) {
+    Ok(s) if s.dev() == meta.dev() && s.ino() == meta.ino() => return Ok(()),
+    Ok(s) => Some(s),
+    Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+    Err(e) => return Err(e.into()),
+  };
+
+  if let Some(expected) = expected {
+    if Oid::hash_file(ObjectType::Blob, path)? != expected {
+      return Ok(());
+    }
+  }
+
+  if existing.is_some() {
+    return match replace_with_link(shared, path) {
+      // swept in the meantime, so leave the file unshared until next time
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+      r => Ok(r?),
+    };
+  }
+
+  fs::create_dir_all(shared.parent().expect("blob path has a parent"))?;
+  match fs::hard_link(path, shared) {
+    // added by another paste in the meantime
+    Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => adopt(shared, path, None),
+    r => Ok(r?),
+  }
+}
+
+fn remove_if_unused(path: &Path) -> Result<()> {
+  match fs::metadata(path) {
+    Ok(m) if m.nlink() == 1 => remove_file(path),
+    Ok(_) => Ok(()),
+    Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+    Err(e) => Err(e.into()),
+  }
+}
+
+/// Removes a file that may already have been removed by someone else.
+fn remove_file(path: &Path) -> Result<()> {
+  match fs::remove_file(path) {
+    Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+    r => Ok(r?),
+  }
+}
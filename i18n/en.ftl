@@ -31,6 +31,7 @@ avatar-alt = {$username} avatar
 new = New
 new-paste = New paste
 new-paste-desc = Create a new paste.
+new-paste-from-template = From template
 
 ## Paste attributes
 
@@ -77,6 +78,8 @@ antispam-honeypot =
 
 ## Paste UI
 
+paste-archive = Upload an archive
+  .help = Create files from a zip, tar or tar.gz archive, one for each file inside it. Anything written above is added too.
 paste-add-file = Add file
 paste-submit-anonymous = Submit anonymously
 paste-submit = Submit
@@ -92,6 +95,10 @@ disp-num-files = {$files} {$files ->
 # put directly before the paste expiration date
 # rendered, it ends up like "expires in 10 minutes"
 disp-expires = expires
+# put directly before when the paste's visibility will change
+disp-visibility-change = becomes {$visibility}
+# put directly before links to download the paste as zip and tar.gz
+disp-download = download
 
 disp-tab-files = files
 disp-tab-revisions = revisions
@@ -107,9 +114,16 @@ disp-dkey-msg-1 = In order to delete this paste, you will need to use the key be
 disp-dkey-msg-2 = Note that this key is saved on <em>this browser only</em> for 30 days. If you clear the cache, the key will be lost.
 
 disp-file-tab-rendered = Rendered
+disp-file-tab-pretty = Pretty-printed
 disp-file-tab-source = Source
 disp-file-raw = Raw
+disp-file-csv = Download CSV
+disp-table-rows = Rows {$first}–{$last} of {$total}
 disp-file-binary-content = Binary content
+disp-image-preview = Preview of {$name}
+
+disp-meta-language = Language
+disp-meta-files = Files
 
 ## Revisions
 
@@ -125,6 +139,9 @@ revisions-subtitle = View the changes made to this paste.
 
 revisions-unknown-file = unknown file
 
+revisions-clone = Clone with git
+  .help = Use one of your API keys as the password to clone private pastes or to push changes to your own. Files in the repository are named by their ids.
+
 ## Edit
 
 edit-page-title =
@@ -158,6 +175,16 @@ user-select = Select
   .all = All
   .none = None
 
+## Feeds
+
+# $siteName (string) name of this paste instance
+feed-title = Public pastes on {$siteName}
+# $siteName (string) name of this paste instance
+# $name (string) user's name
+feed-description =
+  .all = The newest public pastes on {$siteName}.
+  .user = The newest public pastes by {$name}.
+
 ## Pagination
 
 pagination =
@@ -194,10 +221,16 @@ login-password = Password
 login-2fa = 2FA code
   .placeholder = Generated code or backup code
 
+login-2fa-key = Use a security key
+  .error = Your security key could not be used. Try again or enter a code instead.
+
 login-forgot-password = Forgot your password?
 
 login-submit = Log in
 
+# $provider (string) name of the identity provider
+login-oidc = Log in with {$provider}
+
 login-error =
   .username = Username not found.
   .password = Incorrect password.
@@ -207,6 +240,14 @@ login-error =
    *[other] minutes
   }.
   .rate-limit-soon = Please try again in a few seconds.
+  .password-disabled = Logging in with a password is disabled.
+  .oidc = Could not log in with your identity provider. Please try again.
+  .ldap = Could not check your login with the directory. Please try again later.
+  .oidc-unverified-email = Your identity provider has not verified your email.
+  .oidc-unverified-account = An account with your email exists, but its email is not verified. Log in with your password and verify your email first.
+  .oidc-no-account = No account was found for your email.
+  .oidc-duplicate-username = An account with your username already exists. Please register manually.
+  .ldap-duplicate-username = An account with your username already exists that was not created from the directory, so it can't be logged in to with your directory password.
 
 ## Register
 
@@ -272,7 +313,10 @@ reset-success =
 settings-tabs =
   .settings = Settings
   .api-keys = API keys
+  .templates = Templates
   .two-factor = 2FA
+  .sessions = Sessions
+  .export = Export
   .delete = Delete
 
 ## Account
@@ -346,6 +390,95 @@ api-key-add =
 api-key-error =
   .empty-name = API key name cannot be empty.
 
+## Templates
+
+templates = Templates
+  .description = Start new pastes from saved ones.
+
+templates-table-header =
+  .name = Name
+  .files = Files
+  .created = Created
+
+templates-no-templates = You have no templates! Save a paste as one from its page.
+
+template-save = Save as template
+  .description = New pastes can start with this paste's files, name, description, visibility and expiry. Binary files aren't saved.
+  .name-placeholder = Template name
+  .submit = Save
+
+template-saved = Template saved.
+
+## Expiry
+
+expiry-change = Change expiry
+  .description = Push back when this paste will be deleted, or keep it forever.
+  .one-day = By a day
+  .one-week = By a week
+  .thirty-days = By 30 days
+  .one-year = By a year
+  .extend = Extend
+  .remove = Never expire
+  .invalid-duration = That isn't a valid length of time.
+
+expiry-extended = Expiry extended.
+expiry-removed = This paste will no longer expire.
+
+## Sessions
+
+sessions = Sessions
+  .description = See where you're logged in.
+
+sessions-table-header =
+  .created = Created
+  .last-seen = Last seen
+  .ip = IP address
+  .user-agent = Browser
+
+sessions-current = This session
+sessions-revoke = Sign out
+sessions-revoke-all = Sign out all other sessions
+
+sessions-success =
+  .one = Session signed out.
+  .all = All other sessions signed out.
+
+sessions-error =
+  .current = Log out to end the current session.
+  .missing = No such session.
+
+## Export
+
+export = Export your data
+  .description = Download a copy of everything in your account.
+
+export-explanation =
+  .line-1 = An archive of all of your pastes, their files and metadata, your account information and the names of your API keys will be prepared in the background.
+  .line-2 = Once it's ready, a download link will be sent to your email address. The link expires after seven days.
+
+export-include-history = Include full paste histories
+  .help = Adds each paste's git repository to the archive. This can make the archive much larger.
+
+export-submit = Export my data
+
+export-table-header =
+  .created = Requested
+  .expires = Expires
+  .status = Status
+
+export-status =
+  .ready = Ready
+  .pending = Preparing
+
+export-success =
+  .queued = Your export is being prepared. You'll receive an email when it's ready.
+  .pending = That export is still being prepared. Please try again later.
+
+export-error =
+  .unverified = You must verify your email address before exporting your data.
+  .too-soon = You can only request one export an hour.
+  .bad-url = That export link is invalid or has expired.
+
 ## Delete account
 
 delete-account = Delete account
@@ -392,6 +525,24 @@ tfa-error =
   .not-enabled = 2FA is not enabled on your account.
   .missing-secret = No shared secret has been generated yet.
 
+tfa-keys = Security keys
+  .explanation = Security keys, like USB keys or your device's built-in authenticator, can be used instead of a generated code when logging in. Adding a key turns on 2FA.
+
+tfa-keys-table-header =
+  .name = Name
+  .added = Added
+  .last-used = Last used
+
+tfa-keys-rename = Rename
+tfa-keys-never-used = Never
+tfa-keys-none = You haven't added any security keys.
+
+tfa-key-error =
+  .empty-name = Security keys must have a name.
+  .failed = Your security key could not be registered. Please try again.
+  .duplicate = That security key is already registered.
+  .missing = That security key does not exist.
+
 ## Enable 2FA
 
 tfa-enable = Enable 2FA
@@ -406,6 +557,15 @@ tfa-enable-enter-code = After setting up the authenticator app, enter the code i
   .placeholder = 2FA code
   .button = Enable
 
+## Add security key
+
+tfa-key-add = Add security key
+  .description = Register a security key as a second factor.
+  .explanation = Give your key a name so you can recognise it later, then click the button below and follow your browser's instructions.
+  .name-placeholder = Key name
+  .button = Register key
+  .unsupported = Your security key could not be registered. Make sure your browser supports security keys and try again.
+
 ## Disable 2FA
 
 tfa-disable = Disable 2FA
@@ -546,6 +706,12 @@ admin-users-delete =
   .super = Cannot delete a super admin.
   .other-admin = Cannot delete another admin.
 
+admin-users-sign-out = Sign out everywhere
+  .missing = No such user.
+  .success = User signed out everywhere.
+  .super = Cannot sign out a super admin.
+  .other-admin = Cannot sign out another admin.
+
 admin-users-delete-modal =
   .title = Delete user
   .confirm = Please confirm you would like to delete this user.
@@ -587,6 +753,18 @@ admin-config =
 
 admin-config-save = Save
 
+## Admin maintenance
+
+admin-maintenance =
+  .title = Maintenance
+  .subtitle = Look after the site's storage.
+
+admin-maintenance-dedupe =
+  .title = Deduplicate files
+  .description = Pastes made before files were deduplicated each keep their own copies. This moves them into the shared blob store and removes any blobs no paste uses. It runs in the background and is safe to run more than once.
+  .button = Deduplicate
+  .queued = Deduplication has been started.
+
 ## CSV
 
 csv-error =
@@ -606,3 +784,11 @@ csv-error =
     [one] field
    *[other] fields
   }.
+
+notebook-error = {-brand} would like to show you this notebook, but it couldn't be read: {$err}.
+
+markdown-toc = Contents
+
+structured-error =
+  .pos = {-brand} would like to show you this {$format} file as a tree, but it isn't valid {$format} at line {$line}, column {$column}: {$err}.
+  .no-pos = {-brand} would like to show you this {$format} file as a tree, but it isn't valid {$format}: {$err}.
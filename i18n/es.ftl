@@ -31,6 +31,7 @@ avatar-alt = {$username} avatar
 new = Nuevo
 new-paste = Nuevo pegado
 new-paste-desc = Crear un nuevo pegado.
+new-paste-from-template = Desde plantilla
 
 ## Paste attributes
 
@@ -76,6 +77,8 @@ antispam-honeypot =
 
 ## Paste UI
 
+paste-archive = Subir un archivo comprimido
+  .help = Crea archivos a partir de un archivo zip, tar o tar.gz, uno por cada archivo que contenga. Lo que esté escrito arriba también se añade.
 paste-add-file = Añadir archivo
 paste-submit-anonymous = Enviar anónimamente
 paste-submit = Enviar
@@ -91,6 +94,8 @@ disp-num-files = {$files} {$files ->
 # put directly before the paste expiration date
 # rendered, it ends up like "expires in 10 minutes"
 disp-expires = expira
+disp-visibility-change = pasa a {$visibility}
+disp-download = descargar
 
 disp-tab-files = archivos
 disp-tab-revisions = revisiónes
@@ -106,9 +111,16 @@ disp-dkey-msg-1 = Para eliminar este pegado, deberá utilizar la siguiente clave
 disp-dkey-msg-2 = Tenga en cuenta que esta clave se guarda en <em>este navegador sólo</em> durante 30 días. Si borra la caché, la clave se perderá.
 
 disp-file-tab-rendered = Renderizado
+disp-file-tab-pretty = Formateado
 disp-file-tab-source = Fuente
 disp-file-raw = Crudo
+disp-file-csv = Descargar CSV
+disp-table-rows = Filas {$first}–{$last} de {$total}
 disp-file-binary-content = Contenido binario
+disp-image-preview = Vista previa de {$name}
+
+disp-meta-language = Lenguaje
+disp-meta-files = Archivos
 
 ## Revisions
 
@@ -124,6 +136,9 @@ revisions-subtitle = Ver los cambios realizados en esta pegado.
 
 revisions-unknown-file = archivo desconocido
 
+revisions-clone = Clonar con git
+  .help = Use una de sus claves de API como contraseña para clonar pegados privados o enviar cambios a los suyos. Los archivos del repositorio se nombran por sus identificadores.
+
 ## Edit
 
 edit-page-title =
@@ -157,6 +172,16 @@ user-select = Seleccionar
   .all = Todos
   .none = Ninguno
 
+## Feeds
+
+# $siteName (string) name of this paste instance
+feed-title = Pegados públicos en {$siteName}
+# $siteName (string) name of this paste instance
+# $name (string) user's name
+feed-description =
+  .all = Los pegados públicos más recientes en {$siteName}.
+  .user = Los pegados públicos más recientes de {$name}.
+
 ## Pagination
 
 pagination =
@@ -193,10 +218,16 @@ login-password = Contraseña
 login-2fa = Código 2FA
   .placeholder = Código generado o código de respaldo
 
+login-2fa-key = Usar una llave de seguridad
+  .error = No se pudo usar su llave de seguridad. Inténtelo de nuevo o introduzca un código.
+
 login-forgot-password = ¿Olvidó su contraseña?
 
 login-submit = Iniciar sesión
 
+# $provider (string) name of the identity provider
+login-oidc = Iniciar sesión con {$provider}
+
 ## Register
 
 register = Inscribirse
@@ -247,7 +278,10 @@ reset-submit = Enviar
 settings-tabs =
   .settings = Ajustes
   .api-keys = Claves de API
+  .templates = Plantillas
   .two-factor = 2FA
+  .sessions = Sesiones
+  .export = Exportar
   .delete = Eliminar
 
 ## Account
@@ -297,6 +331,78 @@ api-key-add =
   .name-placeholder = Nombre de clave
   .submit = Añadir
 
+## Templates
+
+templates = Plantillas
+  .description = Empezar nuevos pegados desde otros guardados.
+
+templates-table-header =
+  .name = Nombre
+  .files = Archivos
+  .created = Creada
+
+templates-no-templates = ¡No tiene plantillas! Guarde un pegado como plantilla desde su página.
+
+template-save = Guardar como plantilla
+  .description = Nuevos pegados pueden empezar con los archivos, nombre, descripción, visibilidad y caducidad de este pegado. Los archivos binarios no se guardan.
+  .name-placeholder = Nombre de plantilla
+  .submit = Guardar
+
+template-saved = Plantilla guardada.
+
+## Expiry
+
+expiry-change = Cambiar caducidad
+  .description = Retrase cuándo se eliminará este pegado, o consérvelo para siempre.
+  .one-day = Un día
+  .one-week = Una semana
+  .thirty-days = 30 días
+  .one-year = Un año
+  .extend = Extender
+  .remove = No caducar nunca
+  .invalid-duration = Esa no es una duración válida.
+
+expiry-extended = Caducidad extendida.
+expiry-removed = Este pegado ya no caducará.
+
+## Sessions
+
+sessions = Sesiones
+  .description = Vea dónde ha iniciado sesión.
+
+sessions-table-header =
+  .created = Creada
+  .last-seen = Última actividad
+  .ip = Dirección IP
+  .user-agent = Navegador
+
+sessions-current = Esta sesión
+sessions-revoke = Cerrar sesión
+sessions-revoke-all = Cerrar todas las demás sesiones
+
+## Export
+
+export = Exportar sus datos
+  .description = Descargue una copia de todo lo que hay en su cuenta.
+
+export-explanation =
+  .line-1 = Se preparará en segundo plano un archivo con todos sus pegados, sus archivos y metadatos, la información de su cuenta y los nombres de sus claves de API.
+  .line-2 = Cuando esté listo, se enviará un enlace de descarga a su dirección de correo electrónico. El enlace caduca después de siete días.
+
+export-include-history = Incluir el historial completo de los pegados
+  .help = Añade el repositorio git de cada pegado al archivo. Esto puede hacer que el archivo sea mucho más grande.
+
+export-submit = Exportar mis datos
+
+export-table-header =
+  .created = Solicitado
+  .expires = Caduca
+  .status = Estado
+
+export-status =
+  .ready = Listo
+  .pending = Preparando
+
 ## Delete account
 
 delete-account = Eliminar cueva
@@ -333,6 +439,18 @@ tfa-explanation =
   .part-1 = La autenticación de dos factores (una forma de <a class="external" target="_blank" href="https://es.wikipedia.org/wiki/Autenticaci%C3%B3n_de_m%C3%BAltiples_factores">autenticación de múltiples factores</a>) aumenta la seguridad de su cuenta al requerirle que especifique una contraseña única generada por otro dispositivo al iniciar sesión.
   .part-2 = Esta característica requiere un dispositivo inteligente o tarjeta inteligente y una aplicación para generar los códigos. Para los dispositivos Android e iOS, consulte la tabla siguiente.
 
+tfa-keys = Llaves de seguridad
+  .explanation = Las llaves de seguridad, como las llaves USB o el autenticador integrado de su dispositivo, se pueden utilizar en lugar de un código generado al iniciar sesión. Añadir una llave activa 2FA.
+
+tfa-keys-table-header =
+  .name = Nombre
+  .added = Añadida
+  .last-used = Último uso
+
+tfa-keys-rename = Renombrar
+tfa-keys-never-used = Nunca
+tfa-keys-none = No ha añadido ninguna llave de seguridad.
+
 tfa-devices =
   .play-store = Android (Play Store)
   .ios = iOS (App Store)
@@ -352,6 +470,15 @@ tfa-enable-enter-code = Después de configurar la aplicación de autenticación,
   .placeholder = Código 2FA
   .button = Activar
 
+## Add security key
+
+tfa-key-add = Añadir llave de seguridad
+  .description = Registrar una llave de seguridad como segundo factor.
+  .explanation = Dé un nombre a su llave para poder reconocerla más tarde, luego haga clic en el botón de abajo y siga las instrucciones de su navegador.
+  .name-placeholder = Nombre de la llave
+  .button = Registrar llave
+  .unsupported = No se pudo registrar su llave de seguridad. Asegúrese de que su navegador admite llaves de seguridad e inténtelo de nuevo.
+
 ## Disable 2FA
 
 tfa-disable = Desactivar 2FA
@@ -490,6 +617,12 @@ admin-users-delete =
   .super = No se puede eliminar un superadministrador.
   .other-admin = No se puede eliminar otro administrador.
 
+admin-users-sign-out = Cerrar todas las sesiones
+  .missing = No existe tal usuario.
+  .success = Se han cerrado todas las sesiones del usuario.
+  .super = No se puede cerrar la sesión de un superadministrador.
+  .other-admin = No se puede cerrar la sesión de otro administrador.
+
 admin-users-delete-modal =
   .title = Eliminar usuario
   .confirm = Por favor, confirme que desea eliminar a este usuario.
@@ -533,6 +666,18 @@ admin-config =
 
 admin-config-save = Guardar
 
+## Admin maintenance
+
+admin-maintenance =
+  .title = Mantenimiento
+  .subtitle = Cuidar el almacenamiento del sitio.
+
+admin-maintenance-dedupe =
+  .title = Deduplicar archivos
+  .description = Los pastes creados antes de que se deduplicaran los archivos tienen sus propias copias. Esto los mueve al almacén compartido de blobs y elimina los blobs que ningún paste usa. Se ejecuta en segundo plano y se puede ejecutar más de una vez.
+  .button = Deduplicar
+  .queued = Se ha iniciado la deduplicación.
+
 ## CSV
 
 csv-error =
@@ -552,3 +697,11 @@ csv-error =
     [one] campo
    *[other] campos
   }.
+
+notebook-error = A {-brand} le gustaría mostrarle este cuaderno, pero no se podía leer: {$err}.
+
+markdown-toc = Contenido
+
+structured-error =
+  .pos = A {-brand} le gustaría mostrarle este archivo {$format} como un árbol, pero no es {$format} válido en la línea {$line}, columna {$column}: {$err}.
+  .no-pos = A {-brand} le gustaría mostrarle este archivo {$format} como un árbol, pero no es {$format} válido: {$err}.
@@ -0,0 +1,8 @@
+create table oidc_identities (
+  issuer text not null,
+  subject text not null,
+  user_id uuid not null,
+
+  primary key (issuer, subject),
+  foreign key (user_id) references users(id) on delete cascade
+)
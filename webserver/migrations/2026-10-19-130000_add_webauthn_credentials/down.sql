@@ -0,0 +1 @@
+drop table webauthn_credentials
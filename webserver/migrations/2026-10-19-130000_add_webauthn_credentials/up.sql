@@ -0,0 +1,12 @@
+create table webauthn_credentials (
+  id uuid primary key not null,
+  user_id uuid not null,
+  name text not null,
+  credential_id bytea not null unique,
+  public_key bytea not null,
+  sign_count bigint not null default 0,
+  created_at timestamp not null default now(),
+  last_used_at timestamp,
+
+  foreign key (user_id) references users(id) on delete cascade
+)
@@ -0,0 +1,10 @@
+create table data_exports (
+  id uuid primary key not null,
+  user_id uuid not null,
+  secret text not null,
+  include_history boolean not null default false,
+  created_at timestamp not null default now(),
+  expiry timestamp not null,
+
+  foreign key (user_id) references users(id) on delete cascade
+)
@@ -0,0 +1,9 @@
+create table uploads (
+  id uuid primary key not null,
+  user_id uuid not null,
+  length bigint not null,
+  created_at timestamp not null default now(),
+  expiry timestamp not null,
+
+  foreign key (user_id) references users(id) on delete cascade
+)
@@ -0,0 +1,3 @@
+drop table paste_template_files;
+
+drop table paste_templates
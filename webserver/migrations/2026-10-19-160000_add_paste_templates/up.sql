@@ -0,0 +1,23 @@
+create table paste_templates (
+  id uuid primary key not null,
+  user_id uuid not null,
+  name text not null,
+  paste_name text,
+  description text,
+  visibility smallint not null,
+  expires_after bigint,
+  created_at timestamp not null default now(),
+
+  foreign key (user_id) references users(id) on delete cascade
+);
+
+create table paste_template_files (
+  template_id uuid not null,
+  position integer not null,
+  name text not null,
+  highlight_language text,
+  content text not null,
+
+  primary key (template_id, position),
+  foreign key (template_id) references paste_templates(id) on delete cascade
+)
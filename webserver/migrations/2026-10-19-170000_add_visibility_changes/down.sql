@@ -0,0 +1 @@
+drop table visibility_changes
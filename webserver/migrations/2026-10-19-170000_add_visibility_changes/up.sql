@@ -0,0 +1,7 @@
+create table visibility_changes (
+  paste_id uuid primary key not null,
+  visibility smallint not null,
+  scheduled_for timestamp not null,
+
+  foreign key (paste_id) references pastes(id) on delete cascade
+)
@@ -0,0 +1 @@
+drop table ldap_identities
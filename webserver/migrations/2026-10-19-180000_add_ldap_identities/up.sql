@@ -0,0 +1,6 @@
+create table ldap_identities (
+  dn text not null primary key,
+  user_id uuid not null,
+
+  foreign key (user_id) references users(id) on delete cascade
+)
@@ -10,8 +10,8 @@ use crate::{
   },
   filter::Action,
   models::paste::Visibility,
-  sidekiq::Job,
   store::Store,
+  utils::RelativeDuration,
 };
 
 use super::models::{PastePayload, CreateSuccess, CreateError};
@@ -21,7 +21,7 @@ use chrono::Utc;
 use diesel;
 use diesel::prelude::*;
 
-use sidekiq::{Client as SidekiqClient, Value};
+use sidekiq::Client as SidekiqClient;
 
 use unicode_segmentation::UnicodeSegmentation;
 
@@ -35,7 +35,7 @@ impl PastePayload<'u> {
       return Err(CreateError::AnonymousPrivate);
     }
 
-    if self.files.is_empty() {
+    if self.files.is_empty() && self.uploads.is_empty() {
       return Err(CreateError::NoFiles);
     }
 
@@ -45,12 +45,12 @@ impl PastePayload<'u> {
       }
     }
 
-    if self.files.len() > 1 {
-      let mut names: Vec<Cow<str>> = self.files.iter()
+    if self.files.len() + self.uploads.len() > 1 {
+      let mut names: Vec<Cow<str>> = self.file_names()
         .enumerate()
-        .map(|(i, x)| match x.name {
+        .map(|(i, x)| match x {
           None => Cow::Owned(format!("pastefile{}", i + 1)),
-          Some(ref n) => Cow::Borrowed(n.as_str()),
+          Some(n) => Cow::Borrowed(n.as_str()),
         })
         .collect();
       let len = names.len();
@@ -85,19 +85,50 @@ impl PastePayload<'u> {
       return Err(CreateError::EmptyFile);
     }
 
-    if self.files.iter().filter_map(|x| x.name.as_ref()).any(|x| x.len() > MAX_SIZE) {
+    if self.file_names().flatten().any(|x| x.len() > MAX_SIZE) {
       return Err(CreateError::FileNameTooLarge);
     }
 
-    if self.files.iter().filter_map(|x| x.name.as_ref()).any(|x| x.graphemes(true).count() > 255) {
+    if self.file_names().flatten().any(|x| x.graphemes(true).count() > 255) {
       return Err(CreateError::FileNameTooLong);
     }
 
     Ok(())
   }
 
-  pub fn create(self, config: &Config, conn: &DbConn, sidekiq: &SidekiqClient) -> Result<CreateSuccess, CreateError> {
+  /// Gives anonymous pastes the site's default expiry if they have none, and holds them to its
+  /// maximum.
+  fn limit_expiry(&mut self, config: &Config) -> Result<(), CreateError> {
+    if self.author.is_some() {
+      return Ok(());
+    }
+
+    let (default, max) = {
+      let config = config.read();
+      (config.pastes.anonymous_default_expiry, config.pastes.anonymous_max_expiry)
+    };
+
+    if self.expires.is_none() {
+      // anonymous pastes can't outlast the maximum, so it stands in for a missing default
+      let default = match (default, max) {
+        (Some(d), Some(m)) => Some(std::cmp::min(d.duration(), m.duration())),
+        (d, m) => d.or(m).map(RelativeDuration::duration),
+      };
+      self.expires = default.map(|d| Utc::now() + d);
+    }
+
+    if let (Some(max), Some(expires)) = (max, self.expires) {
+      if expires > max.from_now() {
+        return Err(CreateError::ExpiryTooLate(max));
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn create(mut self, config: &Config, conn: &DbConn, sidekiq: &SidekiqClient) -> Result<CreateSuccess, CreateError> {
     self.check()?;
+    self.limit_expiry(config)?;
 
     // process each filter
     for filter in &config.read().filters {
@@ -147,24 +178,14 @@ impl PastePayload<'u> {
         .map_err(CreateError::Internal)?;
       files.push(f);
     }
-
-    if let Some(expiration_date) = self.expires {
-      let timestamp = expiration_date.timestamp();
-
-      let user = match self.author {
-        Some(a) => a.id().to_simple().to_string(),
-        None => "anonymous".to_string(),
-      };
-
-      let job = Job::queue("ExpirePaste", timestamp, vec![
-        Value::Number(timestamp.into()),
-        Value::String(Store::new(config).directory().to_string_lossy().to_string()),
-        Value::String(user),
-        Value::String(id.to_simple().to_string()),
-      ]);
-      sidekiq.push(job.into()).map_err(|e| CreateError::Internal(e.into()))?;
+    for upload in self.uploads {
+      let f = paste.attach_file(&*config, conn, upload.name, upload.highlight_language, &upload.path)
+        .map_err(CreateError::Internal)?;
+      files.push(f);
     }
 
+    paste.schedule_expiry(config, conn, sidekiq).map_err(CreateError::Internal)?;
+
     Ok(CreateSuccess {
       paste,
       files,
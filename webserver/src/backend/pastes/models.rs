@@ -7,14 +7,14 @@ use crate::{
     users::User,
   },
   models::paste::{Content, Visibility},
-  utils::Language,
+  utils::{Language, RelativeDuration},
 };
 
 use chrono::{DateTime, Utc};
 
 use anyhow::Error;
 
-use std::borrow::Cow;
+use std::{borrow::Cow, path::PathBuf};
 
 pub struct PastePayload<'u> {
   pub name: Option<String>,
@@ -23,6 +23,17 @@ pub struct PastePayload<'u> {
   pub expires: Option<DateTime<Utc>>,
   pub author: Option<&'u User>,
   pub files: Vec<FilePayload>,
+  pub uploads: Vec<UploadPayload>,
+}
+
+impl PastePayload<'u> {
+  /// The names of every file and upload, in the order they will be created.
+  pub fn file_names(&self) -> impl Iterator<Item = Option<&String>> {
+    self.files
+      .iter()
+      .map(|x| x.name.as_ref())
+      .chain(self.uploads.iter().map(|x| x.name.as_ref()))
+  }
 }
 
 pub struct FilePayload {
@@ -31,6 +42,13 @@ pub struct FilePayload {
   pub content: Content,
 }
 
+/// A completed resumable upload, moved into the paste as a binary file.
+pub struct UploadPayload {
+  pub name: Option<String>,
+  pub highlight_language: Option<Language>,
+  pub path: PathBuf,
+}
+
 pub struct CreateSuccess {
   pub paste: Paste,
   pub files: Vec<File>,
@@ -51,6 +69,7 @@ pub enum CreateError {
   FileNameTooLong,
   EmptyFile,
   PastExpirationDate,
+  ExpiryTooLate(RelativeDuration),
   Internal(Error),
 }
 
@@ -68,6 +87,7 @@ impl BackendError for CreateError {
       CreateError::FailedSpamFilter => "the spam filter was triggered",
       CreateError::FailedSpamFilterFake(None) => "an error occurred",
       CreateError::FailedSpamFilterFake(Some(s)) => return Ok(Cow::Owned(s)),
+      CreateError::ExpiryTooLate(max) => return Ok(Cow::Owned(format!("anonymous pastes must expire within {}", max))),
       CreateError::FileNameTooLarge => "file name must be less than 25 KiB",
       CreateError::FileNameTooLong => "file name must be less than or equal to 255 characters",
       CreateError::EmptyFile => "file content must not be empty",
@@ -1,6 +1,7 @@
 use crate::{
   errors::*,
   filter::Filter,
+  utils::RelativeDuration,
 };
 
 use parking_lot::RwLock;
@@ -21,6 +22,10 @@ pub struct AppConfig {
   pub pastes: Pastes,
   #[serde(default)]
   pub registration: Registration,
+  #[serde(default)]
+  pub embeds: Embeds,
+  #[serde(default)]
+  pub login: Login,
   #[serde(default, rename = "filter")]
   pub filters: Vec<Filter>,
 }
@@ -48,10 +53,44 @@ pub struct Store {
   pub path: PathBuf,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Pastes {
   pub sign_in_to_create: bool,
+  /// The most files a paste updated by git push or created from an archive may contain.
+  pub max_files: usize,
+  /// The largest a single file in a paste updated by git push or created from an archive may be,
+  /// in bytes.
+  pub max_file_size: u64,
+  /// The largest an uploaded archive may be, in bytes.
+  pub max_archive_size: u64,
+  /// The largest a file sent with a resumable upload may be, in bytes.
+  pub max_upload_size: u64,
+  /// The largest a git push to a paste may be, in bytes.
+  pub max_push_size: u64,
+  /// How long anonymous pastes last when they're created without an expiry.
+  pub anonymous_default_expiry: Option<RelativeDuration>,
+  /// The longest anonymous pastes may last. Anonymous pastes can't be created without an expiry
+  /// if this is set.
+  pub anonymous_max_expiry: Option<RelativeDuration>,
+  /// How long before a paste expires to email its owner, if at all.
+  pub expiry_reminder: Option<RelativeDuration>,
+}
+
+impl Default for Pastes {
+  fn default() -> Self {
+    Self {
+      sign_in_to_create: false,
+      max_files: 100,
+      max_file_size: 10 * 1024 * 1024,
+      max_archive_size: 50 * 1024 * 1024,
+      max_upload_size: 1024 * 1024 * 1024,
+      max_push_size: 100 * 1024 * 1024,
+      anonymous_default_expiry: None,
+      anonymous_max_expiry: None,
+      expiry_reminder: None,
+    }
+  }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -70,6 +109,97 @@ impl Default for Registration {
   }
 }
 
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Embeds {
+  pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Login {
+  pub password: bool,
+  pub oidc: Option<Oidc>,
+  pub ldap: Option<Ldap>,
+}
+
+impl Default for Login {
+  fn default() -> Self {
+    Self {
+      password: true,
+      oidc: None,
+      ldap: None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Oidc {
+  pub name: String,
+  pub issuer: String,
+  pub client_id: String,
+  #[serde(default, skip_serializing)]
+  pub client_secret: Option<String>,
+  #[serde(default = "Oidc::default_scopes")]
+  pub scopes: Vec<String>,
+  #[serde(default)]
+  pub auto_register: bool,
+}
+
+impl Oidc {
+  fn default_scopes() -> Vec<String> {
+    vec!["openid".into(), "email".into(), "profile".into()]
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ldap {
+  pub url: String,
+  #[serde(default)]
+  pub bind_dn: Option<String>,
+  #[serde(default, skip_serializing)]
+  pub bind_password: Option<String>,
+  pub base_dn: String,
+  #[serde(default = "Ldap::default_user_filter")]
+  pub user_filter: String,
+  #[serde(default)]
+  pub attributes: LdapAttributes,
+  #[serde(default = "Ldap::default_group_attribute")]
+  pub group_attribute: String,
+  #[serde(default)]
+  pub admin_groups: Vec<String>,
+  #[serde(default)]
+  pub superadmin_groups: Vec<String>,
+}
+
+impl Ldap {
+  fn default_user_filter() -> String {
+    "(uid={username})".into()
+  }
+
+  fn default_group_attribute() -> String {
+    "memberOf".into()
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LdapAttributes {
+  pub username: String,
+  pub name: String,
+  pub email: String,
+}
+
+impl Default for LdapAttributes {
+  fn default() -> Self {
+    Self {
+      username: "uid".into(),
+      name: "cn".into(),
+      email: "mail".into(),
+    }
+  }
+}
+
 pub fn load_config(s: &str) -> Result<AppConfig> {
   // read in the given path to a string
   let config_str = std::fs::read_to_string(s)?;
@@ -85,6 +215,11 @@ pub fn initialise(mut config: AppConfig, s: &str) -> Result<AppConfig> {
     config.admin.key = None;
   }
 
+  // password login is the only way to log in without oidc or ldap
+  if !config.login.password && config.login.oidc.is_none() && config.login.ldap.is_none() {
+    anyhow::bail!("password login can't be disabled without configuring oidc or ldap");
+  }
+
   // canonicalise the config path and set it on the config
   let path = PathBuf::from(s)
     .canonicalize()
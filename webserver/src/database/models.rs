@@ -1,10 +1,17 @@
 pub mod api_keys;
 pub mod backup_codes;
+pub mod data_exports;
 pub mod deletion_keys;
 pub mod email_verifications;
 pub mod files;
+pub mod ldap_identities;
 pub mod login_attempts;
+pub mod oidc_identities;
+pub mod paste_templates;
 pub mod pastes;
 pub mod password_resets;
 pub mod password_reset_attempts;
+pub mod uploads;
 pub mod users;
+pub mod visibility_changes;
+pub mod webauthn_credentials;
@@ -0,0 +1,154 @@
+use crate::{
+  config::Config,
+  errors::*,
+  models::id::{DataExportId, UserId},
+  sidekiq::Job,
+  store::Store,
+  utils::HashedPassword,
+};
+
+use super::users::User;
+use super::super::DbConn;
+use super::super::schema::data_exports;
+
+use chrono::{Utc, Duration, NaiveDateTime};
+
+use data_encoding::BASE64URL_NOPAD;
+
+use diesel::prelude::*;
+
+use serde_json::json;
+
+use sodiumoxide::{
+  randombytes,
+  crypto::pwhash::{pwhash_verify, HashedPassword as PwhashPassword},
+};
+
+use uuid::Uuid;
+
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize, Identifiable, Queryable, Associations)]
+#[belongs_to(User)]
+pub struct DataExport {
+  id: DataExportId,
+  user_id: UserId,
+  #[serde(skip_serializing)]
+  secret: String,
+  include_history: bool,
+  created_at: NaiveDateTime,
+  expiry: NaiveDateTime,
+}
+
+impl DataExport {
+  pub fn id(&self) -> DataExportId {
+    self.id
+  }
+
+  pub fn user_id(&self) -> UserId {
+    self.user_id
+  }
+
+  pub fn include_history(&self) -> bool {
+    self.include_history
+  }
+
+  pub fn created_at(&self) -> NaiveDateTime {
+    self.created_at
+  }
+
+  pub fn expiry(&self) -> NaiveDateTime {
+    self.expiry
+  }
+
+  pub fn check(&self, bytes: &[u8]) -> bool {
+    let mut secret = self.secret.as_bytes().to_vec();
+    secret.push(0x00);
+
+    let pw = match PwhashPassword::from_slice(&secret) {
+      Some(p) => p,
+      None => return false,
+    };
+
+    pwhash_verify(&pw, bytes)
+  }
+
+  /// The directory holding the export's metadata and, once the job has run, its archive.
+  pub fn directory(&self, config: &Config) -> PathBuf {
+    Store::new(config)
+      .directory()
+      .join("exports")
+      .join(self.id.to_simple().to_string())
+  }
+
+  pub fn archive_path(&self, config: &Config) -> PathBuf {
+    self.directory(config).join("export.zip")
+  }
+
+  /// The archive is written under a temporary name and renamed when complete.
+  pub fn is_ready(&self, config: &Config) -> bool {
+    self.archive_path(config).exists()
+  }
+
+  pub fn job(&self, config: &Config, user: &User, secret: &[u8]) -> Result<Job<'static>> {
+    let email = Job::email(
+      "data_export.html.tera",
+      json!({
+        "config": &*config.read(),
+        "user": user,
+        "expiry": self.expiry.format("%Y-%m-%d %H:%M").to_string(),
+        "download_url": format!(
+          "https://{}/account/export/{}?secret={}",
+          config.read().general.site_domain,
+          self.id.to_simple(),
+          BASE64URL_NOPAD.encode(&secret),
+        ),
+      }),
+      config.read()._path.as_ref().unwrap(),
+      user.email(),
+      "Your data export",
+    )?;
+
+    Ok(Job::ExportData {
+      directory: self.directory(config),
+      user_directory: Store::new(config).directory().join(user.id().to_simple().to_string()),
+      include_history: self.include_history,
+      email: Box::new(email),
+    })
+  }
+
+  pub fn delete(&self, conn: &DbConn) -> Result<()> {
+    diesel::delete(self).execute(&**conn)?;
+    Ok(())
+  }
+}
+
+#[derive(Insertable)]
+#[table_name = "data_exports"]
+pub struct NewDataExport {
+  pub id: DataExportId,
+  user_id: UserId,
+  secret: String,
+  include_history: bool,
+  expiry: NaiveDateTime,
+}
+
+impl NewDataExport {
+  pub fn generate(user_id: UserId, include_history: bool) -> (Self, Vec<u8>) {
+    let id = DataExportId(Uuid::new_v4());
+    let expiry = Utc::now().naive_utc() + Duration::days(7);
+
+    let bytes = randombytes::randombytes(64);
+    let hashed = HashedPassword::from(&bytes).into_string();
+
+    let export = NewDataExport {
+      id,
+      user_id,
+      secret: hashed,
+      include_history,
+      expiry,
+    };
+
+    (export, bytes)
+  }
+}
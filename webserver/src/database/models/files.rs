@@ -15,6 +15,8 @@ use super::super::schema::files;
 
 use chrono::{NaiveDateTime, Utc};
 
+use git2::Oid;
+
 use std::{fs::File as FsFile, io::Read, path::PathBuf};
 
 #[derive(Debug, Identifiable, AsChangeset, Queryable, Associations)]
@@ -76,6 +78,17 @@ impl File {
     paste.files_directory(config).join(self.id().to_simple().to_string())
   }
 
+  /// The ID of the file's content in the paste's latest commit, if it's been committed.
+  pub fn blob_id(&self, config: &Config, paste: &Paste) -> Result<Option<Oid>> {
+    let repo = paste.repository(config)?;
+    let tree = match repo.head() {
+      Ok(head) => head.peel_to_tree()?,
+      Err(_) => return Ok(None),
+    };
+
+    Ok(tree.get_name(&self.id().to_simple().to_string()).map(|x| x.id()))
+  }
+
   pub fn read_content(&self, config: &Config, paste: &Paste) -> Result<Content> {
     let mut file = FsFile::open(self.path(config, paste))?;
     let mut data = Vec::new();
@@ -0,0 +1,35 @@
+use super::super::schema::ldap_identities;
+use super::users::User;
+use crate::models::id::UserId;
+
+/// A link between a directory entry and the local user made for it on its first login.
+#[derive(Debug, Identifiable, Queryable, Associations)]
+#[primary_key(dn)]
+#[belongs_to(User)]
+pub struct LdapIdentity {
+  dn: String,
+  user_id: UserId,
+}
+
+impl LdapIdentity {
+  pub fn dn(&self) -> &str {
+    &self.dn
+  }
+
+  pub fn user_id(&self) -> UserId {
+    self.user_id
+  }
+}
+
+#[derive(Insertable)]
+#[table_name = "ldap_identities"]
+pub struct NewLdapIdentity {
+  dn: String,
+  user_id: UserId,
+}
+
+impl NewLdapIdentity {
+  pub fn new(dn: String, user_id: UserId) -> Self {
+    NewLdapIdentity { dn, user_id }
+  }
+}
@@ -0,0 +1,40 @@
+use super::super::schema::oidc_identities;
+use super::users::User;
+use crate::models::id::UserId;
+
+#[derive(Debug, Identifiable, Queryable, Associations)]
+#[primary_key(issuer, subject)]
+#[belongs_to(User)]
+pub struct OidcIdentity {
+  issuer: String,
+  subject: String,
+  user_id: UserId,
+}
+
+impl OidcIdentity {
+  pub fn issuer(&self) -> &str {
+    &self.issuer
+  }
+
+  pub fn subject(&self) -> &str {
+    &self.subject
+  }
+
+  pub fn user_id(&self) -> UserId {
+    self.user_id
+  }
+}
+
+#[derive(Insertable)]
+#[table_name = "oidc_identities"]
+pub struct NewOidcIdentity {
+  issuer: String,
+  subject: String,
+  user_id: UserId,
+}
+
+impl NewOidcIdentity {
+  pub fn new(issuer: String, subject: String, user_id: UserId) -> Self {
+    NewOidcIdentity { issuer, subject, user_id }
+  }
+}
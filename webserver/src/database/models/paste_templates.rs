@@ -0,0 +1,196 @@
+use crate::{
+  config::Config,
+  errors::*,
+  models::{
+    id::{PasteTemplateId, UserId},
+    paste::{Content, Visibility},
+  },
+  utils::Language,
+};
+
+use super::pastes::Paste;
+use super::users::User;
+use super::super::DbConn;
+use super::super::schema::{paste_templates, paste_template_files};
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+
+use diesel::prelude::*;
+
+use uuid::Uuid;
+
+/// A user's saved starting point for new pastes: their metadata and the files they start with.
+#[derive(Debug, Identifiable, Queryable, Associations)]
+#[belongs_to(User)]
+pub struct PasteTemplate {
+  id: PasteTemplateId,
+  user_id: UserId,
+  name: String,
+  paste_name: Option<String>,
+  description: Option<String>,
+  visibility: Visibility,
+  expires_after: Option<i64>,
+  created_at: NaiveDateTime,
+}
+
+impl PasteTemplate {
+  /// Saves `paste` as a template called `name` for `user`.
+  ///
+  /// Templates only hold text, so binary files are left out. If the paste expires, pastes made from
+  /// the template expire after the same amount of time.
+  pub fn create(config: &Config, conn: &DbConn, user: UserId, name: String, paste: &Paste) -> Result<PasteTemplate> {
+    let expires_after = paste
+      .expires()
+      .map(|expires| (expires - paste.created_at()).num_seconds().max(0));
+
+    let new_template = NewPasteTemplate {
+      id: PasteTemplateId(Uuid::new_v4()),
+      user_id: user,
+      name,
+      paste_name: paste.name().map(ToString::to_string),
+      description: paste.description().map(ToString::to_string),
+      visibility: paste.visibility(),
+      expires_after,
+    };
+
+    let mut files = paste.id().files(conn)?;
+    files.sort_by(|a, b| a.created_at().cmp(b.created_at()));
+
+    let mut new_files = Vec::with_capacity(files.len());
+    for file in files {
+      let content = match file.read_content(config, paste)? {
+        Content::Text(text) => text,
+        _ => continue,
+      };
+      new_files.push(NewTemplateFile {
+        template_id: new_template.id,
+        position: new_files.len() as i32,
+        name: file.name().to_string(),
+        highlight_language: file.highlight_language(),
+        content,
+      });
+    }
+
+    // a template without its files is no use, so save both or neither
+    let template = conn.transaction::<_, diesel::result::Error, _>(|| {
+      let template = diesel::insert_into(paste_templates::table)
+        .values(&new_template)
+        .get_result(&**conn)?;
+      diesel::insert_into(paste_template_files::table)
+        .values(&new_files)
+        .execute(&**conn)?;
+
+      Ok(template)
+    })?;
+
+    Ok(template)
+  }
+
+  /// Finds the template with `id`, if it belongs to `user`.
+  pub fn find(conn: &DbConn, user: UserId, id: PasteTemplateId) -> Result<Option<PasteTemplate>> {
+    Ok(paste_templates::table
+      .find(id)
+      .filter(paste_templates::user_id.eq(user))
+      .first(&**conn)
+      .optional()?)
+  }
+
+  pub fn id(&self) -> PasteTemplateId {
+    self.id
+  }
+
+  pub fn user_id(&self) -> UserId {
+    self.user_id
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  /// The name pastes made from the template start with.
+  pub fn paste_name(&self) -> Option<&str> {
+    self.paste_name.as_ref().map(|x| x.as_str())
+  }
+
+  pub fn description(&self) -> Option<&str> {
+    self.description.as_ref().map(|x| x.as_str())
+  }
+
+  pub fn visibility(&self) -> Visibility {
+    self.visibility
+  }
+
+  /// How long after being made pastes from the template expire.
+  pub fn expires_after(&self) -> Option<Duration> {
+    self.expires_after.map(Duration::seconds)
+  }
+
+  /// When a paste made from the template now would expire.
+  pub fn expires(&self) -> Option<DateTime<Utc>> {
+    self.expires_after().map(|after| Utc::now() + after)
+  }
+
+  pub fn created_at(&self) -> DateTime<Utc> {
+    DateTime::from_utc(self.created_at, Utc)
+  }
+
+  pub fn files(&self, conn: &DbConn) -> Result<Vec<TemplateFile>> {
+    Ok(TemplateFile::belonging_to(self)
+      .order_by(paste_template_files::position)
+      .load(&**conn)?)
+  }
+
+  pub fn delete(&self, conn: &DbConn) -> Result<()> {
+    diesel::delete(self).execute(&**conn)?;
+    Ok(())
+  }
+}
+
+#[derive(Insertable)]
+#[table_name = "paste_templates"]
+pub struct NewPasteTemplate {
+  id: PasteTemplateId,
+  user_id: UserId,
+  name: String,
+  paste_name: Option<String>,
+  description: Option<String>,
+  visibility: Visibility,
+  expires_after: Option<i64>,
+}
+
+/// A file pastes made from a template start with.
+#[derive(Debug, Identifiable, Queryable, Associations)]
+#[primary_key(template_id, position)]
+#[belongs_to(PasteTemplate, foreign_key = "template_id")]
+#[table_name = "paste_template_files"]
+pub struct TemplateFile {
+  template_id: PasteTemplateId,
+  position: i32,
+  name: String,
+  highlight_language: Option<Language>,
+  content: String,
+}
+
+impl TemplateFile {
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn highlight_language(&self) -> Option<Language> {
+    self.highlight_language
+  }
+
+  pub fn content(&self) -> &str {
+    &self.content
+  }
+}
+
+#[derive(Insertable)]
+#[table_name = "paste_template_files"]
+pub struct NewTemplateFile {
+  template_id: PasteTemplateId,
+  position: i32,
+  name: String,
+  highlight_language: Option<Language>,
+  content: String,
+}
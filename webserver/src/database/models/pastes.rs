@@ -16,25 +16,27 @@ use crate::{
 };
 
 use super::files::{File as DbFile, NewFile};
-use super::super::schema::{pastes, files};
+use super::super::schema::{pastes, files, visibility_changes};
 use super::users::User;
+use super::visibility_changes::{NewVisibilityChange, VisibilityChange};
 
 use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
 
 use diesel::prelude::*;
 
-use git2::{Signature, Repository, IndexAddOption, Status};
+use git2::{Oid, Signature, Repository, IndexAddOption, Status, Tree};
 
 use rocket::http::Status as HttpStatus;
 
+use serde_json::json;
+
 use sidekiq::{Client as SidekiqClient, Value};
 
 use uuid::Uuid;
 
 use std::{
-  fs::{self, File},
-  io::Write,
-  path::PathBuf,
+  fs,
+  path::{Path, PathBuf},
 };
 
 #[derive(Debug, Identifiable, AsChangeset, Queryable, Associations)]
@@ -121,7 +123,8 @@ impl Paste {
     let changed = !update.name.is_ignore()
       || update.visibility.is_some()
       || !update.description.is_ignore()
-      || !update.expires.is_ignore();
+      || !update.expires.is_ignore()
+      || !update.visibility_change.is_ignore();
     if !changed {
       return Ok(());
     }
@@ -138,28 +141,22 @@ impl Paste {
     if let Some(ref update) = update.visibility {
       self.set_visibility(*update);
     }
+    let previous_expiry = self.expires();
     match update.expires {
-      Update::Set(ref s) => {
-        self.set_expires(Some(*s));
-
-        let timestamp = s.timestamp();
-
-        let user = match self.author_id() {
-          Some(a) => a.to_simple().to_string(),
-          None => "anonymous".to_string(),
-        };
-
-        let job = Job::queue("ExpirePaste", timestamp, vec![
-          Value::Number(timestamp.into()),
-          Value::String(Store::new(config).directory().to_string_lossy().to_string()),
-          Value::String(user),
-          Value::String(self.id().to_simple().to_string()),
-        ]);
-        sidekiq.push(job.into())?;
-      },
+      Update::Set(ref s) => self.set_expires(Some(*s)),
       Update::Remove => self.set_expires(None),
       _ => {},
     }
+    // the jobs for an unchanged expiry are already queued, and queueing them again would send a
+    // second reminder
+    if update.expires.is_set() && self.expires() != previous_expiry {
+      self.schedule_expiry(config, conn, sidekiq)?;
+    }
+    match update.visibility_change {
+      Update::Set(ref change) => self.schedule_visibility(conn, sidekiq, change.visibility, change.at)?,
+      Update::Remove => self.cancel_visibility_change(conn)?,
+      _ => {},
+    }
     diesel::update(pastes::table)
       .filter(pastes::id.eq(self.id))
       .set(&*self)
@@ -168,6 +165,116 @@ impl Paste {
     Ok(())
   }
 
+  /// Queues the jobs for the paste's expiry: an `ExpirePaste` job to delete it and, if the site sends
+  /// them, an `ExpiryReminder` job to email its owner beforehand.
+  ///
+  /// Both jobs compare their timestamp with the paste's expiry when they run, so the jobs for an
+  /// expiry that has since been changed or removed do nothing.
+  pub fn schedule_expiry(&self, config: &Config, conn: &DbConn, sidekiq: &SidekiqClient) -> Result<()> {
+    let expires = match self.expires() {
+      Some(e) => e,
+      None => return Ok(()),
+    };
+    let timestamp = expires.timestamp();
+
+    let user = match self.author_id() {
+      Some(a) => a.to_simple().to_string(),
+      None => "anonymous".to_string(),
+    };
+
+    let job = Job::queue("ExpirePaste", timestamp, vec![
+      Value::Number(timestamp.into()),
+      Value::String(Store::new(config).directory().to_string_lossy().to_string()),
+      Value::String(user),
+      Value::String(self.id().to_simple().to_string()),
+    ]);
+    sidekiq.push(job.into())?;
+
+    if let Some(job) = self.expiry_reminder(config, conn, expires)? {
+      sidekiq.push(job.into())?;
+    }
+
+    Ok(())
+  }
+
+  fn expiry_reminder(&self, config: &Config, conn: &DbConn, expires: DateTime<Utc>) -> Result<Option<Job<'static>>> {
+    let before = match config.read().pastes.expiry_reminder {
+      Some(b) => b,
+      None => return Ok(None),
+    };
+
+    // too close to the expiry for a reminder to be any use
+    let remind_at = expires - before.duration();
+    if remind_at <= Utc::now() {
+      return Ok(None);
+    }
+
+    let user = match self.author_id() {
+      Some(a) => a.get(conn)?,
+      None => None,
+    };
+    let user = match user {
+      Some(u) if u.email_verified() => u,
+      _ => return Ok(None),
+    };
+
+    let email = Job::email(
+      "expiry_reminder.html.tera",
+      json!({
+        "config": &*config.read(),
+        "user": &user,
+        "paste_name": self.name(),
+        "expiry": expires.format("%Y-%m-%d %H:%M").to_string(),
+        "paste_url": format!(
+          "https://{}/p/{}/{}",
+          config.read().general.site_domain,
+          user.username(),
+          self.id.to_simple(),
+        ),
+      }),
+      config.read()._path.as_ref().unwrap(),
+      user.email(),
+      "Your paste is expiring soon",
+    )?;
+
+    Ok(Some(Job::expiry_reminder(remind_at.timestamp(), expires.timestamp(), self.id, email)))
+  }
+
+  /// The visibility change scheduled for the paste, if there is one.
+  pub fn visibility_change(&self, conn: &DbConn) -> Result<Option<VisibilityChange>> {
+    Ok(visibility_changes::table
+      .find(self.id)
+      .first(&**conn)
+      .optional()?)
+  }
+
+  /// Schedules the paste to become `visibility` at `at`, replacing any change already scheduled.
+  pub fn schedule_visibility(&self, conn: &DbConn, sidekiq: &SidekiqClient, visibility: Visibility, at: DateTime<Utc>) -> Result<()> {
+    let change = NewVisibilityChange::new(self.id, visibility, at);
+    diesel::insert_into(visibility_changes::table)
+      .values(&change)
+      .on_conflict(visibility_changes::paste_id)
+      .do_update()
+      .set(&change)
+      .execute(&**conn)?;
+
+    // the job checks the timestamp against the scheduled change, so rescheduled jobs do nothing
+    let timestamp = at.timestamp();
+    let job = Job::queue("ChangeVisibility", timestamp, vec![
+      Value::Number(timestamp.into()),
+      Value::String(self.id().to_simple().to_string()),
+    ]);
+    sidekiq.push(job.into())?;
+
+    Ok(())
+  }
+
+  pub fn cancel_visibility_change(&self, conn: &DbConn) -> Result<()> {
+    diesel::delete(visibility_changes::table.find(self.id)).execute(&**conn)?;
+
+    Ok(())
+  }
+
   pub fn check_access<U>(&self, user: U) -> Option<(HttpStatus, ErrorKind)>
     where U: Into<Option<UserId>>,
   {
@@ -193,6 +300,22 @@ impl Paste {
     self.directory(config).join("files")
   }
 
+  pub fn thumbnails_directory(&self, config: &Config) -> PathBuf {
+    self.directory(config).join("thumbnails")
+  }
+
+  pub fn thumbnail_path(&self, config: &Config, id: FileId) -> PathBuf {
+    self.thumbnails_directory(config).join(format!("{}.webp", id.to_simple()))
+  }
+
+  /// Removes a file's thumbnail so it will be made again from the file's new content.
+  fn remove_thumbnail(&self, config: &Config, id: FileId) -> Result<()> {
+    match fs::remove_file(self.thumbnail_path(config, id)) {
+      Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+      r => Ok(r?),
+    }
+  }
+
   pub fn repo_dirty(&self, config: &Config) -> Result<bool> {
     let repo = Repository::open(self.files_directory(config))?;
     let dirty = repo
@@ -232,9 +355,44 @@ impl Paste {
 
     repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)?;
 
+    let previous = match parent {
+      Some(ref p) => Some(p.tree()?),
+      None => None,
+    };
+    self.share_blobs(config, &repo, &tree, previous.as_ref())?;
+
     Ok(())
   }
 
+  /// Moves the files in `tree` into the shared blob store, then releases any blobs from `previous`
+  /// that the paste's working files no longer use.
+  pub fn share_blobs(&self, config: &Config, repo: &Repository, tree: &Tree, previous: Option<&Tree>) -> Result<()> {
+    let blobs = Store::new(config).blobs();
+
+    blobs.share(repo, tree)?;
+
+    if let Some(previous) = previous {
+      // objects stay in the repository for its history, so only the content can be released
+      let replaced = previous.iter().map(|x| x.id()).filter(|&x| tree.get_id(x).is_none());
+      blobs.release(replaced)?;
+    }
+
+    Ok(())
+  }
+
+  /// Every object in the paste's repository, which includes every blob it has had.
+  fn object_ids(&self, config: &Config) -> Result<Vec<Oid>> {
+    let repo = self.repository(config)?;
+
+    let mut ids = Vec::new();
+    repo.odb()?.foreach(|id| {
+      ids.push(*id);
+      true
+    })?;
+
+    Ok(ids)
+  }
+
   pub fn repository(&self, config: &Config) -> Result<Repository> {
     let repo = Repository::open(self.files_directory(config))?;
 
@@ -264,10 +422,8 @@ impl Paste {
     // check if content is binary for later
     let binary = content.is_binary();
 
-    // create file on the system
-    let file_path = self.files_directory(config).join(id.to_simple().to_string());
-    let mut f = File::create(file_path)?;
-    f.write_all(&content.into_bytes())?;
+    // create file on the system, sharing its content with any other paste that has it
+    self.write_file(config, id, content)?;
 
     let name = name
       .map(|s| s.as_ref().to_string()) // get a String
@@ -281,9 +437,39 @@ impl Paste {
     Ok(db_file)
   }
 
+  /// Replaces the content of a file on the system.
+  ///
+  /// Files are shared between pastes, so they must always be replaced through this rather than
+  /// written to.
+  pub fn write_file(&self, config: &Config, id: FileId, content: Content) -> Result<()> {
+    let path = self.files_directory(config).join(id.to_simple().to_string());
+    Store::new(config).blobs().write(&content.into_bytes(), &path)?;
+
+    self.remove_thumbnail(config, id)
+  }
+
+  /// Moves a completed upload into the paste as a binary file.
+  pub fn attach_file<S: AsRef<str>>(&self, config: &Config, conn: &DbConn, name: Option<S>, lang: Option<Language>, path: &Path) -> Result<DbFile> {
+    let id = FileId(Uuid::new_v4());
+
+    // the upload is in the store, so this is on the same filesystem
+    fs::rename(path, self.files_directory(config).join(id.to_simple().to_string()))?;
+
+    let name = name
+      .map(|s| s.as_ref().to_string())
+      .or_else(|| self.id().next_generic_name(conn).ok())
+      .unwrap_or_else(|| id.to_simple().to_string());
+
+    let new_file = NewFile::new(id, self.id(), name, Some(true), lang, None);
+    let db_file = diesel::insert_into(files::table).values(&new_file).get_result(&**conn)?;
+
+    Ok(db_file)
+  }
+
   pub fn delete_file(&self, config: &Config, conn: &DbConn, id: FileId) -> Result<()> {
     diesel::delete(files::table.filter(files::id.eq(id))).execute(&**conn)?;
     fs::remove_file(self.files_directory(config).join(id.to_simple().to_string()))?;
+    self.remove_thumbnail(config, id)?;
 
     if self.id().is_empty(conn)? {
       self.delete(config, conn)?;
@@ -292,11 +478,77 @@ impl Paste {
     Ok(())
   }
 
+  /// Brings the files table in line with the tree at `HEAD`, after the repository has been changed
+  /// directly from `previous`.
+  ///
+  /// Every entry in the tree must be named after a file id. Files new to the database get a generic
+  /// name, since the repository has no record of names. Anything made from the content of files
+  /// that changed, like thumbnails, is removed to be made again.
+  pub fn sync_files(&mut self, config: &Config, conn: &DbConn, previous: Option<&Tree>) -> Result<()> {
+    let repo = self.repository(config)?;
+    let tree = repo.head()?.peel_to_tree()?;
+
+    let existing = self.id().files(conn)?;
+
+    for file in &existing {
+      let name = file.id().to_simple().to_string();
+      let old = previous.and_then(|x| x.get_name(&name)).map(|x| x.id());
+      let new = tree.get_name(&name).map(|x| x.id());
+      if old.is_none() || old != new {
+        self.remove_thumbnail(config, file.id())?;
+      }
+    }
+
+    // remove any files that are no longer in the tree
+    for file in &existing {
+      if tree.get_name(&file.id().to_simple().to_string()).is_none() {
+        diesel::delete(files::table.filter(files::id.eq(file.id()))).execute(&**conn)?;
+      }
+    }
+
+    for entry in tree.iter() {
+      let id = match entry.name().and_then(|x| Uuid::parse_str(x).ok()) {
+        Some(u) => FileId(u),
+        None => continue,
+      };
+
+      // text files are read back as utf-8, so anything else has to be marked binary
+      let blob = repo.find_blob(entry.id())?;
+      let binary = std::str::from_utf8(blob.content()).is_err();
+
+      match existing.iter().find(|x| x.id() == id) {
+        Some(file) => if file.is_binary() != Some(binary) {
+          diesel::update(files::table.filter(files::id.eq(id)))
+            .set(files::is_binary.eq(Some(binary)))
+            .execute(&**conn)?;
+        },
+        None => {
+          let name = self.id().next_generic_name(conn)?;
+          let new_file = NewFile::new(id, self.id(), name, Some(binary), None, None);
+          diesel::insert_into(files::table).values(&new_file).execute(&**conn)?;
+        },
+      }
+    }
+
+    self.updated_at = Some(Utc::now().naive_utc());
+    diesel::update(pastes::table)
+      .filter(pastes::id.eq(self.id))
+      .set(&*self)
+      .execute(&**conn)?;
+
+    Ok(())
+  }
+
   pub fn delete(&self, config: &Config, conn: &DbConn) -> Result<()> {
+    // a broken repository shouldn't stop the paste being deleted, though its blobs will wait for a sweep
+    let object_ids = self.object_ids(config).unwrap_or_default();
+
     // database will cascade and delete all files and deletion keys, as well
     diesel::delete(pastes::table.filter(pastes::id.eq(self.id()))).execute(&**conn)?;
     // remove from system
     fs::remove_dir_all(self.directory(config))?;
+    // drop any blobs only this paste was using
+    Store::new(config).blobs().release(object_ids)?;
 
     Ok(())
   }
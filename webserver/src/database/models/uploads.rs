@@ -0,0 +1,128 @@
+use crate::{
+  config::Config,
+  errors::*,
+  models::id::{UploadId, UserId},
+  sidekiq::Job,
+  store::Store,
+};
+
+use super::users::User;
+use super::super::DbConn;
+use super::super::schema::uploads;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+
+use diesel::prelude::*;
+
+use sidekiq::Value;
+
+use uuid::Uuid;
+
+use std::{
+  fs::{self, OpenOptions},
+  path::PathBuf,
+};
+
+/// A resumable upload in progress, whose content is written to a file in the store chunk by chunk.
+#[derive(Debug, Identifiable, Queryable, Associations)]
+#[belongs_to(User)]
+pub struct Upload {
+  id: UploadId,
+  user_id: UserId,
+  length: i64,
+  created_at: NaiveDateTime,
+  expiry: NaiveDateTime,
+}
+
+impl Upload {
+  pub fn id(&self) -> UploadId {
+    self.id
+  }
+
+  pub fn user_id(&self) -> UserId {
+    self.user_id
+  }
+
+  /// The size the upload will be once complete, in bytes.
+  pub fn length(&self) -> u64 {
+    self.length as u64
+  }
+
+  pub fn created_at(&self) -> NaiveDateTime {
+    self.created_at
+  }
+
+  pub fn expiry(&self) -> NaiveDateTime {
+    self.expiry
+  }
+
+  pub fn is_expired(&self) -> bool {
+    self.expiry < Utc::now().naive_utc()
+  }
+
+  /// The directory holding the upload, removed by a `DeleteDirectory` job once the upload expires.
+  pub fn directory(&self, config: &Config) -> PathBuf {
+    Store::new(config)
+      .directory()
+      .join("uploads")
+      .join(self.id.to_simple().to_string())
+  }
+
+  pub fn path(&self, config: &Config) -> PathBuf {
+    self.directory(config).join("data")
+  }
+
+  /// How many bytes have been received so far.
+  pub fn offset(&self, config: &Config) -> Result<u64> {
+    match fs::metadata(self.path(config)) {
+      Ok(m) => Ok(m.len()),
+      Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  pub fn is_complete(&self, config: &Config) -> Result<bool> {
+    Ok(self.offset(config)? == self.length())
+  }
+
+  /// Creates the upload's empty file and schedules its removal for when it expires.
+  pub fn create_file(&self, config: &Config) -> Result<Job<'static>> {
+    fs::create_dir_all(self.directory(config))?;
+    OpenOptions::new().create(true).write(true).open(self.path(config))?;
+
+    Ok(Job::queue("DeleteDirectory", self.expiry.timestamp(), vec![
+      Value::String(self.directory(config).to_string_lossy().to_string()),
+    ]))
+  }
+
+  pub fn delete(&self, config: &Config, conn: &DbConn) -> Result<()> {
+    diesel::delete(self).execute(&**conn)?;
+
+    let directory = self.directory(config);
+    if directory.exists() {
+      fs::remove_dir_all(directory)?;
+    }
+
+    Ok(())
+  }
+}
+
+#[derive(Insertable)]
+#[table_name = "uploads"]
+pub struct NewUpload {
+  pub id: UploadId,
+  user_id: UserId,
+  length: i64,
+  expiry: NaiveDateTime,
+}
+
+impl NewUpload {
+  pub fn new(user_id: UserId, length: u64) -> Self {
+    NewUpload {
+      id: UploadId(Uuid::new_v4()),
+      user_id,
+      length: length as i64,
+      expiry: Utc::now().naive_utc() + Duration::days(1),
+    }
+  }
+}
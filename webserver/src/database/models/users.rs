@@ -8,8 +8,10 @@ use crate::{
 
 use super::api_keys::{ApiKey, NewApiKey};
 use super::email_verifications::{EmailVerification, NewEmailVerification};
+use super::paste_templates::PasteTemplate;
+use super::webauthn_credentials::WebauthnCredential;
 use super::super::DbConn;
-use super::super::schema::{users, api_keys, email_verifications};
+use super::super::schema::{users, api_keys, email_verifications, paste_templates, webauthn_credentials};
 
 use chrono::NaiveDateTime;
 
@@ -157,6 +159,15 @@ impl User {
     Ok(keys)
   }
 
+  pub fn templates(&self, conn: &DbConn) -> Result<Vec<PasteTemplate>> {
+    let templates = paste_templates::table
+      .filter(paste_templates::user_id.eq(self.id))
+      .order_by(paste_templates::name)
+      .load(&**conn)?;
+
+    Ok(templates)
+  }
+
   pub fn create_key(&self, conn: &DbConn, name: String) -> Result<ApiKey> {
     let new_key = NewApiKey::new(name, ApiKeyId(Uuid::new_v4()), self.id);
     let key = diesel::insert_into(api_keys::table)
@@ -172,6 +183,29 @@ impl User {
     Ok(())
   }
 
+  pub fn webauthn_credentials(&self, conn: &DbConn) -> Result<Vec<WebauthnCredential>> {
+    let creds = webauthn_credentials::table
+      .filter(webauthn_credentials::user_id.eq(self.id))
+      .order_by(webauthn_credentials::created_at.asc())
+      .load(&**conn)?;
+
+    Ok(creds)
+  }
+
+  /// Whether logging in requires a second factor, either a TOTP code or a security key.
+  pub fn second_factor_enabled(&self, conn: &DbConn) -> Result<bool> {
+    if self.tfa_enabled {
+      return Ok(true);
+    }
+
+    let keys: i64 = webauthn_credentials::table
+      .filter(webauthn_credentials::user_id.eq(self.id))
+      .count()
+      .get_result(&**conn)?;
+
+    Ok(keys > 0)
+  }
+
   pub fn delete(&self, conn: &DbConn) -> Result<()> {
     diesel::delete(users::table)
       .filter(users::id.eq(self.id))
@@ -0,0 +1,52 @@
+use crate::models::{id::PasteId, paste::Visibility};
+
+use super::pastes::Paste;
+use super::super::schema::visibility_changes;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// A change to a paste's visibility, made by a `ChangeVisibility` job once it's due.
+///
+/// Pastes have at most one scheduled change. Rescheduling replaces it, and the job for the old time
+/// does nothing when it runs.
+#[derive(Debug, Identifiable, Queryable, Associations)]
+#[primary_key(paste_id)]
+#[belongs_to(Paste)]
+pub struct VisibilityChange {
+  paste_id: PasteId,
+  visibility: Visibility,
+  scheduled_for: NaiveDateTime,
+}
+
+impl VisibilityChange {
+  pub fn paste_id(&self) -> PasteId {
+    self.paste_id
+  }
+
+  /// The visibility the paste will have.
+  pub fn visibility(&self) -> Visibility {
+    self.visibility
+  }
+
+  pub fn scheduled_for(&self) -> DateTime<Utc> {
+    DateTime::from_utc(self.scheduled_for, Utc)
+  }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[table_name = "visibility_changes"]
+pub struct NewVisibilityChange {
+  paste_id: PasteId,
+  visibility: Visibility,
+  scheduled_for: NaiveDateTime,
+}
+
+impl NewVisibilityChange {
+  pub fn new(paste_id: PasteId, visibility: Visibility, scheduled_for: DateTime<Utc>) -> Self {
+    NewVisibilityChange {
+      paste_id,
+      visibility,
+      scheduled_for: scheduled_for.naive_utc(),
+    }
+  }
+}
@@ -0,0 +1,95 @@
+use crate::{
+  errors::*,
+  models::id::{UserId, WebauthnCredentialId},
+};
+
+use super::users::User;
+use super::super::DbConn;
+use super::super::schema::webauthn_credentials;
+
+use chrono::NaiveDateTime;
+
+use diesel::prelude::*;
+
+#[derive(Debug, AsChangeset, Identifiable, Queryable, Associations)]
+#[belongs_to(User)]
+pub struct WebauthnCredential {
+  id: WebauthnCredentialId,
+  user_id: UserId,
+  name: String,
+  credential_id: Vec<u8>,
+  public_key: Vec<u8>,
+  sign_count: i64,
+  created_at: NaiveDateTime,
+  last_used_at: Option<NaiveDateTime>,
+}
+
+impl WebauthnCredential {
+  pub fn id(&self) -> WebauthnCredentialId {
+    self.id
+  }
+
+  pub fn user_id(&self) -> UserId {
+    self.user_id
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub fn set_name(&mut self, name: String) {
+    self.name = name;
+  }
+
+  pub fn credential_id(&self) -> &[u8] {
+    &self.credential_id
+  }
+
+  /// The COSE-encoded public key of the credential.
+  pub fn public_key(&self) -> &[u8] {
+    &self.public_key
+  }
+
+  pub fn sign_count(&self) -> i64 {
+    self.sign_count
+  }
+
+  pub fn created_at(&self) -> NaiveDateTime {
+    self.created_at
+  }
+
+  pub fn last_used_at(&self) -> Option<NaiveDateTime> {
+    self.last_used_at
+  }
+
+  pub fn set_used(&mut self, sign_count: i64) {
+    self.sign_count = sign_count;
+    self.last_used_at = Some(chrono::Utc::now().naive_utc());
+  }
+
+  pub fn update(&self, conn: &DbConn) -> Result<()> {
+    diesel::update(webauthn_credentials::table)
+      .filter(webauthn_credentials::id.eq(self.id))
+      .set(self)
+      .execute(&**conn)?;
+
+    Ok(())
+  }
+}
+
+#[derive(Insertable)]
+#[table_name = "webauthn_credentials"]
+pub struct NewWebauthnCredential {
+  id: WebauthnCredentialId,
+  user_id: UserId,
+  name: String,
+  credential_id: Vec<u8>,
+  public_key: Vec<u8>,
+  sign_count: i64,
+}
+
+impl NewWebauthnCredential {
+  pub fn new(id: WebauthnCredentialId, user_id: UserId, name: String, credential_id: Vec<u8>, public_key: Vec<u8>, sign_count: i64) -> Self {
+    NewWebauthnCredential { id, user_id, name, credential_id, public_key, sign_count }
+  }
+}
@@ -13,6 +13,17 @@ table! {
     }
 }
 
+table! {
+    data_exports (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        secret -> Text,
+        include_history -> Bool,
+        created_at -> Timestamp,
+        expiry -> Timestamp,
+    }
+}
+
 table! {
     deletion_keys (paste_id) {
         key -> Text,
@@ -42,6 +53,13 @@ table! {
     }
 }
 
+table! {
+    ldap_identities (dn) {
+        dn -> Text,
+        user_id -> Uuid,
+    }
+}
+
 table! {
     login_attempts (addr) {
         addr -> Cidr,
@@ -50,6 +68,14 @@ table! {
     }
 }
 
+table! {
+    oidc_identities (issuer, subject) {
+        issuer -> Text,
+        subject -> Text,
+        user_id -> Uuid,
+    }
+}
+
 table! {
     password_reset_attempts (addr) {
         addr -> Cidr,
@@ -67,6 +93,29 @@ table! {
     }
 }
 
+table! {
+    paste_template_files (template_id, position) {
+        template_id -> Uuid,
+        position -> Int4,
+        name -> Text,
+        highlight_language -> Nullable<Text>,
+        content -> Text,
+    }
+}
+
+table! {
+    paste_templates (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        paste_name -> Nullable<Text>,
+        description -> Nullable<Text>,
+        visibility -> Int2,
+        expires_after -> Nullable<Int8>,
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     pastes (id) {
         id -> Uuid,
@@ -80,6 +129,16 @@ table! {
     }
 }
 
+table! {
+    uploads (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        length -> Int8,
+        created_at -> Timestamp,
+        expiry -> Timestamp,
+    }
+}
+
 table! {
     users (id) {
         id -> Uuid,
@@ -95,23 +154,60 @@ table! {
     }
 }
 
+table! {
+    visibility_changes (paste_id) {
+        paste_id -> Uuid,
+        visibility -> Int2,
+        scheduled_for -> Timestamp,
+    }
+}
+
+table! {
+    webauthn_credentials (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        credential_id -> Bytea,
+        public_key -> Bytea,
+        sign_count -> Int8,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+    }
+}
+
 joinable!(api_keys -> users (user_id));
 joinable!(backup_codes -> users (user_id));
+joinable!(data_exports -> users (user_id));
 joinable!(deletion_keys -> pastes (paste_id));
 joinable!(email_verifications -> users (user_id));
 joinable!(files -> pastes (paste_id));
+joinable!(ldap_identities -> users (user_id));
+joinable!(oidc_identities -> users (user_id));
 joinable!(password_resets -> users (user_id));
+joinable!(paste_template_files -> paste_templates (template_id));
+joinable!(paste_templates -> users (user_id));
 joinable!(pastes -> users (author_id));
+joinable!(uploads -> users (user_id));
+joinable!(visibility_changes -> pastes (paste_id));
+joinable!(webauthn_credentials -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
     api_keys,
     backup_codes,
+    data_exports,
     deletion_keys,
     email_verifications,
     files,
+    ldap_identities,
     login_attempts,
+    oidc_identities,
     password_reset_attempts,
     password_resets,
+    paste_template_files,
+    paste_templates,
     pastes,
+    uploads,
     users,
+    visibility_changes,
+    webauthn_credentials,
 );
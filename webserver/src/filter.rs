@@ -43,9 +43,8 @@ impl Filter {
           .as_ref()
           .map(|desc| self.kind.matches(&desc))
           .unwrap_or(false),
-        FilterSearch::FileName => paste.files
-          .iter()
-          .flat_map(|file| file.name.as_ref())
+        FilterSearch::FileName => paste.file_names()
+          .flatten()
           .any(|name| self.kind.matches(&name)),
         FilterSearch::Content => paste.files
           .iter()
@@ -108,7 +108,7 @@ fn main() {
     .manage(redis_store::init_pool())
     .manage(redis_store::init_sidekiq())
     .manage(config)
-    .manage(reqwest::Client::new())
+    .manage(reqwest::blocking::Client::new())
     .manage(localisation)
     .attach(fairings::Csp)
     .attach(fairings::SecurityHeaders)
@@ -127,16 +127,27 @@ fn main() {
     ])
     .mount("/", routes![
       routes::web::index::get,
+      routes::web::index::template,
 
       routes::web::about::get,
 
       routes::web::credits::get,
 
+      routes::web::feeds::all_atom,
+      routes::web::feeds::all_rss,
+      routes::web::feeds::user_atom,
+      routes::web::feeds::user_rss,
+
+      routes::web::oembed::get,
+
       routes::web::auth::login::get,
       routes::web::auth::login::post,
       routes::web::auth::login::tfa,
       routes::web::auth::login::tfa_post,
 
+      routes::web::auth::oidc::get,
+      routes::web::auth::oidc::callback,
+
       routes::web::auth::logout::post,
 
       routes::web::auth::register::get,
@@ -148,17 +159,31 @@ fn main() {
 
       routes::web::pastes::get::delete,
 
+      routes::web::pastes::embed::get,
+
       routes::web::pastes::files::raw::get,
+      routes::web::pastes::files::thumbnail::get,
+      routes::web::pastes::files::csv::get,
+
+      routes::web::pastes::archive::zip,
+      routes::web::pastes::archive::tar_gz,
+
+      routes::web::pastes::git::info_refs,
+      routes::web::pastes::git::upload_pack,
+      routes::web::pastes::git::receive_pack,
 
       routes::web::pastes::revisions::get,
 
       routes::web::pastes::get::edit,
 
       routes::web::pastes::post::post,
+      routes::web::pastes::post::post_multipart,
 
       routes::web::pastes::delete::delete,
       routes::web::pastes::delete::ids,
+      routes::web::pastes::expiry::post,
       routes::web::pastes::patch::patch,
+      routes::web::pastes::template::post,
 
       routes::web::account::index::get,
       routes::web::account::index::patch,
@@ -168,6 +193,9 @@ fn main() {
       routes::web::account::keys::post,
       routes::web::account::keys::delete,
 
+      routes::web::account::templates::get,
+      routes::web::account::templates::delete,
+
       routes::web::account::two_factor::get,
       routes::web::account::two_factor::enable_get,
       routes::web::account::two_factor::validate,
@@ -175,6 +203,18 @@ fn main() {
       routes::web::account::two_factor::disable_post,
       routes::web::account::two_factor::new_secret,
       routes::web::account::two_factor::new_backup_codes,
+      routes::web::account::two_factor::add_key_get,
+      routes::web::account::two_factor::add_key_post,
+      routes::web::account::two_factor::key_patch,
+      routes::web::account::two_factor::key_delete,
+
+      routes::web::account::sessions::get,
+      routes::web::account::sessions::delete,
+      routes::web::account::sessions::delete_all,
+
+      routes::web::account::export::get,
+      routes::web::account::export::post,
+      routes::web::account::export::download,
 
       routes::web::account::delete::get,
       routes::web::account::delete::delete,
@@ -192,7 +232,8 @@ fn main() {
       routes::web::account::adminify::get,
 
       routes::web::admin::index::get,
-      // routes::web::admin::maintenance::get,
+      routes::web::admin::maintenance::get,
+      routes::web::admin::maintenance::dedupe,
 
       routes::web::admin::config::get,
       routes::web::admin::config::post,
@@ -206,6 +247,7 @@ fn main() {
       routes::web::admin::users::delete,
       routes::web::admin::users::promote,
       routes::web::admin::users::demote,
+      routes::web::admin::users::sign_out,
 
       routes::web::users::get::get,
     ])
@@ -217,6 +259,8 @@ fn main() {
 
       routes::api::pastes::post::post_json,
       routes::api::pastes::post::post_multipart,
+      routes::api::pastes::raw::post,
+      routes::api::pastes::raw::put,
       routes::api::pastes::delete::delete,
       routes::api::pastes::delete::ids,
       routes::api::pastes::get::get,
@@ -231,6 +275,9 @@ fn main() {
       routes::api::pastes::files::individual::patch::patch,
 
       routes::api::pastes::files::individual::raw::get::get,
+
+      routes::api::pastes::archive::zip,
+      routes::api::pastes::archive::tar_gz,
     ])
     .mount("/api/v0/pastes", routes![
       routes::api::pastes::get::get_all,
@@ -251,6 +298,13 @@ fn main() {
 
       routes::api::pastes::files::individual::raw::get::get,
     ])
+    .mount("/api/v1/uploads", routes![
+      routes::api::uploads::options::options,
+      routes::api::uploads::post::post,
+      routes::api::uploads::head::head,
+      routes::api::uploads::patch::patch,
+      routes::api::uploads::delete::delete,
+    ])
     .mount("/api/v1/users", routes![
       routes::api::users::get::get,
     ])
@@ -97,19 +97,27 @@ macro_rules! uuid_wrapper {
 }
 
 pub mod api_key;
+pub mod data_export;
 pub mod email_verification;
 pub mod file;
 pub mod paste;
+pub mod paste_template;
 pub mod password_reset;
 pub mod session;
+pub mod upload;
 pub mod user;
+pub mod webauthn_credential;
 
 pub use self::{
   api_key::ApiKeyId,
+  data_export::DataExportId,
   email_verification::EmailVerificationId,
   file::FileId,
   paste::PasteId,
+  paste_template::PasteTemplateId,
   password_reset::PasswordResetId,
   session::SessionId,
+  upload::UploadId,
   user::UserId,
+  webauthn_credential::WebauthnCredentialId,
 };
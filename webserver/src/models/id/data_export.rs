@@ -0,0 +1 @@
+uuid_wrapper!(DataExportId);
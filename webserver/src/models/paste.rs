@@ -1,4 +1,7 @@
-use crate::utils::Language;
+use crate::{
+  models::id::UploadId,
+  utils::{Language, RelativeDuration},
+};
 
 use chrono::{DateTime, Utc};
 
@@ -28,6 +31,8 @@ pub struct Paste {
   pub metadata: Metadata,
   #[serde(default, skip_serializing_if = "Vec::is_empty")]
   pub files: Vec<PasteFile>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub uploads: Vec<UploadFile>,
 }
 
 /// Metadata describing a [`Paste`].
@@ -43,6 +48,9 @@ pub struct Metadata {
   pub updated_at: Option<DateTime<Utc>>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub expires: Option<DateTime<Utc>>,
+  /// How long from now the paste should last, instead of giving `expires`.
+  #[serde(default, skip_serializing)]
+  pub expires_in: Option<RelativeDuration>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -185,6 +193,14 @@ pub struct PasteFile {
   pub content: Content,
 }
 
+/// A completed resumable upload to add to a [`Paste`] as a binary file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadFile {
+  pub id: UploadId,
+  pub name: Option<CountedText>,
+  pub highlight_language: Option<Language>,
+}
+
 /// The content of a [`PasteFile`].
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -201,27 +217,37 @@ pub enum Content {
   /// Base64-encoded xz data
   #[serde(with = "xz_base64_serde")]
   Xz(Vec<u8>),
+  /// Base64-encoded zstd data
+  #[serde(with = "zstd_base64_serde")]
+  Zstd(Vec<u8>),
+  /// Base64-encoded brotli data
+  #[serde(with = "brotli_base64_serde")]
+  Brotli(Vec<u8>),
 }
 
 impl Content {
   pub fn into_bytes(self) -> Vec<u8> {
     match self {
       Content::Text(s) => s.into_bytes(),
-      Content::Base64(b) | Content::Gzip(b) | Content::Xz(b) => b,
+      Content::Base64(b) | Content::Gzip(b) | Content::Xz(b) | Content::Zstd(b) | Content::Brotli(b) => b,
     }
   }
 
   pub fn is_binary(&self) -> bool {
     // TODO: allow this to be specified in the paste?
     match *self {
-      Content::Base64(_) | Content::Gzip(_) | Content::Xz(_) => true,
+      Content::Base64(_) | Content::Gzip(_) | Content::Xz(_) | Content::Zstd(_) | Content::Brotli(_) => true,
       _ => false,
     }
   }
 
   pub fn is_empty(&self) -> bool {
     match *self {
-      Content::Base64(ref b) | Content::Gzip(ref b) | Content::Xz(ref b) => b.is_empty(),
+      Content::Base64(ref b)
+        | Content::Gzip(ref b)
+        | Content::Xz(ref b)
+        | Content::Zstd(ref b)
+        | Content::Brotli(ref b) => b.is_empty(),
       Content::Text(ref t) => t.is_empty(),
     }
   }
@@ -340,3 +366,64 @@ mod xz_base64_serde {
     Ok(decoded_bytes)
   }
 }
+
+mod zstd_base64_serde {
+  use super::base64_serde::Base64Visitor;
+
+  use data_encoding::BASE64;
+
+  use serde::{
+    de::{self, Deserializer},
+    ser::{self, Serializer},
+  };
+
+  pub fn serialize<T, S>(data: &T, ser: S) -> Result<S::Ok, S::Error>
+    where S: Serializer,
+          T: AsRef<[u8]> + ?Sized,
+  {
+    let encoded_bytes = zstd::stream::encode_all(data.as_ref(), 19).map_err(ser::Error::custom)?;
+    ser.serialize_str(&BASE64.encode(&encoded_bytes))
+  }
+
+  pub fn deserialize<'de, D>(des: D) -> Result<Vec<u8>, D::Error>
+    where D: Deserializer<'de>,
+  {
+    let bytes = des.deserialize_string(Base64Visitor)?;
+    zstd::stream::decode_all(bytes.as_slice()).map_err(de::Error::custom)
+  }
+}
+
+mod brotli_base64_serde {
+  use super::base64_serde::Base64Visitor;
+
+  use data_encoding::BASE64;
+
+  use brotli::{CompressorWriter, Decompressor};
+
+  use serde::{
+    de::{self, Deserializer},
+    ser::{self, Serializer},
+  };
+
+  use std::io::{Read, Write};
+
+  pub fn serialize<T, S>(data: &T, ser: S) -> Result<S::Ok, S::Error>
+    where S: Serializer,
+          T: AsRef<[u8]> + ?Sized,
+  {
+    let mut encoder = CompressorWriter::new(Vec::new(), 4096, 11, 22);
+    encoder.write_all(data.as_ref()).map_err(ser::Error::custom)?;
+    // dropping the writer or taking its inner value finishes the stream
+    ser.serialize_str(&BASE64.encode(&encoder.into_inner()))
+  }
+
+  pub fn deserialize<'de, D>(des: D) -> Result<Vec<u8>, D::Error>
+    where D: Deserializer<'de>,
+  {
+    let bytes = des.deserialize_string(Base64Visitor)?;
+    let mut decoder = Decompressor::new(bytes.as_slice(), 4096);
+    let mut decoded_bytes = Vec::new();
+    decoder.read_to_end(&mut decoded_bytes).map_err(de::Error::custom)?;
+    Ok(decoded_bytes)
+  }
+}
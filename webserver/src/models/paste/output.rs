@@ -1,7 +1,7 @@
 use crate::{
   database::models::deletion_keys::SecretDeletionKey,
   models::id::{PasteId, FileId, UserId},
-  utils::Language,
+  utils::{structured::Validation, Language},
 };
 
 use super::{Paste, Metadata, Visibility, Content};
@@ -46,10 +46,12 @@ impl Output {
           description: desc.map(|x| x.as_ref().to_string().into()),
           visibility: vis,
           expires,
+          expires_in: None,
           created_at: Some(created_at),
           updated_at,
         },
         files: Vec::new(),
+        uploads: Vec::new(),
       },
       deletion_key: deletion_key.map(Into::into),
       files: files.into_iter().collect(),
@@ -64,6 +66,8 @@ pub struct OutputFile {
   pub highlight_language: Option<&'static str>,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub content: Option<Content>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub validation: Option<Validation>,
 }
 
 impl OutputFile {
@@ -73,8 +77,20 @@ impl OutputFile {
       name: name.map(Into::into),
       highlight_language: language.map(|x| x.hljs()),
       content,
+      validation: None,
     }
   }
+
+  /// Checks whether the file is valid, if it's a JSON, YAML or TOML file with its content included.
+  pub fn validate(&mut self) {
+    let (name, content) = match (&self.name, &self.content) {
+      (Some(name), Some(Content::Text(content))) => (name, content),
+      _ => return,
+    };
+    let language = self.highlight_language.and_then(Language::from_hljs);
+
+    self.validation = Validation::check(name, language, content);
+  }
 }
 
 #[derive(Debug, Clone, Serialize)]
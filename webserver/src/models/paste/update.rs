@@ -5,7 +5,7 @@ use crate::{
     id::FileId,
     paste::{Content, CountedText, Visibility},
   },
-  utils::Language,
+  utils::{Language, RelativeDuration},
 };
 
 use chrono::{DateTime, Utc};
@@ -121,6 +121,20 @@ pub struct MetadataUpdate {
   // double option because expires can be removed, changed, or left alone
   #[serde(default)]
   pub expires: Update<DateTime<Utc>>,
+  // single option because a relative expiry can only be given or not, and is resolved into
+  // `expires` before the update is made
+  #[serde(default)]
+  pub expires_in: Option<RelativeDuration>,
+  // double option because a scheduled visibility change can be cancelled, changed, or left alone
+  #[serde(default)]
+  pub visibility_change: Update<ScheduledVisibility>,
+}
+
+/// A visibility for a paste to take on at a later time.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ScheduledVisibility {
+  pub visibility: Visibility,
+  pub at: DateTime<Utc>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,6 +42,7 @@ pub enum ErrorKind {
   MissingPaste,
   MissingFile,
   MissingUser,
+  MissingUpload,
   BadApiKey(#[serde(skip_serializing_if = "Option::is_none")] Option<String>),
   NotAllowed,
   MustBeAuthed,
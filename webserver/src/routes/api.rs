@@ -1,2 +1,3 @@
 pub mod pastes;
+pub mod uploads;
 pub mod users;
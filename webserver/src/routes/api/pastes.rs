@@ -1,5 +1,7 @@
+pub mod archive;
 pub mod delete;
 pub mod files;
 pub mod get;
 pub mod patch;
 pub mod post;
+pub mod raw;
@@ -0,0 +1,64 @@
+use crate::{
+  config::Config,
+  database::DbConn,
+  errors::*,
+  models::{
+    id::PasteId,
+    status::{Status, ErrorKind},
+  },
+  routes::{AddHeaders, OptionalUser},
+  utils::archive::{Archive, ChannelReader, Format},
+};
+
+use rocket::{
+  http::Status as HttpStatus,
+  request::State,
+  response::{
+    Stream,
+    status::Custom,
+  },
+};
+
+use rocket_contrib::json::Json;
+
+#[get("/<paste_id>/archive.zip?<revision>")]
+pub fn zip(paste_id: PasteId, revision: Option<String>, user: OptionalUser, conn: DbConn, config: State<Config>) -> Result<ArchiveOrError> {
+  archive(paste_id, revision, Format::Zip, user, conn, &*config)
+}
+
+#[get("/<paste_id>/archive.tar.gz?<revision>")]
+pub fn tar_gz(paste_id: PasteId, revision: Option<String>, user: OptionalUser, conn: DbConn, config: State<Config>) -> Result<ArchiveOrError> {
+  archive(paste_id, revision, Format::TarGz, user, conn, &*config)
+}
+
+fn archive(paste_id: PasteId, revision: Option<String>, format: Format, user: OptionalUser, conn: DbConn, config: &Config) -> Result<ArchiveOrError> {
+  let paste = match paste_id.get(&conn)? {
+    Some(paste) => paste,
+    None => return Ok(ArchiveOrError::Error(Status::show_error(HttpStatus::NotFound, ErrorKind::MissingPaste))),
+  };
+
+  if let Some((status, kind)) = paste.check_access(user.as_ref().map(|x| x.id())) {
+    return Ok(ArchiveOrError::Error(Status::show_error(status, kind)));
+  }
+
+  let archive = match Archive::new(config, &conn, &paste, revision.as_ref().map(|x| x.as_str()))? {
+    Some(a) => a,
+    None => return Ok(ArchiveOrError::Error(Status::show_error(HttpStatus::NotFound, ErrorKind::BadParameters(Some("revision does not exist".into()))))),
+  };
+
+  let disposition = format!("attachment; filename=\"{}\"", archive.file_name(format));
+
+  Ok(ArchiveOrError::Archive(AddHeaders::new(
+    archive.stream(format),
+    vec![
+      ("Content-Type".into(), format.content_type().into()),
+      ("Content-Disposition".into(), disposition),
+    ],
+  )))
+}
+
+#[derive(Responder)]
+pub enum ArchiveOrError {
+  Archive(AddHeaders<Stream<ChannelReader>>),
+  Error(Custom<Json<Status<()>>>),
+}
@@ -22,7 +22,10 @@ pub fn get(paste_id: PasteId, user: OptionalUser, conn: DbConn, config: State<Co
     return Ok(Status::show_error(status, kind));
   }
 
-  let files: Vec<OutputFile> = paste_id.output_files(&*config, &conn, &paste, true)?;
+  let mut files: Vec<OutputFile> = paste_id.output_files(&*config, &conn, &paste, true)?;
+  for file in &mut files {
+    file.validate();
+  }
 
   Ok(Status::show_success(HttpStatus::Ok, files))
 }
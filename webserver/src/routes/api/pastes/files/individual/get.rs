@@ -27,7 +27,8 @@ pub fn get(paste_id: PasteId, file_id: FileId, user: OptionalUser, conn: DbConn,
     None => return Ok(Status::show_error(HttpStatus::NotFound, ErrorKind::MissingFile)),
   };
 
-  let pf = db_file.as_output_file(&*config, true, &paste)?;
+  let mut pf = db_file.as_output_file(&*config, true, &paste)?;
+  pf.validate();
 
   Ok(Status::show_success(HttpStatus::Ok, pf))
 }
@@ -18,8 +18,6 @@ use rocket::{http::Status as HttpStatus, State};
 
 use rocket_contrib::json::{Json, JsonError};
 
-use std::{fs::OpenOptions, io::Write};
-
 type UpdateResult<'a> = ::std::result::Result<Json<PasteFileUpdate>, JsonError<'a>>;
 
 #[patch("/<paste_id>/files/<file_id>", format = "application/json", data = "<file>")]
@@ -53,7 +51,6 @@ pub fn patch(paste_id: PasteId, file_id: FileId, file: UpdateResult<'a>, config:
   let mut db_changed = false;
   // TODO: this needs much refactor love
   // update files and database if necessary
-  let files_directory = paste.files_directory(&*config);
 
   let mut db_files = paste_id.files(&conn)?;
   {
@@ -85,11 +82,7 @@ pub fn patch(paste_id: PasteId, file_id: FileId, file: UpdateResult<'a>, config:
     match file.content {
       // replacing contents
       Update::Set(content) => {
-        let mut f = OpenOptions::new()
-          .write(true)
-          .truncate(true)
-          .open(files_directory.join(db_file.id().to_simple().to_string()))?;
-        f.write_all(&content.into_bytes())?;
+        paste.write_file(&*config, db_file.id(), content)?;
         // FIXME: set is_binary field
       },
       // deleting file
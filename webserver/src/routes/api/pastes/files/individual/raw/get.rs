@@ -6,22 +6,20 @@ use crate::{
     id::{PasteId, FileId},
     status::{Status, ErrorKind},
   },
-  routes::OptionalUser,
+  routes::{AddHeaders, OptionalUser},
+  utils::{AcceptEncoding, raw_file::{self, RawFile}},
 };
 
 use rocket::{
   http::Status as HttpStatus,
   request::State,
-  response::{
-    NamedFile,
-    status::Custom,
-  },
+  response::status::Custom,
 };
 
 use rocket_contrib::json::Json;
 
 #[get("/<paste_id>/files/<file_id>/raw")]
-pub fn get(paste_id: PasteId, file_id: FileId, user: OptionalUser, conn: DbConn, config: State<Config>,) -> Result<FileOrError> {
+pub fn get(paste_id: PasteId, file_id: FileId, accept: AcceptEncoding, user: OptionalUser, conn: DbConn, config: State<Config>,) -> Result<FileOrError> {
   let paste = match paste_id.get(&conn)? {
     Some(paste) => paste,
     None => return Ok(FileOrError::Error(Status::show_error(HttpStatus::NotFound, ErrorKind::MissingPaste))),
@@ -31,14 +29,34 @@ pub fn get(paste_id: PasteId, file_id: FileId, user: OptionalUser, conn: DbConn,
     return Ok(FileOrError::Error(Status::show_error(status, kind)));
   }
 
-  let path = paste.files_directory(&*config).join(file_id.to_simple().to_string());
+  let file = match paste_id.file(&conn, file_id)? {
+    Some(f) => f,
+    None => return Ok(FileOrError::Error(Status::show_error(HttpStatus::NotFound, ErrorKind::MissingFile))),
+  };
+
+  let path = file.path(&*config, &paste);
+
+  let headers = if file.is_binary() == Some(true) {
+    let (content_type, inline) = raw_file::content_type(&path, file.name())?;
+    let mut headers = vec![("Content-Type".into(), content_type)];
+    if !inline {
+      headers.push(("Content-Disposition".into(), "attachment".into()));
+    }
+    headers
+  } else {
+    vec![("Content-Type".into(), "text/plain; charset=utf-8".into())]
+  };
+
+  // binary files are usually compressed already
+  let encoding = accept.preferred().filter(|_| file.is_binary() != Some(true));
+  let blob_id = file.blob_id(&*config, &paste)?;
+  let body = RawFile::open(&*config, &paste, &path, blob_id, encoding)?;
 
-  // TODO: specials headers?
-  Ok(FileOrError::File(NamedFile::open(path)?))
+  Ok(FileOrError::File(AddHeaders::new(body, headers)))
 }
 
 #[derive(Responder)]
 pub enum FileOrError {
-  File(NamedFile),
+  File(AddHeaders<RawFile>),
   Error(Custom<Json<Status<()>>>),
 }
@@ -18,8 +18,6 @@ use rocket::{http::Status as HttpStatus, State};
 
 use rocket_contrib::json::{Json, JsonError};
 
-use std::{fs::OpenOptions, io::Write};
-
 type UpdateResult<'a> = ::std::result::Result<Json<Vec<PasteFileUpdate>>, JsonError<'a>>;
 
 #[patch("/<paste_id>/files", format = "application/json", data = "<info>")]
@@ -55,7 +53,6 @@ pub fn patch(paste_id: PasteId, info: UpdateResult<'a>, config: State<Config>, u
   let mut db_changed = false;
   // TODO: this needs much refactor love
   // update files and database if necessary
-  let files_directory = paste.files_directory(&*config);
 
   let mut db_files = paste_id.files(&conn)?;
   {
@@ -86,11 +83,7 @@ pub fn patch(paste_id: PasteId, info: UpdateResult<'a>, config: State<Config>, u
         match file.content {
           // replacing contents
           Update::Set(content) => {
-            let mut f = OpenOptions::new()
-              .write(true)
-              .truncate(true)
-              .open(files_directory.join(db_file.id().to_simple().to_string()))?;
-            f.write_all(&content.into_bytes())?;
+            paste.write_file(&*config, db_file.id(), content)?;
             // FIXME: set is_binary field
           },
           // deleting file
@@ -56,6 +56,7 @@ pub fn get_all(query: Option<Form<AllQuery>>, conn: DbConn, config: State<Config
         description: x.description().map(Into::into),
         visibility: x.visibility(),
         expires: x.expires(),
+        expires_in: None,
         created_at: Some(x.created_at()),
         updated_at: x.updated_at(&*config).ok(),
       },
@@ -83,7 +84,10 @@ pub fn get(id: PasteId, query: Option<Form<Full>>, user: OptionalUser, conn: DbC
   let query = query.map(|x| x.into_inner()).unwrap_or_default();
 
   let full = query.full == Some(true);
-  let files: Vec<OutputFile> = id.output_files(&*config, &conn, &paste, full)?;
+  let mut files: Vec<OutputFile> = id.output_files(&*config, &conn, &paste, full)?;
+  for file in &mut files {
+    file.validate();
+  }
 
   let author = match paste.author_id() {
     Some(author) => {
@@ -3,12 +3,14 @@ use crate::{
   database::DbConn,
   models::{
     id::PasteId,
-    paste::update::MetadataUpdate,
+    paste::update::{MetadataUpdate, Update},
     status::{Status, ErrorKind},
   },
   routes::{RouteResult, RequiredUser},
 };
 
+use chrono::Utc;
+
 use rocket::{http::Status as HttpStatus, State};
 
 use rocket_contrib::json::{Json, JsonError};
@@ -20,7 +22,7 @@ type UpdateResult<'a> = ::std::result::Result<Json<MetadataUpdate>, JsonError<'a
 #[patch("/<paste_id>", format = "application/json", data = "<info>")]
 pub fn patch(paste_id: PasteId, info: UpdateResult<'a>, user: RequiredUser, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>) -> RouteResult<()> {
   // TODO: can this be a request guard?
-  let info = match info {
+  let mut info = match info {
     Ok(x) => x.into_inner(),
     Err(e) => {
       let message = match e {
@@ -40,6 +42,34 @@ pub fn patch(paste_id: PasteId, info: UpdateResult<'a>, user: RequiredUser, conn
     return Ok(Status::show_error(status, kind));
   }
 
+  if let Some(expires_in) = info.expires_in {
+    if !info.expires.is_ignore() {
+      return Ok(Status::show_error(
+        HttpStatus::BadRequest,
+        ErrorKind::BadParameters(Some("only one of expires and expires_in may be given".into())),
+      ));
+    }
+    info.expires = Update::Set(expires_in.from_now());
+  }
+
+  if let Update::Set(expires) = info.expires {
+    if expires <= Utc::now() {
+      return Ok(Status::show_error(
+        HttpStatus::BadRequest,
+        ErrorKind::BadParameters(Some("paste expiry date cannot be in the past".into())),
+      ));
+    }
+  }
+
+  if let Update::Set(ref change) = info.visibility_change {
+    if change.at <= Utc::now() {
+      return Ok(Status::show_error(
+        HttpStatus::BadRequest,
+        ErrorKind::BadParameters(Some("visibility changes must be scheduled for the future".into())),
+      ));
+    }
+  }
+
   // update paste and database if necessary
   paste.update(&*config, &conn, &*sidekiq, &info)?;
 
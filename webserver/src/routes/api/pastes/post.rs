@@ -4,8 +4,14 @@ use crate::{
     pastes::*,
   },
   config::Config,
-  database::DbConn,
+  database::{
+    DbConn,
+    models::{paste_templates::PasteTemplate, uploads::Upload},
+    schema::uploads,
+  },
+  errors::*,
   models::{
+    id::PasteTemplateId,
     paste::{
       Paste,
       output::{Output, OutputFile, OutputAuthor}
@@ -13,16 +19,20 @@ use crate::{
     status::{Status, ErrorKind},
   },
   routes::{RouteResult, OptionalUser},
-  utils::MultipartUpload,
+  utils::{MultipartUpload, RelativeDuration},
 };
 
+use diesel::prelude::*;
+
 use rocket::{State, http::Status as HttpStatus};
 
 use rocket_contrib::json::{Json, JsonError};
 
+use serde_json::{json, Value};
+
 use sidekiq::Client as SidekiqClient;
 
-type JsonResult<'a> = std::result::Result<Json<Paste>, JsonError<'a>>;
+type JsonResult<'a> = std::result::Result<Json<Value>, JsonError<'a>>;
 type MultipartResult = std::result::Result<MultipartUpload, String>;
 
 fn _post(info: Paste, user: OptionalUser, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>) -> RouteResult<Output> {
@@ -34,13 +44,22 @@ fn _post(info: Paste, user: OptionalUser, conn: DbConn, sidekiq: State<SidekiqCl
   }
 
   // check that file names are not the empty string
-  if info.files.iter().filter_map(|x| x.name.as_ref()).any(|x| x.is_empty()) {
+  let upload_names = info.uploads.iter().filter_map(|x| x.name.as_ref());
+  if info.files.iter().filter_map(|x| x.name.as_ref()).chain(upload_names).any(|x| x.is_empty()) {
     return Ok(Status::show_error(
       HttpStatus::BadRequest,
       ErrorKind::InvalidFile(Some("names cannot be empty (for no name, omit the name field)".into())),
     ));
   }
 
+  let expires = match (info.metadata.expires, info.metadata.expires_in) {
+    (Some(_), Some(_)) => return Ok(Status::show_error(
+      HttpStatus::BadRequest,
+      ErrorKind::BadParameters(Some("only one of expires and expires_in may be given".into())),
+    )),
+    (expires, expires_in) => expires.or_else(|| expires_in.map(RelativeDuration::from_now)),
+  };
+
   let files = info.files
     .into_iter()
     .map(|f| FilePayload {
@@ -50,13 +69,47 @@ fn _post(info: Paste, user: OptionalUser, conn: DbConn, sidekiq: State<SidekiqCl
     })
     .collect();
 
+  // uploads can only be used by the account that made them, once they're complete
+  let mut db_uploads: Vec<Upload> = Vec::with_capacity(info.uploads.len());
+  let mut upload_payloads = Vec::with_capacity(info.uploads.len());
+  for upload in info.uploads {
+    let db_upload: Option<Upload> = uploads::table.find(upload.id).first(&*conn).optional()?;
+    let db_upload = match (db_upload, user.as_ref()) {
+      (Some(u), Some(user)) if u.user_id() == user.id() && !u.is_expired() => u,
+      _ => return Ok(Status::show_error(
+        HttpStatus::BadRequest,
+        ErrorKind::BadParameters(Some(format!("upload {} does not exist", upload.id.to_simple()))),
+      )),
+    };
+    if db_uploads.iter().any(|x| x.id() == db_upload.id()) {
+      return Ok(Status::show_error(
+        HttpStatus::BadRequest,
+        ErrorKind::BadParameters(Some(format!("upload {} was used more than once", upload.id.to_simple()))),
+      ));
+    }
+    if !db_upload.is_complete(&*config)? {
+      return Ok(Status::show_error(
+        HttpStatus::BadRequest,
+        ErrorKind::BadParameters(Some(format!("upload {} is not complete", upload.id.to_simple()))),
+      ));
+    }
+
+    upload_payloads.push(UploadPayload {
+      name: upload.name.map(|x| x.into_inner()),
+      highlight_language: upload.highlight_language,
+      path: db_upload.path(&*config),
+    });
+    db_uploads.push(db_upload);
+  }
+
   let pp = PastePayload {
     name: info.metadata.name.map(|x| x.into_inner()),
     description: info.metadata.description.map(|x| x.into_inner()),
     visibility: info.metadata.visibility,
-    expires: info.metadata.expires,
+    expires,
     author: user.as_ref(),
     files,
+    uploads: upload_payloads,
   };
 
   let CreateSuccess { paste, files, deletion_key } = match pp.create(&*config, &conn, &*sidekiq) {
@@ -67,6 +120,11 @@ fn _post(info: Paste, user: OptionalUser, conn: DbConn, sidekiq: State<SidekiqCl
     },
   };
 
+  // the files have been moved into the paste, so only the records are left
+  for upload in db_uploads {
+    upload.delete(&*config, &conn)?;
+  }
+
   match *user {
     Some(ref u) => paste.commit(&*config, u.name(), u.email(), "create paste")?,
     None => paste.commit(&*config, "Anonymous", "none", "create paste")?,
@@ -112,10 +170,10 @@ pub fn post_multipart(info: MultipartResult, user: OptionalUser, conn: DbConn, s
   _post(info, user, conn, sidekiq, config)
 }
 
-#[post("/", format = "application/json", data = "<info>")]
-pub fn post_json<'a>(info: JsonResult<'a>, user: OptionalUser, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>) -> RouteResult<Output> {
+#[post("/?<template>", format = "application/json", data = "<info>")]
+pub fn post_json<'a>(template: Option<PasteTemplateId>, info: JsonResult<'a>, user: OptionalUser, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>) -> RouteResult<Output> {
   // TODO: can this be a request guard?
-  let info = match info {
+  let mut info = match info {
     Ok(x) => x.into_inner(),
     Err(e) => {
       let message = match e {
@@ -126,5 +184,78 @@ pub fn post_json<'a>(info: JsonResult<'a>, user: OptionalUser, conn: DbConn, sid
     },
   };
 
+  // templates can only be used by the account that saved them
+  if let Some(id) = template {
+    let template = match *user {
+      Some(ref u) => PasteTemplate::find(&conn, u.id(), id)?,
+      None => None,
+    };
+    let template = match template {
+      Some(t) => t,
+      None => return Ok(Status::show_error(
+        HttpStatus::BadRequest,
+        ErrorKind::BadParameters(Some(format!("template {} does not exist", id.to_simple()))),
+      )),
+    };
+    apply_template(&conn, &template, &mut info)?;
+  }
+
+  let info: Paste = match serde_json::from_value(info) {
+    Ok(x) => x,
+    Err(e) => return Ok(Status::show_error(
+      HttpStatus::BadRequest,
+      ErrorKind::BadJson(Some(format!("could not parse json: {}", e))),
+    )),
+  };
+
   _post(info, user, conn, sidekiq, config)
 }
+
+/// Fills in whatever `info` leaves out with what `template` has.
+///
+/// The template's files are only used if `info` has no files or uploads of its own.
+fn apply_template(conn: &DbConn, template: &PasteTemplate, info: &mut Value) -> Result<()> {
+  let info = match info.as_object_mut() {
+    Some(i) => i,
+    // not a paste, so leave it for deserialising to complain about
+    None => return Ok(()),
+  };
+
+  let missing = |key: &str| info.get(key).map(Value::is_null).unwrap_or(true);
+  let is_empty = |key: &str| info.get(key).and_then(Value::as_array).map(Vec::is_empty).unwrap_or(true);
+
+  let mut defaults = Vec::new();
+  if let (true, Some(name)) = (missing("name"), template.paste_name()) {
+    defaults.push(("name", json!(name)));
+  }
+  if let (true, Some(description)) = (missing("description"), template.description()) {
+    defaults.push(("description", json!(description)));
+  }
+  if missing("visibility") {
+    defaults.push(("visibility", json!(template.visibility())));
+  }
+  if let (true, true, Some(expires)) = (missing("expires"), missing("expires_in"), template.expires()) {
+    defaults.push(("expires", json!(expires)));
+  }
+  if is_empty("files") && is_empty("uploads") {
+    let files: Vec<Value> = template
+      .files(conn)?
+      .iter()
+      .map(|f| json!({
+        "name": f.name(),
+        "highlight_language": f.highlight_language(),
+        "content": {
+          "format": "text",
+          "value": f.content(),
+        },
+      }))
+      .collect();
+    defaults.push(("files", json!(files)));
+  }
+
+  for (key, value) in defaults {
+    info.insert(key.to_string(), value);
+  }
+
+  Ok(())
+}
@@ -0,0 +1,180 @@
+//! Creating a paste from a plain request body, for use with `curl --data-binary @-` and the like.
+
+use crate::{
+  backend::{
+    errors::BackendError,
+    pastes::*,
+  },
+  config::Config,
+  database::DbConn,
+  errors::*,
+  models::paste::{Content, Visibility},
+  routes::OptionalUser,
+  utils::{Language, RelativeDuration},
+};
+
+use chrono::{DateTime, Utc};
+
+use rocket::{
+  Data, Outcome, Request, State,
+  http::{RawStr, Status as HttpStatus},
+  request::{self, FromFormValue, FromRequest},
+  response::status::Custom,
+};
+
+use sidekiq::Client as SidekiqClient;
+
+use std::{io::Read, str::FromStr};
+
+/// Paste metadata for a raw upload, taken from query params or, failing that, `X-Paste-*` headers.
+pub struct RawOptions {
+  name: Option<String>,
+  description: Option<String>,
+  filename: Option<String>,
+  visibility: Visibility,
+  language: Option<Language>,
+  expires: Option<DateTime<Utc>>,
+}
+
+impl RawOptions {
+  fn from_request_parts(request: &Request) -> std::result::Result<Self, String> {
+    let visibility = match option(request, "visibility", "X-Paste-Visibility") {
+      Some(v) => Visibility::from_form_value(RawStr::from_str(&v))
+        .map_err(|_| format!("invalid visibility: {}", v))?,
+      None => Visibility::default(),
+    };
+
+    let language = match option(request, "language", "X-Paste-Language") {
+      Some(l) => Some(Language::from_str(&l).ok_or_else(|| format!("invalid language: {}", l))?),
+      None => None,
+    };
+
+    let expires = match option(request, "expires", "X-Paste-Expires") {
+      Some(e) => Some(
+        DateTime::parse_from_rfc3339(&e)
+          .map_err(|_| format!("invalid expiry date (expected RFC 3339): {}", e))?
+          .with_timezone(&Utc),
+      ),
+      None => None,
+    };
+
+    let expires = match option(request, "expires_in", "X-Paste-Expires-In") {
+      Some(_) if expires.is_some() => return Err("only one of expires and expires_in may be given".into()),
+      Some(e) => Some(RelativeDuration::from_str(&e)?.from_now()),
+      None => expires,
+    };
+
+    Ok(RawOptions {
+      name: option(request, "name", "X-Paste-Name"),
+      description: option(request, "description", "X-Paste-Description"),
+      filename: option(request, "filename", "X-Paste-Filename"),
+      visibility,
+      language,
+      expires,
+    })
+  }
+}
+
+fn option(request: &Request, query: &str, header: &str) -> Option<String> {
+  request.get_query_value::<String>(query)
+    .and_then(|x| x.ok())
+    .or_else(|| request.headers().get_one(header).map(ToString::to_string))
+    .filter(|x| !x.is_empty())
+}
+
+impl FromRequest<'a, 'r> for RawOptions {
+  type Error = String;
+
+  fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+    match RawOptions::from_request_parts(request) {
+      Ok(o) => Outcome::Success(o),
+      Err(e) => Outcome::Failure((HttpStatus::BadRequest, e)),
+    }
+  }
+}
+
+type OptionsResult = std::result::Result<RawOptions, String>;
+
+#[post("/raw", data = "<data>")]
+pub fn post(data: Data, options: OptionsResult, user: OptionalUser, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>) -> Result<Custom<String>> {
+  create(data, options, None, user, conn, sidekiq, config)
+}
+
+#[put("/raw/<filename>", data = "<data>")]
+pub fn put(filename: String, data: Data, options: OptionsResult, user: OptionalUser, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>) -> Result<Custom<String>> {
+  create(data, options, Some(filename), user, conn, sidekiq, config)
+}
+
+fn create(data: Data, options: OptionsResult, filename: Option<String>, user: OptionalUser, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>) -> Result<Custom<String>> {
+  let options = match options {
+    Ok(o) => o,
+    Err(e) => return Ok(error(HttpStatus::BadRequest, &e)),
+  };
+
+  let (sign_in_to_create, max_file_size, domain) = {
+    let config = config.read();
+    (config.pastes.sign_in_to_create, config.pastes.max_file_size, config.general.site_domain.clone())
+  };
+
+  if sign_in_to_create && user.is_none() {
+    return Ok(error(HttpStatus::BadRequest, "you must be authenticated to create a paste"));
+  }
+
+  let mut body = Vec::new();
+  data.open().take(max_file_size + 1).read_to_end(&mut body)?;
+  if body.len() as u64 > max_file_size {
+    return Ok(error(HttpStatus::PayloadTooLarge, &format!("files can be at most {} bytes", max_file_size)));
+  }
+
+  let content = match String::from_utf8(body) {
+    Ok(s) => Content::Text(s),
+    Err(e) => Content::Base64(e.into_bytes()),
+  };
+
+  let pp = PastePayload {
+    name: options.name,
+    description: options.description,
+    visibility: options.visibility,
+    expires: options.expires,
+    author: user.as_ref(),
+    files: vec![FilePayload {
+      name: filename.or(options.filename),
+      highlight_language: options.language,
+      content,
+    }],
+    uploads: Vec::new(),
+  };
+
+  let CreateSuccess { paste, deletion_key, .. } = match pp.create(&*config, &conn, &*sidekiq) {
+    Ok(s) => s,
+    Err(e) => {
+      let msg = e.into_message()?;
+      return Ok(error(HttpStatus::BadRequest, &msg));
+    },
+  };
+
+  match *user {
+    Some(ref u) => paste.commit(&*config, u.name(), u.email(), "create paste")?,
+    None => paste.commit(&*config, "Anonymous", "none", "create paste")?,
+  }
+
+  let username = match *user {
+    Some(ref u) => u.username(),
+    None => "anonymous",
+  };
+
+  let mut output = format!(
+    "https://{}{}\n",
+    domain,
+    uri!(crate::routes::web::pastes::get::users_username_id: username, paste.id()),
+  );
+  if let Some(key) = deletion_key {
+    output.push_str(&format!("deletion key: {}\n", key.uuid().to_simple()));
+  }
+
+  Ok(Custom(HttpStatus::Created, output))
+}
+
+fn error(status: HttpStatus, message: &str) -> Custom<String> {
+  Custom(status, format!("error: {}\n", message))
+}
@@ -0,0 +1,130 @@
+//! Resumable uploads for large binary files, following the core tus protocol with the creation and
+//! termination extensions.
+//!
+//! Chunks are appended to a file in the store, which is moved into a paste once complete by listing
+//! the upload in the `uploads` of a new paste.
+//!
+//! https://tus.io/protocols/resumable-upload.html
+
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::uploads::Upload,
+    schema::uploads,
+  },
+  errors::*,
+  models::{
+    id::UploadId,
+    status::{ErrorKind, Status},
+  },
+  routes::{AddHeaders, RequiredUser},
+};
+
+use diesel::prelude::*;
+
+use rocket::{
+  Outcome, Request,
+  http::Status as HttpStatus,
+  request::{self, FromRequest},
+  response::status::Custom,
+};
+
+use rocket_contrib::json::Json;
+
+pub mod delete;
+pub mod head;
+pub mod options;
+pub mod patch;
+pub mod post;
+
+const TUS_VERSION: &str = "1.0.0";
+
+#[derive(Responder)]
+pub enum UploadResponse {
+  Upload(AddHeaders<HttpStatus>),
+  Error(Custom<Json<Status<()>>>),
+}
+
+impl UploadResponse {
+  fn new(status: HttpStatus, headers: Vec<(&str, String)>) -> Self {
+    let headers = std::iter::once(("Tus-Resumable".to_string(), TUS_VERSION.to_string()))
+      .chain(headers.into_iter().map(|(name, value)| (name.to_string(), value)))
+      .collect::<Vec<_>>();
+
+    UploadResponse::Upload(AddHeaders::new(status, headers))
+  }
+
+  fn error(status: HttpStatus, kind: ErrorKind) -> Self {
+    UploadResponse::Error(Status::show_error(status, kind))
+  }
+
+  /// The response to requests without a `Tus-Resumable` header for the version supported here.
+  fn unsupported_version() -> Self {
+    UploadResponse::Upload(AddHeaders::new(
+      HttpStatus::PreconditionFailed,
+      vec![("Tus-Version".to_string(), TUS_VERSION.to_string())],
+    ))
+  }
+}
+
+/// The `Tus-Resumable`, `Upload-Offset` and `Upload-Length` headers of a request.
+pub struct UploadHeaders {
+  resumable: Option<String>,
+  offset: Option<u64>,
+  length: Option<u64>,
+}
+
+impl UploadHeaders {
+  /// Whether the request uses the supported version of tus, which every request but `OPTIONS` has
+  /// to say.
+  fn supports_version(&self) -> bool {
+    self.resumable.as_ref().map(|x| x == TUS_VERSION).unwrap_or(false)
+  }
+}
+
+impl FromRequest<'a, 'r> for UploadHeaders {
+  type Error = String;
+
+  fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+    let parse = |name: &str| match request.headers().get_one(name) {
+      Some(value) => value.parse::<u64>().map(Some).map_err(|_| format!("invalid {} header", name)),
+      None => Ok(None),
+    };
+
+    let (offset, length) = match (parse("Upload-Offset"), parse("Upload-Length")) {
+      (Ok(offset), Ok(length)) => (offset, length),
+      (Err(e), _) | (_, Err(e)) => return Outcome::Failure((HttpStatus::BadRequest, e)),
+    };
+
+    let resumable = request.headers().get_one("Tus-Resumable").map(ToString::to_string);
+
+    Outcome::Success(UploadHeaders { resumable, offset, length })
+  }
+}
+
+type HeadersResult = std::result::Result<UploadHeaders, String>;
+
+/// Checks the headers every request but `OPTIONS` has, returning the response to give if they're
+/// wrong.
+fn check_headers(headers: HeadersResult) -> std::result::Result<UploadHeaders, UploadResponse> {
+  let headers = headers.map_err(|e| UploadResponse::error(HttpStatus::BadRequest, ErrorKind::BadParameters(Some(e))))?;
+
+  if !headers.supports_version() {
+    return Err(UploadResponse::unsupported_version());
+  }
+
+  Ok(headers)
+}
+
+/// Finds an unexpired upload belonging to `user`.
+fn find(config: &Config, conn: &DbConn, user: &RequiredUser, id: UploadId) -> Result<Option<Upload>> {
+  let upload: Option<Upload> = uploads::table.find(id).first(&**conn).optional()?;
+
+  Ok(upload.filter(|x| x.user_id() == user.id() && !x.is_expired() && x.path(config).exists()))
+}
+
+/// The format tus uses for `Upload-Expires`.
+fn http_date(date: chrono::NaiveDateTime) -> String {
+  date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
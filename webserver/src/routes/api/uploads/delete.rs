@@ -0,0 +1,30 @@
+use crate::{
+  config::Config,
+  database::DbConn,
+  errors::*,
+  models::{
+    id::UploadId,
+    status::ErrorKind,
+  },
+  routes::RequiredUser,
+};
+
+use super::{HeadersResult, UploadResponse};
+
+use rocket::{State, http::Status as HttpStatus};
+
+#[delete("/<id>")]
+pub fn delete(id: UploadId, headers: HeadersResult, user: RequiredUser, conn: DbConn, config: State<Config>) -> Result<UploadResponse> {
+  if let Err(r) = super::check_headers(headers) {
+    return Ok(r);
+  }
+
+  let upload = match super::find(&*config, &conn, &user, id)? {
+    Some(u) => u,
+    None => return Ok(UploadResponse::error(HttpStatus::NotFound, ErrorKind::MissingUpload)),
+  };
+
+  upload.delete(&*config, &conn)?;
+
+  Ok(UploadResponse::new(HttpStatus::NoContent, vec![]))
+}
@@ -0,0 +1,33 @@
+use crate::{
+  config::Config,
+  database::DbConn,
+  errors::*,
+  models::{
+    id::UploadId,
+    status::ErrorKind,
+  },
+  routes::RequiredUser,
+};
+
+use super::{HeadersResult, UploadResponse};
+
+use rocket::{State, http::Status as HttpStatus};
+
+#[head("/<id>")]
+pub fn head(id: UploadId, headers: HeadersResult, user: RequiredUser, conn: DbConn, config: State<Config>) -> Result<UploadResponse> {
+  if let Err(r) = super::check_headers(headers) {
+    return Ok(r);
+  }
+
+  let upload = match super::find(&*config, &conn, &user, id)? {
+    Some(u) => u,
+    None => return Ok(UploadResponse::error(HttpStatus::NotFound, ErrorKind::MissingUpload)),
+  };
+
+  Ok(UploadResponse::new(HttpStatus::Ok, vec![
+    ("Upload-Offset", upload.offset(&*config)?.to_string()),
+    ("Upload-Length", upload.length().to_string()),
+    ("Upload-Expires", super::http_date(upload.expiry())),
+    ("Cache-Control", "no-store".to_string()),
+  ]))
+}
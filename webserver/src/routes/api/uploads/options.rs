@@ -0,0 +1,16 @@
+use crate::config::Config;
+
+use super::UploadResponse;
+
+use rocket::{State, http::Status as HttpStatus};
+
+#[options("/")]
+pub fn options(config: State<Config>) -> UploadResponse {
+  let max_size = config.read().pastes.max_upload_size;
+
+  UploadResponse::new(HttpStatus::NoContent, vec![
+    ("Tus-Version", super::TUS_VERSION.to_string()),
+    ("Tus-Max-Size", max_size.to_string()),
+    ("Tus-Extension", "creation,termination".to_string()),
+  ])
+}
@@ -0,0 +1,78 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::uploads::Upload,
+    schema::uploads,
+  },
+  errors::*,
+  models::{
+    id::UploadId,
+    status::ErrorKind,
+  },
+  routes::RequiredUser,
+};
+
+use super::{HeadersResult, UploadHeaders, UploadResponse};
+
+use anyhow::Error;
+
+use diesel::prelude::*;
+
+use rocket::{Data, State, http::Status as HttpStatus};
+
+use std::{
+  fs::OpenOptions,
+  io::{self, Read},
+};
+
+#[patch("/<id>", format = "application/offset+octet-stream", data = "<data>")]
+pub fn patch(id: UploadId, data: Data, headers: HeadersResult, user: RequiredUser, conn: DbConn, config: State<Config>) -> Result<UploadResponse> {
+  let expected_offset = match super::check_headers(headers) {
+    Ok(UploadHeaders { offset: Some(o), .. }) => o,
+    Ok(_) => return Ok(UploadResponse::error(
+      HttpStatus::BadRequest,
+      ErrorKind::BadParameters(Some("Upload-Offset must be given".into())),
+    )),
+    Err(r) => return Ok(r),
+  };
+
+  if super::find(&*config, &conn, &user, id)?.is_none() {
+    return Ok(UploadResponse::error(HttpStatus::NotFound, ErrorKind::MissingUpload));
+  }
+
+  // the upload stays locked until the chunk is written, so requests for the same upload take turns
+  // instead of both appending at the same offset
+  conn.transaction::<_, Error, _>(|| {
+    let upload: Upload = match uploads::table.find(id).for_update().first(&*conn).optional()? {
+      Some(u) => u,
+      None => return Ok(UploadResponse::error(HttpStatus::NotFound, ErrorKind::MissingUpload)),
+    };
+
+    // chunks must be sent in order, so a client that lost track has to ask for the offset again
+    let offset = upload.offset(&*config)?;
+    if offset != expected_offset {
+      return Ok(UploadResponse::error(
+        HttpStatus::Conflict,
+        ErrorKind::BadParameters(Some(format!("Upload-Offset does not match the current offset of {}", offset))),
+      ));
+    }
+
+    // anything past the declared length is ignored, keeping uploads within the size limit
+    let remaining = match upload.length().checked_sub(offset) {
+      Some(r) => r,
+      None => return Ok(UploadResponse::error(
+        HttpStatus::Conflict,
+        ErrorKind::BadParameters(Some("the upload is already longer than its Upload-Length".into())),
+      )),
+    };
+
+    let mut file = OpenOptions::new().append(true).open(upload.path(&*config))?;
+    io::copy(&mut data.open().take(remaining), &mut file)?;
+
+    Ok(UploadResponse::new(HttpStatus::NoContent, vec![
+      ("Upload-Offset", upload.offset(&*config)?.to_string()),
+      ("Upload-Expires", super::http_date(upload.expiry())),
+    ]))
+  })
+}
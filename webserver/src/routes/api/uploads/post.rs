@@ -0,0 +1,76 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::uploads::{NewUpload, Upload},
+    schema::uploads,
+  },
+  errors::*,
+  models::status::ErrorKind,
+  routes::RequiredUser,
+};
+
+use super::{HeadersResult, UploadResponse};
+
+use chrono::Utc;
+
+use diesel::prelude::*;
+
+use rocket::{State, http::Status as HttpStatus};
+
+use sidekiq::Client as SidekiqClient;
+
+/// The most uploads an account may have in progress at once.
+const MAX_UPLOADS: i64 = 10;
+
+#[post("/")]
+pub fn post(headers: HeadersResult, user: RequiredUser, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>) -> Result<UploadResponse> {
+  let length = match super::check_headers(headers) {
+    Ok(h) => h.length,
+    Err(r) => return Ok(r),
+  };
+  let length = match length {
+    Some(l) if l > 0 => l,
+    _ => return Ok(UploadResponse::error(
+      HttpStatus::BadRequest,
+      ErrorKind::BadParameters(Some("Upload-Length must be given and more than zero".into())),
+    )),
+  };
+
+  let max_size = config.read().pastes.max_upload_size;
+  if length > max_size {
+    return Ok(UploadResponse::error(
+      HttpStatus::PayloadTooLarge,
+      ErrorKind::BadParameters(Some(format!("uploads can be at most {} bytes", max_size))),
+    ));
+  }
+
+  // expired uploads have had their files removed by now, so their records can go too
+  let expired = uploads::table
+    .filter(uploads::user_id.eq(user.id()))
+    .filter(uploads::expiry.lt(Utc::now().naive_utc()));
+  diesel::delete(expired).execute(&*conn)?;
+
+  let in_progress: i64 = uploads::table
+    .filter(uploads::user_id.eq(user.id()))
+    .count()
+    .get_result(&*conn)?;
+  if in_progress >= MAX_UPLOADS {
+    return Ok(UploadResponse::error(
+      HttpStatus::TooManyRequests,
+      ErrorKind::BadParameters(Some(format!("at most {} uploads can be in progress at once", MAX_UPLOADS))),
+    ));
+  }
+
+  let upload: Upload = diesel::insert_into(uploads::table)
+    .values(&NewUpload::new(user.id(), length))
+    .get_result(&*conn)?;
+
+  let job = upload.create_file(&*config)?;
+  sidekiq.push(job.into())?;
+
+  Ok(UploadResponse::new(HttpStatus::Created, vec![
+    ("Location", format!("/api/v1/uploads/{}", upload.id().to_simple())),
+    ("Upload-Expires", super::http_date(upload.expiry())),
+  ]))
+}
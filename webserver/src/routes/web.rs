@@ -57,8 +57,10 @@ pub mod admin;
 pub mod auth;
 pub mod credits;
 pub mod fairings;
+pub mod feeds;
 pub mod guards;
 pub mod index;
+pub mod oembed;
 pub mod pastes;
 pub mod static_files;
 pub mod users;
@@ -242,7 +244,9 @@ lazy_static! {
       .add("register", uri!(crate::routes::web::auth::register::get))
       .add("settings", uri!(crate::routes::web::account::index::get))
       .add("admin", uri!(crate::routes::web::admin::index::get))
-      .add("credits", uri!(crate::routes::web::credits::get));
+      .add("credits", uri!(crate::routes::web::credits::get))
+      .add("feed_atom", uri!(crate::routes::web::feeds::all_atom))
+      .add("feed_rss", uri!(crate::routes::web::feeds::all_rss));
     links
   };
 }
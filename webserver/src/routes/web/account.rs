@@ -1,9 +1,12 @@
 pub mod adminify;
 pub mod avatar;
 pub mod delete;
+pub mod export;
 pub mod index;
 pub mod keys;
 pub mod reset_password;
+pub mod sessions;
+pub mod templates;
 pub mod two_factor;
 pub mod verify;
 
@@ -13,7 +16,10 @@ pub(crate) fn account_links() -> Links {
   links!(
     "settings" => uri!(crate::routes::web::account::index::get),
     "keys" => uri!(crate::routes::web::account::keys::get),
+    "templates" => uri!(crate::routes::web::account::templates::get),
     "tfa" => uri!(crate::routes::web::account::two_factor::get),
+    "sessions" => uri!(crate::routes::web::account::sessions::get),
+    "export" => uri!(crate::routes::web::account::export::get),
     "delete_account" => uri!(crate::routes::web::account::delete::get),
   )
 }
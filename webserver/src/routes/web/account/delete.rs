@@ -3,6 +3,7 @@ use crate::{
   database::DbConn,
   errors::*,
   i18n::prelude::*,
+  redis_store::Redis,
   routes::web::{context, Rst, OptionalWebUser, Session},
   sidekiq::Job,
   utils::AcceptLanguage,
@@ -35,7 +36,7 @@ pub fn get(config: State<Config>, user: OptionalWebUser, mut sess: Session, lang
 }
 
 #[delete("/account", format = "application/x-www-form-urlencoded", data = "<delete>")]
-pub fn delete(delete: Form<DeleteRequest>, user: OptionalWebUser, mut sess: Session, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>, l10n: L10n) -> Result<Redirect> {
+pub fn delete(delete: Form<DeleteRequest>, user: OptionalWebUser, mut sess: Session, conn: DbConn, mut redis: Redis, sidekiq: State<SidekiqClient>, config: State<Config>, l10n: L10n) -> Result<Redirect> {
   let delete = delete.into_inner();
 
   if !sess.check_token(&delete.anti_csrf_token) {
@@ -55,6 +56,9 @@ pub fn delete(delete: Form<DeleteRequest>, user: OptionalWebUser, mut sess: Sess
 
   user.delete(&conn)?;
 
+  Session::revoke_all(&mut redis, user.id(), Some(sess.id))?;
+  sess.user_id = None;
+
   sidekiq.push(Job::DeleteAllPastes(&*config, user.id()).into())?;
 
   sess.add_data("info", "Account deleted.");
@@ -0,0 +1,218 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::{
+      data_exports::{DataExport, NewDataExport},
+      pastes::Paste as DbPaste,
+    },
+    schema::{data_exports, pastes},
+  },
+  errors::*,
+  i18n::prelude::*,
+  models::{
+    id::DataExportId,
+    paste::output::{Output, OutputAuthor},
+  },
+  routes::{AddHeaders, web::{context, Rst, OptionalWebUser, Session}},
+  sidekiq::Job,
+  utils::AcceptLanguage,
+};
+
+use chrono::{Duration, Utc};
+
+use data_encoding::BASE64URL_NOPAD;
+
+use diesel::prelude::*;
+
+use rocket::{
+  request::Form,
+  response::Redirect,
+  State,
+};
+
+use rocket_contrib::templates::Template;
+
+use serde_json::json;
+
+use sidekiq::{Client as SidekiqClient, Value};
+
+use std::fs::{self, File};
+
+#[derive(Responder)]
+pub enum Download {
+  Archive(AddHeaders<File>),
+  Redirect(Redirect),
+}
+
+#[get("/account/export")]
+pub fn get(config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, langs: AcceptLanguage) -> Result<Rst> {
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Rst::Redirect(Redirect::to(uri!(crate::routes::web::auth::login::get)))),
+  };
+
+  let exports: Vec<DataExport> = DataExport::belonging_to(user)
+    .filter(data_exports::expiry.gt(Utc::now().naive_utc()))
+    .order_by(data_exports::created_at.desc())
+    .load(&*conn)?;
+
+  let exports: Vec<_> = exports
+    .iter()
+    .map(|e| json!({
+      "export": e,
+      "ready": e.is_ready(&*config),
+    }))
+    .collect();
+
+  let mut ctx = context(&*config, Some(&user), &mut sess, langs);
+  ctx["exports"] = json!(exports);
+  ctx["links"] = json!(links!(super::account_links(),
+    "export_action" => uri!(crate::routes::web::account::export::post),
+  ));
+
+  Ok(Rst::Template(Template::render("account/export", ctx)))
+}
+
+#[post("/account/export", format = "application/x-www-form-urlencoded", data = "<form>")]
+pub fn post(form: Form<ExportRequest>, config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, sidekiq: State<SidekiqClient>, l10n: L10n) -> Result<Redirect> {
+  if !sess.check_token(&form.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get))),
+  };
+
+  // the download link is sent by email, so the address has to be confirmed first
+  if !user.email_verified() {
+    sess.add_data("error", l10n.tr(("export-error", "unverified"))?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  // only allow one export an hour
+  let recent: i64 = DataExport::belonging_to(user)
+    .filter(data_exports::created_at.gt(Utc::now().naive_utc() - Duration::hours(1)))
+    .count()
+    .get_result(&*conn)?;
+  if recent > 0 {
+    sess.add_data("error", l10n.tr(("export-error", "too-soon"))?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  // clean up any old exports, since their files are gone
+  diesel::delete(DataExport::belonging_to(user).filter(data_exports::expiry.le(Utc::now().naive_utc())))
+    .execute(&*conn)?;
+
+  let (new_export, secret) = NewDataExport::generate(user.id(), form.include_history);
+  let export: DataExport = diesel::insert_into(data_exports::table)
+    .values(&new_export)
+    .get_result(&*conn)?;
+
+  // write out the metadata for the worker to build the archive from
+  let pastes: Vec<DbPaste> = DbPaste::belonging_to(user)
+    .order_by(pastes::created_at.desc())
+    .load(&*conn)?;
+
+  let author = OutputAuthor::new(user.id(), user.username(), user.name());
+
+  let mut outputs = Vec::with_capacity(pastes.len());
+  for paste in pastes {
+    let files = paste.id().output_files(&*config, &conn, &paste, false)?;
+
+    outputs.push(Output::new(
+      paste.id(),
+      Some(author.clone()),
+      paste.name(),
+      paste.description(),
+      paste.visibility(),
+      paste.created_at(),
+      paste.updated_at(&*config).ok(),
+      paste.expires(),
+      None,
+      files,
+    ));
+  }
+
+  let api_keys: Vec<String> = user.keys(&conn)?
+    .into_iter()
+    .map(|k| k.name)
+    .collect();
+
+  let metadata = json!({
+    "exported_at": Utc::now(),
+    "account": user,
+    "api_keys": api_keys,
+    "pastes": outputs,
+  });
+
+  let directory = export.directory(&*config);
+  fs::create_dir_all(&directory)?;
+  serde_json::to_writer_pretty(File::create(directory.join("metadata.json"))?, &metadata)?;
+
+  sidekiq.push(export.job(&*config, user, &secret)?.into())?;
+
+  // remove the archive once the link has expired
+  sidekiq.push(Job::queue(
+    "DeleteDirectory",
+    export.expiry().timestamp(),
+    vec![Value::String(directory.to_string_lossy().into_owned())],
+  ).into())?;
+
+  sess.add_data("info", l10n.tr(("export-success", "queued"))?);
+  Ok(Redirect::to(uri!(get)))
+}
+
+#[get("/account/export/<id>?<secret>")]
+pub fn download(id: DataExportId, secret: String, config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, l10n: L10n) -> Result<Download> {
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Download::Redirect(Redirect::to(uri!(crate::routes::web::auth::login::get)))),
+  };
+
+  let export: Option<DataExport> = DataExport::belonging_to(user)
+    .filter(data_exports::id.eq(id))
+    .filter(data_exports::expiry.gt(Utc::now().naive_utc()))
+    .first(&*conn)
+    .optional()?;
+
+  let valid = match BASE64URL_NOPAD.decode(secret.as_bytes()) {
+    Ok(secret) => export.filter(|e| e.check(&secret)),
+    Err(_) => None,
+  };
+
+  let export = match valid {
+    Some(e) => e,
+    None => {
+      sess.add_data("error", l10n.tr(("export-error", "bad-url"))?);
+      return Ok(Download::Redirect(Redirect::to(uri!(get))));
+    },
+  };
+
+  if !export.is_ready(&*config) {
+    sess.add_data("info", l10n.tr(("export-success", "pending"))?);
+    return Ok(Download::Redirect(Redirect::to(uri!(get))));
+  }
+
+  let file_name = format!(
+    "{}-export-{}.zip",
+    user.username(),
+    export.created_at().format("%Y-%m-%d"),
+  );
+
+  Ok(Download::Archive(AddHeaders::new(
+    File::open(export.archive_path(&*config))?,
+    vec![
+      ("Content-Type".into(), "application/zip".into()),
+      ("Content-Disposition".into(), format!("attachment; filename=\"{}\"", file_name)),
+    ],
+  )))
+}
+
+#[derive(FromForm)]
+pub struct ExportRequest {
+  anti_csrf_token: String,
+  include_history: bool,
+}
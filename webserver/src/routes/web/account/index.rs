@@ -4,6 +4,7 @@ use crate::{
   errors::*,
   i18n::L10n,
   models::user::AvatarProvider,
+  redis_store::Redis,
   routes::web::{context, Rst, OptionalWebUser, Session},
   utils::{email, AcceptLanguage, HashedPassword, Validator},
 };
@@ -47,7 +48,7 @@ pub fn get(config: State<Config>, user: OptionalWebUser, mut sess: Session, lang
 }
 
 #[patch("/account", format = "application/x-www-form-urlencoded", data = "<update>")]
-pub fn patch(config: State<Config>, update: Form<AccountUpdate>, user: OptionalWebUser, mut sess: Session, conn: DbConn, sidekiq: State<SidekiqClient>, l10n: L10n) -> Result<Redirect> {
+pub fn patch(config: State<Config>, update: Form<AccountUpdate>, user: OptionalWebUser, mut sess: Session, conn: DbConn, mut redis: Redis, sidekiq: State<SidekiqClient>, l10n: L10n) -> Result<Redirect> {
   let update = update.into_inner();
   sess.set_form(&update);
 
@@ -142,6 +143,11 @@ pub fn patch(config: State<Config>, update: Form<AccountUpdate>, user: OptionalW
 
   user.update(&conn)?;
 
+  // a new password signs out everywhere else
+  if !update.password.is_empty() {
+    Session::revoke_all(&mut redis, user.id(), Some(sess.id))?;
+  }
+
   sess.take_form();
   sess.add_data("info", l10n.tr("account-success")?);
   Ok(Redirect::to(uri!(get)))
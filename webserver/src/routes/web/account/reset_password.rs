@@ -11,6 +11,7 @@ use crate::{
   },
   errors::*,
   i18n::prelude::*,
+  redis_store::Redis,
   routes::web::{context, Session, Rst, OptionalWebUser},
   sidekiq::Job,
   utils::{email, AcceptLanguage, ClientIp, PasswordContext, HashedPassword},
@@ -145,7 +146,7 @@ pub fn reset_get(data: Form<ResetPassword>, config: State<Config>, user: Optiona
 }
 
 #[post("/account/reset_password", data = "<data>")]
-pub fn reset_post(data: Form<Reset>, mut sess: Session, conn: DbConn, l10n: L10n) -> Result<Redirect> {
+pub fn reset_post(data: Form<Reset>, mut sess: Session, conn: DbConn, mut redis: Redis, l10n: L10n) -> Result<Redirect> {
   let data = data.into_inner();
 
   let res = Ok(Redirect::to(uri!(
@@ -204,6 +205,9 @@ pub fn reset_post(data: Form<Reset>, mut sess: Session, conn: DbConn, l10n: L10n
   user.set_hashed_password(hashed);
   user.update(&conn)?;
 
+  // whoever knew the old password shouldn't stay logged in
+  Session::revoke_all(&mut redis, user.id(), Some(sess.id))?;
+
   sess.add_data("info", l10n.tr(("reset-success", "reset"))?);
 
   sess.user_id = Some(user.id());
@@ -0,0 +1,93 @@
+use crate::{
+  config::Config,
+  errors::*,
+  i18n::prelude::*,
+  models::id::SessionId,
+  redis_store::Redis,
+  routes::web::{context, Links, Rst, OptionalWebUser, Session},
+  utils::AcceptLanguage,
+};
+
+use rocket::{
+  request::Form,
+  response::Redirect,
+  State,
+};
+
+use rocket_contrib::templates::Template;
+
+use serde_json::json;
+
+#[get("/account/sessions")]
+pub fn get(config: State<Config>, user: OptionalWebUser, mut sess: Session, mut redis: Redis, langs: AcceptLanguage) -> Result<Rst> {
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Rst::Redirect(Redirect::to(uri!(crate::routes::web::auth::login::get)))),
+  };
+
+  let sessions = Session::list(&mut redis, user.id())?;
+
+  let mut ctx = context(&*config, Some(&user), &mut sess, langs);
+  ctx["current_session"] = json!(sess.id);
+  ctx["sessions"] = json!(sessions);
+  ctx["links"] = json!(links!(super::account_links(),
+    "revoke_all" => uri!(crate::routes::web::account::sessions::delete_all),
+  ).add_value("revoke", sessions
+    .iter()
+    .fold(&mut Links::default(), |l, x| l.add(
+      x.id.to_simple().to_string(),
+      uri!(crate::routes::web::account::sessions::delete: x.id),
+    ))));
+
+  Ok(Rst::Template(Template::render("account/sessions", ctx)))
+}
+
+#[delete("/account/sessions/<id>", data = "<form>")]
+pub fn delete(id: SessionId, form: Form<Revoke>, user: OptionalWebUser, mut sess: Session, mut redis: Redis, l10n: L10n) -> Result<Redirect> {
+  if !sess.check_token(&form.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get))),
+  };
+
+  // the current session is ended by logging out
+  if id == sess.id {
+    sess.add_data("error", l10n.tr(("sessions-error", "current"))?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  if !Session::revoke(&mut redis, user.id(), id)? {
+    sess.add_data("error", l10n.tr(("sessions-error", "missing"))?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  sess.add_data("info", l10n.tr(("sessions-success", "one"))?);
+  Ok(Redirect::to(uri!(get)))
+}
+
+#[delete("/account/sessions", data = "<form>")]
+pub fn delete_all(form: Form<Revoke>, user: OptionalWebUser, mut sess: Session, mut redis: Redis, l10n: L10n) -> Result<Redirect> {
+  if !sess.check_token(&form.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get))),
+  };
+
+  Session::revoke_all(&mut redis, user.id(), Some(sess.id))?;
+
+  sess.add_data("info", l10n.tr(("sessions-success", "all"))?);
+  Ok(Redirect::to(uri!(get)))
+}
+
+#[derive(FromForm)]
+pub struct Revoke {
+  anti_csrf_token: String,
+}
@@ -0,0 +1,91 @@
+use crate::{
+  config::Config,
+  database::{DbConn, models::paste_templates::PasteTemplate},
+  errors::*,
+  i18n::prelude::*,
+  models::id::PasteTemplateId,
+  routes::web::{context, Links, Rst, OptionalWebUser, Session},
+  utils::AcceptLanguage,
+};
+
+use rocket::{
+  request::Form,
+  response::Redirect,
+  State,
+};
+use rocket_contrib::templates::Template;
+
+use serde_json::json;
+
+#[get("/account/templates")]
+pub fn get(config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, langs: AcceptLanguage) -> Result<Rst> {
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Rst::Redirect(Redirect::to(uri!(crate::routes::web::auth::login::get)))),
+  };
+
+  let templates = user.templates(&conn)?;
+
+  let mut template_values = Vec::with_capacity(templates.len());
+  for template in &templates {
+    template_values.push(json!({
+      "id": template.id(),
+      "name": template.name(),
+      "paste_name": template.paste_name(),
+      "visibility": template.visibility(),
+      "files": template.files(&conn)?.len(),
+      "created_at": template.created_at().to_rfc3339(),
+    }));
+  }
+
+  let mut ctx = context(&*config, Some(&user), &mut sess, langs);
+  ctx["templates"] = json!(template_values);
+  ctx["links"] = json!(
+    super::account_links()
+      .add_value(
+        "use_template_links",
+        templates
+          .iter()
+          .fold(&mut Links::default(), |l, x| l.add(x.id().to_simple().to_string(), uri!(
+            crate::routes::web::index::get:
+            x.id(),
+          )))
+      )
+      .add_value(
+        "delete_template_links",
+        templates
+          .iter()
+          .fold(&mut Links::default(), |l, x| l.add(x.id().to_simple().to_string(), uri!(
+            crate::routes::web::account::templates::delete:
+            x.id(),
+          )))
+      )
+  );
+  Ok(Rst::Template(Template::render("account/templates", ctx)))
+}
+
+#[delete("/account/templates/<id>", data = "<data>")]
+pub fn delete(id: PasteTemplateId, data: Form<DeleteTemplate>, user: OptionalWebUser, mut sess: Session, conn: DbConn, l10n: L10n) -> Result<Redirect> {
+  let data = data.into_inner();
+
+  if !sess.check_token(&data.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get))),
+  };
+
+  if let Some(template) = PasteTemplate::find(&conn, user.id(), id)? {
+    template.delete(&conn)?;
+  }
+
+  Ok(Redirect::to(uri!(get)))
+}
+
+#[derive(FromForm)]
+pub struct DeleteTemplate {
+  anti_csrf_token: String,
+}
@@ -1,19 +1,29 @@
 use crate::{
   config::Config,
-  database::{DbConn, models::users::User},
+  database::{
+    DbConn,
+    models::{
+      users::User,
+      webauthn_credentials::{WebauthnCredential, NewWebauthnCredential},
+    },
+    schema::webauthn_credentials,
+  },
   errors::*,
   i18n::prelude::*,
-  models::id::UserId,
+  models::id::{UserId, WebauthnCredentialId},
   redis_store::Redis,
-  routes::web::{context, Rst, OptionalWebUser, Session},
+  routes::web::{context, Links, Rst, OptionalWebUser, Session},
   utils::{
     AcceptLanguage,
     totp::totp_raw_skew,
+    webauthn::{self, RegistrationResponse, RelyingParty},
   },
 };
 
 use data_encoding::{BASE32_NOPAD, HEXLOWER};
 
+use diesel::prelude::*;
+
 use anyhow::bail;
 
 use r2d2_redis::redis::Commands;
@@ -34,23 +44,42 @@ use sodiumoxide::randombytes;
 
 use url::Url;
 
+use uuid::Uuid;
+
 #[get("/account/2fa")]
-pub fn get(config: State<Config>, user: OptionalWebUser, mut sess: Session, langs: AcceptLanguage) -> Result<Rst> {
+pub fn get(config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, langs: AcceptLanguage) -> Result<Rst> {
   let user = match *user {
     Some(ref u) => u,
     None => return Ok(Rst::Redirect(Redirect::to(uri!(crate::routes::web::auth::login::get)))),
   };
 
   let backups = sess.data.remove("backup_codes");
+  let keys = user.webauthn_credentials(&conn)?;
 
   let mut ctx = context(&*config, Some(&user), &mut sess, langs);
   ctx["tfa_enabled"] = json!(user.tfa_enabled());
+  ctx["second_factor"] = json!(user.tfa_enabled() || !keys.is_empty());
+  ctx["security_keys"] = json!(keys
+    .iter()
+    .map(|key| json!({
+      "id": key.id(),
+      "name": key.name(),
+      "created_at": key.created_at(),
+      "last_used_at": key.last_used_at(),
+    }))
+    .collect::<Vec<_>>());
   ctx["backups"] = json!(backups);
   ctx["links"] = json!(links!(super::account_links(),
     "enable" => uri!(crate::routes::web::account::two_factor::enable_get),
     "disable" => uri!(crate::routes::web::account::two_factor::disable_get),
     "new_backup_codes" => uri!(crate::routes::web::account::two_factor::new_backup_codes),
-  ));
+    "add_key" => uri!(crate::routes::web::account::two_factor::add_key_get),
+  ).add_value("security_keys", keys
+    .iter()
+    .fold(&mut Links::default(), |l, x| l.add(
+      x.id().to_simple().to_string(),
+      uri!(crate::routes::web::account::two_factor::key_patch: x.id()),
+    ))));
 
   Ok(Rst::Template(Template::render("account/2fa/index", ctx)))
 }
@@ -171,6 +200,8 @@ pub fn validate(form: Form<Validate>, user: OptionalWebUser, mut sess: Session,
   user.set_tfa_enabled(true);
   user.update(&conn)?;
 
+  Session::revoke_all(&mut redis, user.id(), Some(sess.id))?;
+
   let backups = generate_backup_codes(&conn, user.id())?;
   sess.add_data("backup_codes", backups.join("\n"));
 
@@ -203,7 +234,7 @@ pub fn disable_get(config: State<Config>, user: OptionalWebUser, mut sess: Sessi
 }
 
 #[post("/account/2fa/disable", format = "application/x-www-form-urlencoded", data = "<form>")]
-pub fn disable_post(form: Form<Disable>, user: OptionalWebUser, mut sess: Session, conn: DbConn, l10n: L10n) -> Result<Redirect> {
+pub fn disable_post(form: Form<Disable>, user: OptionalWebUser, mut sess: Session, conn: DbConn, mut redis: Redis, l10n: L10n) -> Result<Redirect> {
   let form = form.into_inner();
 
   if !sess.check_token(&form.anti_csrf_token) {
@@ -230,7 +261,12 @@ pub fn disable_post(form: Form<Disable>, user: OptionalWebUser, mut sess: Sessio
   user.set_shared_secret(None);
   user.update(&conn)?;
 
-  delete_backup_codes(&conn, user.id())?;
+  // backup codes are still needed if the user has security keys
+  if !user.second_factor_enabled(&conn)? {
+    delete_backup_codes(&conn, user.id())?;
+  }
+
+  Session::revoke_all(&mut redis, user.id(), Some(sess.id))?;
 
   Ok(Redirect::to(uri!(get)))
 }
@@ -253,7 +289,7 @@ pub fn new_backup_codes(form: Form<TokenOnly>, user: OptionalWebUser, mut sess:
     None => return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get))),
   };
 
-  if !user.tfa_enabled() {
+  if !user.second_factor_enabled(&conn)? {
     sess.add_data("error", l10n.tr(("tfa-error", "not-enabled"))?);
     return Ok(Redirect::to("lastpage"));
   }
@@ -264,6 +300,202 @@ pub fn new_backup_codes(form: Form<TokenOnly>, user: OptionalWebUser, mut sess:
   Ok(Redirect::to(uri!(get)))
 }
 
+#[get("/account/2fa/keys/add")]
+pub fn add_key_get(config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, langs: AcceptLanguage) -> Result<Rst> {
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Rst::Redirect(Redirect::to(uri!(crate::routes::web::auth::login::get)))),
+  };
+
+  // don't let the same key be registered twice
+  let existing = user.webauthn_credentials(&conn)?;
+  let challenge = webauthn::challenge();
+  let options = webauthn::creation_options(
+    &RelyingParty::from_config(&*config),
+    &challenge,
+    user.id().as_bytes(),
+    user.username(),
+    user.name(),
+    existing.iter().map(WebauthnCredential::credential_id),
+  );
+  sess.json.insert("webauthn_registration".into(), json!(challenge));
+
+  let mut ctx = context(&*config, Some(&user), &mut sess, langs);
+  ctx["options"] = json!(options.to_string());
+  ctx["links"] = json!(links!(super::account_links(),
+    "add_key" => uri!(crate::routes::web::account::two_factor::add_key_post),
+  ));
+
+  Ok(Rst::Template(Template::render("account/2fa/add_key", ctx)))
+}
+
+#[post("/account/2fa/keys/add", format = "application/x-www-form-urlencoded", data = "<form>")]
+pub fn add_key_post(form: Form<AddKey>, config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, mut redis: Redis, l10n: L10n) -> Result<Redirect> {
+  let form = form.into_inner();
+
+  if !sess.check_token(&form.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Redirect::to("lastpage"));
+  }
+
+  let user = match user.into_inner() {
+    Some(u) => u,
+    None => return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get))),
+  };
+
+  let name = form.name.trim();
+  if name.is_empty() {
+    sess.add_data("error", l10n.tr(("tfa-key-error", "empty-name"))?);
+    return Ok(Redirect::to(uri!(add_key_get)));
+  }
+
+  // the challenge can only be used once
+  let challenge = sess.json
+    .remove("webauthn_registration")
+    .and_then(|x| x.as_str().map(ToOwned::to_owned));
+  let challenge = match challenge {
+    Some(c) => c,
+    None => {
+      sess.add_data("error", l10n.tr(("tfa-key-error", "failed"))?);
+      return Ok(Redirect::to(uri!(add_key_get)));
+    },
+  };
+
+  let rp = RelyingParty::from_config(&*config);
+  let cred = serde_json::from_str::<RegistrationResponse>(&form.credential)
+    .map_err(anyhow::Error::from)
+    .and_then(|resp| webauthn::verify_registration(&rp, &challenge, &resp));
+  let cred = match cred {
+    Ok(c) => c,
+    Err(e) => {
+      println!("could not register security key: {}", e);
+      sess.add_data("error", l10n.tr(("tfa-key-error", "failed"))?);
+      return Ok(Redirect::to(uri!(add_key_get)));
+    },
+  };
+
+  let registered: i64 = webauthn_credentials::table
+    .filter(webauthn_credentials::credential_id.eq(&cred.id))
+    .count()
+    .get_result(&*conn)?;
+  if registered > 0 {
+    sess.add_data("error", l10n.tr(("tfa-key-error", "duplicate"))?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  let first_factor = !user.second_factor_enabled(&conn)?;
+
+  let nk = NewWebauthnCredential::new(
+    WebauthnCredentialId(Uuid::new_v4()),
+    user.id(),
+    name.to_string(),
+    cred.id,
+    cred.public_key,
+    i64::from(cred.sign_count),
+  );
+  diesel::insert_into(webauthn_credentials::table)
+    .values(&nk)
+    .execute(&*conn)?;
+
+  Session::revoke_all(&mut redis, user.id(), Some(sess.id))?;
+
+  // the key just turned on 2fa, so give the user a way back in if they lose it
+  if first_factor {
+    let backups = generate_backup_codes(&conn, user.id())?;
+    sess.add_data("backup_codes", backups.join("\n"));
+  }
+
+  Ok(Redirect::to(uri!(get)))
+}
+
+#[derive(Debug, FromForm)]
+pub struct AddKey {
+  anti_csrf_token: String,
+  name: String,
+  credential: String,
+}
+
+#[patch("/account/2fa/keys/<id>", data = "<form>")]
+pub fn key_patch(id: WebauthnCredentialId, form: Form<RenameKey>, user: OptionalWebUser, mut sess: Session, conn: DbConn, l10n: L10n) -> Result<Redirect> {
+  let form = form.into_inner();
+
+  if !sess.check_token(&form.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get))),
+  };
+
+  let name = form.name.trim();
+  if name.is_empty() {
+    sess.add_data("error", l10n.tr(("tfa-key-error", "empty-name"))?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  let key: Option<WebauthnCredential> = webauthn_credentials::table
+    .filter(webauthn_credentials::id.eq(id).and(webauthn_credentials::user_id.eq(user.id())))
+    .first(&*conn)
+    .optional()?;
+  let mut key = match key {
+    Some(k) => k,
+    None => {
+      sess.add_data("error", l10n.tr(("tfa-key-error", "missing"))?);
+      return Ok(Redirect::to(uri!(get)));
+    },
+  };
+
+  key.set_name(name.to_string());
+  key.update(&conn)?;
+
+  Ok(Redirect::to(uri!(get)))
+}
+
+#[derive(Debug, FromForm)]
+pub struct RenameKey {
+  anti_csrf_token: String,
+  name: String,
+}
+
+#[delete("/account/2fa/keys/<id>", data = "<form>")]
+pub fn key_delete(id: WebauthnCredentialId, form: Form<Disable>, user: OptionalWebUser, mut sess: Session, conn: DbConn, mut redis: Redis, l10n: L10n) -> Result<Redirect> {
+  let form = form.into_inner();
+
+  if !sess.check_token(&form.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get))),
+  };
+
+  if !user.check_password(&form.password) {
+    sess.add_data("error", l10n.tr(("login-error", "password"))?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  let deleted = diesel::delete(webauthn_credentials::table)
+    .filter(webauthn_credentials::id.eq(id).and(webauthn_credentials::user_id.eq(user.id())))
+    .execute(&*conn)?;
+  if deleted == 0 {
+    sess.add_data("error", l10n.tr(("tfa-key-error", "missing"))?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  // removing the last second factor turns off 2fa
+  if !user.second_factor_enabled(&conn)? {
+    delete_backup_codes(&conn, user.id())?;
+  }
+
+  Session::revoke_all(&mut redis, user.id(), Some(sess.id))?;
+
+  Ok(Redirect::to(uri!(get)))
+}
+
 fn generate_secret(conn: &DbConn, user: &mut User) -> Result<()> {
   // make the shared secret and base32 encode it
   let raw_key = randombytes::randombytes(32);
@@ -296,7 +528,6 @@ fn secret_segments(s: &str) -> Vec<&str> {
 
 fn delete_backup_codes(conn: &DbConn, user: UserId) -> Result<()> {
   use crate::database::schema::backup_codes;
-  use diesel::prelude::*;
 
   // delete any existing backup codes
   diesel::delete(backup_codes::table)
@@ -309,7 +540,6 @@ fn delete_backup_codes(conn: &DbConn, user: UserId) -> Result<()> {
 fn generate_backup_codes(conn: &DbConn, user: UserId) -> Result<Vec<String>> {
   use crate::database::schema::backup_codes;
   use crate::database::models::backup_codes::NewBackupCode;
-  use diesel::prelude::*;
 
   // delete any existing backup codes
   delete_backup_codes(conn, user)?;
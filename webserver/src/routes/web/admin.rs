@@ -1,6 +1,6 @@
 pub mod config;
 pub mod index;
-// pub mod maintenance;
+pub mod maintenance;
 pub mod pastes;
 pub mod users;
 
@@ -22,7 +22,7 @@ pub(crate) fn admin_links() -> Links {
     "pastes" => uri!(self::pastes::get: _),
     "users" => uri!(self::users::get: _),
     "config" => uri!(self::config::get),
-    // "maintenance" => uri!(self::maintenance::get),
+    "maintenance" => uri!(self::maintenance::get),
   )
 }
 
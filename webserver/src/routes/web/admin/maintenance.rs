@@ -1,4 +1,54 @@
+use crate::{
+  config::Config,
+  errors::*,
+  i18n::prelude::*,
+  routes::web::{context, Rst, Session},
+  sidekiq::Job,
+  utils::AcceptLanguage,
+};
+
+use super::AdminUser;
+
+use rocket::{
+  State,
+  request::Form,
+  response::Redirect,
+};
+
+use rocket_contrib::templates::Template;
+
+use serde_json::json;
+
+use sidekiq::Client as SidekiqClient;
+
 #[get("/admin/maintenance")]
-pub fn get() -> String {
-  unimplemented!();
+pub fn get(config: State<Config>, user: AdminUser, mut sess: Session, langs: AcceptLanguage) -> Result<Rst> {
+  let user = user.into_inner();
+
+  let mut ctx = context(&*config, Some(&user), &mut sess, langs);
+  ctx["links"] = json!(super::admin_links()
+    .add_value("dedupe", uri!(dedupe).to_string()));
+
+  Ok(Rst::Template(Template::render("admin/maintenance", ctx)))
+}
+
+#[post("/admin/maintenance/dedupe", format = "application/x-www-form-urlencoded", data = "<form>")]
+pub fn dedupe(form: Form<Dedupe>, config: State<Config>, _user: AdminUser, mut sess: Session, sidekiq: State<SidekiqClient>, l10n: L10n) -> Result<Redirect> {
+  // check the anti csrf token
+  if !sess.check_token(&form.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Redirect::to(uri!(get)));
+  }
+
+  // add a job to move existing pastes into the blob store
+  sidekiq.push(Job::DedupeStore(&*config).into())?;
+
+  sess.add_data("info", l10n.tr(("admin-maintenance-dedupe", "queued"))?);
+
+  Ok(Redirect::to(uri!(get)))
+}
+
+#[derive(FromForm)]
+pub struct Dedupe {
+  pub anti_csrf_token: String,
 }
@@ -11,6 +11,7 @@ use crate::{
     id::UserId,
     user::Admin,
   },
+  redis_store::Redis,
   routes::web::{context, Links, Rst, Session},
   sidekiq::Job,
   utils::AcceptLanguage,
@@ -76,8 +77,8 @@ pub fn get(page: Option<u32>, config: State<Config>, user: AdminUser, mut sess:
   let mut ctx = context(&*config, Some(&user), &mut sess, langs);
   // add 2fa statuses
   ctx["tfa"] = json!(users.iter()
-    .map(|u| (u.id(), u.tfa_enabled()))
-    .collect::<HashMap<UserId, bool>>());
+    .map(|u| Ok((u.id(), u.second_factor_enabled(&conn)?)))
+    .collect::<Result<HashMap<UserId, bool>>>()?);
   // add some links
   ctx["links"] = json!(super::admin_links()
     // add the deletion endpoints
@@ -101,6 +102,13 @@ pub fn get(page: Option<u32>, config: State<Config>, user: AdminUser, mut sess:
         x.id().to_simple().to_string(),
         uri!(demote: x.id()),
       )))
+    // add the sign out endpoints
+    .add_value("sign_out", users
+      .iter()
+      .fold(&mut Links::default(), |l, x| l.add(
+        x.id().to_simple().to_string(),
+        uri!(sign_out: x.id()),
+      )))
     // add the previous page link
     .add("prev", if page > 2 {
       uri!(get: page as u32 - 1)
@@ -126,7 +134,7 @@ pub fn get(page: Option<u32>, config: State<Config>, user: AdminUser, mut sess:
 }
 
 #[delete("/admin/users/<id>", format = "application/x-www-form-urlencoded", data = "<form>")]
-pub fn delete(id: UserId, form: Form<Delete>, config: State<Config>, user: AdminUser, mut sess: Session, conn: DbConn, sidekiq: State<SidekiqClient>, l10n: L10n) -> Result<Redirect> {
+pub fn delete(id: UserId, form: Form<Delete>, config: State<Config>, user: AdminUser, mut sess: Session, conn: DbConn, mut redis: Redis, sidekiq: State<SidekiqClient>, l10n: L10n) -> Result<Redirect> {
   // check the anti csrf token
   if !sess.check_token(&form.anti_csrf_token) {
     sess.add_data("error", l10n.tr("error-csrf")?);
@@ -157,6 +165,9 @@ pub fn delete(id: UserId, form: Form<Delete>, config: State<Config>, user: Admin
   // delete the user
   target.delete(&conn)?;
 
+  // sign them out everywhere
+  Session::revoke_all(&mut redis, target.id(), None)?;
+
   // add a job to delete all their pastes
   sidekiq.push(Job::DeleteAllPastes(&*config, target.id()).into())?;
 
@@ -280,3 +291,45 @@ pub fn demote(id: UserId, form: Form<Demote>, user: AdminUser, mut sess: Session
 pub struct Demote {
   pub anti_csrf_token: String,
 }
+
+#[delete("/admin/users/<id>/sessions", format = "application/x-www-form-urlencoded", data = "<form>")]
+pub fn sign_out(id: UserId, form: Form<SignOut>, user: AdminUser, mut sess: Session, conn: DbConn, mut redis: Redis, l10n: L10n) -> Result<Redirect> {
+  // check the anti csrf token
+  if !sess.check_token(&form.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Redirect::to("lastpage"));
+  }
+
+  // get the user from the id
+  let target = match id.get(&conn)? {
+    Some(u) => u,
+    None => {
+      sess.add_data("error", l10n.tr(("admin-users-sign-out", "missing"))?);
+      return Ok(Redirect::to("lastpage"));
+    },
+  };
+
+  // can't sign out superadmins, and admins can't sign out other admins
+  if target.admin() == Admin::Super && user.id() != target.id() {
+    sess.add_data("error", l10n.tr(("admin-users-sign-out", "super"))?);
+    return Ok(Redirect::to("lastpage"));
+  }
+  if user.admin() == Admin::Normal && target.admin() == Admin::Normal && user.id() != target.id() {
+    sess.add_data("error", l10n.tr(("admin-users-sign-out", "other-admin"))?);
+    return Ok(Redirect::to("lastpage"));
+  }
+
+  // end every session, keeping the admin's own
+  Session::revoke_all(&mut redis, target.id(), Some(sess.id))?;
+
+  // add a notification of success
+  sess.add_data("info", l10n.tr(("admin-users-sign-out", "success"))?);
+
+  // go back
+  Ok(Redirect::to("lastpage"))
+}
+
+#[derive(FromForm)]
+pub struct SignOut {
+  pub anti_csrf_token: String,
+}
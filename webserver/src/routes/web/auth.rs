@@ -1,5 +1,6 @@
 pub mod login;
 pub mod logout;
+pub mod oidc;
 pub mod register;
 
 use chrono::Duration;
@@ -1,22 +1,33 @@
 use crate::{
-  config::Config,
+  config::{Config, Ldap},
   database::{
     DbConn,
-    models::{backup_codes::BackupCode, login_attempts::LoginAttempt, users::User},
-    schema::{backup_codes, users},
+    models::{
+      backup_codes::BackupCode,
+      ldap_identities::{LdapIdentity, NewLdapIdentity},
+      login_attempts::LoginAttempt,
+      users::{User, NewUser},
+      webauthn_credentials::WebauthnCredential,
+    },
+    schema::{backup_codes, ldap_identities, users, webauthn_credentials},
   },
   errors::*,
   i18n::prelude::*,
+  models::id::UserId,
   redis_store::Redis,
   routes::web::{context, AddCsp, Honeypot, Rst, OptionalWebUser, Session, auth::PotentialUser},
   utils::{
+    email,
     AcceptLanguage,
     ClientIp,
+    HashedPassword,
+    Validator,
     totp::totp_raw_skew,
+    webauthn::{self, AssertionResponse, RelyingParty},
   },
 };
 
-use diesel::prelude::*;
+use diesel::{dsl::count_star, prelude::*};
 
 use r2d2_redis::redis::Commands;
 
@@ -31,6 +42,8 @@ use rocket_contrib::templates::Template;
 
 use serde_json::json;
 
+use uuid::Uuid;
+
 #[get("/login")]
 pub fn get(config: State<Config>, user: OptionalWebUser, mut sess: Session, langs: AcceptLanguage) -> AddCsp<Rst> {
   if user.is_some() {
@@ -43,6 +56,7 @@ pub fn get(config: State<Config>, user: OptionalWebUser, mut sess: Session, lang
   ctx["links"] = json!(links!(
     "login_action" => uri!(crate::routes::web::auth::login::post),
     "forgot_password" => uri!(crate::routes::web::account::reset_password::get),
+    "oidc" => uri!(crate::routes::web::auth::oidc::get),
   ));
   AddCsp::new(
     Rst::Template(Template::render("auth/login", ctx)),
@@ -63,7 +77,7 @@ pub struct RegistrationData {
 }
 
 #[post("/login", format = "application/x-www-form-urlencoded", data = "<data>")]
-pub fn post(data: Form<RegistrationData>, mut sess: Session, conn: DbConn, mut redis: Redis, mut cookies: Cookies, addr: ClientIp, l10n: L10n) -> Result<Redirect> {
+pub fn post(data: Form<RegistrationData>, config: State<Config>, mut sess: Session, conn: DbConn, mut redis: Redis, mut cookies: Cookies, addr: ClientIp, l10n: L10n) -> Result<Redirect> {
   let data = data.into_inner();
   sess.set_form(&data);
 
@@ -72,6 +86,16 @@ pub fn post(data: Form<RegistrationData>, mut sess: Session, conn: DbConn, mut r
     return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get)));
   }
 
+  let (password_login, ldap) = {
+    let config = config.read();
+    (config.login.password, config.login.ldap.clone())
+  };
+
+  if !password_login && ldap.is_none() {
+    sess.add_data("error", l10n.tr(("login-error", "password-disabled"))?);
+    return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get)));
+  }
+
   if !data.honeypot.is_empty() {
     sess.add_data("error", l10n.tr(("antispam-honeypot", "error"))?);
     return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get)));
@@ -82,10 +106,44 @@ pub fn post(data: Form<RegistrationData>, mut sess: Session, conn: DbConn, mut r
     return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get)));
   }
 
-  let user: Option<User> = users::table
-    .filter(users::username.eq(&data.username))
-    .first(&*conn)
-    .optional()?;
+  // check the directory first, then fall back to local passwords
+  let ldap_user = match ldap {
+    Some(ref ldap) => match ldap_login(ldap, &conn, &l10n, &data.username, &data.password) {
+      Ok(Some(Ok(u))) => Some(u),
+      // a local account with the directory user's name can still log in with its own password
+      Ok(Some(Err(msg))) if password_login => {
+        println!("could not log in with ldap: {}", msg);
+        None
+      },
+      Ok(Some(Err(msg))) => {
+        sess.add_data("error", msg);
+        return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get)));
+      },
+      Ok(None) => None,
+      // don't let a directory outage lock out local accounts
+      Err(e) if password_login => {
+        println!("could not log in with ldap: {}", e);
+        None
+      },
+      Err(e) => {
+        println!("could not log in with ldap: {}", e);
+        sess.add_data("error", l10n.tr(("login-error", "ldap"))?);
+        return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get)));
+      },
+    },
+    None => None,
+  };
+
+  let ldap_user_id = ldap_user.as_ref().map(User::id);
+
+  let user: Option<User> = match ldap_user {
+    Some(u) => Some(u),
+    None if password_login => users::table
+      .filter(users::username.eq(&data.username))
+      .first(&*conn)
+      .optional()?,
+    None => None,
+  };
 
   let user = match user {
     Some(u) => u,
@@ -99,7 +157,7 @@ pub fn post(data: Form<RegistrationData>, mut sess: Session, conn: DbConn, mut r
     },
   };
 
-  if !user.check_password(&data.password) {
+  if ldap_user_id != Some(user.id()) && !user.check_password(&data.password) {
     let msg = match LoginAttempt::find_increment(&conn, &l10n, *addr)? {
       Some(msg) => msg,
       None => l10n.tr(("login-error", "password"))?,
@@ -110,7 +168,7 @@ pub fn post(data: Form<RegistrationData>, mut sess: Session, conn: DbConn, mut r
 
   sess.take_form();
 
-  if user.tfa_enabled() {
+  if user.second_factor_enabled(&conn)? {
     PotentialUser::set(&mut redis, &mut cookies, user.id())?;
     return Ok(Redirect::to(uri!(tfa)));
   }
@@ -120,21 +178,137 @@ pub fn post(data: Form<RegistrationData>, mut sess: Session, conn: DbConn, mut r
   Ok(Redirect::to("lastpage"))
 }
 
-#[get("/login/2fa")]
-pub fn tfa(config: State<Config>, user: OptionalWebUser, pot: Option<PotentialUser>, mut sess: Session, langs: AcceptLanguage) -> Rst {
-  if user.is_some() || pot.is_none() {
-    return Rst::Redirect(Redirect::to("lastpage"));
+/// Checks the username and password against the directory, creating or updating the local user.
+///
+/// Only users created for a directory entry are logged in through it, so a directory entry can never
+/// take over an account with the same username. The inner error is a message to show the user.
+///
+/// Returns `None` if the directory doesn't know the user or the password is wrong.
+fn ldap_login(ldap: &Ldap, conn: &DbConn, l10n: &L10n, username: &str, password: &str) -> Result<Option<std::result::Result<User, String>>> {
+  let ldap_user = match crate::utils::ldap::authenticate(ldap, username, password)? {
+    Some(u) => u,
+    None => return Ok(None),
+  };
+
+  let name = ldap_user.name
+    .as_deref()
+    .and_then(|x| Validator::validate_display_name(x).ok())
+    .map(|x| x.into_owned());
+  let email = ldap_user.email.filter(|x| email::check_email(x));
+
+  let identity: Option<LdapIdentity> = ldap_identities::table
+    .find(&ldap_user.dn)
+    .first(&**conn)
+    .optional()?;
+
+  // keep the local user in sync with the directory
+  if let Some(identity) = identity {
+    let mut user: User = users::table.find(identity.user_id()).first(&**conn)?;
+    if let Some(name) = name {
+      user.set_name(name);
+    }
+    if let Some(email) = email {
+      if email != user.email() {
+        // never give a user an email that already belongs to another account
+        let taken: i64 = users::table
+          .filter(users::email.eq(&email).and(users::id.ne(user.id())))
+          .select(count_star())
+          .get_result(&**conn)?;
+        if taken > 0 {
+          println!("not syncing ldap email for {}: another account uses {}", user.id(), email);
+        } else {
+          user.set_email(email);
+          user.set_email_verified(true);
+        }
+      }
+    }
+    if let Some(admin) = ldap_user.admin {
+      user.set_admin(admin);
+    }
+    user.update(conn)?;
+    return Ok(Some(Ok(user)));
   }
 
+  let username = Validator::validate_username(&ldap_user.username)
+    .map_err(|e| anyhow::anyhow!("invalid username {} from ldap: {}", ldap_user.username, e))?
+    .into_owned();
+  let email = email.ok_or_else(|| anyhow::anyhow!("ldap user {} has no valid email", username))?;
+
+  let existing_names: i64 = users::table
+    .filter(users::username.eq(&username))
+    .select(count_star())
+    .get_result(&**conn)?;
+  if existing_names > 0 {
+    return Ok(Some(Err(l10n.tr(("login-error", "ldap-duplicate-username"))?)));
+  }
+
+  // the directory holds the password, so the local one is never used
+  let nu = NewUser::new(
+    UserId(Uuid::new_v4()),
+    username.clone(),
+    HashedPassword::random().into_string(),
+    Some(name.unwrap_or(username)),
+    Some(email),
+  );
+
+  let (dn, admin) = (ldap_user.dn, ldap_user.admin);
+  let user = conn.transaction::<_, anyhow::Error, _>(|| {
+    let mut user: User = diesel::insert_into(users::table)
+      .values(&nu)
+      .get_result(&**conn)?;
+
+    diesel::insert_into(ldap_identities::table)
+      .values(&NewLdapIdentity::new(dn, user.id()))
+      .execute(&**conn)?;
+
+    user.set_email_verified(true);
+    if let Some(admin) = admin {
+      user.set_admin(admin);
+    }
+    user.update(conn)?;
+
+    Ok(user)
+  })?;
+
+  Ok(Some(Ok(user)))
+}
+
+#[get("/login/2fa")]
+pub fn tfa(config: State<Config>, user: OptionalWebUser, pot: Option<PotentialUser>, mut sess: Session, conn: DbConn, langs: AcceptLanguage) -> Result<Rst> {
+  let pot = match (user.is_some(), pot) {
+    (false, Some(pot)) => pot,
+    _ => return Ok(Rst::Redirect(Redirect::to("lastpage"))),
+  };
+
+  let keys = match pot.get(&conn)? {
+    Some(u) => u.webauthn_credentials(&conn)?,
+    None => return Ok(Rst::Redirect(Redirect::to("lastpage"))),
+  };
+
+  // offer security keys if the user has any
+  let options = if keys.is_empty() {
+    None
+  } else {
+    let challenge = webauthn::challenge();
+    let options = webauthn::request_options(
+      &RelyingParty::from_config(&*config),
+      &challenge,
+      keys.iter().map(WebauthnCredential::credential_id),
+    );
+    sess.json.insert("webauthn_login".into(), json!(challenge));
+    Some(options.to_string())
+  };
+
   let mut ctx = context(&*config, user.as_ref(), &mut sess, langs);
+  ctx["webauthn_options"] = json!(options);
   ctx["links"] = json!(links!(
     "tfa_action" => uri!(tfa_post),
   ));
-  Rst::Template(Template::render("auth/2fa", ctx))
+  Ok(Rst::Template(Template::render("auth/2fa", ctx)))
 }
 
 #[post("/login/2fa", format = "application/x-www-form-urlencoded", data = "<form>")]
-pub fn tfa_post(form: Form<TwoFactor>, pot: PotentialUser, mut sess: Session, conn: DbConn, mut redis: Redis, mut cookies: Cookies, addr: ClientIp, l10n: L10n) -> Result<Redirect> {
+pub fn tfa_post(form: Form<TwoFactor>, config: State<Config>, pot: PotentialUser, mut sess: Session, conn: DbConn, mut redis: Redis, mut cookies: Cookies, addr: ClientIp, l10n: L10n) -> Result<Redirect> {
   if !sess.check_token(&form.anti_csrf_token) {
     sess.add_data("error", l10n.tr("error-csrf")?);
     return Ok(Redirect::to(uri!(crate::routes::web::auth::login::get)));
@@ -145,15 +319,28 @@ pub fn tfa_post(form: Form<TwoFactor>, pot: PotentialUser, mut sess: Session, co
     None => return Ok(Redirect::to("lastpage")),
   };
 
+  // the challenge can only be used once
+  let challenge = sess.json
+    .remove("webauthn_login")
+    .and_then(|x| x.as_str().map(ToOwned::to_owned));
+
   let mut tfa_check = || -> Result<bool> {
-    if !user.tfa_enabled() {
+    if !user.second_factor_enabled(&conn)? {
       return Ok(true);
     }
 
+    if let Some(assertion) = form.webauthn.as_deref().filter(|x| !x.is_empty()) {
+      return match challenge {
+        Some(ref challenge) => check_webauthn(&*config, &conn, &user, challenge, assertion),
+        None => Ok(false),
+      };
+    }
+
     let tfa_code_s = &form.code;
 
     match tfa_code_s.len() {
       6 => if_chain! {
+        if user.tfa_enabled();
         if let Some(ss) = user.shared_secret();
         if let Ok(tfa_code) = tfa_code_s.parse::<u64>();
         if !redis.exists::<_, bool>(format!("otp:{},{}", user.id(), tfa_code))?;
@@ -196,8 +383,44 @@ pub fn tfa_post(form: Form<TwoFactor>, pot: PotentialUser, mut sess: Session, co
   Ok(Redirect::to("lastpage"))
 }
 
+/// Checks a security key assertion against the user's registered keys, updating the key's counter.
+fn check_webauthn(config: &Config, conn: &DbConn, user: &User, challenge: &str, assertion: &str) -> Result<bool> {
+  let resp: AssertionResponse = match serde_json::from_str(assertion) {
+    Ok(r) => r,
+    Err(_) => return Ok(false),
+  };
+  let id = match resp.credential_id() {
+    Ok(id) => id,
+    Err(_) => return Ok(false),
+  };
+
+  let key: Option<WebauthnCredential> = webauthn_credentials::table
+    .filter(webauthn_credentials::user_id.eq(user.id()).and(webauthn_credentials::credential_id.eq(&id)))
+    .first(&**conn)
+    .optional()?;
+  let mut key = match key {
+    Some(k) => k,
+    None => return Ok(false),
+  };
+
+  let rp = RelyingParty::from_config(config);
+  let sign_count = match webauthn::verify_assertion(&rp, challenge, key.public_key(), key.sign_count() as u32, &resp) {
+    Ok(c) => c,
+    Err(e) => {
+      println!("could not verify security key for {}: {}", user.id(), e);
+      return Ok(false);
+    },
+  };
+
+  key.set_used(i64::from(sign_count));
+  key.update(conn)?;
+
+  Ok(true)
+}
+
 #[derive(FromForm)]
 pub struct TwoFactor {
   pub anti_csrf_token: String,
   pub code: String,
+  pub webauthn: Option<String>,
 }
@@ -0,0 +1,212 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::{
+      oidc_identities::{OidcIdentity, NewOidcIdentity},
+      users::{User, NewUser},
+    },
+    schema::{oidc_identities, users},
+  },
+  errors::*,
+  i18n::prelude::*,
+  models::id::UserId,
+  redis_store::Redis,
+  routes::web::{OptionalWebUser, Rst, Session, auth::PotentialUser},
+  utils::{
+    HashedPassword, Validator,
+    oidc::{AuthRequest, IdClaims, Provider},
+  },
+};
+
+use diesel::{dsl::count_star, prelude::*};
+
+use reqwest::blocking::Client;
+
+use rocket::{
+  State,
+  http::{Cookies, Status as HttpStatus},
+  response::Redirect,
+};
+
+use serde_json::json;
+
+use uuid::Uuid;
+
+fn redirect_uri(config: &Config) -> String {
+  format!("https://{}{}", config.read().general.site_domain, uri!(callback: _, _, _))
+}
+
+#[get("/login/oidc")]
+pub fn get(config: State<Config>, client: State<Client>, user: OptionalWebUser, mut sess: Session, l10n: L10n) -> Result<Rst> {
+  if user.is_some() {
+    return Ok(Rst::Redirect(Redirect::to("lastpage")));
+  }
+
+  let oidc = match config.read().login.oidc.clone() {
+    Some(o) => o,
+    None => return Ok(Rst::Status(HttpStatus::NotFound)),
+  };
+
+  let provider = match Provider::get(&*client, &oidc) {
+    Ok(p) => p,
+    Err(e) => {
+      println!("could not get oidc provider: {}", e);
+      sess.add_data("error", l10n.tr(("login-error", "oidc"))?);
+      return Ok(Rst::Redirect(Redirect::to(uri!(super::login::get))));
+    },
+  };
+
+  // keep the state, nonce and pkce verifier in the session for the callback
+  let auth = AuthRequest::generate();
+  let url = provider.authorization_url(&oidc, &redirect_uri(&*config), &auth)?;
+  sess.json.insert("oidc".into(), json!(auth));
+
+  Ok(Rst::Redirect(Redirect::to(url.to_string())))
+}
+
+#[get("/login/oidc/callback?<code>&<state>&<error>")]
+pub fn callback(code: Option<String>, state: Option<String>, error: Option<String>, config: State<Config>, client: State<Client>, user: OptionalWebUser, mut sess: Session, conn: DbConn, mut redis: Redis, mut cookies: Cookies, l10n: L10n) -> Result<Rst> {
+  if user.is_some() {
+    return Ok(Rst::Redirect(Redirect::to("lastpage")));
+  }
+
+  let oidc = match config.read().login.oidc.clone() {
+    Some(o) => o,
+    None => return Ok(Rst::Status(HttpStatus::NotFound)),
+  };
+
+  // the auth request can only be used once
+  let auth: Option<AuthRequest> = sess.json
+    .remove("oidc")
+    .and_then(|x| serde_json::from_value(x).ok());
+
+  let (auth, code) = match (auth, code, error) {
+    (Some(auth), Some(code), None) if state.as_deref() == Some(&auth.state) => (auth, code),
+    _ => {
+      sess.add_data("error", l10n.tr(("login-error", "oidc"))?);
+      return Ok(Rst::Redirect(Redirect::to(uri!(super::login::get))));
+    },
+  };
+
+  let result = Provider::get(&*client, &oidc)
+    .and_then(|p| p.exchange(&*client, &oidc, &redirect_uri(&*config), &auth, &code).map(|c| (p, c)));
+  let (provider, claims) = match result {
+    Ok(x) => x,
+    Err(e) => {
+      println!("could not complete oidc login: {}", e);
+      sess.add_data("error", l10n.tr(("login-error", "oidc"))?);
+      return Ok(Rst::Redirect(Redirect::to(uri!(super::login::get))));
+    },
+  };
+  let issuer = &provider.discovery.issuer;
+
+  let identity: Option<OidcIdentity> = oidc_identities::table
+    .find((issuer, &claims.sub))
+    .first(&*conn)
+    .optional()?;
+
+  let user: User = match identity {
+    Some(identity) => users::table.find(identity.user_id()).first(&*conn)?,
+    None => match link_or_register(&*config, oidc.auto_register, &conn, &claims, &l10n)? {
+      Ok(user) => {
+        diesel::insert_into(oidc_identities::table)
+          .values(&NewOidcIdentity::new(issuer.clone(), claims.sub.clone(), user.id()))
+          .execute(&*conn)?;
+        user
+      },
+      Err(msg) => {
+        sess.add_data("error", msg);
+        return Ok(Rst::Redirect(Redirect::to(uri!(super::login::get))));
+      },
+    },
+  };
+
+  if user.second_factor_enabled(&conn)? {
+    PotentialUser::set(&mut redis, &mut cookies, user.id())?;
+    return Ok(Rst::Redirect(Redirect::to(uri!(super::login::tfa))));
+  }
+
+  sess.user_id = Some(user.id());
+
+  Ok(Rst::Redirect(Redirect::to("lastpage")))
+}
+
+/// Finds an existing user with the same verified email or, if allowed, creates a new user.
+///
+/// The inner error is a message to show the user.
+fn link_or_register(config: &Config, auto_register: bool, conn: &DbConn, claims: &IdClaims, l10n: &L10n) -> Result<std::result::Result<User, String>> {
+  let email = match claims.verified_email() {
+    Some(e) => e,
+    None => return Ok(Err(l10n.tr(("login-error", "oidc-unverified-email"))?)),
+  };
+
+  let existing: Option<User> = users::table
+    .filter(users::email.eq(email))
+    .first(&**conn)
+    .optional()?;
+
+  if let Some(user) = existing {
+    // only link accounts whose owner has proven they own the email
+    if !user.email_verified() {
+      return Ok(Err(l10n.tr(("login-error", "oidc-unverified-account"))?));
+    }
+    return Ok(Ok(user));
+  }
+
+  if !auto_register {
+    return Ok(Err(l10n.tr(("login-error", "oidc-no-account"))?));
+  }
+
+  {
+    let config = config.read();
+    if !config.registration.open && !config.registration.whitelisted_emails.iter().any(|x| x == email) {
+      return Ok(Err(l10n.tr(("register-error", "closed"))?));
+    }
+  }
+
+  let username = claims.preferred_username
+    .as_deref()
+    .unwrap_or_else(|| email.split('@').next().unwrap_or(email));
+  let username = match Validator::validate_username(username) {
+    Ok(u) => u.into_owned(),
+    Err(e) => return Ok(Err(l10n.tr_ex(
+      ("account-error", "invalid-username"),
+      |req| req.arg("err", e),
+    )?)),
+  };
+
+  let existing_names: i64 = users::table
+    .filter(users::username.eq(&username))
+    .select(count_star())
+    .get_result(&**conn)?;
+  if existing_names > 0 {
+    return Ok(Err(l10n.tr(("login-error", "oidc-duplicate-username"))?));
+  }
+
+  // fall back to the username if the provider's name isn't usable
+  let display_name = claims.name
+    .as_deref()
+    .and_then(|x| Validator::validate_display_name(x).ok())
+    .map(|x| x.into_owned())
+    .unwrap_or_else(|| username.clone());
+
+  // the user never sees this password, but can reset it to log in without the provider
+  let nu = NewUser::new(
+    UserId(Uuid::new_v4()),
+    username,
+    HashedPassword::random().into_string(),
+    Some(display_name),
+    Some(email.to_string()),
+  );
+
+  let mut user: User = diesel::insert_into(users::table)
+    .values(&nu)
+    .get_result(&**conn)?;
+
+  // the provider has already verified the email
+  user.set_email_verified(true);
+  user.update(conn)?;
+
+  Ok(Ok(user))
+}
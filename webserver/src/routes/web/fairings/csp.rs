@@ -69,6 +69,10 @@ impl CspHeader {
 
   fn update_directive(&mut self, dir: &str, update: &str) {
     let entry = self.directives.entry(dir.to_string()).or_insert_with(Default::default);
+    // 'none' can't be combined with other sources, so replace it instead
+    if entry == "'none'" && !update.is_empty() {
+      entry.clear();
+    }
     if !entry.is_empty() {
       *entry += " ";
     }
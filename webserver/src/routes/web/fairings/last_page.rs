@@ -28,12 +28,17 @@ impl LastPage {
     // don't track auth pages
     if path == "/login"
       || path == "/login/2fa"
+      || path.starts_with("/login/oidc")
       || path.starts_with("/admin/pastes/") && path.ends_with("/delete")
       || path == "/register"
       || path == "/favicon.ico"
       || path.starts_with("/static/")
       || path.starts_with("/account/reset_password")
       || path.starts_with("/account/avatar/")
+      || path.ends_with("/feed.atom")
+      || path.ends_with("/feed.rss")
+      || path == "/oembed"
+      || path.starts_with("/p/") && path.ends_with("/embed")
     {
       return;
     }
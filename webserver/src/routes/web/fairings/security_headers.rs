@@ -1,4 +1,7 @@
+use crate::config::Config;
+
 use rocket::{
+  State,
   fairing::{Fairing, Info, Kind},
   http::Header,
   request::Request,
@@ -16,7 +19,15 @@ impl Fairing for SecurityHeaders {
   }
 
   fn on_response(&self, req: &Request, resp: &mut Response) {
-    resp.set_header(Header::new("X-Frame-Options", "DENY"));
+    // embeds may be framed by allow-listed origins, which is handled by their frame-ancestors
+    // CSP directive, as X-Frame-Options can't express an allow-list
+    let can_frame = is_embed(req.uri().path()) && req.guard::<State<Config>>()
+      .succeeded()
+      .map(|config| !config.read().embeds.allowed_origins.is_empty())
+      .unwrap_or(false);
+    if !can_frame {
+      resp.set_header(Header::new("X-Frame-Options", "DENY"));
+    }
     resp.set_header(Header::new("X-XSS-Protection", "1; mode=block"));
     resp.set_header(Header::new("X-Content-Type-Options", "nosniff"));
     resp.set_header(Header::new("Referrer-Policy", "strict-origin-when-cross-origin"));
@@ -27,3 +38,7 @@ impl Fairing for SecurityHeaders {
     }
   }
 }
+
+fn is_embed(path: &str) -> bool {
+  path.starts_with("/p/") && path.ends_with("/embed")
+}
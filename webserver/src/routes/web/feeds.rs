@@ -0,0 +1,181 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::{pastes::Paste as DbPaste, users::User},
+    schema::{pastes, users},
+  },
+  errors::*,
+  models::paste::Visibility,
+  utils::AcceptLanguage,
+};
+
+use chrono::{DateTime, Utc};
+
+use diesel::prelude::*;
+
+use rocket::{
+  http::{ContentType, Status as HttpStatus},
+  response::content::Content,
+  State,
+};
+
+use rocket_contrib::templates::Template;
+
+use serde_json::json;
+
+const FEED_SIZE: i64 = 25;
+
+#[derive(Debug, Clone, Copy)]
+pub enum FeedKind {
+  Atom,
+  Rss,
+}
+
+impl FeedKind {
+  fn template(self) -> &'static str {
+    match self {
+      FeedKind::Atom => "feeds/atom",
+      FeedKind::Rss => "feeds/rss",
+    }
+  }
+
+  fn content_type(self) -> ContentType {
+    match self {
+      FeedKind::Atom => ContentType::new("application", "atom+xml"),
+      FeedKind::Rss => ContentType::new("application", "rss+xml"),
+    }
+  }
+}
+
+#[derive(Responder)]
+pub enum Feed {
+  Feed(Content<Template>),
+  Status(HttpStatus),
+}
+
+#[derive(Debug, Serialize)]
+struct FeedEntry {
+  id: String,
+  name: Option<String>,
+  description: Option<String>,
+  author_name: String,
+  author_link: Option<String>,
+  link: String,
+  created_at: DateTime<Utc>,
+  updated_at: DateTime<Utc>,
+}
+
+impl FeedEntry {
+  fn new(config: &Config, paste: &DbPaste, author: Option<&User>) -> Self {
+    let domain = config.read().general.site_domain.clone();
+    let username = author.map(User::username).unwrap_or("anonymous");
+
+    FeedEntry {
+      id: format!("urn:uuid:{}", paste.id().to_hyphenated()),
+      name: paste.name().map(ToString::to_string),
+      description: paste.description().map(ToString::to_string),
+      author_name: author.map(|x| x.name().to_string()).unwrap_or_else(|| "anonymous".into()),
+      author_link: author.map(|x| format!(
+        "https://{}{}",
+        domain,
+        uri!(crate::routes::web::users::get::get: x.username(), _),
+      )),
+      link: format!(
+        "https://{}{}",
+        domain,
+        uri!(crate::routes::web::pastes::get::users_username_id: username, paste.id()),
+      ),
+      created_at: paste.created_at(),
+      // fall back to the creation date if the repository can't be read
+      updated_at: paste.updated_at(config).unwrap_or_else(|_| paste.created_at()),
+    }
+  }
+}
+
+fn render(kind: FeedKind, config: &Config, langs: AcceptLanguage, target: Option<&User>, entries: Vec<FeedEntry>) -> Feed {
+  let domain = config.read().general.site_domain.clone();
+
+  let (self_link, alternate_link) = match (kind, target) {
+    (FeedKind::Atom, None) => (uri!(all_atom), uri!(crate::routes::web::index::get)),
+    (FeedKind::Rss, None) => (uri!(all_rss), uri!(crate::routes::web::index::get)),
+    (FeedKind::Atom, Some(u)) => (uri!(user_atom: u.username()), uri!(crate::routes::web::users::get::get: u.username(), _)),
+    (FeedKind::Rss, Some(u)) => (uri!(user_rss: u.username()), uri!(crate::routes::web::users::get::get: u.username(), _)),
+  };
+
+  // the feed is as new as its newest entry
+  let updated = entries
+    .iter()
+    .map(|x| x.updated_at)
+    .max()
+    .unwrap_or_else(Utc::now);
+
+  let ctx = json!({
+    "config": &*config.read(),
+    "langs": langs.into_strings(),
+    "target": target,
+    "self_link": format!("https://{}{}", domain, self_link),
+    "alternate_link": format!("https://{}{}", domain, alternate_link),
+    "updated": updated,
+    "entries": entries,
+  });
+
+  Feed::Feed(Content(kind.content_type(), Template::render(kind.template(), ctx)))
+}
+
+fn all(kind: FeedKind, config: &Config, conn: &DbConn, langs: AcceptLanguage) -> Result<Feed> {
+  let pastes: Vec<(DbPaste, Option<User>)> = pastes::table
+    .left_join(users::table)
+    .filter(pastes::visibility.eq(Visibility::Public))
+    .order(pastes::created_at.desc())
+    .limit(FEED_SIZE)
+    .load(&**conn)?;
+
+  let entries = pastes
+    .iter()
+    .map(|(paste, author)| FeedEntry::new(config, paste, author.as_ref()))
+    .collect();
+
+  Ok(render(kind, config, langs, None, entries))
+}
+
+fn user(kind: FeedKind, username: &str, config: &Config, conn: &DbConn, langs: AcceptLanguage) -> Result<Feed> {
+  let target: User = match users::table.filter(users::username.eq(username)).first(&**conn).optional()? {
+    Some(u) => u,
+    None => return Ok(Feed::Status(HttpStatus::NotFound)),
+  };
+
+  // feeds are always anonymous, so only ever include public pastes, even for the owner
+  let pastes: Vec<DbPaste> = DbPaste::belonging_to(&target)
+    .filter(pastes::visibility.eq(Visibility::Public))
+    .order_by(pastes::created_at.desc())
+    .limit(FEED_SIZE)
+    .load(&**conn)?;
+
+  let entries = pastes
+    .iter()
+    .map(|paste| FeedEntry::new(config, paste, Some(&target)))
+    .collect();
+
+  Ok(render(kind, config, langs, Some(&target), entries))
+}
+
+#[get("/feed.atom")]
+pub fn all_atom(config: State<Config>, conn: DbConn, langs: AcceptLanguage) -> Result<Feed> {
+  all(FeedKind::Atom, &*config, &conn, langs)
+}
+
+#[get("/feed.rss")]
+pub fn all_rss(config: State<Config>, conn: DbConn, langs: AcceptLanguage) -> Result<Feed> {
+  all(FeedKind::Rss, &*config, &conn, langs)
+}
+
+#[get("/u/<username>/feed.atom")]
+pub fn user_atom(username: String, config: State<Config>, conn: DbConn, langs: AcceptLanguage) -> Result<Feed> {
+  user(FeedKind::Atom, &username, &*config, &conn, langs)
+}
+
+#[get("/u/<username>/feed.rss")]
+pub fn user_rss(username: String, config: State<Config>, conn: DbConn, langs: AcceptLanguage) -> Result<Feed> {
+  user(FeedKind::Rss, &username, &*config, &conn, langs)
+}
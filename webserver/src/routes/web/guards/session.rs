@@ -1,9 +1,10 @@
 use crate::{
+  errors::*,
   models::id::{SessionId, UserId},
   redis_store::Redis,
 };
 
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
 
 //use cookie::{Cookie, SameSite};
 
@@ -27,10 +28,15 @@ use sodiumoxide::randombytes;
 
 use uuid::Uuid;
 
-use std::str::FromStr;
+use std::{
+  net::IpAddr,
+  str::FromStr,
+};
 
 // set session expiration to 30 days
 const SESS_EXPIRE: usize = 30 * 24 * 60 * 60;
+// don't store huge user agents
+const MAX_USER_AGENT: usize = 256;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Session<'a, 'r> where 'r: 'a {
@@ -43,6 +49,17 @@ pub struct Session<'a, 'r> where 'r: 'a {
   #[serde(default)]
   pub json: HashMap<String, JsonValue>,
   pub anti_csrf_token: String,
+  #[serde(default = "Utc::now")]
+  pub created_at: DateTime<Utc>,
+  #[serde(default = "Utc::now")]
+  pub last_seen: DateTime<Utc>,
+  #[serde(default)]
+  pub ip: Option<IpAddr>,
+  #[serde(default)]
+  pub user_agent: Option<String>,
+  /// The user the session belonged to when it was loaded from redis.
+  #[serde(skip)]
+  loaded_user_id: Option<UserId>,
 }
 
 impl Session<'a, 'r> {
@@ -54,6 +71,11 @@ impl Session<'a, 'r> {
       data: Default::default(),
       json: Default::default(),
       anti_csrf_token: BASE64URL_NOPAD.encode(&randombytes::randombytes(64)),
+      created_at: Utc::now(),
+      last_seen: Utc::now(),
+      ip: None,
+      user_agent: None,
+      loaded_user_id: None,
     }
   }
 
@@ -111,6 +133,7 @@ impl FromRequest<'a, 'r> for Session<'a, 'r> {
     };
 
     session.request = Some(req);
+    session.loaded_user_id = session.user_id;
 
     Outcome::Success(session)
   }
@@ -119,6 +142,12 @@ impl FromRequest<'a, 'r> for Session<'a, 'r> {
 impl Drop for Session<'a, 'r> {
   fn drop(&mut self) {
     if let Some(req) = self.request {
+      self.last_seen = Utc::now();
+      self.ip = req.client_ip();
+      self.user_agent = req.headers()
+        .get_one("User-Agent")
+        .map(|x| x.chars().take(MAX_USER_AGENT).collect());
+
       let json = match serde_json::to_string(self) {
         Ok(b) => b,
         Err(e) => {
@@ -137,6 +166,18 @@ impl Drop for Session<'a, 'r> {
 
       let id = self.id.to_simple().to_string();
 
+      // don't bring back a session that was revoked while this request was running
+      if self.loaded_user_id.is_some() {
+        match redis.exists::<_, bool>(self.id.redis_key()) {
+          Ok(true) => {},
+          Ok(false) => return,
+          Err(e) => {
+            println!("could not check session in redis: {}", e);
+            return;
+          },
+        }
+      }
+
       match redis.set_ex(self.id.redis_key(), json, SESS_EXPIRE) {
         Ok(Value::Okay) => {},
         Ok(Value::Status(s)) => println!("redis responded with an unexpected status: {}", s),
@@ -147,6 +188,10 @@ impl Drop for Session<'a, 'r> {
         },
       }
 
+      if let Err(e) = self.update_index(&mut redis) {
+        println!("could not update session index: {}", e);
+      }
+
       let current_cookie = req.cookies().get_private("session");
       let current_id = current_cookie.as_ref().map(|x| x.value());
 
@@ -162,3 +207,113 @@ impl Drop for Session<'a, 'r> {
     }
   }
 }
+
+/// A logged-in session, as shown to its user.
+#[derive(Debug, Serialize)]
+pub struct ActiveSession {
+  pub id: SessionId,
+  pub created_at: DateTime<Utc>,
+  pub last_seen: DateTime<Utc>,
+  pub ip: Option<IpAddr>,
+  pub user_agent: Option<String>,
+}
+
+fn index_key(user: UserId) -> String {
+  format!("user_sessions:{}", user.to_simple())
+}
+
+impl Session<'a, 'r> {
+  /// Keeps the user's set of sessions up to date with who this session belongs to.
+  fn update_index(&self, redis: &mut Redis) -> Result<()> {
+    if let Some(old) = self.loaded_user_id {
+      if self.user_id != Some(old) {
+        redis.srem::<_, _, ()>(index_key(old), self.id.to_simple().to_string())?;
+      }
+    }
+
+    if let Some(user) = self.user_id {
+      let key = index_key(user);
+      redis.sadd::<_, _, ()>(&key, self.id.to_simple().to_string())?;
+      redis.expire::<_, ()>(&key, SESS_EXPIRE)?;
+    }
+
+    Ok(())
+  }
+
+  /// Lists the user's sessions, most recently used first.
+  pub fn list(redis: &mut Redis, user: UserId) -> Result<Vec<ActiveSession>> {
+    let ids: Vec<String> = redis.smembers(index_key(user))?;
+
+    let mut sessions = Vec::with_capacity(ids.len());
+    for raw_id in ids {
+      let id = match Uuid::from_str(&raw_id) {
+        Ok(u) => SessionId(u),
+        Err(_) => continue,
+      };
+
+      let json: Option<String> = redis.get(id.redis_key())?;
+      let sess: Option<Session> = json.and_then(|x| serde_json::from_str(&x).ok());
+
+      // forget sessions that have expired or logged out
+      let sess = match sess {
+        Some(s) if s.user_id == Some(user) => s,
+        _ => {
+          redis.srem::<_, _, ()>(index_key(user), &raw_id)?;
+          continue;
+        },
+      };
+
+      sessions.push(ActiveSession {
+        id,
+        created_at: sess.created_at,
+        last_seen: sess.last_seen,
+        ip: sess.ip,
+        user_agent: sess.user_agent.clone(),
+      });
+    }
+
+    sessions.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+
+    Ok(sessions)
+  }
+
+  /// Signs out one of the user's sessions, returning false if the user doesn't own it.
+  pub fn revoke(redis: &mut Redis, user: UserId, id: SessionId) -> Result<bool> {
+    let key = index_key(user);
+    let id_s = id.to_simple().to_string();
+
+    if !redis.sismember::<_, _, bool>(&key, &id_s)? {
+      return Ok(false);
+    }
+
+    redis.del::<_, ()>(id.redis_key())?;
+    redis.srem::<_, _, ()>(&key, &id_s)?;
+
+    Ok(true)
+  }
+
+  /// Signs out all of the user's sessions, optionally keeping one (usually the current session).
+  pub fn revoke_all(redis: &mut Redis, user: UserId, except: Option<SessionId>) -> Result<()> {
+    let key = index_key(user);
+    let ids: Vec<String> = redis.smembers(&key)?;
+
+    for raw_id in ids {
+      let id = match Uuid::from_str(&raw_id) {
+        Ok(u) => SessionId(u),
+        Err(_) => {
+          redis.srem::<_, _, ()>(&key, &raw_id)?;
+          continue;
+        },
+      };
+
+      if Some(id) == except {
+        continue;
+      }
+
+      redis.del::<_, ()>(id.redis_key())?;
+      redis.srem::<_, _, ()>(&key, &raw_id)?;
+    }
+
+    Ok(())
+  }
+}
@@ -1,5 +1,8 @@
 use crate::{
   config::Config,
+  database::{DbConn, models::paste_templates::PasteTemplate},
+  errors::*,
+  models::id::PasteTemplateId,
   routes::web::{context, AddCsp, AntiSpam, Honeypot, OptionalWebUser, Session},
   utils::{AcceptLanguage, Language},
 };
@@ -11,26 +14,91 @@ use rocket_contrib::templates::Template;
 use serde_json::json;
 
 #[get("/")]
-pub fn get(config: State<Config>, user: OptionalWebUser, mut sess: Session, antispam: AntiSpam, langs: AcceptLanguage) -> AddCsp<Template> {
+pub fn get(config: State<Config>, user: OptionalWebUser, sess: Session, antispam: AntiSpam, conn: DbConn, langs: AcceptLanguage) -> Result<AddCsp<Template>> {
+  render(&*config, user, sess, antispam, &conn, langs, None)
+}
+
+/// The new paste page, filled in from one of the user's templates.
+#[get("/?<template>")]
+pub fn template(template: PasteTemplateId, config: State<Config>, user: OptionalWebUser, sess: Session, antispam: AntiSpam, conn: DbConn, langs: AcceptLanguage) -> Result<Option<AddCsp<Template>>> {
+  let template = match *user {
+    Some(ref u) => PasteTemplate::find(&conn, u.id(), template)?,
+    None => None,
+  };
+  let template = match template {
+    Some(t) => t,
+    None => return Ok(None),
+  };
+
+  Ok(Some(render(&*config, user, sess, antispam, &conn, langs, Some(&template))?))
+}
+
+fn render(config: &Config, user: OptionalWebUser, mut sess: Session, antispam: AntiSpam, conn: &DbConn, langs: AcceptLanguage, template: Option<&PasteTemplate>) -> Result<AddCsp<Template>> {
   if config.read().pastes.sign_in_to_create && user.is_none() {
-    let ctx = context(&*config, user.as_ref(), &mut sess, langs);
+    let ctx = context(config, user.as_ref(), &mut sess, langs);
     // TODO: Not use AddCsp for this
-    return AddCsp::new(
+    return Ok(AddCsp::new(
       Template::render("index_no_create", ctx),
       Vec::<String>::new(),
-    );
+    ));
   }
   let honeypot = Honeypot::new();
-  let mut ctx = context(&*config, user.as_ref(), &mut sess, langs);
+  let mut ctx = context(config, user.as_ref(), &mut sess, langs);
   ctx["languages"] = json!(Language::context());
   ctx["honeypot"] = json!(honeypot);
   ctx["antispam"] = json!(antispam);
-  ctx["links"] = json!(links!("upload" => uri!(crate::routes::web::pastes::post::post)));
-  AddCsp::new(
+
+  if let Some(template) = template {
+    // a form saved in the session is the user's own input, which wins over the template's
+    if ctx["form"].is_null() {
+      ctx["form"] = json!({
+        "name": template.paste_name(),
+        "description": template.description(),
+        "visibility": template.visibility(),
+      });
+    }
+
+    let files: Vec<_> = template
+      .files(conn)?
+      .iter()
+      .map(|f| json!({
+        "name": f.name(),
+        "highlight_language": f.highlight_language().map(|l| l.as_str().to_lowercase()),
+        "content": f.content(),
+      }))
+      .collect();
+    ctx["template_files"] = json!(files);
+
+    if let Some(after) = template.expires_after() {
+      let minutes = after.num_minutes();
+      ctx["template_expiry"] = json!({
+        "years": minutes / (365 * 24 * 60),
+        "days": minutes / (24 * 60) % 365,
+        "hours": minutes / 60 % 24,
+        "minutes": minutes % 60,
+      });
+    }
+  }
+
+  let mut links = links!("upload" => uri!(crate::routes::web::pastes::post::post));
+  if let Some(ref user) = *user {
+    let templates = user.templates(conn)?;
+    let template_links: Vec<_> = templates
+      .iter()
+      .map(|t| json!({
+        "name": t.name(),
+        "url": uri!(crate::routes::web::index::template: t.id()).to_string(),
+      }))
+      .collect();
+    links.add_value("templates", template_links);
+  }
+  ctx["links"] = json!(links);
+
+  Ok(AddCsp::new(
     Template::render("index", ctx),
     vec![
       format!("style-src '{}'", honeypot.integrity_hash),
       format!("script-src '{}'", antispam.integrity_hash),
     ],
-  )
+  ))
 }
@@ -0,0 +1,168 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::users::User,
+    schema::users,
+  },
+  errors::*,
+  i18n::L10n,
+  models::{
+    id::PasteId,
+    paste::{
+      Visibility,
+      output::{OutputAuthor, OutputFile},
+    },
+  },
+  routes::web::pastes::PasteMeta,
+};
+
+use diesel::prelude::*;
+
+use rocket::{
+  http::{RawStr, Status as HttpStatus},
+  State,
+};
+
+use rocket_contrib::json::Json;
+
+use url::Url;
+
+use uuid::Uuid;
+
+use std::str::FromStr;
+
+#[derive(Debug, Serialize)]
+pub struct OEmbed {
+  version: &'static str,
+  #[serde(rename = "type")]
+  kind: &'static str,
+  title: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  author_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  author_url: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  html: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  width: Option<u32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  height: Option<u32>,
+  provider_name: String,
+  provider_url: String,
+  cache_age: u32,
+}
+
+#[derive(Responder)]
+pub enum OEmbedResponse {
+  OEmbed(Json<OEmbed>),
+  Status(HttpStatus),
+}
+
+/// Extracts the paste ID and the username in the URL, if any, from a URL pointing at a paste.
+fn paste_from_url(domain: &str, url: &str) -> Option<(PasteId, Option<String>)> {
+  let url = Url::parse(url).ok()?;
+
+  if url.host_str() != Some(domain) {
+    return None;
+  }
+
+  let segments: Vec<&str> = url.path_segments()?.filter(|x| !x.is_empty()).collect();
+
+  let (username, id) = match segments.as_slice() {
+    // /p/<username>/<id>, as well as any of its subpages
+    ["p", username, id, ..] => (Some(*username), *id),
+    // /<username>/<id>
+    [username, id] => (Some(*username), *id),
+    // /<id>
+    [id] => (None, *id),
+    _ => return None,
+  };
+
+  let id = Uuid::from_str(id).ok().map(PasteId)?;
+  let username = match username {
+    Some(u) => Some(RawStr::from_str(u).percent_decode().ok()?.into_owned()),
+    None => None,
+  };
+
+  Some((id, username))
+}
+
+const EMBED_WIDTH: u32 = 600;
+const EMBED_HEIGHT: u32 = 300;
+
+#[get("/oembed?<url>&<format>&<maxwidth>&<maxheight>")]
+pub fn get(url: String, format: Option<String>, maxwidth: Option<u32>, maxheight: Option<u32>, config: State<Config>, conn: DbConn, l10n: L10n) -> Result<OEmbedResponse> {
+  // only json is supported
+  if format.as_deref().map(|x| x != "json").unwrap_or(false) {
+    return Ok(OEmbedResponse::Status(HttpStatus::NotImplemented));
+  }
+
+  let domain = config.read().general.site_domain.clone();
+
+  let (id, username) = match paste_from_url(&domain, &url) {
+    Some(x) => x,
+    None => return Ok(OEmbedResponse::Status(HttpStatus::NotFound)),
+  };
+
+  let paste = match id.get(&conn)? {
+    Some(p) => p,
+    None => return Ok(OEmbedResponse::Status(HttpStatus::NotFound)),
+  };
+
+  // never describe private pastes, even to their owners
+  if paste.visibility() == Visibility::Private {
+    return Ok(OEmbedResponse::Status(HttpStatus::NotFound));
+  }
+
+  let author: Option<OutputAuthor> = match paste.author_id() {
+    Some(author) => {
+      let user: User = users::table.find(author).first(&*conn)?;
+      Some(OutputAuthor::new(author, user.username(), user.name()))
+    },
+    None => None,
+  };
+
+  let expected_username = author.as_ref().map(|x| x.username.as_str()).unwrap_or("anonymous");
+  if username.is_some() && username.as_deref() != Some(expected_username) {
+    return Ok(OEmbedResponse::Status(HttpStatus::NotFound));
+  }
+
+  let files: Vec<OutputFile> = id.output_files(&*config, &conn, &paste, false)?;
+
+  let meta = PasteMeta::new(&*config, &paste, author.as_ref(), &files, &l10n)?;
+
+  // only offer an embed if some origin is allowed to frame it
+  let can_embed = !config.read().embeds.allowed_origins.is_empty();
+
+  let (kind, html, width, height) = if can_embed {
+    let width = maxwidth.map(|x| x.min(EMBED_WIDTH)).unwrap_or(EMBED_WIDTH);
+    let height = maxheight.map(|x| x.min(EMBED_HEIGHT)).unwrap_or(EMBED_HEIGHT);
+    let html = format!(
+      "<iframe src=\"{src}/embed\" width=\"{width}\" height=\"{height}\" title=\"{title}\" frameborder=\"0\" loading=\"lazy\"></iframe>",
+      src = tera::escape_html(&meta.url),
+      width = width,
+      height = height,
+      title = tera::escape_html(&meta.title),
+    );
+    ("rich", Some(html), Some(width), Some(height))
+  } else {
+    ("link", None, None, None)
+  };
+
+  let oembed = OEmbed {
+    version: "1.0",
+    kind,
+    title: meta.title,
+    author_name: meta.author.map(|x| x.name),
+    author_url: meta.author_url,
+    html,
+    width,
+    height,
+    provider_name: config.read().general.site_name.clone(),
+    provider_url: format!("https://{}/", domain),
+    cache_age: 3600,
+  };
+
+  Ok(OEmbedResponse::OEmbed(Json(oembed)))
+}
@@ -1,20 +1,38 @@
 use crate::{
-  database::models::users::User,
-  models::id::{PasteId, UserId},
+  config::Config,
+  database::models::{pastes::Paste as DbPaste, users::User},
+  errors::*,
+  i18n::L10n,
+  models::{
+    id::{PasteId, UserId},
+    paste::{
+      Content,
+      output::{OutputAuthor, OutputFile},
+    },
+  },
   routes::web::Links,
 };
 
+use unicode_segmentation::UnicodeSegmentation;
+
+pub mod archive;
 pub mod delete;
+pub mod embed;
+pub mod expiry;
 pub mod files;
 pub mod get;
+pub mod git;
 pub mod patch;
 pub mod post;
 pub mod revisions;
+pub mod template;
 
 pub(crate) fn paste_links(id: PasteId, author_id: Option<UserId>, author_name: &str, user: Option<&User>) -> Links {
   let mut links = links!(
     "files" => uri!(crate::routes::web::pastes::get::users_username_id: author_name, id),
     "revisions" => uri!(crate::routes::web::pastes::revisions::get: author_name, id),
+    "archive_zip" => uri!(crate::routes::web::pastes::archive::zip: author_name, id, _),
+    "archive_tar_gz" => uri!(crate::routes::web::pastes::archive::tar_gz: author_name, id, _),
     "delete" => uri!(crate::routes::web::pastes::delete::delete: author_name, id),
     "delete_standalone" => uri!(crate::routes::web::pastes::get::delete: author_name, id),
     "author_page" => uri!(crate::routes::web::users::get::get: author_name, _),
@@ -24,6 +42,14 @@ pub(crate) fn paste_links(id: PasteId, author_id: Option<UserId>, author_name: &
       "edit",
       uri!(crate::routes::web::pastes::get::edit: u.username(), id),
     );
+    links.add(
+      "expiry",
+      uri!(crate::routes::web::pastes::expiry::post: author_name, id),
+    );
+    links.add(
+      "save_template",
+      uri!(crate::routes::web::pastes::template::post: author_name, id),
+    );
   }
   if let Some(ref id) = author_id {
     links.add(
@@ -33,3 +59,86 @@ pub(crate) fn paste_links(id: PasteId, author_id: Option<UserId>, author_name: &
   }
   links
 }
+
+/// Public-facing metadata about a paste, used for link previews.
+///
+/// This must only ever be created for pastes that are not private.
+#[derive(Debug, Serialize)]
+pub(crate) struct PasteMeta {
+  pub title: String,
+  /// The paste's description or, failing that, an excerpt of its first text file.
+  pub description: Option<String>,
+  pub author: Option<OutputAuthor>,
+  pub author_url: Option<String>,
+  pub language: Option<&'static str>,
+  pub files: usize,
+  pub url: String,
+  pub oembed: String,
+}
+
+impl PasteMeta {
+  pub(crate) fn new(config: &Config, paste: &DbPaste, author: Option<&OutputAuthor>, files: &[OutputFile], l10n: &L10n) -> Result<Self> {
+    let domain = config.read().general.site_domain.clone();
+    let username = author.map(|x| x.username.as_str()).unwrap_or("anonymous");
+
+    let url = format!(
+      "https://{}{}",
+      domain,
+      uri!(crate::routes::web::pastes::get::users_username_id: username, paste.id()),
+    );
+
+    let first = files.first();
+
+    let title = match paste.name().or_else(|| first.and_then(|x| x.name.as_deref())) {
+      Some(t) => t.to_string(),
+      None => l10n.tr("disp-untitled")?,
+    };
+
+    let description = match paste.description() {
+      Some(d) => Some(d.to_string()),
+      // use the first text file for the excerpt
+      None => files
+        .iter()
+        .find_map(|x| match x.content {
+          Some(Content::Text(ref s)) => Some(excerpt(s)),
+          _ => None,
+        }),
+    };
+
+    Ok(PasteMeta {
+      title,
+      description,
+      author: author.cloned(),
+      author_url: author.map(|x| format!(
+        "https://{}{}",
+        domain,
+        uri!(crate::routes::web::users::get::get: &x.username, _),
+      )),
+      language: first.and_then(|x| x.highlight_language),
+      files: files.len(),
+      oembed: format!(
+        "https://{}{}",
+        domain,
+        uri!(crate::routes::web::oembed::get: &url, _, _, _),
+      ),
+      url,
+    })
+  }
+}
+
+fn excerpt(content: &str) -> String {
+  const MAX_LINES: usize = 10;
+  const MAX_GRAPHEMES: usize = 280;
+
+  let mut lines = content.lines();
+  let joined = lines.by_ref().take(MAX_LINES).collect::<Vec<_>>().join("\n");
+
+  let mut graphemes = joined.graphemes(true);
+  let mut excerpt: String = graphemes.by_ref().take(MAX_GRAPHEMES).collect();
+
+  if graphemes.next().is_some() || lines.next().is_some() {
+    excerpt.push('…');
+  }
+
+  excerpt
+}
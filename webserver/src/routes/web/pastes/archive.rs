@@ -0,0 +1,74 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::{pastes::Paste as DbPaste, users::User},
+    schema::users,
+  },
+  errors::*,
+  models::id::PasteId,
+  routes::{AddHeaders, web::OptionalWebUser},
+  utils::archive::{Archive, ChannelReader, Format},
+};
+
+use diesel::prelude::*;
+
+use rocket::{
+  http::Status as HttpStatus,
+  response::Stream,
+  State,
+};
+
+#[derive(Responder)]
+pub enum As {
+  Add(AddHeaders<Stream<ChannelReader>>),
+  Status(HttpStatus),
+}
+
+#[get("/p/<username>/<paste_id>/archive.zip?<revision>")]
+pub fn zip(username: String, paste_id: PasteId, revision: Option<String>, config: State<Config>, user: OptionalWebUser, conn: DbConn) -> Result<As> {
+  archive(username, paste_id, revision, Format::Zip, &*config, user, conn)
+}
+
+#[get("/p/<username>/<paste_id>/archive.tar.gz?<revision>")]
+pub fn tar_gz(username: String, paste_id: PasteId, revision: Option<String>, config: State<Config>, user: OptionalWebUser, conn: DbConn) -> Result<As> {
+  archive(username, paste_id, revision, Format::TarGz, &*config, user, conn)
+}
+
+fn archive(username: String, paste_id: PasteId, revision: Option<String>, format: Format, config: &Config, user: OptionalWebUser, conn: DbConn) -> Result<As> {
+  let paste: DbPaste = match paste_id.get(&conn)? {
+    Some(p) => p,
+    None => return Ok(As::Status(HttpStatus::NotFound)),
+  };
+
+  let expected_username: String = match paste.author_id() {
+    Some(author) => {
+      let user: User = users::table.find(author).first(&*conn)?;
+      user.username().to_string()
+    },
+    None => "anonymous".into(),
+  };
+
+  if username != expected_username {
+    return Ok(As::Status(HttpStatus::NotFound));
+  }
+
+  if let Some((status, _)) = paste.check_access(user.as_ref().map(|x| x.id())) {
+    return Ok(As::Status(status));
+  }
+
+  let archive = match Archive::new(config, &conn, &paste, revision.as_ref().map(|x| x.as_str()))? {
+    Some(a) => a,
+    None => return Ok(As::Status(HttpStatus::NotFound)),
+  };
+
+  let disposition = format!("attachment; filename=\"{}\"", archive.file_name(format));
+
+  Ok(As::Add(AddHeaders::new(
+    archive.stream(format),
+    vec![
+      ("Content-Type".into(), format.content_type().into()),
+      ("Content-Disposition".into(), disposition),
+    ],
+  )))
+}
@@ -0,0 +1,196 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::{pastes::Paste as DbPaste, users::User},
+    schema::users,
+  },
+  errors::*,
+  models::{
+    id::{FileId, PasteId},
+    paste::{
+      Content, Visibility,
+      output::{OutputAuthor, OutputFile},
+    },
+  },
+  routes::web::{AddCsp, Links, Rst},
+  utils::AcceptLanguage,
+};
+
+use diesel::prelude::*;
+
+use rocket::{http::Status as HttpStatus, State};
+
+use rocket_contrib::templates::Template;
+
+use serde_json::json;
+
+/// A 1-indexed, inclusive range of lines to show in an embed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LineRange {
+  start: usize,
+  end: Option<usize>,
+}
+
+impl LineRange {
+  /// Parses `n`, `n-m` or `n-` into a range.
+  fn parse(s: &str) -> Option<Self> {
+    let mut parts = s.splitn(2, '-');
+
+    let start = parts.next()?.trim().parse().ok().filter(|&x| x > 0)?;
+    let end = match parts.next().map(str::trim) {
+      // a single line
+      None => Some(start),
+      // until the end of the file
+      Some("") => None,
+      Some(e) => Some(e.parse().ok().filter(|&x| x >= start)?),
+    };
+
+    Some(LineRange { start, end })
+  }
+
+  fn apply(self, content: &str) -> String {
+    let lines = content.lines().skip(self.start - 1);
+    match self.end {
+      Some(end) => lines.take(end - self.start + 1).collect::<Vec<_>>().join("\n"),
+      None => lines.collect::<Vec<_>>().join("\n"),
+    }
+  }
+}
+
+#[get("/p/<username>/<id>/embed?<file>&<lines>")]
+pub fn get(username: String, id: PasteId, file: Option<FileId>, lines: Option<String>, config: State<Config>, conn: DbConn, langs: AcceptLanguage) -> Result<AddCsp<Rst>> {
+  let origins = config.read().embeds.allowed_origins.join(" ");
+  let csp = if origins.is_empty() {
+    Vec::new()
+  } else {
+    vec![format!("frame-ancestors {}", origins)]
+  };
+
+  let rst = embed(username, id, file, lines, &*config, &conn, langs)?;
+
+  Ok(AddCsp::new(rst, csp))
+}
+
+fn embed(username: String, id: PasteId, file: Option<FileId>, lines: Option<String>, config: &Config, conn: &DbConn, langs: AcceptLanguage) -> Result<Rst> {
+  let range = match lines {
+    Some(l) => match LineRange::parse(&l) {
+      Some(r) => Some(r),
+      None => return Ok(Rst::Status(HttpStatus::BadRequest)),
+    },
+    None => None,
+  };
+
+  let paste: DbPaste = match id.get(conn)? {
+    Some(p) => p,
+    None => return Ok(Rst::Status(HttpStatus::NotFound)),
+  };
+
+  // embeds are shown anonymously on other sites, so never show private pastes
+  if paste.visibility() == Visibility::Private {
+    return Ok(Rst::Status(HttpStatus::NotFound));
+  }
+
+  let (expected_username, author): (String, Option<OutputAuthor>) = match paste.author_id() {
+    Some(author) => {
+      let user: User = users::table.find(author).first(&**conn)?;
+      (user.username().to_string(), Some(OutputAuthor::new(author, user.username(), user.name())))
+    },
+    None => ("anonymous".into(), None),
+  };
+
+  if username != expected_username {
+    return Ok(Rst::Status(HttpStatus::NotFound));
+  }
+
+  let mut files: Vec<OutputFile> = id.output_files(config, conn, &paste, true)?;
+
+  if let Some(file) = file {
+    files.retain(|x| x.id == file);
+    if files.is_empty() {
+      return Ok(Rst::Status(HttpStatus::NotFound));
+    }
+  }
+
+  if let Some(range) = range {
+    for file in &mut files {
+      if let Some(Content::Text(ref mut s)) = file.content {
+        *s = range.apply(s);
+      }
+    }
+  }
+
+  let links = files
+    .iter()
+    .fold(Links::default(), |mut acc, x| {
+      acc.add(
+        x.id.to_simple().to_string(),
+        uri!(crate::routes::web::pastes::files::raw::get: &expected_username, paste.id(), x.id, _),
+      );
+      acc
+    });
+
+  let ctx = json!({
+    "config": &*config.read(),
+    "langs": langs.into_strings(),
+    "resources_version": &*crate::RESOURCES_VERSION,
+    "paste": {
+      "id": paste.id(),
+      "name": paste.name(),
+      "author": author,
+    },
+    "files": files,
+    "line_offset": range.map(|x| x.start - 1).unwrap_or(0),
+    "paste_link": uri!(crate::routes::web::pastes::get::users_username_id: &expected_username, paste.id()).to_string(),
+    "raw_links": links,
+  });
+
+  Ok(Rst::Template(Template::render("paste/embed", ctx)))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn range(start: usize, end: Option<usize>) -> Option<LineRange> {
+    Some(LineRange { start, end })
+  }
+
+  #[test]
+  fn single_line() {
+    assert_eq!(LineRange::parse("3"), range(3, Some(3)));
+    assert_eq!(LineRange::parse(" 3 "), range(3, Some(3)));
+  }
+
+  #[test]
+  fn closed_range() {
+    assert_eq!(LineRange::parse("3-5"), range(3, Some(5)));
+    assert_eq!(LineRange::parse("3 - 5"), range(3, Some(5)));
+    assert_eq!(LineRange::parse("3-3"), range(3, Some(3)));
+  }
+
+  #[test]
+  fn open_range() {
+    assert_eq!(LineRange::parse("3-"), range(3, None));
+  }
+
+  #[test]
+  fn invalid() {
+    assert_eq!(LineRange::parse(""), None);
+    assert_eq!(LineRange::parse("0"), None);
+    assert_eq!(LineRange::parse("-5"), None);
+    assert_eq!(LineRange::parse("5-3"), None);
+    assert_eq!(LineRange::parse("3-5-7"), None);
+    assert_eq!(LineRange::parse("a-b"), None);
+  }
+
+  #[test]
+  fn apply() {
+    let content = "one\ntwo\nthree\nfour";
+    assert_eq!(LineRange::parse("2").unwrap().apply(content), "two");
+    assert_eq!(LineRange::parse("2-3").unwrap().apply(content), "two\nthree");
+    assert_eq!(LineRange::parse("3-").unwrap().apply(content), "three\nfour");
+    assert_eq!(LineRange::parse("3-10").unwrap().apply(content), "three\nfour");
+    assert_eq!(LineRange::parse("10").unwrap().apply(content), "");
+  }
+}
@@ -0,0 +1,110 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::{pastes::Paste as DbPaste, users::User},
+    schema::users,
+  },
+  errors::*,
+  i18n::prelude::*,
+  models::{
+    id::PasteId,
+    paste::update::{MetadataUpdate, Update},
+  },
+  routes::web::{Rst, OptionalWebUser, Session},
+  utils::RelativeDuration,
+};
+
+use chrono::Utc;
+
+use diesel::prelude::*;
+
+use rocket::{
+  http::Status as HttpStatus,
+  request::{Form, State},
+  response::Redirect,
+};
+
+use sidekiq::Client as SidekiqClient;
+
+use std::str::FromStr;
+
+/// Lets a paste's owner push back its expiry or remove it.
+///
+/// Extensions count from the current expiry, or from now if that's already passed.
+#[post("/p/<username>/<id>/expiry", format = "application/x-www-form-urlencoded", data = "<change>")]
+pub fn post(change: Form<ExpiryChange>, username: String, id: PasteId, config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, sidekiq: State<SidekiqClient>, l10n: L10n) -> Result<Rst> {
+  let change = change.into_inner();
+
+  if !sess.check_token(&change.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Rst::Redirect(Redirect::to("lastpage")));
+  }
+
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Rst::Redirect(Redirect::to(uri!(crate::routes::web::auth::login::get)))),
+  };
+
+  let mut paste: DbPaste = match id.get(&conn)? {
+    Some(p) => p,
+    None => return Ok(Rst::Status(HttpStatus::NotFound)),
+  };
+
+  let expected_username: String = match paste.author_id() {
+    Some(author) => {
+      let user: User = users::table.find(author).first(&*conn)?;
+      user.username().to_string()
+    },
+    None => "anonymous".into(),
+  };
+
+  if username != expected_username {
+    return Ok(Rst::Status(HttpStatus::NotFound));
+  }
+
+  if let Some((status, _)) = paste.check_access(user.id()) {
+    return Ok(Rst::Status(status));
+  }
+
+  if paste.author_id() != Some(user.id()) {
+    return Ok(Rst::Status(HttpStatus::Forbidden));
+  }
+
+  let (expires, message) = match change.action.as_str() {
+    "remove" => (Update::Remove, "expiry-removed"),
+    "extend" => {
+      let extend_by = match change.extend_by.as_ref().map(|x| RelativeDuration::from_str(x)) {
+        Some(Ok(e)) => e,
+        _ => {
+          sess.add_data("error", l10n.tr(("expiry-change", "invalid-duration"))?);
+          return Ok(Rst::Redirect(Redirect::to("lastpage")));
+        },
+      };
+      let now = Utc::now();
+      let from = paste.expires().map(|e| std::cmp::max(e, now)).unwrap_or(now);
+      (Update::Set(from + extend_by.duration()), "expiry-extended")
+    },
+    _ => return Ok(Rst::Status(HttpStatus::BadRequest)),
+  };
+
+  let metadata = MetadataUpdate {
+    name: Update::Ignore,
+    description: Update::Ignore,
+    visibility: None,
+    expires,
+    expires_in: None,
+    visibility_change: Update::Ignore,
+  };
+  paste.update(&*config, &conn, &*sidekiq, &metadata)?;
+
+  sess.add_data("info", l10n.tr(message)?);
+  Ok(Rst::Redirect(Redirect::to(uri!(crate::routes::web::pastes::get::users_username_id: username, id))))
+}
+
+#[derive(Debug, FromForm)]
+pub struct ExpiryChange {
+  action: String,
+  extend_by: Option<String>,
+  anti_csrf_token: String,
+}
@@ -0,0 +1,84 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::{pastes::Paste as DbPaste, users::User},
+    schema::users,
+  },
+  errors::*,
+  models::{
+    id::{PasteId, FileId},
+    paste::Content,
+  },
+  routes::{AddHeaders, web::OptionalWebUser},
+  utils::csv,
+};
+
+use diesel::prelude::*;
+
+use rocket::{http::Status as HttpStatus, State};
+
+#[derive(Responder)]
+pub enum As {
+  Add(AddHeaders<Vec<u8>>),
+  Status(HttpStatus),
+}
+
+/// Downloads a CSV or TSV file as comma-separated CSV, whatever delimiter it used.
+#[get("/p/<username>/<paste_id>/files/<file_id>/csv")]
+pub fn get(username: String, paste_id: PasteId, file_id: FileId, config: State<Config>, user: OptionalWebUser, conn: DbConn) -> Result<As> {
+  let paste: DbPaste = match paste_id.get(&conn)? {
+    Some(p) => p,
+    None => return Ok(As::Status(HttpStatus::NotFound)),
+  };
+
+  let expected_username: String = match paste.author_id() {
+    Some(author) => {
+      let user: User = users::table.find(author).first(&*conn)?;
+      user.username().to_string()
+    },
+    None => "anonymous".into(),
+  };
+
+  if username != expected_username {
+    return Ok(As::Status(HttpStatus::NotFound));
+  }
+
+  if let Some((status, _)) = paste.check_access(user.as_ref().map(|x| x.id())) {
+    return Ok(As::Status(status));
+  }
+
+  let file = match paste_id.file(&conn, file_id)? {
+    Some(f) if f.is_binary() != Some(true) => f,
+    _ => return Ok(As::Status(HttpStatus::NotFound)),
+  };
+
+  let content = match file.read_content(&*config, &paste)? {
+    Content::Text(s) => s,
+    _ => return Ok(As::Status(HttpStatus::NotFound)),
+  };
+
+  let delimiter = csv::delimiter_for(file.name(), &content);
+  let normalized = match csv::normalize(&content, delimiter) {
+    Ok(n) => n,
+    Err(_) => return Ok(As::Status(HttpStatus::UnprocessableEntity)),
+  };
+
+  let stem = match file.name().rsplit_once('.') {
+    Some((stem, _)) if !stem.is_empty() => stem,
+    _ => file.name(),
+  };
+  // keep the name simple so it can't break out of the header
+  let stem: String = stem
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+    .collect();
+
+  Ok(As::Add(AddHeaders::new(
+    normalized,
+    vec![
+      ("Content-Type".into(), "text/csv; charset=utf-8".into()),
+      ("Content-Disposition".into(), format!("attachment; filename=\"{}.csv\"", stem)),
+    ],
+  )))
+}
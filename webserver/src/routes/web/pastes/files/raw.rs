@@ -8,22 +8,21 @@ use crate::{
   errors::*,
   models::id::{PasteId, FileId},
   routes::{AddHeaders, web::OptionalWebUser},
+  utils::{AcceptEncoding, raw_file::{self, RawFile}},
 };
 
 use diesel::prelude::*;
 
 use rocket::{http::Status as HttpStatus, State};
 
-use std::fs::File;
-
 #[derive(Responder)]
 pub enum As {
-  Add(AddHeaders<File>),
+  Add(AddHeaders<RawFile>),
   Status(HttpStatus),
 }
 
 #[get("/p/<username>/<paste_id>/files/<file_id>/raw?<svg>")]
-pub fn get(username: String, paste_id: PasteId, file_id: FileId, svg: Option<bool>, config: State<Config>, user: OptionalWebUser, conn: DbConn) -> Result<As> {
+pub fn get(username: String, paste_id: PasteId, file_id: FileId, svg: Option<bool>, accept: AcceptEncoding, config: State<Config>, user: OptionalWebUser, conn: DbConn) -> Result<As> {
   let paste: DbPaste = match paste_id.get(&conn)? {
     Some(p) => p,
     None => return Ok(As::Status(HttpStatus::NotFound)),
@@ -50,16 +49,25 @@ pub fn get(username: String, paste_id: PasteId, file_id: FileId, svg: Option<boo
     None => return Ok(As::Status(HttpStatus::NotFound)),
   };
 
-  let h = if file.is_binary() == Some(true) {
-    ("Content-Disposition".into(), "attachment".into())
+  let path = file.path(&*config, &paste);
+
+  let headers = if file.is_binary() == Some(true) {
+    let (content_type, inline) = raw_file::content_type(&path, file.name())?;
+    let mut headers = vec![("Content-Type".into(), content_type)];
+    if !inline {
+      headers.push(("Content-Disposition".into(), "attachment".into()));
+    }
+    headers
   } else if svg.unwrap_or(false) && file.name().ends_with(".svg") {
-    ("Content-Type".into(), "image/svg+xml".into())
+    vec![("Content-Type".into(), "image/svg+xml".into())]
   } else {
-    ("Content-Type".into(), "text/plain; charset=utf-8".into())
+    vec![("Content-Type".into(), "text/plain; charset=utf-8".into())]
   };
 
-  Ok(As::Add(AddHeaders::new(
-    File::open(file.path(&*config, &paste))?,
-    vec![h],
-  )))
+  // binary files are usually compressed already
+  let encoding = accept.preferred().filter(|_| file.is_binary() != Some(true));
+  let blob_id = file.blob_id(&*config, &paste)?;
+  let body = RawFile::open(&*config, &paste, &path, blob_id, encoding)?;
+
+  Ok(As::Add(AddHeaders::new(body, headers)))
 }
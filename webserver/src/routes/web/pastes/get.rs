@@ -14,13 +14,23 @@ use crate::{
       output::{Output, OutputFile, OutputAuthor},
     },
   },
-  routes::web::{context, Rst, OptionalWebUser, Session},
-  utils::{csv::csv_to_table, post_processing, AcceptLanguage, Language},
+  routes::web::{context, AddCsp, Rst, OptionalWebUser, Session},
+  utils::{
+    csv::{self, TableQuery, TableView},
+    images,
+    markdown,
+    markup::{asciidoc, notebook, rst},
+    math,
+    post_processing::{self, FileLinks},
+    structured::{self, Format},
+    AcceptLanguage,
+    Language,
+  },
 };
 
 use ammonia::Builder;
 
-use comrak::{markdown_to_html, ComrakOptions, ComrakExtensionOptions, ComrakParseOptions, ComrakRenderOptions};
+use comrak::{ComrakOptions, ComrakExtensionOptions, ComrakParseOptions, ComrakRenderOptions};
 
 use diesel::prelude::*;
 
@@ -44,6 +54,7 @@ lazy_static! {
       autolink: true,
       tasklist: true,
       footnotes: true,
+      description_lists: true,
       ..Default::default()
     },
     parse: ComrakParseOptions::default(),
@@ -55,18 +66,48 @@ lazy_static! {
     },
   };
 
-  static ref CLEANER: Builder<'static> = {
-    let mut b = Builder::default();
+  static ref CLEANER: Builder<'static> = cleaner();
+
+  static ref NOTEBOOK_CLEANER: Builder<'static> = {
+    let mut b = cleaner();
     b
-      .link_rel(Some("noopener noreferrer nofollow"))
-      .add_tags(std::iter::once("input"))
-      .add_tag_attribute_values("input", "checked", vec!["", "checked"].into_iter())
-      .add_tag_attribute_values("input", "disabled", vec!["", "disabled"].into_iter())
-      .add_tag_attribute_values("input", "type", std::iter::once("checkbox"));
+      .add_url_schemes(std::iter::once("data"))
+      // notebooks embed their output images, so let those through but no other data urls
+      .attribute_filter(|element, attribute, value| match value.strip_prefix("data:") {
+        Some(data) if element == "img" && attribute == "src" && notebook::IMAGE_TYPES
+          .iter()
+          .any(|t| data.starts_with(&format!("{};base64,", t))) => Some(value.into()),
+        Some(_) => None,
+        None => Some(value.into()),
+      });
     b
   };
 }
 
+fn cleaner() -> Builder<'static> {
+  let mut b = Builder::default();
+  b
+    .link_rel(Some("noopener noreferrer nofollow"))
+    .add_tags(std::iter::once("input"))
+    // footnotes and the table of contents
+    .add_tags(vec!["section", "details", "summary"].into_iter())
+    .add_tag_attributes("details", std::iter::once("open"))
+    .add_allowed_classes("a", vec!["anchor", "footnote-backref"].into_iter())
+    .add_allowed_classes("sup", std::iter::once("footnote-ref"))
+    .add_allowed_classes("section", std::iter::once("footnotes"))
+    .add_allowed_classes("details", std::iter::once("markdown-toc"))
+    // ids for headings and footnotes, which post-processing prefixes so they can't clobber the page's
+    .add_generic_attributes(std::iter::once("id"))
+    .add_tags(math::TAGS.iter())
+    .add_tag_attribute_values("input", "checked", vec!["", "checked"].into_iter())
+    .add_tag_attribute_values("input", "disabled", vec!["", "disabled"].into_iter())
+    .add_tag_attribute_values("input", "type", std::iter::once("checkbox"));
+  for &(tag, attributes) in math::TAG_ATTRIBUTES {
+    b.add_tag_attributes(tag, attributes.iter());
+  }
+  b
+}
+
 #[get("/<id>", rank = 10)]
 pub fn id(id: PasteId, user: OptionalWebUser, conn: DbConn) -> Result<Rst> {
   let result: Option<(Option<String>, DbPaste)> = pastes::table
@@ -103,10 +144,10 @@ pub fn username_id(username: String, id: PasteId) -> Redirect {
 }
 
 #[get("/p/<username>/<id>")]
-pub fn users_username_id(username: String, id: PasteId, config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, langs: AcceptLanguage, l10n: L10n) -> Result<Rst> {
+pub fn users_username_id(username: String, id: PasteId, config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, langs: AcceptLanguage, l10n: L10n, table_query: TableQuery) -> Result<AddCsp<Rst>> {
   let paste: DbPaste = match id.get(&conn)? {
     Some(p) => p,
-    None => return Ok(Rst::Status(HttpStatus::NotFound)),
+    None => return Ok(AddCsp::none(Rst::Status(HttpStatus::NotFound))),
   };
 
   let (expected_username, author): (String, Option<OutputAuthor>) = match paste.author_id() {
@@ -118,19 +159,61 @@ pub fn users_username_id(username: String, id: PasteId, config: State<Config>, u
   };
 
   if username != expected_username {
-    return Ok(Rst::Status(HttpStatus::NotFound));
+    return Ok(AddCsp::none(Rst::Status(HttpStatus::NotFound)));
   }
 
   if let Some((status, _)) = paste.check_access(user.as_ref().map(|x| x.id())) {
-    return Ok(Rst::Status(status));
+    return Ok(AddCsp::none(Rst::Status(status)));
   }
 
   let files: Vec<OutputFile> = id.output_files(&*config, &conn, &paste, true)?;
 
   let mut rendered: HashMap<FileId, String> = HashMap::with_capacity(files.len());
   let mut notices: HashMap<FileId, String> = HashMap::new();
+  let mut image_files: HashMap<FileId, bool> = HashMap::new();
+  let mut pretty: HashMap<FileId, serde_json::Value> = HashMap::new();
+  let mut tables: HashMap<FileId, serde_json::Value> = HashMap::new();
+  let mut has_notebook = false;
+
+  // rendered files can link to and show each other by name
+  let paste_files: HashMap<String, (FileId, String)> = files
+    .iter()
+    .filter_map(|f| Some((f.name.clone()?, (f.id, uri!(super::files::raw::get: &username, paste.id(), f.id, _).to_string()))))
+    .collect();
+  let toc_title = l10n.tr("markdown-toc")?;
 
   for file in &files {
+    if let Some(Content::Base64(ref data)) = file.content {
+      if images::detect(data).is_some() {
+        image_files.insert(file.id, true);
+      }
+      continue;
+    }
+
+    // config files are shown as trees if they're valid and explain why if they aren't
+    let format = match (&file.name, &file.content) {
+      (Some(name), Some(Content::Text(content))) => {
+        let language = file.highlight_language.and_then(Language::from_hljs);
+        Format::detect(name, language).map(|format| (format, content))
+      },
+      _ => None,
+    };
+    if let Some((format, content)) = format {
+      match format.parse(content) {
+        Ok(documents) => {
+          rendered.insert(file.id, structured::to_tree(&documents));
+          pretty.insert(file.id, json!({
+            "content": format.pretty(&documents)?,
+            "language": format.hljs(),
+          }));
+        },
+        Err(e) => {
+          notices.insert(file.id, e.explain(format, &l10n)?);
+        },
+      }
+      continue;
+    }
+
     if let Some(ref name) = file.name {
       let lower = name.to_lowercase();
 
@@ -139,9 +222,14 @@ pub fn users_username_id(username: String, id: PasteId, config: State<Config>, u
       let is_md = md_ext || md_lang;
       let is_svg = lower.ends_with(".svg");
 
-      let is_csv = file.highlight_language.is_none() && lower.ends_with(".csv");
+      let is_csv = file.highlight_language.is_none() && (lower.ends_with(".csv") || lower.ends_with(".tsv"));
 
-      if !is_md && !is_csv && !is_svg {
+      let is_notebook = file.highlight_language.is_none() && lower.ends_with(".ipynb");
+      let adoc_ext = file.highlight_language.is_none() && (lower.ends_with(".adoc") || lower.ends_with(".asciidoc") || lower.ends_with(".asc"));
+      let is_adoc = adoc_ext || file.highlight_language == Some(Language::AsciiDoc.hljs());
+      let is_rst = file.highlight_language.is_none() && (lower.ends_with(".rst") || lower.ends_with(".rest"));
+
+      if !is_md && !is_csv && !is_svg && !is_notebook && !is_adoc && !is_rst {
         continue;
       }
 
@@ -150,18 +238,53 @@ pub fn users_username_id(username: String, id: PasteId, config: State<Config>, u
         _ => continue,
       };
 
+      let links = FileLinks {
+        id_prefix: post_processing::id_prefix(file.id),
+        files: &paste_files,
+      };
+
       let processed = if is_md {
-        let md = markdown_to_html(content, &*OPTIONS);
+        let md = markdown::to_html(content, &*OPTIONS, &toc_title);
         let cleaned = CLEANER.clean(&md).to_string();
-        post_processing::process(&*config, &cleaned)
-      } else if is_csv {
-        match csv_to_table(content, &l10n) {
+        post_processing::process(&*config, &cleaned, &links)
+      } else if is_notebook {
+        let html = match notebook::to_html(content, &*OPTIONS) {
           Ok(h) => h,
+          Err(e) => {
+            notices.insert(file.id, l10n.tr_ex("notebook-error", |req| req.arg("err", e.to_string()))?);
+            continue;
+          },
+        };
+        has_notebook = true;
+        let cleaned = NOTEBOOK_CLEANER.clean(&html).to_string();
+        post_processing::process(&*config, &cleaned, &links)
+      } else if is_adoc || is_rst {
+        let html = if is_adoc { asciidoc::to_html(content) } else { rst::to_html(content) };
+        let cleaned = CLEANER.clean(&html).to_string();
+        post_processing::process(&*config, &cleaned, &links)
+      } else if is_csv {
+        let view = table_query.view(file.id);
+        let delimiter = csv::delimiter_for(name, content);
+        let table = match csv::csv_to_table(content, delimiter, file.id, view, &l10n) {
+          Ok(t) => t,
           Err(e) => {
             notices.insert(file.id, e?);
             continue;
           },
-        }
+        };
+        // page links keep the current sort
+        let page_link = |page| TableView { page, ..view }.query(file.id);
+        tables.insert(file.id, json!({
+          "page": table.page,
+          "pages": table.pages,
+          "rows": table.rows,
+          "first_row": (table.page - 1) * csv::ROWS_PER_PAGE + 1,
+          "last_row": (table.page * csv::ROWS_PER_PAGE).min(table.rows),
+          "prev": page_link(table.page - 1),
+          "next": page_link(table.page + 1),
+          "download": uri!(super::files::csv::get: &username, paste.id(), file.id).to_string(),
+        }));
+        table.html
       } else {
         format!(
           "<img src=\"{src}\" alt=\"{name} SVG preview\"/>",
@@ -174,6 +297,12 @@ pub fn users_username_id(username: String, id: PasteId, config: State<Config>, u
     }
   }
 
+  // only advertise metadata for pastes that are reachable by link
+  let meta = match paste.visibility() {
+    Visibility::Private => None,
+    _ => Some(super::PasteMeta::new(&*config, &paste, author.as_ref(), &files, &l10n)?),
+  };
+
   let output = Output::new(
     id,
     author,
@@ -212,13 +341,29 @@ pub fn users_username_id(username: String, id: PasteId, config: State<Config>, u
   ctx["num_commits"] = json!(paste.num_commits(&*config)?);
   ctx["rendered"] = json!(rendered);
   ctx["notices"] = json!(notices);
+  ctx["images"] = json!(image_files);
+  ctx["pretty"] = json!(pretty);
+  ctx["tables"] = json!(tables);
   ctx["user"] = json!(*user);
   ctx["deletion_key"] = json!(sess.data.remove(&format!("deletion_key_{}", paste.id().to_simple())));
   ctx["is_owner"] = json!(is_owner);
+  // only the owner knows when the paste will change
+  if is_owner {
+    if let Some(change) = paste.visibility_change(&conn)? {
+      ctx["visibility_change"] = json!({
+        "visibility": change.visibility(),
+        "at": change.scheduled_for(),
+      });
+    }
+  }
   ctx["author_name"] = json!(author_name);
   ctx["links"] = json!(links);
+  ctx["meta"] = json!(meta);
+
+  // notebooks embed their output images as data urls, which no other page needs
+  let csp = if has_notebook { vec!["img-src data:"] } else { Vec::new() };
 
-  Ok(Rst::Template(Template::render("paste/index", ctx)))
+  Ok(AddCsp::new(Rst::Template(Template::render("paste/index", ctx)), csp))
 }
 
 #[get("/p/<username>/<id>/delete")]
@@ -328,6 +473,12 @@ pub fn edit(username: String, id: PasteId, config: State<Config>, user: Optional
   ctx["num_commits"] = json!(paste.num_commits(&*config)?);
   ctx["is_owner"] = json!(is_owner);
   ctx["author_name"] = json!(author_name);
+  if let Some(change) = paste.visibility_change(&conn)? {
+    ctx["visibility_change"] = json!({
+      "visibility": change.visibility(),
+      "at": change.scheduled_for(),
+    });
+  }
   ctx["links"] = json!(
     super::paste_links(paste.id(), paste.author_id(), &author_name, Some(&user))
       .add(
@@ -0,0 +1,427 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    PostgresPool,
+    models::{files::File as DbFile, pastes::Paste as DbPaste, users::User},
+    schema::{api_keys, files, users},
+  },
+  errors::*,
+  models::id::{FileId, PasteId},
+  routes::AddHeaders,
+  utils::git_http::{self, Quarantine, RefUpdate},
+};
+
+use data_encoding::BASE64;
+
+use diesel::prelude::*;
+
+use git2::{Oid, Repository, Tree, build::CheckoutBuilder};
+
+use libflate::gzip::Decoder;
+
+use rocket::{
+  Data, Outcome, Request, State,
+  http::{RawStr, Status as HttpStatus},
+  request::{self, FromParam, FromRequest},
+  response::status::Custom,
+};
+
+use uuid::Uuid;
+
+use std::{
+  fs,
+  io::Read,
+  str::FromStr,
+};
+
+// fetch requests are only wants and haves, so they should never get very large
+const MAX_REQUEST_SIZE: u64 = 10 * 1024 * 1024;
+
+/// A paste id followed by `.git`, as used in clone urls.
+pub struct RepoId(pub PasteId);
+
+impl FromParam<'a> for RepoId {
+  type Error = &'a RawStr;
+
+  fn from_param(param: &'a RawStr) -> std::result::Result<Self, Self::Error> {
+    let id = param.as_str().strip_suffix(".git").ok_or(param)?;
+    Uuid::from_str(id)
+      .map(|u| RepoId(PasteId(u)))
+      .map_err(|_| param)
+  }
+}
+
+/// A user authenticated with an API key through HTTP basic auth, which is all git clients send.
+///
+/// The key may be given as either the username or the password.
+pub enum GitAuth {
+  Anonymous,
+  User(User),
+  Invalid,
+}
+
+impl FromRequest<'a, 'r> for GitAuth {
+  type Error = ();
+
+  fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+    let header = match request.headers().get_one("Authorization") {
+      Some(h) => h,
+      None => return Outcome::Success(GitAuth::Anonymous),
+    };
+
+    let credentials = header
+      .strip_prefix("Basic ")
+      .and_then(|x| BASE64.decode(x.trim().as_bytes()).ok())
+      .and_then(|x| String::from_utf8(x).ok());
+    let credentials = match credentials {
+      Some(c) => c,
+      None => return Outcome::Success(GitAuth::Invalid),
+    };
+
+    let key = credentials
+      .split(':')
+      .find_map(|x| Uuid::from_str(x).ok());
+    let key = match key {
+      Some(k) => k,
+      None => return Outcome::Success(GitAuth::Invalid),
+    };
+
+    let conn = match request.guard::<State<PostgresPool>>() {
+      Outcome::Success(p) => match p.get() {
+        Ok(c) => c,
+        Err(_) => return Outcome::Failure((HttpStatus::ServiceUnavailable, ())),
+      },
+      Outcome::Failure((status, _)) => return Outcome::Failure((status, ())),
+      Outcome::Forward(()) => return Outcome::Forward(()),
+    };
+
+    let user = users::table
+      .inner_join(api_keys::table)
+      .filter(api_keys::key.eq(key))
+      .select(users::all_columns)
+      .first(&*conn)
+      .optional();
+    match user {
+      Ok(Some(u)) => Outcome::Success(GitAuth::User(u)),
+      Ok(None) => Outcome::Success(GitAuth::Invalid),
+      Err(_) => Outcome::Failure((HttpStatus::ServiceUnavailable, ())),
+    }
+  }
+}
+
+impl GitAuth {
+  pub fn user(&self) -> Option<&User> {
+    match *self {
+      GitAuth::User(ref u) => Some(u),
+      _ => None,
+    }
+  }
+}
+
+/// Whether the request body was gzipped, which git does for larger requests.
+pub struct Gzipped(bool);
+
+impl FromRequest<'a, 'r> for Gzipped {
+  type Error = ();
+
+  fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+    let gzipped = request.headers().get_one("Content-Encoding") == Some("gzip");
+    Outcome::Success(Gzipped(gzipped))
+  }
+}
+
+pub type GitResponse = AddHeaders<Custom<Vec<u8>>>;
+
+fn git_response(content_type: &str, body: Vec<u8>) -> GitResponse {
+  AddHeaders::new(
+    Custom(HttpStatus::Ok, body),
+    vec![
+      ("Content-Type".into(), content_type.into()),
+      ("Cache-Control".into(), "no-cache".into()),
+    ],
+  )
+}
+
+fn status(status: HttpStatus) -> GitResponse {
+  AddHeaders::new(Custom(status, Vec::new()), Vec::new())
+}
+
+/// Asks the client for credentials.
+fn unauthorized(config: &Config) -> GitResponse {
+  AddHeaders::new(
+    Custom(HttpStatus::Unauthorized, Vec::new()),
+    vec![
+      ("WWW-Authenticate".into(), format!("Basic realm=\"{}\"", config.read().general.site_name)),
+    ],
+  )
+}
+
+/// Finds the paste for a repository url, or the response to send if it can't be accessed.
+pub(crate) fn find_paste(config: &Config, conn: &DbConn, auth: &GitAuth, username: &str, id: PasteId) -> Result<std::result::Result<DbPaste, GitResponse>> {
+  // anonymous requests are challenged rather than refused, so git will ask for credentials
+  let denied = match *auth {
+    GitAuth::Invalid => return Ok(Err(unauthorized(config))),
+    GitAuth::Anonymous => unauthorized(config),
+    GitAuth::User(_) => status(HttpStatus::NotFound),
+  };
+
+  let paste: DbPaste = match id.get(conn)? {
+    Some(p) => p,
+    None => return Ok(Err(denied)),
+  };
+
+  let expected_username: String = match paste.author_id() {
+    Some(author) => {
+      let user: User = users::table.find(author).first(&**conn)?;
+      user.username().to_string()
+    },
+    None => "anonymous".into(),
+  };
+
+  if username != expected_username {
+    return Ok(Err(denied));
+  }
+
+  if paste.check_access(auth.user().map(|x| x.id())).is_some() {
+    return Ok(Err(denied));
+  }
+
+  Ok(Ok(paste))
+}
+
+/// Finds a paste that the user is allowed to push to.
+fn find_owned_paste(config: &Config, conn: &DbConn, auth: &GitAuth, username: &str, id: PasteId) -> Result<std::result::Result<DbPaste, GitResponse>> {
+  // even public pastes need credentials to push
+  if let GitAuth::Anonymous = *auth {
+    return Ok(Err(unauthorized(config)));
+  }
+
+  let paste = match find_paste(config, conn, auth, username, id)? {
+    Ok(p) => p,
+    Err(resp) => return Ok(Err(resp)),
+  };
+
+  // anonymous pastes have no owner to push to them
+  if paste.author_id().is_none() || paste.author_id() != auth.user().map(|x| x.id()) {
+    return Ok(Err(status(HttpStatus::Forbidden)));
+  }
+
+  Ok(Ok(paste))
+}
+
+#[get("/p/<username>/<repo>/info/refs?<service>")]
+pub fn info_refs(username: String, repo: RepoId, service: Option<String>, config: State<Config>, auth: GitAuth, conn: DbConn) -> Result<GitResponse> {
+  let (paste, capabilities): (_, &[&str]) = match service.as_deref() {
+    Some("git-upload-pack") => (find_paste(&*config, &conn, &auth, &username, repo.0)?, &[]),
+    Some("git-receive-pack") => (find_owned_paste(&*config, &conn, &auth, &username, repo.0)?, &["report-status", "ofs-delta"]),
+    // the dumb protocol isn't supported
+    _ => return Ok(status(HttpStatus::Forbidden)),
+  };
+  let service = service.unwrap_or_default();
+
+  let paste = match paste {
+    Ok(p) => p,
+    Err(resp) => return Ok(resp),
+  };
+
+  let repository = Repository::open(paste.files_directory(&*config))?;
+  let body = git_http::advertise_refs(&repository, &service, capabilities)?;
+
+  Ok(git_response(&format!("application/x-{}-advertisement", service), body))
+}
+
+#[post("/p/<username>/<repo>/git-upload-pack", data = "<data>")]
+pub fn upload_pack(username: String, repo: RepoId, data: Data, gzipped: Gzipped, config: State<Config>, auth: GitAuth, conn: DbConn) -> Result<GitResponse> {
+  let paste = match find_paste(&*config, &conn, &auth, &username, repo.0)? {
+    Ok(p) => p,
+    Err(resp) => return Ok(resp),
+  };
+
+  let request = read_request(data, gzipped, MAX_REQUEST_SIZE)?;
+
+  let repository = Repository::open(paste.files_directory(&*config))?;
+  let body = git_http::upload_pack(&repository, &request)?;
+
+  Ok(git_response("application/x-git-upload-pack-result", body))
+}
+
+#[post("/p/<username>/<repo>/git-receive-pack", data = "<data>")]
+pub fn receive_pack(username: String, repo: RepoId, data: Data, gzipped: Gzipped, config: State<Config>, auth: GitAuth, conn: DbConn) -> Result<GitResponse> {
+  let mut paste = match find_owned_paste(&*config, &conn, &auth, &username, repo.0)? {
+    Ok(p) => p,
+    Err(resp) => return Ok(resp),
+  };
+
+  // the pack is streamed into the repository rather than read into memory, but still has to be
+  // kept from filling the disk
+  let limit = config.read().pastes.max_push_size;
+  let mut stream = open_request(data, gzipped, limit)?;
+  let request = git_http::read_receive_pack(&mut stream)?;
+
+  let repository = paste.repository(&*config)?;
+  let previous = repository.head().ok().and_then(|x| x.peel_to_tree().ok());
+
+  // the pack is only moved into the repository once something is going to use it
+  let quarantine = Quarantine::new(&repository)?;
+  let unpack = quarantine.write_pack(&mut stream).map_err(|e| e.to_string());
+
+  let mut results = Vec::with_capacity(request.updates.len());
+  for update in &request.updates {
+    let result = match unpack {
+      Ok(()) => check_update(&*config, &conn, &paste, &repository, update),
+      Err(_) => Err("unpacker error".into()),
+    };
+    results.push((update.name.clone(), result));
+  }
+
+  let mut updated = false;
+  if results.iter().any(|(_, result)| result.is_ok()) {
+    quarantine.migrate(&repository)?;
+
+    for (update, (_, result)) in request.updates.iter().zip(&mut results) {
+      if result.is_ok() {
+        *result = repository.reference(&update.name, update.new, true, "push")
+          .map(|_| ())
+          .map_err(|e| e.to_string());
+      }
+      updated |= result.is_ok();
+    }
+  }
+
+  if updated {
+    // checkout can write over existing files, which may be shared with other pastes, so remove them
+    // and let it write new ones
+    if let Some(ref previous) = previous {
+      let files_directory = paste.files_directory(&*config);
+      for entry in previous.iter() {
+        if let Some(name) = entry.name() {
+          fs::remove_file(files_directory.join(name)).ok();
+        }
+      }
+    }
+
+    // the working directory is what gets served, so bring it up to date
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force();
+    repository.checkout_head(Some(&mut checkout))?;
+
+    paste.sync_files(&*config, &conn, previous.as_ref())?;
+
+    let tree = repository.head()?.peel_to_tree()?;
+    paste.share_blobs(&*config, &repository, &tree, previous.as_ref())?;
+  }
+
+  let body = if request.wants_report() {
+    git_http::report_status(unpack, &results)
+  } else {
+    Vec::new()
+  };
+
+  Ok(git_response("application/x-git-receive-pack-result", body))
+}
+
+/// Checks a pushed ref update, returning the reason it was refused if it was.
+fn check_update(config: &Config, conn: &DbConn, paste: &DbPaste, repo: &Repository, update: &RefUpdate) -> std::result::Result<(), String> {
+  // pastes only have the one branch
+  let branch = repo.find_reference("HEAD")
+    .ok()
+    .and_then(|x| x.symbolic_target().map(ToString::to_string))
+    .unwrap_or_else(|| "refs/heads/master".into());
+  if update.name != branch {
+    return Err(format!("only {} can be pushed to", branch));
+  }
+
+  if update.new.is_zero() {
+    return Err("the branch can't be deleted".into());
+  }
+
+  let current = repo.refname_to_id(&update.name).ok();
+  if current.unwrap_or_else(Oid::zero) != update.old {
+    return Err("fetch first".into());
+  }
+
+  if let Some(current) = current {
+    let fast_forward = current == update.new
+      || repo.graph_descendant_of(update.new, current).map_err(|e| e.to_string())?;
+    if !fast_forward {
+      return Err("non-fast-forward".into());
+    }
+  }
+
+  let commit = repo.find_commit(update.new).map_err(|_| "missing necessary objects".to_string())?;
+  let tree = commit.tree().map_err(|e| e.to_string())?;
+  check_tree(config, conn, paste, repo, &tree)?;
+
+  Ok(())
+}
+
+/// Makes sure a pushed tree can be represented as a paste.
+fn check_tree(config: &Config, conn: &DbConn, paste: &DbPaste, repo: &Repository, tree: &Tree) -> std::result::Result<(), String> {
+  let (max_files, max_file_size) = {
+    let config = config.read();
+    (config.pastes.max_files, config.pastes.max_file_size)
+  };
+
+  if tree.is_empty() {
+    return Err("pastes must have at least one file".into());
+  }
+
+  if tree.len() > max_files {
+    return Err(format!("pastes can have at most {} files", max_files));
+  }
+
+  let existing: Vec<FileId> = paste.id()
+    .files(conn)
+    .map_err(|e| e.to_string())?
+    .iter()
+    .map(|x| x.id())
+    .collect();
+
+  for entry in tree.iter() {
+    let name = entry.name().unwrap_or_default();
+
+    match entry.filemode() {
+      0o100644 | 0o100755 => {},
+      0o040000 => return Err(format!("{}: directories are not supported", name)),
+      _ => return Err(format!("{}: only regular files are supported", name)),
+    }
+
+    // files are stored under their ids, which have to stay unique across every paste
+    let id = match Uuid::parse_str(name) {
+      Ok(u) if u.to_simple().to_string() == name => FileId(u),
+      _ => return Err(format!("{}: files must be named with a new or existing file id", name)),
+    };
+    if !existing.contains(&id) && files::table.find(id).first::<DbFile>(&**conn).optional().map_err(|e| e.to_string())?.is_some() {
+      return Err(format!("{}: that file id is already in use", name));
+    }
+
+    let blob = repo.find_blob(entry.id()).map_err(|e| e.to_string())?;
+    if blob.size() == 0 {
+      return Err(format!("{}: files can't be empty", name));
+    }
+    if blob.size() as u64 > max_file_size {
+      return Err(format!("{}: files can be at most {} bytes", name, max_file_size));
+    }
+  }
+
+  Ok(())
+}
+
+pub(crate) fn read_request(data: Data, gzipped: Gzipped, limit: u64) -> Result<Vec<u8>> {
+  let mut request = Vec::new();
+  open_request(data, gzipped, limit)?.read_to_end(&mut request)?;
+
+  Ok(request)
+}
+
+/// The body of a request, decompressed if need be, and cut off after `limit` bytes.
+fn open_request(data: Data, gzipped: Gzipped, limit: u64) -> Result<Box<dyn Read>> {
+  let stream = data.open().take(limit);
+
+  if gzipped.0 {
+    Ok(Box::new(Decoder::new(stream)?.take(limit)))
+  } else {
+    Ok(Box::new(stream))
+  }
+}
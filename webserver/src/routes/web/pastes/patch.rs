@@ -11,13 +11,15 @@ use crate::{
     id::{PasteId, FileId},
     paste::{
       Visibility, Content,
-      update::{MetadataUpdate, Update},
+      update::{MetadataUpdate, ScheduledVisibility, Update},
     },
   },
   routes::web::{OptionalWebUser, Rst, Session},
   utils::{FormDate, Language},
 };
 
+use chrono::Utc;
+
 use diesel::prelude::*;
 
 use rocket::{
@@ -33,8 +35,6 @@ use unicode_segmentation::UnicodeSegmentation;
 
 use std::{
   borrow::Cow,
-  fs::OpenOptions,
-  io::Write,
   result,
 };
 
@@ -150,15 +150,45 @@ pub fn patch(update: LenientForm<PasteUpdate>, username: String, paste_id: Paste
     None => Update::Remove,
   };
 
+  let current_change = paste.visibility_change(&conn)?;
+  let visibility_change = match update.scheduled_visibility {
+    Some(visibility) => {
+      let at = match update.visibility_at {
+        Some(ref at) => **at,
+        None => {
+          sess.add_data("error", "Choose when the visibility should change.");
+          return Ok(Rst::Redirect(Redirect::to("lastpage")));
+        },
+      };
+      // the form only has minutes, so compare to the minute
+      let unchanged = current_change
+        .as_ref()
+        .map(|c| c.visibility() == visibility && c.scheduled_for().timestamp() / 60 == at.timestamp() / 60)
+        .unwrap_or(false);
+      if unchanged {
+        Update::Ignore
+      } else if at <= Utc::now() {
+        sess.add_data("error", "Visibility changes must be scheduled for the future.");
+        return Ok(Rst::Redirect(Redirect::to("lastpage")));
+      } else {
+        Update::Set(ScheduledVisibility { visibility, at })
+      }
+    },
+    None if current_change.is_some() => Update::Remove,
+    None => Update::Ignore,
+  };
+
   let metadata = MetadataUpdate {
     name: into_update(update.name, paste.name()),
     description: into_update(update.description, paste.description()),
     expires,
+    expires_in: None,
     visibility: if update.visibility == paste.visibility() {
       None
     } else {
       Some(update.visibility)
     },
+    visibility_change,
   };
 
   paste.update(&*config, &conn, &*sidekiq, &metadata)?;
@@ -166,7 +196,6 @@ pub fn patch(update: LenientForm<PasteUpdate>, username: String, paste_id: Paste
   let mut db_changed = false;
   // TODO: this needs much refactor love
   // update files and database if necessary
-  let files_directory = paste.files_directory(&*config);
 
   let mut db_files = paste_id.files(&conn)?;
   {
@@ -214,11 +243,7 @@ pub fn patch(update: LenientForm<PasteUpdate>, username: String, paste_id: Paste
           db_file.set_name(file.name);
           db_changed = true;
         }
-        let mut f = OpenOptions::new()
-          .write(true)
-          .truncate(true)
-          .open(files_directory.join(db_file.id().to_simple().to_string()))?;
-        f.write_all(&file.content.into_bytes())?;
+        paste.write_file(&*config, db_file.id(), Content::Text(file.content))?;
         // FIXME: set is_binary field
 
         if file.language != db_file.highlight_language() {
@@ -273,6 +298,8 @@ pub struct PasteUpdate {
   visibility: Visibility,
   description: String,
   expires: Option<FormDate>,
+  scheduled_visibility: Option<Visibility>,
+  visibility_at: Option<FormDate>,
   #[serde(skip)]
   upload_json: Option<String>,
   #[serde(skip)]
@@ -6,11 +6,11 @@ use crate::{
   i18n::prelude::*,
   models::paste::{Visibility, Content},
   routes::web::{AntiSpam, OptionalWebUser, Session},
-  utils::{FormDate, Language},
+  utils::{FormDate, Language, MultipartForm, extract},
 };
 
 use rocket::{
-  request::Form,
+  request::{Form, FormItems, FromForm},
   response::Redirect,
   State,
 };
@@ -34,8 +34,35 @@ fn handle_js(input: &str) -> Result<Vec<MultiFile>> {
 }
 
 #[post("/pastes", format = "application/x-www-form-urlencoded", data = "<paste>")]
-pub fn post(paste: Form<PasteUpload>, user: OptionalWebUser, mut sess: Session, antispam: AntiSpam, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>, l10n: L10n) -> Result<Redirect> {
-  let paste = paste.into_inner();
+pub fn post(paste: Form<PasteUpload>, user: OptionalWebUser, sess: Session, antispam: AntiSpam, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>, l10n: L10n) -> Result<Redirect> {
+  create(paste.into_inner(), None, user, sess, antispam, conn, sidekiq, config, l10n)
+}
+
+/// Accepts the paste form when it includes an archive to create the paste's files from.
+#[post("/pastes", format = "multipart/form-data", data = "<form>")]
+pub fn post_multipart(form: std::result::Result<MultipartForm, String>, user: OptionalWebUser, mut sess: Session, antispam: AntiSpam, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>, l10n: L10n) -> Result<Redirect> {
+  let mut form = match form {
+    Ok(f) => f,
+    Err(e) => {
+      sess.add_data("error", format!("Could not read the upload: {}.", e));
+      return Ok(Redirect::to(uri!(crate::routes::web::index::get)));
+    },
+  };
+
+  let archive = form.take_file("archive");
+  let fields = form.encoded_fields();
+  let paste = match PasteUpload::from_form(&mut FormItems::from(fields.as_str()), false) {
+    Ok(p) => p,
+    Err(_) => {
+      sess.add_data("error", "Invalid form. Did you tamper with it?");
+      return Ok(Redirect::to(uri!(crate::routes::web::index::get)));
+    },
+  };
+
+  create(paste, archive, user, sess, antispam, conn, sidekiq, config, l10n)
+}
+
+fn create(paste: PasteUpload, archive: Option<Vec<u8>>, user: OptionalWebUser, mut sess: Session, antispam: AntiSpam, conn: DbConn, sidekiq: State<SidekiqClient>, config: State<Config>, l10n: L10n) -> Result<Redirect> {
   sess.set_form(&paste);
 
   if !sess.check_token(&paste.anti_csrf_token) {
@@ -87,8 +114,10 @@ pub fn post(paste: Form<PasteUpload>, user: OptionalWebUser, mut sess: Session,
     Some(paste.description)
   };
 
-  let files = files
+  let mut files: Vec<FilePayload> = files
     .into_iter()
+    // the editor always submits at least one file, which is left empty when uploading an archive
+    .filter(|f| archive.is_none() || !f.content.is_empty())
     .map(|f| FilePayload {
       name: if f.name.is_empty() { None } else { Some(f.name) },
       highlight_language: f.language,
@@ -96,6 +125,35 @@ pub fn post(paste: Form<PasteUpload>, user: OptionalWebUser, mut sess: Session,
     })
     .collect();
 
+  let archive_files = match archive {
+    Some(ref data) => {
+      let (max_files, max_file_size) = {
+        let config = config.read();
+        (config.pastes.max_files, config.pastes.max_file_size)
+      };
+      // the archive's files go alongside the editor's, so they share the paste's allowance
+      let remaining = max_files.saturating_sub(files.len());
+      match extract::extract(data, remaining, max_file_size) {
+        Ok(f) => f,
+        Err(extract::ExtractError::TooManyFiles(_)) => {
+          sess.add_data("error", format!("Could not create a paste from the archive: pastes can have at most {} files.", max_files));
+          return Ok(Redirect::to(uri!(crate::routes::web::index::get)));
+        },
+        Err(e) => {
+          sess.add_data("error", format!("Could not create a paste from the archive: {}.", e));
+          return Ok(Redirect::to(uri!(crate::routes::web::index::get)));
+        },
+      }
+    },
+    None => Vec::new(),
+  };
+
+  files.extend(archive_files.into_iter().map(|f| FilePayload {
+    name: Some(f.name),
+    highlight_language: None,
+    content: f.content,
+  }));
+
   let pp = PastePayload {
     name,
     description,
@@ -103,6 +161,7 @@ pub fn post(paste: Form<PasteUpload>, user: OptionalWebUser, mut sess: Session,
     expires: paste.expires.map(|x| x.into_inner()),
     author: user.as_ref(),
     files,
+    uploads: Vec::new(),
   };
 
   let CreateSuccess { paste, deletion_key, .. } = match pp.create(&*config, &conn, &*sidekiq) {
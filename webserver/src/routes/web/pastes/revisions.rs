@@ -156,6 +156,12 @@ pub fn get(username: String, id: PasteId, config: State<Config>, user: OptionalW
   ctx["num_commits"] = json!(count);
   ctx["author_name"] = json!(author_name);
   ctx["revisions"] = json!(all_revisions);
+  ctx["clone_url"] = json!(format!(
+    "https://{}/p/{}/{}.git",
+    config.read().general.site_domain,
+    author_name,
+    paste.id(),
+  ));
   ctx["links"] = json!(super::paste_links(paste.id(), paste.author_id(), &author_name, user.as_ref()));
 
   Ok(Rst::Template(Template::render("paste/revisions", ctx)))
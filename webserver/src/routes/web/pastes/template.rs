@@ -0,0 +1,81 @@
+use crate::{
+  config::Config,
+  database::{
+    DbConn,
+    models::{
+      paste_templates::PasteTemplate,
+      pastes::Paste as DbPaste,
+      users::User,
+    },
+    schema::users,
+  },
+  errors::*,
+  i18n::prelude::*,
+  models::id::PasteId,
+  routes::web::{Rst, OptionalWebUser, Session},
+};
+
+use diesel::prelude::*;
+
+use rocket::{
+  http::Status as HttpStatus,
+  request::{Form, State},
+  response::Redirect,
+};
+
+#[post("/p/<username>/<id>/template", format = "application/x-www-form-urlencoded", data = "<template>")]
+pub fn post(template: Form<NewTemplate>, username: String, id: PasteId, config: State<Config>, user: OptionalWebUser, mut sess: Session, conn: DbConn, l10n: L10n) -> Result<Rst> {
+  let template = template.into_inner();
+
+  if !sess.check_token(&template.anti_csrf_token) {
+    sess.add_data("error", l10n.tr("error-csrf")?);
+    return Ok(Rst::Redirect(Redirect::to("lastpage")));
+  }
+
+  let user = match *user {
+    Some(ref u) => u,
+    None => return Ok(Rst::Redirect(Redirect::to(uri!(crate::routes::web::auth::login::get)))),
+  };
+
+  let paste: DbPaste = match id.get(&conn)? {
+    Some(p) => p,
+    None => return Ok(Rst::Status(HttpStatus::NotFound)),
+  };
+
+  let expected_username: String = match paste.author_id() {
+    Some(author) => {
+      let user: User = users::table.find(author).first(&*conn)?;
+      user.username().to_string()
+    },
+    None => "anonymous".into(),
+  };
+
+  if username != expected_username {
+    return Ok(Rst::Status(HttpStatus::NotFound));
+  }
+
+  if let Some((status, _)) = paste.check_access(user.id()) {
+    return Ok(Rst::Status(status));
+  }
+
+  let name = template.name.trim();
+  let name = if name.is_empty() {
+    match paste.name() {
+      Some(n) => n.to_string(),
+      None => l10n.tr("disp-untitled")?,
+    }
+  } else {
+    name.to_string()
+  };
+
+  PasteTemplate::create(&*config, &conn, user.id(), name, &paste)?;
+
+  sess.add_data("info", l10n.tr("template-saved")?);
+  Ok(Rst::Redirect(Redirect::to(uri!(crate::routes::web::account::templates::get))))
+}
+
+#[derive(Debug, FromForm)]
+pub struct NewTemplate {
+  name: String,
+  anti_csrf_token: String,
+}
@@ -6,14 +6,19 @@ use crate::{
     schema::{users, pastes},
   },
   errors::*,
-  models::paste::{
-    Visibility, Content,
-    output::{Output, OutputAuthor},
+  models::{
+    id::PasteId,
+    paste::{
+      Visibility, Content,
+      output::{Output, OutputAuthor},
+    },
   },
   routes::web::{context, Rst, Links, OptionalWebUser, Session},
-  utils::AcceptLanguage,
+  utils::{images, AcceptLanguage},
 };
 
+use hashbrown::HashMap;
+
 use diesel::{dsl::count, prelude::*};
 
 use rocket::{State, http::Status as HttpStatus};
@@ -44,6 +49,8 @@ pub fn get(username: String, page: Option<u32>, config: State<Config>, user: Opt
   }
   let total_pastes: i64 = query.get_result(&*conn)?;
 
+  let mut thumbnails: HashMap<PasteId, serde_json::Value> = HashMap::new();
+
   let outputs = if total_pastes == 0 && page == 1 {
     Vec::default()
   } else {
@@ -88,6 +95,16 @@ pub fn get(username: String, page: Option<u32>, config: State<Config>, user: Opt
           Some(f) => f,
           None => continue,
         };
+        // show the first image in the paste, if it has any
+        if !thumbnails.contains_key(&id)
+          && file.is_binary() == Some(true)
+          && images::detect_file(&file.path(&*config, &paste))?.is_some()
+        {
+          thumbnails.insert(id, json!({
+            "src": uri!(crate::routes::web::pastes::files::thumbnail::get: target.username(), id, file.id()).to_string(),
+            "name": file.name(),
+          }));
+        }
         // TODO: maybe store this in database or its own file?
         if !has_preview && file.is_binary() != Some(true) {
           let path = file.path(&*config, &paste);
@@ -149,6 +166,7 @@ pub fn get(username: String, page: Option<u32>, config: State<Config>, user: Opt
 
   let mut ctx = context(&*config, user.as_ref(), &mut sess, langs);
   ctx["pastes"] = json!(outputs);
+  ctx["thumbnails"] = json!(thumbnails);
   ctx["target"] = json!(target);
   ctx["page"] = json!(page);
   ctx["total"] = json!(total_pastes);
@@ -159,6 +177,8 @@ pub fn get(username: String, page: Option<u32>, config: State<Config>, user: Opt
 fn user_links(user: Option<&User>, target: &User, pastes: &[Output], page: u32) -> Links {
   let mut links = links!(
     "target_avatar" => uri!(crate::routes::web::account::avatar::get: target.id()),
+    "feed_atom" => uri!(crate::routes::web::feeds::user_atom: target.username()),
+    "feed_rss" => uri!(crate::routes::web::feeds::user_rss: target.username()),
     "next_page" => uri!(crate::routes::web::users::get::get:
       target.username(),
       page + 1,
@@ -1,7 +1,7 @@
 use crate::{
   config::Config,
   errors::*,
-  models::id::UserId,
+  models::id::{PasteId, UserId},
   store::Store,
 };
 
@@ -15,6 +15,8 @@ use std::path::PathBuf;
 
 pub enum Job<'c> {
   DeleteAllPastes(&'c Config, UserId),
+  /// Moves existing pastes' files into the shared blob store.
+  DedupeStore(&'c Config),
   Email {
     config_path: PathBuf,
     email: String,
@@ -26,6 +28,13 @@ pub enum Job<'c> {
     timestamp: i64,
     args: Vec<Value>,
   },
+  /// Builds a data export archive, then sends the contained email job.
+  ExportData {
+    directory: PathBuf,
+    user_directory: PathBuf,
+    include_history: bool,
+    email: Box<Job<'c>>,
+  },
 }
 
 impl Job<'c> {
@@ -56,26 +65,46 @@ impl Job<'c> {
     }
   }
 
+  /// Sends `email` at `timestamp` with an `ExpiryReminder` job, unless the paste no longer expires
+  /// at `expires` by then.
+  pub fn expiry_reminder(timestamp: i64, expires: i64, paste: PasteId, email: Job<'c>) -> Job<'c> {
+    let mut args = vec![
+      Value::Number(expires.into()),
+      Value::String(paste.to_simple().to_string()),
+    ];
+    args.extend(email.args());
+    Job::queue("ExpiryReminder", timestamp, args)
+  }
+
   fn class(&self) -> &str {
     match *self {
       Job::DeleteAllPastes(_, _) => "DeleteDirectory",
+      Job::DedupeStore(_) => "DedupeStore",
       Job::Email { .. } => "Email",
       Job::Queue { .. } => "Queue",
+      Job::ExportData { .. } => "ExportData",
     }
   }
 
   fn args(&self) -> Vec<Value> {
     match *self {
       Job::DeleteAllPastes(config, u) => {
-        let path = Store::new(config)
+        let store = Store::new(config);
+        let path = store
           .directory()
           .join(u.to_simple().to_string())
           .to_string_lossy()
           .into_owned();
+        // the pastes' blobs have to be released once they're gone
+        let blobs = store.blobs().directory().to_string_lossy().into_owned();
         vec![
           Value::String(path),
+          Value::String(blobs),
         ]
       },
+      Job::DedupeStore(config) => vec![
+        Value::String(Store::new(config).directory().to_string_lossy().into_owned()),
+      ],
       Job::Email { ref config_path, ref email, ref subject, ref content } => vec![
         Value::String(config_path.to_string_lossy().into_owned()),
         Value::String(email.to_string()),
@@ -87,12 +116,21 @@ impl Job<'c> {
         Value::Number(timestamp.into()),
         Value::Array(args.clone()),
       ],
+      Job::ExportData { ref directory, ref user_directory, include_history, ref email } => {
+        let mut args = vec![
+          Value::String(directory.to_string_lossy().into_owned()),
+          Value::String(user_directory.to_string_lossy().into_owned()),
+          Value::Bool(include_history),
+        ];
+        args.extend(email.args());
+        args
+      },
     }
   }
 
   fn opts(&self) -> JobOpts {
     match *self {
-      Job::DeleteAllPastes(_, _) | Job::Queue { .. } => JobOpts {
+      Job::DeleteAllPastes(_, _) | Job::DedupeStore(_) | Job::Queue { .. } | Job::ExportData { .. } => JobOpts {
         queue: "low".into(),
         .. Default::default()
       },
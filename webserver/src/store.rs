@@ -8,7 +8,12 @@ use git2::Repository;
 
 use uuid::Uuid;
 
-use std::{fs, path::PathBuf};
+use std::{
+  fs,
+  path::PathBuf,
+};
+
+pub use blob_store::Blobs;
 
 pub struct Store<'c> {
   config: &'c Config,
@@ -23,6 +28,10 @@ impl Store<'c> {
     self.config.read().store.path.clone()
   }
 
+  pub fn blobs(&self) -> Blobs {
+    Blobs::new(self.directory().join("blobs"))
+  }
+
   pub fn new_paste(&self, author: Option<UserId>) -> Result<PasteId> {
     let id = PasteId(Uuid::new_v4());
 
@@ -1,26 +1,42 @@
 pub mod accept_language;
+pub mod archive;
 pub mod bit_mask;
 pub mod client_ip;
+pub mod content_encoding;
 pub mod csv;
 pub mod email;
+pub mod extract;
 pub mod form_date;
+pub mod git_http;
 pub mod hashed_password;
+pub mod images;
 pub mod language;
+pub mod ldap;
+pub mod markdown;
+pub mod markup;
+pub mod math;
 pub mod multipart;
+pub mod oidc;
 pub mod password;
 pub mod post_processing;
+pub mod raw_file;
+pub mod relative_duration;
+pub mod structured;
 pub mod totp;
 pub mod validator;
+pub mod webauthn;
 pub mod webp;
 
 pub use self::{
   accept_language::AcceptLanguage,
   bit_mask::BitMask,
   client_ip::ClientIp,
+  content_encoding::AcceptEncoding,
   form_date::FormDate,
   hashed_password::HashedPassword,
   language::Language,
-  multipart::MultipartUpload,
+  multipart::{MultipartForm, MultipartUpload},
   password::PasswordContext,
+  relative_duration::RelativeDuration,
   validator::Validator,
 };
@@ -0,0 +1,282 @@
+//! Streaming zip and tar.gz archives of a paste's files.
+//!
+//! Archives are written on a separate thread and sent to the response in chunks, so only one file
+//! is ever held in memory at a time. Zip archives go back to fill in each file's header after its
+//! data, so they're built in a temporary file before being sent.
+
+use crate::{
+  config::Config,
+  database::{DbConn, models::pastes::Paste as DbPaste},
+  errors::*,
+};
+
+use anyhow::Error;
+
+use chrono::{Datelike, NaiveDateTime, Timelike};
+
+use git2::{ObjectType, Oid, Repository};
+
+use libflate::gzip;
+
+use rocket::response::Stream;
+
+use zip::{
+  CompressionMethod, DateTime as ZipDateTime, ZipWriter,
+  result::ZipError,
+  write::FileOptions,
+};
+
+use std::{
+  collections::{HashMap, HashSet},
+  io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+  path::PathBuf,
+  sync::mpsc::{self, Receiver, SyncSender},
+  thread,
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+  Zip,
+  TarGz,
+}
+
+impl Format {
+  pub fn extension(self) -> &'static str {
+    match self {
+      Format::Zip => "zip",
+      Format::TarGz => "tar.gz",
+    }
+  }
+
+  pub fn content_type(self) -> &'static str {
+    match self {
+      Format::Zip => "application/zip",
+      Format::TarGz => "application/gzip",
+    }
+  }
+}
+
+struct Entry {
+  name: String,
+  blob: Oid,
+  executable: bool,
+}
+
+/// The files of a paste at one revision, ready to be written out.
+pub struct Archive {
+  repo_path: PathBuf,
+  prefix: String,
+  entries: Vec<Entry>,
+  modified: NaiveDateTime,
+}
+
+impl Archive {
+  /// Collects the files of `paste` at `revision`, or `HEAD` if not specified.
+  ///
+  /// Returns `None` if the revision doesn't exist.
+  pub fn new(config: &Config, conn: &DbConn, paste: &DbPaste, revision: Option<&str>) -> Result<Option<Self>> {
+    let repo_path = paste.files_directory(config);
+    let repo = Repository::open(&repo_path)?;
+
+    let commit = match repo.revparse_single(revision.unwrap_or("HEAD")).and_then(|x| x.peel_to_commit()) {
+      Ok(c) => c,
+      Err(_) => return Ok(None),
+    };
+
+    // only current files have names, so older ones fall back to their ids
+    let names: HashMap<String, String> = paste.id()
+      .files(conn)?
+      .into_iter()
+      .map(|x| (x.id().to_simple().to_string(), x.name().to_string()))
+      .collect();
+
+    let mut used = HashSet::new();
+    let mut entries = Vec::new();
+    for entry in commit.tree()?.iter() {
+      if entry.kind() != Some(ObjectType::Blob) {
+        continue;
+      }
+
+      let id = entry.name().unwrap_or_default().to_string();
+      let name = names
+        .get(&id)
+        .map(|x| sanitise(x))
+        .filter(|x| !x.is_empty() && used.insert(x.clone()))
+        .unwrap_or(id);
+
+      entries.push(Entry {
+        name,
+        blob: entry.id(),
+        executable: entry.filemode() == 0o100755,
+      });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let modified = NaiveDateTime::from_timestamp(commit.time().seconds(), 0);
+
+    Ok(Some(Archive {
+      repo_path,
+      prefix: paste.id().to_simple().to_string(),
+      entries,
+      modified,
+    }))
+  }
+
+  pub fn file_name(&self, format: Format) -> String {
+    format!("{}.{}", self.prefix, format.extension())
+  }
+
+  /// Starts writing the archive, returning a stream of its bytes.
+  pub fn stream(self, format: Format) -> Stream<ChannelReader> {
+    let (tx, rx) = mpsc::sync_channel(4);
+
+    thread::spawn(move || {
+      let writer = BufWriter::with_capacity(CHUNK_SIZE, ChannelWriter(tx));
+      let res = match format {
+        Format::Zip => self.write_zip(writer),
+        Format::TarGz => self.write_tar_gz(writer),
+      };
+      // the client going away part way through isn't worth reporting
+      if let Err(e) = res {
+        if !is_closed(&e) {
+          println!("could not write paste archive {}: {}", self.prefix, e);
+        }
+      }
+    });
+
+    Stream::from(ChannelReader {
+      rx,
+      chunk: Vec::new(),
+      pos: 0,
+    })
+  }
+
+  fn write_zip<W: Write>(&self, mut writer: W) -> Result<()> {
+    let repo = Repository::open(&self.repo_path)?;
+    let mut zip = ZipWriter::new(tempfile::tempfile()?);
+
+    let m = self.modified;
+    // zip dates can't go before 1980
+    let modified = ZipDateTime::from_date_and_time(
+      m.year() as u16,
+      m.month() as u8,
+      m.day() as u8,
+      m.hour() as u8,
+      m.minute() as u8,
+      m.second() as u8,
+    ).unwrap_or_default();
+
+    for entry in &self.entries {
+      let blob = repo.find_blob(entry.blob)?;
+      let options = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(modified)
+        .unix_permissions(if entry.executable { 0o755 } else { 0o644 });
+
+      zip.start_file(format!("{}/{}", self.prefix, entry.name), options).map_err(zip_error)?;
+      zip.write_all(blob.content())?;
+    }
+
+    let mut file = zip.finish().map_err(zip_error)?;
+    file.seek(SeekFrom::Start(0))?;
+    io::copy(&mut file, &mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+  }
+
+  fn write_tar_gz<W: Write>(&self, writer: W) -> Result<()> {
+    let repo = Repository::open(&self.repo_path)?;
+    let mut tar = tar::Builder::new(gzip::Encoder::new(writer)?);
+
+    for entry in &self.entries {
+      let blob = repo.find_blob(entry.blob)?;
+      let content = blob.content();
+
+      let mut header = tar::Header::new_gnu();
+      header.set_entry_type(tar::EntryType::Regular);
+      header.set_size(content.len() as u64);
+      header.set_mode(if entry.executable { 0o755 } else { 0o644 });
+      header.set_mtime(self.modified.timestamp() as u64);
+
+      tar.append_data(&mut header, format!("{}/{}", self.prefix, entry.name), content)?;
+    }
+
+    tar.into_inner()?.finish().into_result()?.flush()?;
+
+    Ok(())
+  }
+}
+
+/// Makes a file name safe to use as a single path component in an archive.
+fn sanitise(name: &str) -> String {
+  let name: String = name
+    .chars()
+    .map(|c| if c == '/' || c == '\\' || c.is_control() { '_' } else { c })
+    .collect();
+
+  match name.as_str() {
+    "." | ".." => String::new(),
+    _ => name,
+  }
+}
+
+/// Sends everything written to it over a channel, failing once the receiver is gone.
+struct ChannelWriter(SyncSender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.0
+      .send(buf.to_vec())
+      .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "archive stream closed"))?;
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+/// Reads the chunks sent by a `ChannelWriter` until it is dropped.
+pub struct ChannelReader {
+  rx: Receiver<Vec<u8>>,
+  chunk: Vec<u8>,
+  pos: usize,
+}
+
+impl Read for ChannelReader {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    while self.pos >= self.chunk.len() {
+      match self.rx.recv() {
+        Ok(chunk) => {
+          self.chunk = chunk;
+          self.pos = 0;
+        },
+        Err(_) => return Ok(0),
+      }
+    }
+
+    let len = std::cmp::min(buf.len(), self.chunk.len() - self.pos);
+    buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+    self.pos += len;
+
+    Ok(len)
+  }
+}
+
+/// Keeps the I/O errors zip gives as they are, so a closed stream can be recognised.
+fn zip_error(e: ZipError) -> Error {
+  match e {
+    ZipError::Io(e) => e.into(),
+    e => e.into(),
+  }
+}
+
+/// Whether writing failed because the client stopped reading the archive.
+fn is_closed(e: &Error) -> bool {
+  e.downcast_ref::<io::Error>()
+    .map(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    .unwrap_or(false)
+}
@@ -0,0 +1,160 @@
+//! Compressing raw files for clients that send `Accept-Encoding`.
+//!
+//! Compressed files are cached in the blob store under the ID git gives the uncompressed blob, so
+//! each version of a file is only compressed once per encoding, however many pastes contain it. A
+//! blob ID always refers to the same content, so cached copies only go when the blob store removes
+//! the blob they were made from.
+
+use crate::{
+  config::Config,
+  errors::*,
+  store::Store,
+};
+
+use brotli::CompressorWriter;
+
+use git2::Oid;
+
+use libflate::gzip;
+
+use rocket::{
+  Outcome,
+  request::{self, Request, FromRequest},
+};
+
+use uuid::Uuid;
+
+use std::{
+  convert::Infallible,
+  fs::{self, File},
+  io::{self, BufReader, BufWriter, Write},
+  path::{Path, PathBuf},
+};
+
+/// Files smaller than this are sent uncompressed, since the saving wouldn't be worth a cache entry.
+const MIN_SIZE: u64 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+  Brotli,
+  Zstd,
+  Gzip,
+}
+
+impl Encoding {
+  /// Every supported encoding, most preferred first.
+  const ALL: [Encoding; 3] = [Encoding::Brotli, Encoding::Zstd, Encoding::Gzip];
+
+  /// The name of the encoding in `Accept-Encoding` and `Content-Encoding`.
+  pub fn name(self) -> &'static str {
+    match self {
+      Encoding::Brotli => "br",
+      Encoding::Zstd => "zstd",
+      Encoding::Gzip => "gzip",
+    }
+  }
+
+  fn compress(self, input: &Path, output: &Path) -> Result<()> {
+    let mut input = BufReader::new(File::open(input)?);
+    let output = BufWriter::new(File::create(output)?);
+
+    let mut output = match self {
+      Encoding::Brotli => {
+        let mut encoder = CompressorWriter::new(output, 4096, 9, 22);
+        io::copy(&mut input, &mut encoder)?;
+        encoder.into_inner()
+      },
+      Encoding::Zstd => {
+        let mut encoder = zstd::stream::Encoder::new(output, 12)?;
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?
+      },
+      Encoding::Gzip => {
+        let mut encoder = gzip::Encoder::new(output)?;
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish().into_result()?
+      },
+    };
+    output.flush()?;
+
+    Ok(())
+  }
+}
+
+/// The encodings a client accepts, with their quality values.
+pub struct AcceptEncoding(Vec<(String, f32)>);
+
+impl AcceptEncoding {
+  /// The supported encoding the client most wants, if it accepts any.
+  pub fn preferred(&self) -> Option<Encoding> {
+    let quality = |name: &str| self.0.iter()
+      .find(|(x, _)| x == name)
+      .or_else(|| self.0.iter().find(|(x, _)| x == "*"))
+      .map(|&(_, q)| q)
+      .unwrap_or(0.0);
+
+    let mut best: Option<(Encoding, f32)> = None;
+    for &encoding in &Encoding::ALL {
+      let q = quality(encoding.name());
+      // ties go to the encoding listed first, which compresses better
+      if q > 0.0 && best.map(|(_, best_q)| q > best_q).unwrap_or(true) {
+        best = Some((encoding, q));
+      }
+    }
+
+    best.map(|(encoding, _)| encoding)
+  }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for AcceptEncoding {
+  type Error = Infallible;
+
+  fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+    let encodings = request.headers()
+      .get("accept-encoding")
+      .flat_map(|x| x.split(','))
+      // only accept 10 possible encodings
+      .take(10)
+      .filter_map(|x| {
+        let mut parts = x.split(';');
+        let name = parts.next()?.trim().to_lowercase();
+        let q = parts
+          .find_map(|x| x.trim().strip_prefix("q="))
+          .map(|x| x.parse().unwrap_or(0.0))
+          .unwrap_or(1.0);
+        Some((name, q))
+      })
+      .collect();
+    Outcome::Success(AcceptEncoding(encodings))
+  }
+}
+
+/// Opens the file at `path`, compressed with `encoding` if one is given and the file is large enough
+/// to be worth compressing. `id` is the git blob ID of the file.
+///
+/// Returns the encoding actually used alongside the file.
+pub fn open(config: &Config, path: &Path, id: Oid, encoding: Option<Encoding>) -> Result<(File, Option<Encoding>)> {
+  let encoding = match encoding {
+    Some(e) if fs::metadata(path)?.len() >= MIN_SIZE => e,
+    _ => return Ok((File::open(path)?, None)),
+  };
+
+  let cached = cached_path(config, id, encoding);
+  if !cached.exists() {
+    fs::create_dir_all(cached.parent().expect("cache path has a parent"))?;
+
+    // compress to a unique file first so concurrent requests never see a partial file
+    let temp = cached.with_file_name(format!(".tmp-{}", Uuid::new_v4().to_simple()));
+    if let Err(e) = encoding.compress(path, &temp) {
+      fs::remove_file(&temp).ok();
+      return Err(e);
+    }
+    fs::rename(&temp, &cached)?;
+  }
+
+  Ok((File::open(cached)?, Some(encoding)))
+}
+
+fn cached_path(config: &Config, id: Oid, encoding: Encoding) -> PathBuf {
+  Store::new(config).blobs().encoded_path(id, encoding.name())
+}
@@ -1,41 +1,267 @@
 use tera::escape_html;
 
-use csv::{Error, ErrorKind, Reader};
+use csv::{Error, ErrorKind, ReaderBuilder, StringRecord, Terminator, WriterBuilder};
 
 use crate::{
   errors::*,
   i18n::L10n,
+  models::id::FileId,
 };
 
-pub fn csv_to_table(content: &str, l10n: &L10n) -> std::result::Result<String, Result<String>> {
-  let mut table = String::new();
+use rocket::{
+  Outcome,
+  request::{self, Request, FromRequest},
+};
+
+use std::{cmp::Ordering, convert::Infallible};
+
+/// Rows shown on each page of a table.
+pub const ROWS_PER_PAGE: usize = 250;
+/// Delimiters to try when a file doesn't say which one it uses.
+const DELIMITERS: &[u8] = b",\t;|";
+/// Lines to look at when guessing a file's delimiter.
+const SNIFF_LINES: usize = 20;
+
+/// The delimiter of the CSV file called `name`.
+pub fn delimiter_for(name: &str, content: &str) -> u8 {
+  if name.to_lowercase().ends_with(".tsv") {
+    return b'\t';
+  }
+
+  sniff_delimiter(content)
+}
+
+/// Guesses a file's delimiter by picking the one that splits its first lines into the same number
+/// of fields, preferring more fields. Files no delimiter splits are taken to use commas.
+pub fn sniff_delimiter(content: &str) -> u8 {
+  let mut best = (b',', 1);
+
+  for &delimiter in DELIMITERS {
+    let mut reader = ReaderBuilder::new()
+      .delimiter(delimiter)
+      .has_headers(false)
+      .flexible(true)
+      .from_reader(content.as_bytes());
+
+    let mut fields = None;
+    for record in reader.records().take(SNIFF_LINES) {
+      let len = match record {
+        Ok(r) => r.len(),
+        Err(_) => {
+          fields = None;
+          break;
+        },
+      };
+      match fields {
+        Some(f) if f != len => {
+          fields = None;
+          break;
+        },
+        _ => fields = Some(len),
+      }
+    }
+
+    if let Some(f) = fields {
+      if f > best.1 {
+        best = (delimiter, f);
+      }
+    }
+  }
+
+  best.0
+}
+
+/// Rewrites a file with any delimiter as comma-separated CSV with consistent quoting.
+pub fn normalize(content: &str, delimiter: u8) -> std::result::Result<Vec<u8>, Error> {
+  let mut reader = ReaderBuilder::new()
+    .delimiter(delimiter)
+    .has_headers(false)
+    .flexible(true)
+    .from_reader(content.as_bytes());
+  let mut writer = WriterBuilder::new()
+    .terminator(Terminator::CRLF)
+    .flexible(true)
+    .from_writer(Vec::new());
+
+  for record in reader.records() {
+    writer.write_record(&record?)?;
+  }
+
+  writer.into_inner().map_err(|e| e.into_error().into())
+}
 
-  let mut reader = Reader::from_reader(content.as_bytes());
+/// Which page of a table to show and how to sort it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TableView {
+  pub page: usize,
+  pub sort: Option<usize>,
+  pub descending: bool,
+}
 
-  let headers = reader.headers().map_err(|e| pretty_error(e, l10n))?;
+impl Default for TableView {
+  fn default() -> Self {
+    TableView {
+      page: 1,
+      sort: None,
+      descending: false,
+    }
+  }
+}
+
+impl TableView {
+  /// The query string that shows `file` like this, scrolled to the file.
+  pub fn query(&self, file: FileId) -> String {
+    let mut query = format!("?table={}&page={}", file, self.page);
+    if let Some(sort) = self.sort {
+      query.push_str(&format!("&sort={}&desc={}", sort, self.descending));
+    }
+    query.push_str(&format!("#{}-title", file));
+    query
+  }
+}
+
+/// The table view asked for by the paste page's query string, which only applies to one file.
+pub struct TableQuery {
+  file: Option<FileId>,
+  view: TableView,
+}
+
+impl TableQuery {
+  pub fn view(&self, file: FileId) -> TableView {
+    match self.file {
+      Some(f) if f == file => self.view,
+      _ => TableView::default(),
+    }
+  }
+}
+
+impl FromRequest<'a, 'r> for TableQuery {
+  type Error = Infallible;
+
+  fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+    let file = request.get_query_value::<FileId>("table").and_then(|x| x.ok());
+    let page = request.get_query_value::<usize>("page")
+      .and_then(|x| x.ok())
+      .filter(|&p| p > 0)
+      .unwrap_or(1);
+    let sort = request.get_query_value::<usize>("sort").and_then(|x| x.ok());
+    let descending = request.get_query_value::<bool>("desc")
+      .and_then(|x| x.ok())
+      .unwrap_or(false);
+
+    Outcome::Success(TableQuery {
+      file,
+      view: TableView { page, sort, descending },
+    })
+  }
+}
+
+pub struct Table {
+  pub html: String,
+  pub page: usize,
+  pub pages: usize,
+  pub rows: usize,
+}
+
+pub fn csv_to_table(content: &str, delimiter: u8, file: FileId, view: TableView, l10n: &L10n) -> std::result::Result<Table, Result<String>> {
+  let mut reader = ReaderBuilder::new()
+    .delimiter(delimiter)
+    .from_reader(content.as_bytes());
+
+  let headers = reader.headers().map_err(|e| pretty_error(e, l10n))?.clone();
+  let mut records: Vec<StringRecord> = reader
+    .records()
+    .collect::<std::result::Result<_, _>>()
+    .map_err(|e| pretty_error(e, l10n))?;
+
+  // columns of numbers are right-aligned and sorted by value
+  let numeric: Vec<bool> = (0..headers.len())
+    .map(|i| {
+      let mut any = false;
+      let all = records.iter().all(|r| match r.get(i).map(str::trim) {
+        None | Some("") => true,
+        Some(field) => {
+          any = true;
+          parse_number(field).is_some()
+        },
+      });
+      any && all
+    })
+    .collect();
+
+  if let Some(column) = view.sort.filter(|&c| c < headers.len()) {
+    records.sort_by(|a, b| {
+      let (a, b) = (a.get(column).unwrap_or_default(), b.get(column).unwrap_or_default());
+      let ordering = if numeric[column] {
+        parse_number(a).partial_cmp(&parse_number(b)).unwrap_or(Ordering::Equal)
+      } else {
+        a.cmp(b)
+      };
+      if view.descending { ordering.reverse() } else { ordering }
+    });
+  }
+
+  let rows = records.len();
+  let pages = ((rows + ROWS_PER_PAGE - 1) / ROWS_PER_PAGE).max(1);
+  let page = view.page.min(pages);
+
+  let align = |column: usize| if numeric.get(column).copied().unwrap_or(false) {
+    " class=\"has-text-right\""
+  } else {
+    ""
+  };
+
+  let mut table = String::new();
 
   table.push_str("<table>\n");
   table.push_str("  <thead>\n");
   table.push_str("    <tr>\n");
-  for header in headers.iter() {
+  for (i, header) in headers.iter().enumerate() {
     let safe_header = escape_html(&header);
-    table.push_str(&format!("      <th>{}</th>\n", safe_header));
+    // sorting starts over from the first page, and a sorted column's link reverses it
+    let sorted = view.sort == Some(i);
+    let link = TableView {
+      page: 1,
+      sort: Some(i),
+      descending: sorted && !view.descending,
+    };
+    let arrow = match (sorted, view.descending) {
+      (true, false) => " \u{25b2}",
+      (true, true) => " \u{25bc}",
+      _ => "",
+    };
+    table.push_str(&format!(
+      "      <th{}><a href=\"{}\">{}</a>{}</th>\n",
+      align(i),
+      escape_html(&link.query(file)),
+      safe_header,
+      arrow,
+    ));
   }
   table.push_str("    </tr>\n");
   table.push_str("  </thead>\n");
   table.push_str("  <tbody>\n");
-  for rec in reader.records() {
+  for rec in records.iter().skip((page - 1) * ROWS_PER_PAGE).take(ROWS_PER_PAGE) {
     table.push_str("    <tr>\n");
-    for field in rec.map_err(|e| pretty_error(e, l10n))?.iter() {
+    for (i, field) in rec.iter().enumerate() {
       let safe_field = escape_html(&field);
-      table.push_str(&format!("      <td>{}</td>\n", safe_field));
+      table.push_str(&format!("      <td{}>{}</td>\n", align(i), safe_field));
     }
     table.push_str("    </tr>\n");
   }
   table.push_str("  </tbody>\n");
   table.push_str("</table>\n");
 
-  Ok(table)
+  Ok(Table {
+    html: table,
+    page,
+    pages,
+    rows,
+  })
+}
+
+fn parse_number(field: &str) -> Option<f64> {
+  field.trim().parse::<f64>().ok().filter(|x| x.is_finite())
 }
 
 fn pretty_error(e: Error, l10n: &L10n) -> Result<String> {
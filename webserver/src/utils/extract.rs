@@ -0,0 +1,349 @@
+//! Reading the files out of uploaded zip and tar archives.
+
+use crate::models::paste::Content;
+
+use libflate::gzip;
+
+use mime::Mime;
+
+use std::{
+  collections::HashSet,
+  fmt::{self, Display, Formatter},
+  io::{Cursor, Read},
+};
+
+/// The most rejected entries listed in an error, so a hostile archive can't produce a huge message.
+const MAX_LISTED_REJECTIONS: usize = 10;
+
+/// A file read out of an archive.
+#[derive(Debug)]
+pub struct ExtractedFile {
+  /// The file's path in the archive, always relative and using `/` as a separator.
+  pub name: String,
+  pub content: Content,
+}
+
+/// An entry in an archive that couldn't be turned into a file.
+#[derive(Debug)]
+pub struct Rejection {
+  pub path: String,
+  pub reason: String,
+}
+
+#[derive(Debug)]
+pub enum ExtractError {
+  /// The upload couldn't be read as a supported archive.
+  Invalid(String),
+  /// The archive contained more files than a paste may have.
+  TooManyFiles(usize),
+  /// Some entries in the archive couldn't be used.
+  Rejected(Vec<Rejection>),
+}
+
+impl Display for ExtractError {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match *self {
+      ExtractError::Invalid(ref e) => write!(f, "could not read archive: {}", e),
+      ExtractError::TooManyFiles(max) => write!(f, "archives can contain at most {} files", max),
+      ExtractError::Rejected(ref rejections) => {
+        write!(f, "some archive entries were rejected: ")?;
+        for (i, rejection) in rejections.iter().take(MAX_LISTED_REJECTIONS).enumerate() {
+          if i != 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{} ({})", rejection.path, rejection.reason)?;
+        }
+        if rejections.len() > MAX_LISTED_REJECTIONS {
+          write!(f, " and {} more", rejections.len() - MAX_LISTED_REJECTIONS)?;
+        }
+        Ok(())
+      },
+    }
+  }
+}
+
+/// Whether an upload with this content type should be extracted rather than stored as one file.
+pub fn is_archive_type(mime: &Mime) -> bool {
+  if mime.type_() != mime::APPLICATION {
+    return false;
+  }
+
+  match mime.subtype().as_str() {
+    "zip" | "x-zip" | "x-zip-compressed" | "x-tar" | "x-gtar" | "gzip" | "x-gzip" | "x-compressed-tar" => true,
+    _ => false,
+  }
+}
+
+/// Reads every file out of a zip, tar or gzipped tar archive, detected by its contents.
+///
+/// Directories and empty files are skipped, since pastes can't contain them. If every file is
+/// inside the same top-level directory, that directory is removed from the names.
+pub fn extract(data: &[u8], max_files: usize, max_file_size: u64) -> Result<Vec<ExtractedFile>, ExtractError> {
+  let mut extractor = Extractor {
+    max_files,
+    max_file_size,
+    files: Vec::new(),
+    names: HashSet::new(),
+    rejections: Vec::new(),
+  };
+
+  if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+    extractor.zip(data)?;
+  } else if data.starts_with(&[0x1f, 0x8b]) {
+    let decoder = gzip::Decoder::new(data).map_err(|e| ExtractError::Invalid(e.to_string()))?;
+    extractor.tar(decoder)?;
+  } else if data.get(257..262) == Some(&b"ustar"[..]) {
+    extractor.tar(data)?;
+  } else {
+    return Err(ExtractError::Invalid("only zip, tar and tar.gz archives are supported".into()));
+  }
+
+  extractor.finish()
+}
+
+struct Extractor {
+  max_files: usize,
+  max_file_size: u64,
+  files: Vec<ExtractedFile>,
+  names: HashSet<String>,
+  rejections: Vec<Rejection>,
+}
+
+impl Extractor {
+  fn zip(&mut self, data: &[u8]) -> Result<(), ExtractError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(|e| ExtractError::Invalid(e.to_string()))?;
+
+    for i in 0..archive.len() {
+      let file = archive.by_index(i).map_err(|e| ExtractError::Invalid(e.to_string()))?;
+      let raw_path = file.name().to_string();
+
+      if file.is_dir() {
+        continue;
+      }
+
+      // only zips made on unix record file types, and a symlink's content is its target
+      if file.unix_mode().map(|x| x & 0o170_000 == 0o120_000).unwrap_or(false) {
+        self.reject(raw_path, "not a regular file");
+        continue;
+      }
+
+      let size = file.size();
+      self.add(raw_path, size, file)?;
+    }
+
+    Ok(())
+  }
+
+  fn tar<R: Read>(&mut self, reader: R) -> Result<(), ExtractError> {
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive.entries().map_err(|e| ExtractError::Invalid(e.to_string()))?;
+
+    for entry in entries {
+      let entry = entry.map_err(|e| ExtractError::Invalid(e.to_string()))?;
+      let raw_path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+
+      match entry.header().entry_type() {
+        tar::EntryType::Directory => continue,
+        tar::EntryType::Regular | tar::EntryType::Continuous => {},
+        _ => {
+          self.reject(raw_path, "not a regular file");
+          continue;
+        },
+      }
+
+      let size = entry.header().size().map_err(|e| ExtractError::Invalid(e.to_string()))?;
+      self.add(raw_path, size, entry)?;
+    }
+
+    Ok(())
+  }
+
+  fn add<R: Read>(&mut self, raw_path: String, size: u64, reader: R) -> Result<(), ExtractError> {
+    let name = match normalise_path(&raw_path) {
+      Ok(Some(n)) => n,
+      Ok(None) => return Ok(()),
+      Err(reason) => {
+        self.reject(raw_path, reason);
+        return Ok(());
+      },
+    };
+
+    if size > self.max_file_size {
+      self.reject(raw_path, format!("larger than {} bytes", self.max_file_size));
+      return Ok(());
+    }
+
+    // the recorded size can't be trusted, so never read past the limit
+    let mut data = Vec::new();
+    if let Err(e) = reader.take(self.max_file_size + 1).read_to_end(&mut data) {
+      return Err(ExtractError::Invalid(format!("{}: {}", raw_path, e)));
+    }
+    if data.len() as u64 > self.max_file_size {
+      self.reject(raw_path, format!("larger than {} bytes", self.max_file_size));
+      return Ok(());
+    }
+
+    if data.is_empty() {
+      return Ok(());
+    }
+
+    if !self.names.insert(name.clone()) {
+      self.reject(raw_path, "duplicate path");
+      return Ok(());
+    }
+
+    if self.files.len() == self.max_files {
+      return Err(ExtractError::TooManyFiles(self.max_files));
+    }
+
+    let content = match String::from_utf8(data) {
+      Ok(s) => Content::Text(s),
+      Err(e) => Content::Base64(e.into_bytes()),
+    };
+
+    self.files.push(ExtractedFile { name, content });
+
+    Ok(())
+  }
+
+  fn reject<R: Into<String>>(&mut self, path: String, reason: R) {
+    self.rejections.push(Rejection {
+      path,
+      reason: reason.into(),
+    });
+  }
+
+  fn finish(mut self) -> Result<Vec<ExtractedFile>, ExtractError> {
+    if !self.rejections.is_empty() {
+      return Err(ExtractError::Rejected(self.rejections));
+    }
+
+    if self.files.is_empty() {
+      return Err(ExtractError::Invalid("archive contains no files".into()));
+    }
+
+    // archives of a directory usually put everything inside it
+    let prefix = self.files[0].name.split('/').next().unwrap_or_default().to_string() + "/";
+    if self.files.iter().all(|x| x.name.starts_with(&prefix)) {
+      for file in &mut self.files {
+        file.name.drain(..prefix.len());
+      }
+    }
+
+    Ok(self.files)
+  }
+}
+
+/// Turns a path from an archive into a relative path, refusing anything that could point outside
+/// of the archive.
+///
+/// Returns `None` for entries that should be silently skipped.
+fn normalise_path(raw: &str) -> Result<Option<String>, &'static str> {
+  // zips made on windows sometimes use backslashes
+  let path = raw.replace('\\', "/");
+
+  if path.starts_with('/') || path.get(1..2) == Some(":") {
+    return Err("absolute path");
+  }
+
+  let mut parts = Vec::new();
+  for part in path.split('/') {
+    match part {
+      "" | "." => continue,
+      ".." => return Err("path leaves the archive"),
+      _ if part.chars().any(char::is_control) => return Err("invalid file name"),
+      _ => parts.push(part),
+    }
+  }
+
+  // metadata added by macos's archive utility
+  if parts.first() == Some(&"__MACOSX") || parts.last() == Some(&".DS_Store") {
+    return Ok(None);
+  }
+
+  if parts.is_empty() {
+    return Ok(None);
+  }
+
+  Ok(Some(parts.join("/")))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn build_tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for &(name, data) in files {
+      let mut header = tar::Header::new_gnu();
+      header.set_size(data.len() as u64);
+      header.set_mode(0o644);
+      header.set_cksum();
+      builder.append_data(&mut header, name, data).unwrap();
+    }
+    builder.into_inner().unwrap()
+  }
+
+  #[test]
+  fn relative_paths() {
+    assert_eq!(normalise_path("a.txt"), Ok(Some("a.txt".into())));
+    assert_eq!(normalise_path("dir/a.txt"), Ok(Some("dir/a.txt".into())));
+    assert_eq!(normalise_path("dir\\a.txt"), Ok(Some("dir/a.txt".into())));
+  }
+
+  #[test]
+  fn empty_components() {
+    assert_eq!(normalise_path("a//b"), Ok(Some("a/b".into())));
+    assert_eq!(normalise_path("./a/./b/"), Ok(Some("a/b".into())));
+    assert_eq!(normalise_path(""), Ok(None));
+    assert_eq!(normalise_path("./"), Ok(None));
+  }
+
+  #[test]
+  fn parent_components() {
+    assert_eq!(normalise_path("../x"), Err("path leaves the archive"));
+    assert_eq!(normalise_path("a/../../x"), Err("path leaves the archive"));
+    assert_eq!(normalise_path("a\\..\\x"), Err("path leaves the archive"));
+  }
+
+  #[test]
+  fn absolute_paths() {
+    assert_eq!(normalise_path("/etc/x"), Err("absolute path"));
+    assert_eq!(normalise_path("C:\\x"), Err("absolute path"));
+    assert_eq!(normalise_path("\\x"), Err("absolute path"));
+  }
+
+  #[test]
+  fn skipped_paths() {
+    assert_eq!(normalise_path("__MACOSX/._a.txt"), Ok(None));
+    assert_eq!(normalise_path("dir/.DS_Store"), Ok(None));
+    assert_eq!(normalise_path("a\nb"), Err("invalid file name"));
+  }
+
+  #[test]
+  fn max_files() {
+    let archive = build_tar(&[("a", b"a"), ("b", b"b")]);
+    assert_eq!(extract(&archive, 2, 10).unwrap().len(), 2);
+
+    let archive = build_tar(&[("a", b"a"), ("b", b"b"), ("c", b"c")]);
+    match extract(&archive, 2, 10) {
+      Err(ExtractError::TooManyFiles(2)) => {},
+      res => panic!("expected too many files, got {:?}", res),
+    }
+  }
+
+  #[test]
+  fn max_file_size() {
+    let archive = build_tar(&[("a", b"0123456789")]);
+    assert_eq!(extract(&archive, 10, 10).unwrap().len(), 1);
+
+    let archive = build_tar(&[("a", b"0123456789"), ("b", b"0123456789x")]);
+    match extract(&archive, 10, 10) {
+      Err(ExtractError::Rejected(rejections)) => {
+        assert_eq!(rejections.len(), 1);
+        assert_eq!(rejections[0].path, "b");
+      },
+      res => panic!("expected a rejection, got {:?}", res),
+    }
+  }
+}
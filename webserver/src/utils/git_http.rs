@@ -0,0 +1,409 @@
+//! The server side of git's smart HTTP protocol.
+//!
+//! Only version 0 of the wire protocol is spoken, without side-band or multi-ack support, which
+//! every git client still understands.
+//!
+//! https://git-scm.com/docs/http-protocol
+//! https://git-scm.com/docs/pack-protocol
+
+use crate::errors::*;
+
+use anyhow::{anyhow, bail};
+
+use git2::{Buf, Oid, Repository};
+
+use tempfile::TempDir;
+
+use std::{
+  fs,
+  io::{self, Read, Write},
+  path::Path,
+  str::FromStr,
+};
+
+const AGENT: &str = concat!("agent=paste/", env!("CARGO_PKG_VERSION"));
+
+/// Appends a pkt-line containing `data` to `out`.
+pub fn pkt_line(out: &mut Vec<u8>, data: &[u8]) {
+  out.extend(format!("{:04x}", data.len() + 4).as_bytes());
+  out.extend(data);
+}
+
+/// Appends a flush-pkt to `out`.
+pub fn flush(out: &mut Vec<u8>) {
+  out.extend(b"0000");
+}
+
+/// An iterator over the pkt-lines in a request body, yielding `None` for flush-pkts.
+pub struct PktLines<'a> {
+  data: &'a [u8],
+}
+
+impl PktLines<'a> {
+  pub fn new(data: &'a [u8]) -> Self {
+    PktLines { data }
+  }
+}
+
+impl Iterator for PktLines<'a> {
+  type Item = Result<Option<&'a [u8]>>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.data.is_empty() {
+      return None;
+    }
+
+    let len = match self.data.get(..4).and_then(|x| std::str::from_utf8(x).ok()).and_then(|x| usize::from_str_radix(x, 16).ok()) {
+      Some(l) => l,
+      None => {
+        self.data = &[];
+        return Some(Err(anyhow!("invalid pkt-line length")));
+      },
+    };
+
+    if len == 0 {
+      self.data = &self.data[4..];
+      return Some(Ok(None));
+    }
+
+    if len < 4 || len > self.data.len() {
+      self.data = &[];
+      return Some(Err(anyhow!("invalid pkt-line length")));
+    }
+
+    let line = &self.data[4..len];
+    self.data = &self.data[len..];
+
+    // the trailing newline is optional, so never rely on it
+    Some(Ok(Some(line.strip_suffix(b"\n").unwrap_or(line))))
+  }
+}
+
+/// Reads one pkt-line from `stream`, returning `None` for a flush-pkt.
+fn read_pkt_line<R: Read>(stream: &mut R) -> Result<Option<Vec<u8>>> {
+  let mut len = [0; 4];
+  stream.read_exact(&mut len)?;
+
+  let len = match std::str::from_utf8(&len).ok().and_then(|x| usize::from_str_radix(x, 16).ok()) {
+    Some(l) => l,
+    None => bail!("invalid pkt-line length"),
+  };
+
+  if len == 0 {
+    return Ok(None);
+  }
+
+  if len < 4 {
+    bail!("invalid pkt-line length");
+  }
+
+  let mut line = vec![0; len - 4];
+  stream.read_exact(&mut line)?;
+
+  // the trailing newline is optional, so never rely on it
+  if line.last() == Some(&b'\n') {
+    line.pop();
+  }
+
+  Ok(Some(line))
+}
+
+/// Builds the ref advertisement returned from `info/refs` for `service`.
+pub fn advertise_refs(repo: &Repository, service: &str, capabilities: &[&str]) -> Result<Vec<u8>> {
+  let mut out = Vec::new();
+  pkt_line(&mut out, format!("# service={}\n", service).as_bytes());
+  flush(&mut out);
+
+  let mut refs = Vec::new();
+
+  if let Ok(head) = repo.head() {
+    if let Some(oid) = head.target() {
+      refs.push((oid, "HEAD".to_string()));
+    }
+  }
+
+  let mut names = Vec::new();
+  for reference in repo.references()? {
+    let reference = reference?;
+    if let (Some(name), Some(oid)) = (reference.name(), reference.target()) {
+      names.push((oid, name.to_string()));
+    }
+  }
+  names.sort_by(|a, b| a.1.cmp(&b.1));
+  refs.extend(names);
+
+  let mut capabilities = capabilities.to_vec();
+  let symref = repo.find_reference("HEAD")
+    .ok()
+    .and_then(|x| x.symbolic_target().map(|t| format!("symref=HEAD:{}", t)));
+  if let Some(ref symref) = symref {
+    capabilities.push(symref);
+  }
+  capabilities.push(AGENT);
+  let capabilities = capabilities.join(" ");
+
+  if refs.is_empty() {
+    // an empty repository still has to advertise its capabilities
+    refs.push((Oid::zero(), "capabilities^{}".to_string()));
+  }
+
+  for (i, (oid, name)) in refs.iter().enumerate() {
+    let line = if i == 0 {
+      format!("{} {}\0{}\n", oid, name, capabilities)
+    } else {
+      format!("{} {}\n", oid, name)
+    };
+    pkt_line(&mut out, line.as_bytes());
+  }
+  flush(&mut out);
+
+  Ok(out)
+}
+
+/// Responds to a `git-upload-pack` request, negotiating common commits and sending a packfile of
+/// everything the client is missing.
+pub fn upload_pack(repo: &Repository, request: &[u8]) -> Result<Vec<u8>> {
+  let mut out = Vec::new();
+
+  let mut wants = Vec::new();
+  let mut common = Vec::new();
+  let mut flushes = 0;
+  let mut done = false;
+
+  for line in PktLines::new(request) {
+    let line = match line? {
+      Some(l) => l,
+      None => {
+        flushes += 1;
+        // the first flush ends the wants, the second ends a round of haves without "done"
+        if flushes > 1 {
+          break;
+        }
+        continue;
+      },
+    };
+
+    if line == b"done" {
+      done = true;
+      break;
+    }
+
+    if let Some(want) = line.strip_prefix(b"want ") {
+      let oid = parse_oid(want)?;
+      if repo.find_commit(oid).is_err() {
+        pkt_line(&mut out, format!("ERR upload-pack: not our ref {}", oid).as_bytes());
+        return Ok(out);
+      }
+      wants.push(oid);
+    } else if let Some(have) = line.strip_prefix(b"have ") {
+      let oid = parse_oid(have)?;
+      if repo.find_commit(oid).is_ok() {
+        // without multi-ack, only the first common commit is acknowledged
+        if common.is_empty() {
+          pkt_line(&mut out, format!("ACK {}\n", oid).as_bytes());
+        }
+        common.push(oid);
+      }
+    } else if line.starts_with(b"shallow ") || line.starts_with(b"deepen") {
+      pkt_line(&mut out, b"ERR upload-pack: shallow clones are not supported");
+      return Ok(out);
+    }
+  }
+
+  if wants.is_empty() {
+    return Ok(out);
+  }
+
+  if common.is_empty() {
+    pkt_line(&mut out, b"NAK\n");
+  }
+
+  // the client will send another round of haves
+  if !done {
+    return Ok(out);
+  }
+
+  out.extend(&*build_pack(repo, &wants, &common)?);
+
+  Ok(out)
+}
+
+fn build_pack(repo: &Repository, wants: &[Oid], common: &[Oid]) -> Result<Buf> {
+  let mut walk = repo.revwalk()?;
+  for want in wants {
+    walk.push(*want)?;
+  }
+  for have in common {
+    walk.hide(*have)?;
+  }
+
+  let mut builder = repo.packbuilder()?;
+  builder.insert_walk(&mut walk)?;
+
+  let mut buf = Buf::new();
+  builder.write_buf(&mut buf)?;
+
+  Ok(buf)
+}
+
+/// A ref update requested by `git push`.
+#[derive(Debug)]
+pub struct RefUpdate {
+  pub old: Oid,
+  pub new: Oid,
+  pub name: String,
+}
+
+/// A parsed `git-receive-pack` request.
+pub struct ReceiveRequest {
+  pub updates: Vec<RefUpdate>,
+  pub capabilities: Vec<String>,
+}
+
+impl ReceiveRequest {
+  /// Whether the client wants to be told the result of each update.
+  pub fn wants_report(&self) -> bool {
+    self.capabilities.iter().any(|x| x == "report-status")
+  }
+}
+
+/// Reads the ref updates at the start of a `git-receive-pack` request, leaving the packfile that
+/// follows them in `stream`.
+pub fn read_receive_pack<R: Read>(stream: &mut R) -> Result<ReceiveRequest> {
+  let mut updates = Vec::new();
+  let mut capabilities = Vec::new();
+
+  while let Some(line) = read_pkt_line(stream)? {
+    // capabilities follow a nul byte on the first line
+    let (command, caps) = match line.iter().position(|&b| b == 0) {
+      Some(i) => (&line[..i], Some(&line[i + 1..])),
+      None => (&line[..], None),
+    };
+    if let Some(caps) = caps {
+      capabilities = String::from_utf8_lossy(caps)
+        .split(' ')
+        .filter(|x| !x.is_empty())
+        .map(ToString::to_string)
+        .collect();
+    }
+
+    let command = std::str::from_utf8(command)?;
+    let mut parts = command.splitn(3, ' ');
+    let (old, new, name) = match (parts.next(), parts.next(), parts.next()) {
+      (Some(o), Some(n), Some(r)) => (o, n, r),
+      _ => bail!("invalid ref update: {}", command),
+    };
+
+    updates.push(RefUpdate {
+      old: Oid::from_str(old)?,
+      new: Oid::from_str(new)?,
+      name: name.to_string(),
+    });
+  }
+
+  Ok(ReceiveRequest {
+    updates,
+    capabilities,
+  })
+}
+
+/// A temporary object database that a pushed packfile is written into, so nothing from a refused
+/// push is left in the repository.
+///
+/// The repository can read the quarantined objects while the push is checked, and they are only
+/// moved into it with `migrate`. Anything left over is removed when the quarantine is dropped.
+pub struct Quarantine {
+  dir: TempDir,
+  repo: Repository,
+}
+
+impl Quarantine {
+  pub fn new(repo: &Repository) -> Result<Self> {
+    let objects = repo.path().join("objects");
+    let dir = tempfile::Builder::new()
+      .prefix("incoming-")
+      .tempdir_in(&objects)?;
+    let quarantine = Repository::init_bare(dir.path())?;
+
+    // thin packs are completed with objects the repository already has
+    quarantine.odb()?.add_disk_alternate(path_str(&objects)?)?;
+    // and the repository needs the pushed objects to check the push
+    repo.odb()?.add_disk_alternate(path_str(&dir.path().join("objects"))?)?;
+
+    Ok(Quarantine {
+      dir,
+      repo: quarantine,
+    })
+  }
+
+  /// Streams a packfile sent by the client into the quarantine.
+  pub fn write_pack<R: Read>(&self, pack: &mut R) -> Result<()> {
+    let odb = self.repo.odb()?;
+    let mut writer = odb.packwriter()?;
+
+    // pushes that only delete refs have no packfile
+    if io::copy(pack, &mut writer)? == 0 {
+      return Ok(());
+    }
+    writer.commit()?;
+
+    Ok(())
+  }
+
+  /// Moves the quarantined objects into the repository.
+  pub fn migrate(self, repo: &Repository) -> Result<()> {
+    let source = self.dir.path().join("objects").join("pack");
+    let target = repo.path().join("objects").join("pack");
+
+    let mut paths = fs::read_dir(&source)?
+      .map(|entry| entry.map(|x| x.path()))
+      .collect::<io::Result<Vec<_>>>()?;
+    // a pack is used as soon as its index exists, so the pack has to be moved first
+    paths.sort_by_key(|path| path.extension().and_then(|x| x.to_str()) != Some("pack"));
+
+    for path in paths {
+      if let Some(name) = path.file_name() {
+        fs::rename(&path, target.join(name))?;
+      }
+    }
+
+    repo.odb()?.refresh()?;
+
+    Ok(())
+  }
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+  path.to_str().ok_or_else(|| anyhow!("non-utf-8 path: {}", path.display()))
+}
+
+/// Builds the `report-status` response to a push.
+pub fn report_status(unpack: std::result::Result<(), String>, results: &[(String, std::result::Result<(), String>)]) -> Vec<u8> {
+  let mut out = Vec::new();
+
+  match unpack {
+    Ok(()) => pkt_line(&mut out, b"unpack ok\n"),
+    Err(e) => pkt_line(&mut out, format!("unpack {}\n", e).as_bytes()),
+  }
+
+  for (name, result) in results {
+    match *result {
+      Ok(()) => pkt_line(&mut out, format!("ok {}\n", name).as_bytes()),
+      Err(ref e) => pkt_line(&mut out, format!("ng {} {}\n", name, e).as_bytes()),
+    }
+  }
+
+  flush(&mut out);
+
+  out
+}
+
+/// Parses the object id at the start of a line, ignoring anything after it (like capabilities).
+pub fn parse_oid(line: &[u8]) -> Result<Oid> {
+  let hex = match line.get(..40).and_then(|x| std::str::from_utf8(x).ok()) {
+    Some(h) => h,
+    None => bail!("invalid object id"),
+  };
+
+  Ok(Oid::from_str(hex)?)
+}
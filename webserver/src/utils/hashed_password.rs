@@ -1,3 +1,5 @@
+use rand::{Rng, distributions::Alphanumeric};
+
 use rocket::{request::FromFormValue, http::RawStr};
 
 use sodiumoxide::crypto::pwhash;
@@ -17,6 +19,16 @@ impl FromFormValue<'v> for HashedPassword {
 }
 
 impl HashedPassword {
+  /// Hashes an unguessable password, for users that log in through an external provider.
+  pub fn random() -> Self {
+    let password: String = rand::thread_rng()
+      .sample_iter(&Alphanumeric)
+      .take(64)
+      .map(char::from)
+      .collect();
+    HashedPassword::from(password)
+  }
+
   pub fn into_string(mut self) -> String {
     self.0.pop(); // remove the 0x00
     unsafe { String::from_utf8_unchecked(self.0) }
@@ -0,0 +1,89 @@
+//! Detecting image files and making thumbnails of them.
+
+use crate::{
+  config::Config,
+  database::models::{files::File as DbFile, pastes::Paste},
+  errors::*,
+  utils::webp,
+};
+
+use image::{ImageFormat, io::Reader};
+
+use uuid::Uuid;
+
+use std::{
+  fs::{self, File},
+  io::{self, Cursor, Read},
+  path::{Path, PathBuf},
+};
+
+/// Images with more pixels than this aren't thumbnailed, since decoding them takes too much memory.
+const MAX_PIXELS: u64 = 50_000_000;
+/// The largest width or height of a thumbnail.
+const THUMBNAIL_SIZE: u32 = 320;
+const WEBP_QUALITY: f32 = 80.0;
+
+/// The format of `data` if it's an image that can be shown inline.
+pub fn detect(data: &[u8]) -> Option<ImageFormat> {
+  match image::guess_format(data).ok()? {
+    f @ ImageFormat::Png | f @ ImageFormat::Jpeg | f @ ImageFormat::Gif | f @ ImageFormat::WebP => Some(f),
+    _ => None,
+  }
+}
+
+/// The format of the file at `path` if it's an image that can be shown inline, only reading as much
+/// as is needed to tell.
+pub fn detect_file(path: &Path) -> Result<Option<ImageFormat>> {
+  let mut start = Vec::with_capacity(16);
+  File::open(path)?.take(16).read_to_end(&mut start)?;
+
+  Ok(detect(&start))
+}
+
+pub fn content_type(format: ImageFormat) -> &'static str {
+  match format {
+    ImageFormat::Png => "image/png",
+    ImageFormat::Jpeg => "image/jpeg",
+    ImageFormat::Gif => "image/gif",
+    ImageFormat::WebP => "image/webp",
+    _ => "application/octet-stream",
+  }
+}
+
+/// Gets the path to the WebP thumbnail of a file, making it first if necessary.
+///
+/// Returns `None` if the file isn't an image or can't be thumbnailed.
+pub fn thumbnail(config: &Config, paste: &Paste, file: &DbFile) -> Result<Option<PathBuf>> {
+  let path = paste.thumbnail_path(config, file.id());
+
+  // an empty thumbnail records that one couldn't be made
+  match fs::metadata(&path) {
+    Ok(m) if m.len() == 0 => return Ok(None),
+    Ok(_) => return Ok(Some(path)),
+    Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+    Err(e) => return Err(e.into()),
+  }
+
+  let data = fs::read(file.path(config, paste))?;
+  let thumbnail = detect(&data).and_then(|format| make_thumbnail(&data, format));
+
+  let directory = path.parent().expect("thumbnail path has a parent");
+  fs::create_dir_all(directory)?;
+
+  // write somewhere else first so a concurrent request never serves half a thumbnail
+  let temp = directory.join(format!(".tmp-{}", Uuid::new_v4().to_simple()));
+  fs::write(&temp, thumbnail.as_deref().unwrap_or_default())?;
+  fs::rename(&temp, &path)?;
+
+  Ok(thumbnail.map(|_| path))
+}
+
+fn make_thumbnail(data: &[u8], format: ImageFormat) -> Option<Vec<u8>> {
+  let (width, height) = Reader::with_format(Cursor::new(data), format).into_dimensions().ok()?;
+  if u64::from(width) * u64::from(height) > MAX_PIXELS {
+    return None;
+  }
+
+  let image = image::load_from_memory_with_format(data, format).ok()?;
+  webp::convert(&image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE), WEBP_QUALITY)
+}
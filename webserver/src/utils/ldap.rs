@@ -0,0 +1,106 @@
+use crate::{
+  config::Ldap,
+  errors::*,
+  models::user::Admin,
+};
+
+use ldap3::{LdapConn, LdapError, Scope, SearchEntry, ldap_escape};
+
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// https://ldap.com/ldap-result-code-reference/
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// A user as described by the directory.
+#[derive(Debug)]
+pub struct LdapUser {
+  /// The distinguished name of the user's entry, which identifies them in the directory.
+  pub dn: String,
+  pub username: String,
+  pub name: Option<String>,
+  pub email: Option<String>,
+  /// The admin level from the user's groups, if admin groups are configured.
+  pub admin: Option<Admin>,
+}
+
+/// Checks a username and password against the directory.
+///
+/// Returns `None` if the user doesn't exist or the password is wrong.
+pub fn authenticate(config: &Ldap, username: &str, password: &str) -> Result<Option<LdapUser>> {
+  // an empty password would be an unauthenticated bind, which always succeeds
+  if username.is_empty() || password.is_empty() {
+    return Ok(None);
+  }
+
+  let mut conn = LdapConn::new(&config.url)?;
+  conn.with_timeout(TIMEOUT);
+
+  if let Some(ref dn) = config.bind_dn {
+    conn
+      .simple_bind(dn, config.bind_password.as_deref().unwrap_or_default())?
+      .success()?;
+  }
+
+  let filter = config.user_filter.replace("{username}", &ldap_escape(username));
+  let attrs = vec![
+    config.attributes.username.as_str(),
+    config.attributes.name.as_str(),
+    config.attributes.email.as_str(),
+    config.group_attribute.as_str(),
+  ];
+  let (entries, _) = conn
+    .search(&config.base_dn, Scope::Subtree, &filter, attrs)?
+    .success()?;
+
+  // the filter must identify exactly one user
+  let mut entries = entries.into_iter().map(SearchEntry::construct);
+  let entry = match (entries.next(), entries.next()) {
+    (Some(e), None) => e,
+    _ => return Ok(None),
+  };
+
+  match conn.simple_bind(&entry.dn, password)?.success() {
+    Ok(_) => {},
+    Err(LdapError::LdapResult { result }) if result.rc == INVALID_CREDENTIALS => return Ok(None),
+    Err(e) => return Err(e.into()),
+  }
+
+  conn.unbind()?;
+
+  let first = |attr: &str| entry.attrs
+    .get(attr)
+    .and_then(|x| x.first())
+    .cloned();
+
+  let username = match first(&config.attributes.username) {
+    Some(u) => u,
+    None => anyhow::bail!("ldap user {} has no {} attribute", entry.dn, config.attributes.username),
+  };
+
+  let admin = if config.admin_groups.is_empty() && config.superadmin_groups.is_empty() {
+    None
+  } else {
+    let groups = entry.attrs.get(&config.group_attribute).map(Vec::as_slice).unwrap_or_default();
+    let in_any = |wanted: &[String]| groups
+      .iter()
+      .any(|group| wanted.iter().any(|x| x.eq_ignore_ascii_case(group)));
+
+    Some(if in_any(&config.superadmin_groups) {
+      Admin::Super
+    } else if in_any(&config.admin_groups) {
+      Admin::Normal
+    } else {
+      Admin::None
+    })
+  };
+
+  Ok(Some(LdapUser {
+    dn: entry.dn.clone(),
+    username,
+    name: first(&config.attributes.name),
+    email: first(&config.attributes.email),
+    admin,
+  }))
+}
@@ -0,0 +1,279 @@
+//! Rendering markdown with anchors on its headings, a table of contents and math.
+//!
+//! Like the other renderers, the HTML made here has to be cleaned by ammonia and passed through
+//! [`post_processing::process`](super::post_processing::process) before it's shown.
+
+use super::math;
+
+use comrak::{
+  arena_tree::Node,
+  format_html,
+  nodes::{Ast, AstNode, NodeValue},
+  parse_document,
+  Arena,
+  ComrakOptions,
+};
+
+use hashbrown::HashMap;
+
+use tera::escape_html;
+
+use std::cell::RefCell;
+
+/// Documents with at least this many headings get a table of contents.
+pub const TOC_MIN_HEADINGS: usize = 4;
+
+pub struct Heading {
+  pub level: u32,
+  pub text: String,
+  pub anchor: String,
+}
+
+/// Gives headings unique anchors, numbering repeated ones like GitHub does.
+#[derive(Default)]
+pub struct Anchors {
+  seen: HashMap<String, usize>,
+}
+
+impl Anchors {
+  pub fn anchor(&mut self, text: &str) -> String {
+    let base: String = text
+      .trim()
+      .to_lowercase()
+      .chars()
+      .filter_map(|c| match c {
+        ' ' => Some('-'),
+        c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+        _ => None,
+      })
+      .collect();
+    let base = if base.is_empty() { "section".to_string() } else { base };
+
+    let count = self.seen.entry(base.clone()).or_insert(0);
+    let anchor = match *count {
+      0 => base,
+      n => format!("{}-{}", base, n),
+    };
+    *count += 1;
+
+    anchor
+  }
+}
+
+/// Renders `content`, with a table of contents titled `toc_title` if it has enough headings.
+pub fn to_html(content: &str, options: &ComrakOptions, toc_title: &str) -> String {
+  let (html, headings) = render(content, options, &mut Anchors::default());
+
+  if headings.len() < TOC_MIN_HEADINGS {
+    return html;
+  }
+
+  format!("{}{}", toc(&headings, toc_title), html)
+}
+
+/// Renders `content`, returning its HTML and headings.
+///
+/// Documents rendered in pieces, like notebooks, share `anchors` between them so their headings'
+/// anchors stay unique.
+pub fn render(content: &str, options: &ComrakOptions, anchors: &mut Anchors) -> (String, Vec<Heading>) {
+  let arena = Arena::new();
+  let root = parse_document(&arena, content, options);
+
+  let mut headings = Vec::new();
+  let nodes: Vec<&AstNode> = root.descendants().collect();
+  for node in nodes {
+    let level = match node.data.borrow().value {
+      NodeValue::Heading(ref h) => Some(h.level),
+      _ => None,
+    };
+    if let Some(level) = level {
+      let text = text_of(node);
+      let anchor = anchors.anchor(&text);
+      node.prepend(new_node(&arena, NodeValue::HtmlInline(format!(
+        "<a class=\"anchor\" href=\"#{0}\" id=\"{0}\"></a>",
+        escape_html(&anchor),
+      ).into_bytes())));
+      headings.push(Heading { level, text, anchor });
+    }
+
+    replace_math(&arena, node);
+  }
+
+  let mut html = Vec::new();
+  format_html(root, options, &mut html).expect("writing to a vec can't fail");
+
+  (String::from_utf8_lossy(&html).into_owned(), headings)
+}
+
+/// A nested list of links to `headings`.
+pub fn toc(headings: &[Heading], title: &str) -> String {
+  let mut html = format!("<details class=\"markdown-toc\" open><summary>{}</summary>\n", escape_html(title));
+
+  let min = headings.iter().map(|h| h.level).min().unwrap_or(1);
+  let mut depth = 0;
+  for heading in headings {
+    let level = heading.level - min + 1;
+    if level > depth {
+      while depth < level {
+        html.push_str("<ul>\n<li>");
+        depth += 1;
+      }
+    } else {
+      html.push_str("</li>\n");
+      while depth > level {
+        html.push_str("</ul>\n</li>\n");
+        depth -= 1;
+      }
+      html.push_str("<li>");
+    }
+    html.push_str(&format!(
+      "<a href=\"#{}\">{}</a>",
+      escape_html(&heading.anchor),
+      escape_html(&heading.text),
+    ));
+  }
+  while depth > 0 {
+    html.push_str("</li>\n</ul>\n");
+    depth -= 1;
+  }
+
+  html.push_str("</details>\n");
+  html
+}
+
+fn new_node<'a>(arena: &'a Arena<AstNode<'a>>, value: NodeValue) -> &'a AstNode<'a> {
+  arena.alloc(Node::new(RefCell::new(Ast::new(value))))
+}
+
+fn text_of<'a>(node: &'a AstNode<'a>) -> String {
+  node
+    .descendants()
+    .filter_map(|n| match n.data.borrow().value {
+      NodeValue::Text(ref t) => Some(String::from_utf8_lossy(t).into_owned()),
+      _ => None,
+    })
+    .collect()
+}
+
+/// Replaces `$...$` and `$$...$$` in `node`'s text with MathML.
+///
+/// Formulas can be split across text nodes and lines, so runs of them are joined first.
+fn replace_math<'a>(arena: &'a Arena<AstNode<'a>>, node: &'a AstNode<'a>) {
+  let mut run: Vec<&AstNode> = Vec::new();
+
+  let children: Vec<&AstNode> = node.children().collect();
+  for child in children {
+    match child.data.borrow().value {
+      NodeValue::Text(_) | NodeValue::SoftBreak => {
+        run.push(child);
+        continue;
+      },
+      _ => {},
+    }
+    replace_math_in_run(arena, &run);
+    run.clear();
+  }
+  replace_math_in_run(arena, &run);
+}
+
+fn replace_math_in_run<'a>(arena: &'a Arena<AstNode<'a>>, run: &[&'a AstNode<'a>]) {
+  let text: String = run
+    .iter()
+    .map(|n| match n.data.borrow().value {
+      NodeValue::Text(ref t) => String::from_utf8_lossy(t).into_owned(),
+      _ => "\n".to_string(),
+    })
+    .collect();
+
+  let pieces = match split_math(&text) {
+    Some(p) => p,
+    None => return,
+  };
+
+  for piece in pieces {
+    let value = match piece {
+      Piece::Text(t) => NodeValue::Text(t.into_bytes()),
+      Piece::Math(m) => NodeValue::HtmlInline(m.into_bytes()),
+    };
+    run[0].insert_before(new_node(arena, value));
+  }
+  for node in run {
+    node.detach();
+  }
+}
+
+enum Piece {
+  Text(String),
+  Math(String),
+}
+
+/// Splits `text` into plain text and rendered formulas, if it has any formulas.
+///
+/// Inline formulas follow pandoc's rules so prices aren't taken for math: the opening `$` can't be
+/// followed by a space, and the closing one can't come after a space or before a digit.
+fn split_math(text: &str) -> Option<Vec<Piece>> {
+  let mut pieces = Vec::new();
+  let mut plain = String::new();
+  let mut rest = text;
+
+  while let Some(start) = rest.find('$') {
+    let after = &rest[start..];
+
+    let (display, tex, len) = if let Some(inner) = after.strip_prefix("$$") {
+      match inner.find("$$") {
+        Some(end) => (true, &inner[..end], end + 4),
+        None => {
+          plain.push_str(&rest[..start + 2]);
+          rest = inner;
+          continue;
+        },
+      }
+    } else {
+      match inline_end(&after[1..]) {
+        Some(end) => (false, &after[1..end + 1], end + 2),
+        None => {
+          plain.push_str(&rest[..start + 1]);
+          rest = &after[1..];
+          continue;
+        },
+      }
+    };
+
+    plain.push_str(&rest[..start]);
+    if !plain.is_empty() {
+      pieces.push(Piece::Text(std::mem::take(&mut plain)));
+    }
+    pieces.push(Piece::Math(math::to_mathml(tex, display)));
+    rest = &after[len..];
+  }
+
+  if pieces.is_empty() {
+    return None;
+  }
+
+  plain.push_str(rest);
+  if !plain.is_empty() {
+    pieces.push(Piece::Text(plain));
+  }
+
+  Some(pieces)
+}
+
+/// Where the inline formula at the start of `s` ends.
+fn inline_end(s: &str) -> Option<usize> {
+  if s.chars().next().map(char::is_whitespace).unwrap_or(true) {
+    return None;
+  }
+
+  let mut prev = ' ';
+  let mut chars = s.char_indices().peekable();
+  while let Some((i, c)) = chars.next() {
+    let next_is_digit = chars.peek().map(|&(_, n)| n.is_ascii_digit()).unwrap_or(false);
+    if c == '$' && !prev.is_whitespace() && !next_is_digit {
+      return Some(i);
+    }
+    prev = c;
+  }
+
+  None
+}
@@ -0,0 +1,99 @@
+//! Rendering document formats other than markdown and CSV as HTML.
+//!
+//! None of the HTML made here is safe to show as-is. Like rendered markdown, it has to be cleaned by
+//! ammonia and passed through [`post_processing::process`](super::post_processing::process) first.
+
+pub mod asciidoc;
+pub mod notebook;
+pub mod rst;
+
+use tera::escape_html;
+
+/// Splits `content` into lines without trailing whitespace, with tabs expanded so indentation can be
+/// compared.
+fn lines(content: &str) -> Vec<String> {
+  content
+    .lines()
+    .map(|l| l.trim_end().replace('\t', "    "))
+    .collect()
+}
+
+fn indent_of(line: &str) -> usize {
+  line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Removes the indentation common to every non-empty line.
+fn dedent(lines: &[String]) -> Vec<String> {
+  let indent = lines
+    .iter()
+    .filter(|l| !l.is_empty())
+    .map(|l| indent_of(l))
+    .min()
+    .unwrap_or(0);
+
+  lines
+    .iter()
+    .map(|l| l.get(indent..).unwrap_or_default().to_string())
+    .collect()
+}
+
+/// Takes the indented lines starting at `start`, including blank lines between them.
+///
+/// Returns the lines without trailing blank lines and the index of the first line after them.
+fn indented_block(lines: &[String], start: usize) -> (Vec<String>, usize) {
+  let mut end = start;
+  while end < lines.len() && (lines[end].is_empty() || indent_of(&lines[end]) > 0) {
+    end += 1;
+  }
+
+  let mut block = lines[start..end].to_vec();
+  while block.last().map(|l| l.is_empty()).unwrap_or(false) {
+    block.pop();
+  }
+
+  (block, end)
+}
+
+/// Unwraps a list item's HTML if it's only a single paragraph, so short lists stay compact.
+fn tight(html: String) -> String {
+  if html.starts_with("<p>") && html.ends_with("</p>\n") && html.matches("<p>").count() == 1 {
+    return html["<p>".len()..html.len() - "</p>\n".len()].to_string();
+  }
+
+  html
+}
+
+fn push_code_block(html: &mut String, lang: Option<&str>, lines: &[String]) {
+  match lang {
+    Some(lang) => html.push_str(&format!("<pre lang=\"{}\"><code>", escape_html(lang))),
+    None => html.push_str("<pre><code>"),
+  }
+  html.push_str(&escape_html(&lines.join("\n")));
+  html.push_str("</code></pre>\n");
+}
+
+/// The length of the URL at the start of `s`, if it starts with one.
+fn url_len(s: &str) -> Option<usize> {
+  if !s.starts_with("https://") && !s.starts_with("http://") {
+    return None;
+  }
+
+  let end = s
+    .find(|c: char| c.is_whitespace() || c == '<' || c == '>' || c == '"' || c == '[')
+    .unwrap_or(s.len());
+  // punctuation at the end of a url usually belongs to the sentence around it
+  let url = s[..end].trim_end_matches(|c: char| ".,;:!?)'".contains(c));
+
+  Some(url.len())
+}
+
+fn link(url: &str, text_html: &str) -> String {
+  format!("<a href=\"{}\">{}</a>", escape_html(url), text_html)
+}
+
+/// Escapes the first character of `s`, returning its length.
+fn push_escaped_char(html: &mut String, s: &str) -> usize {
+  let len = s.chars().next().map(char::len_utf8).unwrap_or(0);
+  html.push_str(&escape_html(&s[..len]));
+  len
+}
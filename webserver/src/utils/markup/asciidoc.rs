@@ -0,0 +1,548 @@
+//! Rendering the commonly used parts of AsciiDoc: titles, paragraphs, lists, description lists,
+//! delimited blocks, tables, images, admonitions and inline formatting.
+//!
+//! Attribute entries are skipped rather than substituted, and includes are left out, since they'd
+//! refer to files outside the paste.
+
+use super::{dedent, indent_of, link, lines, push_code_block, push_escaped_char, tight, url_len};
+
+use tera::escape_html;
+
+const ADMONITIONS: &[&str] = &["NOTE", "TIP", "IMPORTANT", "WARNING", "CAUTION"];
+
+pub fn to_html(content: &str) -> String {
+  let mut html = String::new();
+  blocks(&lines(content), &mut html);
+  html
+}
+
+fn blocks(lines: &[String], html: &mut String) {
+  // the attribute list from a line like [source,rust], which applies to the next block
+  let mut attributes: Option<String> = None;
+  let mut i = 0;
+
+  while i < lines.len() {
+    let line = &lines[i];
+
+    if line.is_empty() {
+      i += 1;
+      continue;
+    }
+
+    if line.starts_with("////") && is_delimiter(line) {
+      i = closing_delimiter(lines, i).map(|end| end + 1).unwrap_or(lines.len());
+      continue;
+    }
+
+    if line.starts_with("//") || is_attribute_entry(line) || line.starts_with("include::") {
+      i += 1;
+      continue;
+    }
+
+    if line.starts_with('[') && line.ends_with(']') {
+      attributes = Some(line[1..line.len() - 1].to_string());
+      i += 1;
+      continue;
+    }
+
+    // a block title
+    if line.starts_with('.') && line.len() > 1 && !line[1..].starts_with(|c: char| c == '.' || c == ' ') {
+      html.push_str(&format!("<p><strong>{}</strong></p>\n", inline(&line[1..])));
+      i += 1;
+      continue;
+    }
+
+    if let Some((level, title)) = heading(line) {
+      html.push_str(&format!("<h{level}>{}</h{level}>\n", inline(title), level = level));
+      attributes = None;
+      i += 1;
+      continue;
+    }
+
+    if line == "'''" || line == "---" || line == "***" {
+      html.push_str("<hr>\n");
+      i += 1;
+      continue;
+    }
+
+    if let Some(lang) = line.strip_prefix("```") {
+      let end = (i + 1..lines.len()).find(|&j| lines[j] == "```").unwrap_or(lines.len());
+      push_code_block(html, Some(lang.trim()).filter(|x| !x.is_empty()), &lines[i + 1..end]);
+      attributes = None;
+      i = end + 1;
+      continue;
+    }
+
+    if is_delimiter(line) || line == "--" {
+      let end = closing_delimiter(lines, i).unwrap_or(lines.len());
+      delimited_block(line, &lines[i + 1..end], attributes.take(), html);
+      i = end + 1;
+      continue;
+    }
+
+    if line.starts_with("|===") {
+      let end = (i + 1..lines.len()).find(|&j| lines[j].starts_with("|===")).unwrap_or(lines.len());
+      table(&lines[i + 1..end], attributes.take(), html);
+      i = end + 1;
+      continue;
+    }
+
+    if let Some(rest) = line.strip_prefix("image::") {
+      let (target, alt) = macro_parts(rest).unwrap_or((rest, ""));
+      html.push_str(&format!("<p><img src=\"{}\" alt=\"{}\"></p>\n", escape_html(target), escape_html(alt)));
+      attributes = None;
+      i += 1;
+      continue;
+    }
+
+    if list_marker(line).is_some() {
+      i = list(lines, i, html);
+      attributes = None;
+      continue;
+    }
+
+    if description_term(line).is_some() {
+      i = description_list(lines, i, html);
+      attributes = None;
+      continue;
+    }
+
+    // indented lines are a literal paragraph
+    if indent_of(line) > 0 {
+      let start = i;
+      while i < lines.len() && !lines[i].is_empty() {
+        i += 1;
+      }
+      push_code_block(html, None, &dedent(&lines[start..i]));
+      attributes = None;
+      continue;
+    }
+
+    let start = i;
+    while i < lines.len() && !lines[i].is_empty() && (i == start || !starts_block(&lines[i])) {
+      i += 1;
+    }
+    let text = lines[start..i].join("\n");
+
+    let admonition = ADMONITIONS
+      .iter()
+      .find(|a| text.starts_with(&format!("{}: ", a)))
+      .map(|a| (*a, &text[a.len() + 2..]))
+      .or_else(|| {
+        let a = attributes.as_deref().filter(|a| ADMONITIONS.contains(a))?;
+        Some((a, &*text))
+      });
+
+    match admonition {
+      Some((label, text)) => html.push_str(&format!(
+        "<blockquote>\n<p><strong>{}</strong></p>\n<p>{}</p>\n</blockquote>\n",
+        admonition_title(label),
+        paragraph(text),
+      )),
+      None => html.push_str(&format!("<p>{}</p>\n", paragraph(&text))),
+    }
+    attributes = None;
+  }
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+  let level = line.len() - line.trim_start_matches(|c: char| c == '=' || c == '#').len();
+  if level == 0 || level > 6 || !line[level..].starts_with(' ') {
+    return None;
+  }
+
+  Some((level, line[level..].trim()))
+}
+
+fn is_attribute_entry(line: &str) -> bool {
+  line.starts_with(':') && line[1..]
+    .find(':')
+    .map(|end| end > 0 && line[1..=end].chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '!' || c == ':'))
+    .unwrap_or(false)
+}
+
+/// Whether `line` opens or closes a delimited block, like `----` or `====`.
+fn is_delimiter(line: &str) -> bool {
+  let first = match line.chars().next() {
+    Some(c) if "-.=_*+/".contains(c) => c,
+    _ => return false,
+  };
+
+  line.len() >= 4 && line.chars().all(|c| c == first)
+}
+
+fn closing_delimiter(lines: &[String], start: usize) -> Option<usize> {
+  (start + 1..lines.len()).find(|&j| lines[j] == lines[start])
+}
+
+/// Whether `line` starts a new block, ending the paragraph before it.
+fn starts_block(line: &str) -> bool {
+  is_delimiter(line) || list_marker(line).is_some() || heading(line).is_some() || line.starts_with("|===")
+    || (line.starts_with('[') && line.ends_with(']'))
+}
+
+fn delimited_block(delimiter: &str, lines: &[String], attributes: Option<String>, html: &mut String) {
+  let mut attributes = attributes.as_deref().unwrap_or_default().split(',').map(str::trim);
+  let style = attributes.next().unwrap_or_default();
+
+  match delimiter.chars().next() {
+    Some('-') if delimiter.len() > 2 => {
+      let lang = match style {
+        "source" => attributes.next().filter(|x| !x.is_empty()),
+        _ => None,
+      };
+      push_code_block(html, lang, lines);
+    },
+    Some('.') => push_code_block(html, None, lines),
+    // passthrough blocks are raw HTML, which is cleaned like everything else
+    Some('+') => {
+      html.push_str(&lines.join("\n"));
+      html.push('\n');
+    },
+    Some('_') => {
+      html.push_str("<blockquote>\n");
+      blocks(lines, html);
+      let attribution: Vec<&str> = attributes.filter(|x| !x.is_empty()).collect();
+      if style == "quote" && !attribution.is_empty() {
+        html.push_str(&format!("<p>— {}</p>\n", inline(&attribution.join(", "))));
+      }
+      html.push_str("</blockquote>\n");
+    },
+    Some('=') if ADMONITIONS.contains(&style) => {
+      html.push_str(&format!("<blockquote>\n<p><strong>{}</strong></p>\n", admonition_title(style)));
+      blocks(lines, html);
+      html.push_str("</blockquote>\n");
+    },
+    Some('*') => {
+      html.push_str("<aside>\n");
+      blocks(lines, html);
+      html.push_str("</aside>\n");
+    },
+    // example blocks and open blocks only group their contents
+    _ => {
+      html.push_str("<div>\n");
+      blocks(lines, html);
+      html.push_str("</div>\n");
+    },
+  }
+}
+
+fn admonition_title(label: &str) -> String {
+  let mut title = label.to_lowercase();
+  title[..1].make_ascii_uppercase();
+  title
+}
+
+fn table(lines: &[String], attributes: Option<String>, html: &mut String) {
+  let first = match lines.iter().position(|l| !l.is_empty()) {
+    Some(f) => f,
+    None => return,
+  };
+
+  // the first row is a header if it's followed by a blank line or the table says it has one
+  let columns = lines[first].matches('|').count().max(1);
+  let header = lines.get(first + 1).map(|l| l.is_empty()).unwrap_or(false)
+    || attributes.map(|a| a.contains("header")).unwrap_or(false);
+
+  let cells: Vec<&str> = lines[first..]
+    .iter()
+    .filter(|l| !l.is_empty())
+    .flat_map(|l| l.split('|').skip(1))
+    .map(str::trim)
+    .collect();
+
+  html.push_str("<table>\n");
+  for (i, row) in cells.chunks(columns).enumerate() {
+    let tag = if header && i == 0 { "th" } else { "td" };
+    html.push_str("<tr>\n");
+    for cell in row {
+      html.push_str(&format!("<{tag}>{}</{tag}>\n", inline(cell), tag = tag));
+    }
+    html.push_str("</tr>\n");
+  }
+  html.push_str("</table>\n");
+}
+
+/// The kind and depth of the list item starting `line`, along with where its text starts.
+fn list_marker(line: &str) -> Option<(bool, usize, usize)> {
+  let trimmed = line.trim_start();
+  let indent = line.len() - trimmed.len();
+
+  if let Some(rest) = trimmed.strip_prefix("- ") {
+    return Some((false, 1, line.len() - rest.len()));
+  }
+
+  for &(marker, ordered) in &[('*', false), ('.', true)] {
+    let depth = trimmed.len() - trimmed.trim_start_matches(marker).len();
+    if depth > 0 && trimmed[depth..].starts_with(' ') {
+      return Some((ordered, depth, indent + depth + 1));
+    }
+  }
+
+  let digits = trimmed.len() - trimmed.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+  if digits > 0 && trimmed[digits..].starts_with(". ") {
+    return Some((true, 1, indent + digits + 2));
+  }
+
+  None
+}
+
+fn list(lines: &[String], start: usize, html: &mut String) -> usize {
+  let mut items: Vec<(bool, usize, String)> = Vec::new();
+  let mut i = start;
+
+  while i < lines.len() {
+    let line = &lines[i];
+
+    if line.is_empty() {
+      // blank lines only separate items of the same list
+      match lines[i..].iter().find(|l| !l.is_empty()) {
+        Some(next) if list_marker(next).is_some() => {
+          i += 1;
+          continue;
+        },
+        _ => break,
+      }
+    }
+
+    match list_marker(line) {
+      Some((ordered, depth, text)) => items.push((ordered, depth, line[text..].to_string())),
+      // a "+" on its own joins the next block to the item, which is shown as part of its text
+      None if line == "+" => {},
+      None if starts_block(line) => break,
+      None => {
+        let item = &mut items.last_mut().expect("list has an item").2;
+        item.push(' ');
+        item.push_str(line.trim());
+      },
+    }
+
+    i += 1;
+  }
+
+  list_items(&items, html);
+
+  i
+}
+
+fn list_items(items: &[(bool, usize, String)], html: &mut String) {
+  let mut i = 0;
+
+  while i < items.len() {
+    let (ordered, depth, _) = items[i];
+    let tag = if ordered { "ol" } else { "ul" };
+
+    html.push_str(&format!("<{}>\n", tag));
+    while i < items.len() && items[i].0 == ordered && items[i].1 == depth {
+      let text = &items[i].2;
+      // checklist items
+      let text = text
+        .strip_prefix("[x] ")
+        .or_else(|| text.strip_prefix("[*] "))
+        .map(|t| format!("\u{2611} {}", t))
+        .or_else(|| text.strip_prefix("[ ] ").map(|t| format!("\u{2610} {}", t)))
+        .unwrap_or_else(|| text.clone());
+
+      html.push_str(&format!("<li>{}", inline(&text)));
+      i += 1;
+
+      let children = items[i..].iter().take_while(|x| x.1 > depth).count();
+      if children > 0 {
+        html.push('\n');
+        list_items(&items[i..i + children], html);
+        i += children;
+      }
+      html.push_str("</li>\n");
+    }
+    html.push_str(&format!("</{}>\n", tag));
+  }
+}
+
+/// The term of a description list item, like `CPU:: The brain of the computer`, and its definition
+/// if it's on the same line.
+fn description_term(line: &str) -> Option<(&str, &str)> {
+  let i = line.find("::")?;
+  let rest = &line[i + 2..];
+  if i == 0 || !(rest.is_empty() || rest.starts_with(' ')) {
+    return None;
+  }
+
+  Some((&line[..i], rest.trim()))
+}
+
+fn description_list(lines: &[String], start: usize, html: &mut String) -> usize {
+  let mut i = start;
+
+  html.push_str("<dl>\n");
+  while i < lines.len() {
+    let (term, definition) = match description_term(&lines[i]) {
+      Some(x) => x,
+      None => break,
+    };
+    i += 1;
+
+    let mut body = Vec::new();
+    if !definition.is_empty() {
+      body.push(definition.to_string());
+    }
+    while i < lines.len() && !lines[i].is_empty() && description_term(&lines[i]).is_none() {
+      body.push(lines[i].trim().to_string());
+      i += 1;
+    }
+
+    let mut definition = String::new();
+    blocks(&body, &mut definition);
+    html.push_str(&format!("<dt>{}</dt>\n<dd>{}</dd>\n", inline(term), tight(definition)));
+
+    while i < lines.len() && lines[i].is_empty() && lines.get(i + 1).and_then(|l| description_term(l)).is_some() {
+      i += 1;
+    }
+  }
+  html.push_str("</dl>\n");
+
+  i
+}
+
+/// Renders a paragraph, keeping the hard line breaks marked by a trailing `+`.
+fn paragraph(text: &str) -> String {
+  text
+    .split('\n')
+    .map(|line| match line.strip_suffix(" +") {
+      Some(line) => format!("{}<br>", inline(line)),
+      None => inline(line),
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Splits the `target[text]` of a macro like `link:target[text]`.
+fn macro_parts(s: &str) -> Option<(&str, &str)> {
+  let open = s.find('[')?;
+  let close = s[open..].find(']')? + open;
+  if s[..open].contains(char::is_whitespace) {
+    return None;
+  }
+
+  Some((&s[..open], &s[open + 1..close]))
+}
+
+fn inline(text: &str) -> String {
+  let mut html = String::new();
+  let mut rest = text;
+  let mut prev: Option<char> = None;
+
+  while !rest.is_empty() {
+    let after_word = prev.map(|p| p.is_alphanumeric()).unwrap_or(false);
+
+    if let Some((len, markup)) = inline_markup(rest, after_word) {
+      html.push_str(&markup);
+      prev = rest[..len].chars().last();
+      rest = &rest[len..];
+      continue;
+    }
+
+    let len = push_escaped_char(&mut html, rest);
+    prev = rest[..len].chars().last();
+    rest = &rest[len..];
+  }
+
+  html
+}
+
+/// Renders the inline markup at the start of `s`, returning how much of `s` it took up.
+///
+/// `after_word` is whether `s` directly follows a letter or digit, in which case only unconstrained
+/// formatting like `**bold**` can start.
+fn inline_markup(s: &str, after_word: bool) -> Option<(usize, String)> {
+  if !after_word {
+    if let Some(len) = url_len(s) {
+      // a url followed by [text] is a link with that text
+      if s[len..].starts_with('[') {
+        if let Some((url, text)) = macro_parts(s) {
+          let text = if text.is_empty() { escape_html(url) } else { inline(text) };
+          return Some((url.len() + text_len(&s[url.len()..]), link(url, &text)));
+        }
+      }
+      return Some((len, link(&s[..len], &escape_html(&s[..len]))));
+    }
+
+    for (prefix, image) in &[("link:", false), ("image:", true)] {
+      if let Some(rest) = s.strip_prefix(prefix) {
+        let (target, text) = macro_parts(rest)?;
+        let len = prefix.len() + target.len() + text_len(&rest[target.len()..]);
+        let markup = if *image {
+          format!("<img src=\"{}\" alt=\"{}\">", escape_html(target), escape_html(text))
+        } else if text.is_empty() {
+          link(target, &escape_html(target))
+        } else {
+          link(target, &inline(text))
+        };
+        return Some((len, markup));
+      }
+    }
+
+    // cross references, which are shown as their text
+    if s.starts_with("<<") {
+      let end = s.find(">>")?;
+      let inner = &s[2..end];
+      let text = inner.split_once(',').map(|(_, t)| t.trim()).unwrap_or(inner);
+      return Some((end + 2, escape_html(text)));
+    }
+  }
+
+  for &(delimiter, tag) in &[("**", "strong"), ("__", "em"), ("##", "mark"), ("``", "code")] {
+    if let Some(markup) = formatted(s, delimiter, tag, true) {
+      return Some(markup);
+    }
+  }
+
+  if after_word {
+    return None;
+  }
+
+  for &(delimiter, tag) in &[("*", "strong"), ("_", "em"), ("#", "mark"), ("`", "code"), ("+", "")] {
+    if let Some(markup) = formatted(s, delimiter, tag, false) {
+      return Some(markup);
+    }
+  }
+
+  None
+}
+
+/// The length of the `[text]` at the start of `s`.
+fn text_len(s: &str) -> usize {
+  s.find(']').map(|i| i + 1).unwrap_or(s.len())
+}
+
+/// Renders text between a pair of `delimiter`s at the start of `s` as a `tag` element, or as plain
+/// text if `tag` is empty.
+///
+/// Constrained formatting, using single characters, only ends at the end of a word.
+fn formatted(s: &str, delimiter: &str, tag: &str, unconstrained: bool) -> Option<(usize, String)> {
+  let after = s.strip_prefix(delimiter)?;
+  if after.starts_with(char::is_whitespace) {
+    return None;
+  }
+
+  let mut search = 0;
+  let end = loop {
+    let end = after[search..].find(delimiter)? + search;
+    let next = after[end + delimiter.len()..].chars().next();
+    if end > 0 && (unconstrained || !next.map(char::is_alphanumeric).unwrap_or(false)) {
+      break end;
+    }
+    search = end + delimiter.len();
+  };
+
+  let inner = &after[..end];
+  let len = delimiter.len() * 2 + end;
+
+  let markup = match tag {
+    "" => escape_html(inner),
+    // monospace text can't contain other formatting, though `+` can still be used to escape it
+    "code" => format!("<code>{}</code>", escape_html(inner.trim_matches('+'))),
+    _ => format!("<{tag}>{}</{tag}>", inline(inner), tag = tag),
+  };
+
+  Some((len, markup))
+}
@@ -0,0 +1,200 @@
+//! Rendering Jupyter notebooks (nbformat 4): markdown cells, code cells in the notebook's language and
+//! their text and image outputs.
+
+use crate::utils::markdown::{self, Anchors};
+
+use super::push_code_block;
+
+use comrak::ComrakOptions;
+
+use serde_json::Value;
+
+use tera::escape_html;
+
+use std::collections::BTreeMap;
+
+/// Image types that can be shown inline, most preferred first.
+///
+/// SVG is left out, since it can carry scripts.
+pub const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+#[derive(Deserialize)]
+struct Notebook {
+  cells: Vec<Cell>,
+  #[serde(default)]
+  metadata: Metadata,
+}
+
+#[derive(Deserialize, Default)]
+struct Metadata {
+  language_info: Option<LanguageInfo>,
+  kernelspec: Option<KernelSpec>,
+}
+
+#[derive(Deserialize)]
+struct LanguageInfo {
+  name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct KernelSpec {
+  language: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "cell_type", rename_all = "lowercase")]
+enum Cell {
+  Markdown {
+    source: Text,
+  },
+  Code {
+    source: Text,
+    #[serde(default)]
+    outputs: Vec<CellOutput>,
+  },
+  Raw {
+    source: Text,
+  },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+enum CellOutput {
+  Stream {
+    text: Text,
+  },
+  ExecuteResult {
+    data: BTreeMap<String, Value>,
+  },
+  DisplayData {
+    data: BTreeMap<String, Value>,
+  },
+  Error {
+    ename: String,
+    evalue: String,
+    #[serde(default)]
+    traceback: Vec<String>,
+  },
+}
+
+/// Multiline strings, which notebooks store either whole or split into lines.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Text {
+  Whole(String),
+  Lines(Vec<String>),
+}
+
+impl Text {
+  fn into_string(self) -> String {
+    match self {
+      Text::Whole(s) => s,
+      Text::Lines(l) => l.concat(),
+    }
+  }
+}
+
+pub fn to_html(content: &str, options: &ComrakOptions) -> Result<String, serde_json::Error> {
+  let notebook: Notebook = serde_json::from_str(content)?;
+
+  let lang = notebook.metadata.language_info
+    .and_then(|x| x.name)
+    .or_else(|| notebook.metadata.kernelspec.and_then(|x| x.language));
+
+  let mut html = String::new();
+  // headings in every cell share anchors, since they're all on one page
+  let mut anchors = Anchors::default();
+
+  for cell in notebook.cells {
+    match cell {
+      Cell::Markdown { source } => html.push_str(&markdown::render(&source.into_string(), options, &mut anchors).0),
+      Cell::Raw { source } => push_text(&mut html, None, &source.into_string()),
+      Cell::Code { source, outputs } => {
+        push_text(&mut html, lang.as_deref(), &source.into_string());
+        for output in outputs {
+          push_output(&mut html, output, options, &mut anchors);
+        }
+      },
+    }
+  }
+
+  Ok(html)
+}
+
+fn push_text(html: &mut String, lang: Option<&str>, text: &str) {
+  let text = text.trim_end_matches('\n');
+  if text.is_empty() {
+    return;
+  }
+
+  push_code_block(html, lang, &[text.to_string()]);
+}
+
+fn push_output(html: &mut String, output: CellOutput, options: &ComrakOptions, anchors: &mut Anchors) {
+  let data = match output {
+    CellOutput::Stream { text } => {
+      push_output_text(html, &strip_ansi(&text.into_string()));
+      return;
+    },
+    CellOutput::Error { ename, evalue, traceback } => {
+      let text = if traceback.is_empty() {
+        format!("{}: {}", ename, evalue)
+      } else {
+        strip_ansi(&traceback.join("\n"))
+      };
+      push_output_text(html, &text);
+      return;
+    },
+    CellOutput::ExecuteResult { data } | CellOutput::DisplayData { data } => data,
+  };
+
+  let text = |mime: &str| -> Option<String> {
+    serde_json::from_value::<Text>(data.get(mime)?.clone()).ok().map(Text::into_string)
+  };
+
+  // show the richest representation we can
+  if let Some((mime, image)) = IMAGE_TYPES.iter().find_map(|&mime| Some((mime, text(mime)?))) {
+    let image: String = image.split_whitespace().collect();
+    html.push_str(&format!("<p><img src=\"data:{};base64,{}\" alt=\"\"></p>\n", mime, escape_html(&image)));
+  } else if let Some(output) = text("text/html") {
+    html.push_str(&output);
+    html.push('\n');
+  } else if let Some(output) = text("text/markdown") {
+    html.push_str(&markdown::render(&output, options, anchors).0);
+  } else if let Some(output) = text("text/plain") {
+    push_output_text(html, &output);
+  }
+}
+
+fn push_output_text(html: &mut String, text: &str) {
+  let text = text.trim_end_matches('\n');
+  if text.is_empty() {
+    return;
+  }
+
+  html.push_str(&format!("<pre><samp>{}</samp></pre>\n", escape_html(text)));
+}
+
+/// Removes the terminal colour codes that tracebacks and some program output are full of.
+fn strip_ansi(text: &str) -> String {
+  let mut stripped = String::with_capacity(text.len());
+  let mut chars = text.chars();
+
+  while let Some(c) = chars.next() {
+    if c != '\u{1b}' {
+      stripped.push(c);
+      continue;
+    }
+
+    // skip the escape sequence up to and including its final byte
+    if chars.next() == Some('[') {
+      for c in &mut chars {
+        if ('@'..='~').contains(&c) {
+          break;
+        }
+      }
+    }
+  }
+
+  stripped
+}
@@ -0,0 +1,432 @@
+//! Rendering the commonly used parts of reStructuredText: sections, paragraphs, lists, definition
+//! lists, literal and code blocks, block quotes, images, admonitions and inline markup.
+//!
+//! Tables are kept as preformatted text, and directives that need a whole project to make sense of,
+//! like `toctree`, are left out.
+
+use super::{dedent, indent_of, indented_block, link, lines, push_code_block, push_escaped_char, tight, url_len};
+
+use tera::escape_html;
+
+const ADMONITIONS: &[&str] = &[
+  "attention", "caution", "danger", "error", "hint", "important", "note", "tip", "warning",
+];
+
+pub fn to_html(content: &str) -> String {
+  let mut html = String::new();
+  blocks(&lines(content), &mut Vec::new(), &mut html);
+  html
+}
+
+/// Renders a run of body elements.
+///
+/// `styles` holds the section title styles seen so far, since reStructuredText decides heading
+/// levels by the order styles are first used in.
+fn blocks(lines: &[String], styles: &mut Vec<(char, bool)>, html: &mut String) {
+  let mut i = 0;
+
+  while i < lines.len() {
+    let line = &lines[i];
+
+    if line.is_empty() {
+      i += 1;
+      continue;
+    }
+
+    // a section title with an overline
+    if is_adornment(line) && i + 2 < lines.len() && !lines[i + 1].trim().is_empty() && lines[i + 2] == *line {
+      push_heading(html, styles, (adornment_char(line), true), lines[i + 1].trim());
+      i += 3;
+      continue;
+    }
+
+    // a section title with only an underline
+    if !is_adornment(line)
+      && indent_of(line) == 0
+      && i + 1 < lines.len()
+      && is_adornment(&lines[i + 1])
+      && lines[i + 1].chars().count() >= line.chars().count()
+    {
+      push_heading(html, styles, (adornment_char(&lines[i + 1]), false), line);
+      i += 2;
+      continue;
+    }
+
+    // a transition
+    if is_adornment(line) && line.len() >= 4 {
+      html.push_str("<hr>\n");
+      i += 1;
+      continue;
+    }
+
+    if indent_of(line) > 0 {
+      let (block, end) = indented_block(lines, i);
+      html.push_str("<blockquote>\n");
+      blocks(&dedent(&block), styles, html);
+      html.push_str("</blockquote>\n");
+      i = end;
+      continue;
+    }
+
+    if line == ".." || line.starts_with(".. ") {
+      let (body, end) = indented_block(lines, i + 1);
+      directive(&line[2..], &dedent(&body), styles, html);
+      i = end;
+      continue;
+    }
+
+    // tables are kept as they are, since their layout is already readable
+    if is_table_border(line) {
+      let mut end = i;
+      while end < lines.len() && !lines[end].is_empty() {
+        end += 1;
+      }
+      push_code_block(html, None, &lines[i..end]);
+      i = end;
+      continue;
+    }
+
+    if bullet(line).is_some() {
+      i = list(lines, i, "ul", bullet, styles, html);
+      continue;
+    }
+
+    if enumerator(line).is_some() {
+      i = list(lines, i, "ol", enumerator, styles, html);
+      continue;
+    }
+
+    // a definition list item is a single line followed straight away by an indented definition
+    if i + 1 < lines.len() && !lines[i + 1].is_empty() && indent_of(&lines[i + 1]) > 0 {
+      html.push_str("<dl>\n");
+      while i + 1 < lines.len() && indent_of(&lines[i]) == 0 && !lines[i].is_empty() && indent_of(&lines[i + 1]) > 0 && !lines[i + 1].is_empty() {
+        let (body, end) = indented_block(lines, i + 1);
+        html.push_str(&format!("<dt>{}</dt>\n<dd>\n", inline(&lines[i])));
+        blocks(&dedent(&body), styles, html);
+        html.push_str("</dd>\n");
+        i = end;
+        while i < lines.len() && lines[i].is_empty() {
+          i += 1;
+        }
+      }
+      html.push_str("</dl>\n");
+      continue;
+    }
+
+    let start = i;
+    while i < lines.len() && !lines[i].is_empty() && indent_of(&lines[i]) == 0 {
+      i += 1;
+    }
+    let mut text = lines[start..i].join("\n");
+
+    // a paragraph ending in "::" introduces a literal block
+    let literal = text.ends_with("::");
+    if literal {
+      if text == "::" {
+        text.clear();
+      } else if text.ends_with(" ::") {
+        text.truncate(text.len() - 3);
+      } else {
+        text.pop();
+      }
+    }
+
+    if !text.is_empty() {
+      html.push_str(&format!("<p>{}</p>\n", inline(&text)));
+    }
+
+    if literal {
+      while i < lines.len() && lines[i].is_empty() {
+        i += 1;
+      }
+      let (block, end) = indented_block(lines, i);
+      if !block.is_empty() {
+        push_code_block(html, None, &dedent(&block));
+      }
+      i = end;
+    }
+  }
+}
+
+/// Whether `line` is made of one punctuation character repeated, like a section title's underline.
+fn is_adornment(line: &str) -> bool {
+  let mut chars = line.chars();
+  let first = match chars.next() {
+    Some(c) if c.is_ascii_punctuation() => c,
+    _ => return false,
+  };
+
+  line.len() >= 2 && chars.all(|c| c == first)
+}
+
+fn adornment_char(line: &str) -> char {
+  line.chars().next().unwrap_or('=')
+}
+
+fn is_table_border(line: &str) -> bool {
+  line.starts_with("+-") || line.starts_with("+=")
+    || (line.starts_with('=') && line.contains(' ') && line.chars().all(|c| c == '=' || c == ' '))
+}
+
+fn push_heading(html: &mut String, styles: &mut Vec<(char, bool)>, style: (char, bool), title: &str) {
+  let level = match styles.iter().position(|&s| s == style) {
+    Some(i) => i + 1,
+    None => {
+      styles.push(style);
+      styles.len()
+    },
+  };
+  let level = level.min(6);
+
+  html.push_str(&format!("<h{level}>{}</h{level}>\n", inline(title), level = level));
+}
+
+fn directive(rest: &str, body: &[String], styles: &mut Vec<(char, bool)>, html: &mut String) {
+  // anything that isn't a directive is a comment or a link target, neither of which are shown
+  let (name, argument) = match rest.find("::") {
+    Some(i) => (rest[..i].trim().to_lowercase(), rest[i + 2..].trim()),
+    None => return,
+  };
+
+  // options come first in the body, followed by the content
+  let options: Vec<(&str, &str)> = body
+    .iter()
+    .take_while(|l| l.starts_with(':'))
+    .filter_map(|l| {
+      let end = l[1..].find(':')? + 1;
+      Some((&l[1..end], l[end + 1..].trim()))
+    })
+    .collect();
+  let content = &body[options.len()..];
+  let content_start = content.iter().position(|l| !l.is_empty()).unwrap_or(content.len());
+  let content = &content[content_start..];
+
+  match &*name {
+    "code" | "code-block" | "sourcecode" => {
+      let lang = Some(argument).filter(|x| !x.is_empty());
+      push_code_block(html, lang, content);
+    },
+    "image" | "figure" => {
+      let alt = options.iter().find(|(k, _)| *k == "alt").map(|(_, v)| *v).unwrap_or_default();
+      html.push_str(&format!("<p><img src=\"{}\" alt=\"{}\"></p>\n", escape_html(argument), escape_html(alt)));
+      // a figure's content is its caption
+      if name == "figure" && !content.is_empty() {
+        blocks(content, styles, html);
+      }
+    },
+    "admonition" | "topic" | "sidebar" => {
+      html.push_str(&format!("<blockquote>\n<p><strong>{}</strong></p>\n", inline(argument)));
+      blocks(content, styles, html);
+      html.push_str("</blockquote>\n");
+    },
+    n if ADMONITIONS.contains(&n) => {
+      let mut title = n.to_string();
+      title[..1].make_ascii_uppercase();
+      html.push_str(&format!("<blockquote>\n<p><strong>{}</strong></p>\n", title));
+      // the argument is the start of the admonition's content
+      let mut content = content.to_vec();
+      if !argument.is_empty() {
+        content.insert(0, argument.to_string());
+      }
+      blocks(&content, styles, html);
+      html.push_str("</blockquote>\n");
+    },
+    _ => {},
+  }
+}
+
+fn bullet(line: &str) -> Option<usize> {
+  let mut chars = line.chars();
+  match (chars.next(), chars.next()) {
+    (Some('-'), Some(' ')) | (Some('*'), Some(' ')) | (Some('+'), Some(' ')) => Some(2),
+    _ => None,
+  }
+}
+
+fn enumerator(line: &str) -> Option<usize> {
+  let label = line.find(|c: char| !c.is_ascii_digit() && c != '#').unwrap_or(0);
+  if label == 0 || (label > 1 && line.starts_with('#')) {
+    return None;
+  }
+
+  match line[label..].chars().next() {
+    Some('.') | Some(')') if line[label + 1..].starts_with(' ') => Some(label + 2),
+    _ => None,
+  }
+}
+
+/// Renders the list starting at `lines[start]`, returning the index of the first line after it.
+fn list(lines: &[String], start: usize, tag: &str, marker: fn(&str) -> Option<usize>, styles: &mut Vec<(char, bool)>, html: &mut String) -> usize {
+  let mut items: Vec<Vec<String>> = Vec::new();
+  let mut width = 0;
+  let mut i = start;
+
+  while i < lines.len() {
+    let line = &lines[i];
+
+    if line.is_empty() {
+      if let Some(item) = items.last_mut() {
+        item.push(String::new());
+      }
+      i += 1;
+      continue;
+    }
+
+    if indent_of(line) == 0 {
+      match marker(line) {
+        Some(w) => {
+          width = w;
+          items.push(vec![line[w..].to_string()]);
+        },
+        None => break,
+      }
+    } else if indent_of(line) >= width {
+      items.last_mut().expect("list has an item").push(line[width..].to_string());
+    } else {
+      break;
+    }
+
+    i += 1;
+  }
+
+  // blank lines after the last item belong to whatever comes next
+  while i > start && lines[i - 1].is_empty() {
+    i -= 1;
+  }
+
+  html.push_str(&format!("<{}>\n", tag));
+  for mut item in items {
+    while item.last().map(|l| l.is_empty()).unwrap_or(false) {
+      item.pop();
+    }
+
+    let mut body = String::new();
+    blocks(&item, styles, &mut body);
+    html.push_str(&format!("<li>{}</li>\n", tight(body)));
+  }
+  html.push_str(&format!("</{}>\n", tag));
+
+  i
+}
+
+fn inline(text: &str) -> String {
+  let mut html = String::new();
+  let mut rest = text;
+  let mut prev: Option<char> = None;
+
+  while let Some(c) = rest.chars().next() {
+    // inline markup only starts at the beginning of a word
+    let at_start = prev.map(|p| p.is_whitespace() || "([{<'\"-/:".contains(p)).unwrap_or(true);
+
+    if at_start {
+      if let Some((len, markup)) = inline_markup(rest) {
+        html.push_str(&markup);
+        prev = rest[..len].chars().last();
+        rest = &rest[len..];
+        continue;
+      }
+    }
+
+    // backslashes escape the character after them
+    if c == '\\' && rest.len() > 1 {
+      rest = &rest[1..];
+    }
+
+    let len = push_escaped_char(&mut html, rest);
+    prev = rest[..len].chars().last();
+    rest = &rest[len..];
+  }
+
+  html
+}
+
+/// Renders the inline markup at the start of `s`, returning how much of `s` it took up.
+fn inline_markup(s: &str) -> Option<(usize, String)> {
+  if let Some(len) = url_len(s) {
+    return Some((len, link(&s[..len], &escape_html(&s[..len]))));
+  }
+
+  if s.starts_with("``") {
+    let (inner, len) = enclosed(s, "``")?;
+    return Some((len, format!("<code>{}</code>", escape_html(inner))));
+  }
+
+  if s.starts_with("**") {
+    let (inner, len) = enclosed(s, "**")?;
+    return Some((len, format!("<strong>{}</strong>", escape_html(inner))));
+  }
+
+  if s.starts_with('*') {
+    let (inner, len) = enclosed(s, "*")?;
+    return Some((len, format!("<em>{}</em>", escape_html(inner))));
+  }
+
+  // interpreted text with a role, like :code:`x`
+  if s.starts_with(':') {
+    let end = s[1..].find(":`")? + 1;
+    let role = &s[1..end];
+    if !role.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ':') {
+      return None;
+    }
+    let (inner, len) = enclosed(&s[end + 1..], "`")?;
+    let markup = match role {
+      "code" | "literal" | "file" | "samp" | "kbd" => format!("<code>{}</code>", escape_html(inner)),
+      "emphasis" => format!("<em>{}</em>", escape_html(inner)),
+      "strong" => format!("<strong>{}</strong>", escape_html(inner)),
+      "sub" | "subscript" => format!("<sub>{}</sub>", escape_html(inner)),
+      "sup" | "superscript" => format!("<sup>{}</sup>", escape_html(inner)),
+      // cross-references point into a project we don't have, so only their text is shown
+      _ => escape_html(reference_text(inner)),
+    };
+    return Some((end + 1 + len, markup));
+  }
+
+  if s.starts_with('`') {
+    let (inner, mut len) = enclosed(s, "`")?;
+    let markup = if s[len..].starts_with('_') {
+      len += if s[len..].starts_with("__") { 2 } else { 1 };
+      match reference_url(inner) {
+        Some(url) => link(url, &escape_html(reference_text(inner))),
+        None => escape_html(inner),
+      }
+    } else {
+      format!("<cite>{}</cite>", escape_html(inner))
+    };
+    return Some((len, markup));
+  }
+
+  None
+}
+
+/// Finds the text between a pair of `delimiter`s at the start of `s`, returning it and the length of
+/// the whole thing.
+fn enclosed<'s>(s: &'s str, delimiter: &str) -> Option<(&'s str, usize)> {
+  let after = &s[delimiter.len()..];
+  if after.starts_with(char::is_whitespace) {
+    return None;
+  }
+
+  let end = after.find(delimiter)?;
+  if end == 0 {
+    return None;
+  }
+
+  Some((&after[..end], delimiter.len() * 2 + end))
+}
+
+/// The text of a reference like `` `Example <https://example.com>`_ ``.
+fn reference_text(inner: &str) -> &str {
+  match inner.rfind(" <") {
+    Some(i) if inner.ends_with('>') => inner[..i].trim(),
+    _ => inner,
+  }
+}
+
+fn reference_url(inner: &str) -> Option<&str> {
+  let start = inner.rfind('<')?;
+  if !inner.ends_with('>') {
+    return None;
+  }
+
+  Some(&inner[start + 1..inner.len() - 1])
+}
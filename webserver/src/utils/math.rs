@@ -0,0 +1,724 @@
+//! Rendering TeX math as MathML, so formulas show up without any scripts.
+//!
+//! This covers the commonly used subset of TeX: scripts, fractions, roots, greek letters, symbols,
+//! fonts, delimiters and matrix-like environments. Anything else is shown as an error in place.
+
+use tera::escape_html;
+
+/// The MathML elements made here, for the HTML cleaner to let through.
+pub const TAGS: &[&str] = &[
+  "math", "mrow", "mi", "mn", "mo", "mtext", "mspace", "merror", "msub", "msup", "msubsup", "mfrac",
+  "msqrt", "mroot", "munder", "mover", "munderover", "mtable", "mtr", "mtd",
+];
+
+/// The attributes used on MathML elements, by element.
+pub const TAG_ATTRIBUTES: &[(&str, &[&str])] = &[
+  ("math", &["display"]),
+  ("mi", &["mathvariant"]),
+  ("mo", &["stretchy", "fence"]),
+  ("mspace", &["width"]),
+  ("mfrac", &["linethickness"]),
+  ("mtable", &["columnalign"]),
+];
+
+/// How deeply groups, scripts and arguments may nest before the rest of a formula is shown as an
+/// error, so that formulas like `x^^^^...` can't overflow the stack.
+const MAX_DEPTH: usize = 64;
+
+/// Renders `tex` as a MathML `<math>` element, shown as a block if `display` is set.
+pub fn to_mathml(tex: &str, display: bool) -> String {
+  let (tokens, spaced) = tokenize(tex).into_iter().unzip();
+  let mut parser = Parser {
+    tokens,
+    spaced,
+    pos: 0,
+    depth: 0,
+    display,
+  };
+  let row = parser.row(Stop::End);
+
+  format!(
+    "<math display=\"{}\">{}</math>",
+    if display { "block" } else { "inline" },
+    mrow(row),
+  )
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Command(String),
+  Open,
+  Close,
+  Sup,
+  Sub,
+  Align,
+  Number(String),
+  Letter(char),
+  Symbol(char),
+}
+
+/// Splits `tex` into tokens, along with whether each one came after whitespace.
+fn tokenize(tex: &str) -> Vec<(Token, bool)> {
+  let mut tokens = Vec::new();
+  let mut chars = tex.chars().peekable();
+  let mut spaced = false;
+
+  while let Some(c) = chars.next() {
+    let token = match c {
+      '\\' => {
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+          if !c.is_ascii_alphabetic() {
+            break;
+          }
+          name.push(c);
+          chars.next();
+        }
+        // commands are either a run of letters or a single other character, like \, or \{
+        if name.is_empty() {
+          match chars.next() {
+            Some(c) => name.push(c),
+            None => continue,
+          }
+        }
+        Token::Command(name)
+      },
+      '{' => Token::Open,
+      '}' => Token::Close,
+      '^' => Token::Sup,
+      '_' => Token::Sub,
+      '&' => Token::Align,
+      '0'..='9' | '.' if c != '.' || chars.peek().map(char::is_ascii_digit).unwrap_or(false) => {
+        let mut number = c.to_string();
+        while let Some(&c) = chars.peek() {
+          if !c.is_ascii_digit() && c != '.' {
+            break;
+          }
+          number.push(c);
+          chars.next();
+        }
+        Token::Number(number)
+      },
+      c if c.is_whitespace() => {
+        spaced = true;
+        continue;
+      },
+      c if c.is_alphabetic() => Token::Letter(c),
+      c => Token::Symbol(c),
+    };
+    tokens.push((token, spaced));
+    spaced = false;
+  }
+
+  tokens
+}
+
+/// What ends a row of math.
+#[derive(Clone, Copy, PartialEq)]
+enum Stop {
+  End,
+  Close,
+  Right,
+  /// The end of an environment, which also stops at `&` and `\\` between cells.
+  Cell,
+}
+
+struct Parser {
+  tokens: Vec<Token>,
+  /// Whether each token came after whitespace, which only matters in text.
+  spaced: Vec<bool>,
+  pos: usize,
+  /// How many groups, scripts and arguments are being parsed inside each other.
+  depth: usize,
+  display: bool,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.pos)
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    let token = self.tokens.get(self.pos).cloned();
+    self.pos += 1;
+    token
+  }
+
+  /// Goes a level deeper, unless that would be too deep, in which case the rest of the formula is
+  /// skipped.
+  fn enter(&mut self) -> bool {
+    if self.depth >= MAX_DEPTH {
+      self.pos = self.tokens.len();
+      return false;
+    }
+    self.depth += 1;
+    true
+  }
+
+  fn leave(&mut self) {
+    self.depth -= 1;
+  }
+
+  fn row(&mut self, stop: Stop) -> Vec<String> {
+    if !self.enter() {
+      return vec![too_deep()];
+    }
+
+    let mut row = Vec::new();
+
+    while let Some(token) = self.peek() {
+      match (token, stop) {
+        (Token::Close, Stop::Close) => {
+          self.pos += 1;
+          break;
+        },
+        (Token::Command(c), Stop::Right) if c == "right" => break,
+        (Token::Align, Stop::Cell) => break,
+        (Token::Command(c), Stop::Cell) if c == "\\" || c == "end" => break,
+        // stray closing braces and commands are kept, so nothing the writer typed goes missing
+        (Token::Close, _) => {
+          self.pos += 1;
+          row.push(error("}"));
+          continue;
+        },
+        _ => {},
+      }
+
+      match self.scripted() {
+        Some(item) => row.push(item),
+        None => break,
+      }
+    }
+
+    self.leave();
+    row
+  }
+
+  /// An atom along with any sub- and superscripts after it.
+  fn scripted(&mut self) -> Option<String> {
+    let (base, limits) = self.atom()?;
+
+    let mut sub = None;
+    let mut sup = None;
+    loop {
+      match self.peek() {
+        Some(Token::Sub) if sub.is_none() => {
+          self.pos += 1;
+          sub = Some(self.argument());
+        },
+        Some(Token::Sup) if sup.is_none() => {
+          self.pos += 1;
+          sup = Some(self.argument());
+        },
+        Some(Token::Symbol('\'')) if sup.is_none() => {
+          let mut primes = String::new();
+          while self.peek() == Some(&Token::Symbol('\'')) {
+            self.pos += 1;
+            primes.push('\u{2032}');
+          }
+          sup = Some(format!("<mo>{}</mo>", primes));
+        },
+        _ => break,
+      }
+    }
+
+    let (under, over, both) = if limits {
+      ("munder", "mover", "munderover")
+    } else {
+      ("msub", "msup", "msubsup")
+    };
+
+    Some(match (sub, sup) {
+      (None, None) => base,
+      (Some(sub), None) => format!("<{0}>{1}{2}</{0}>", under, base, sub),
+      (None, Some(sup)) => format!("<{0}>{1}{2}</{0}>", over, base, sup),
+      (Some(sub), Some(sup)) => format!("<{0}>{1}{2}{3}</{0}>", both, base, sub, sup),
+    })
+  }
+
+  /// A single argument to a command or script: a group in braces or one atom.
+  fn argument(&mut self) -> String {
+    if !self.enter() {
+      return too_deep();
+    }
+
+    let argument = match self.peek() {
+      Some(Token::Open) => {
+        self.pos += 1;
+        mrow(self.row(Stop::Close))
+      },
+      Some(_) => self.atom().map(|(atom, _)| atom).unwrap_or_default(),
+      None => error("?"),
+    };
+
+    self.leave();
+    argument
+  }
+
+  /// The raw text of a group in braces, for commands like `\text` that take text rather than math.
+  fn text_argument(&mut self) -> String {
+    if self.peek() != Some(&Token::Open) {
+      return match self.next() {
+        Some(token) => token_text(&token),
+        None => String::new(),
+      };
+    }
+    self.pos += 1;
+
+    let mut text = String::new();
+    let mut depth = 0;
+    while let Some(token) = self.next() {
+      if self.spaced[self.pos - 1] {
+        // spaces at the ends of text would be collapsed away otherwise
+        text.push('\u{a0}');
+      }
+      match token {
+        Token::Open => depth += 1,
+        Token::Close if depth == 0 => break,
+        Token::Close => depth -= 1,
+        _ => {},
+      }
+      text.push_str(&token_text(&token));
+    }
+
+    text
+  }
+
+  /// Returns an atom and whether scripts on it go under and over it instead of to its side.
+  fn atom(&mut self) -> Option<(String, bool)> {
+    if self.peek().is_none() {
+      return None;
+    }
+    if !self.enter() {
+      return Some((too_deep(), false));
+    }
+
+    let atom = self.bare_atom();
+
+    self.leave();
+    atom
+  }
+
+  fn bare_atom(&mut self) -> Option<(String, bool)> {
+    let atom = match self.next()? {
+      Token::Open => mrow(self.row(Stop::Close)),
+      Token::Number(n) => format!("<mn>{}</mn>", n),
+      Token::Letter(c) => format!("<mi>{}</mi>", escape_html(&c.to_string())),
+      Token::Symbol(c) => format!("<mo>{}</mo>", escape_html(&symbol_char(c).to_string())),
+      Token::Sup | Token::Sub => return self.atom(),
+      Token::Align => error("&"),
+      Token::Close => error("}"),
+      Token::Command(name) => return Some(self.command(&name)),
+    };
+
+    Some((atom, false))
+  }
+
+  fn command(&mut self, name: &str) -> (String, bool) {
+    let atom = match name {
+      "frac" | "dfrac" | "tfrac" => {
+        let (num, den) = (self.argument(), self.argument());
+        format!("<mfrac>{}{}</mfrac>", num, den)
+      },
+      "binom" => {
+        let (n, k) = (self.argument(), self.argument());
+        format!("<mrow><mo>(</mo><mfrac linethickness=\"0\">{}{}</mfrac><mo>)</mo></mrow>", n, k)
+      },
+      "sqrt" => {
+        // an optional index in brackets, as in \sqrt[3]{x}
+        if self.peek() == Some(&Token::Symbol('[')) {
+          self.pos += 1;
+          let mut index = Vec::new();
+          while let Some(token) = self.peek() {
+            if *token == Token::Symbol(']') {
+              self.pos += 1;
+              break;
+            }
+            match self.scripted() {
+              Some(item) => index.push(item),
+              None => break,
+            }
+          }
+          let radicand = self.argument();
+          format!("<mroot>{}{}</mroot>", radicand, mrow(index))
+        } else {
+          format!("<msqrt>{}</msqrt>", self.argument())
+        }
+      },
+      "text" | "textrm" | "mbox" | "operatorname" => {
+        let text = self.text_argument();
+        let tag = if name == "operatorname" { "mi" } else { "mtext" };
+        format!("<{0}>{1}</{0}>", tag, escape_html(&text))
+      },
+      "left" | "right" | "big" | "Big" | "bigg" | "Bigg" => {
+        let fence = self.next().map(|t| delimiter(&t)).unwrap_or_default();
+        let fence = format!("<mo fence=\"true\" stretchy=\"true\">{}</mo>", escape_html(&fence));
+        if name != "left" {
+          fence
+        } else {
+          let mut row = vec![fence];
+          row.extend(self.row(Stop::Right));
+          if self.peek() == Some(&Token::Command("right".into())) {
+            self.pos += 1;
+            let close = self.next().map(|t| delimiter(&t)).unwrap_or_default();
+            row.push(format!("<mo fence=\"true\" stretchy=\"true\">{}</mo>", escape_html(&close)));
+          }
+          mrow(row)
+        }
+      },
+      "begin" => self.environment(),
+      "end" => {
+        self.text_argument();
+        String::new()
+      },
+      "overline" | "bar" => format!("<mover>{}<mo stretchy=\"true\">\u{af}</mo></mover>", self.argument()),
+      "underline" => format!("<munder>{}<mo stretchy=\"true\">_</mo></munder>", self.argument()),
+      "hat" | "widehat" => format!("<mover>{}<mo>^</mo></mover>", self.argument()),
+      "tilde" | "widetilde" => format!("<mover>{}<mo>~</mo></mover>", self.argument()),
+      "vec" => format!("<mover>{}<mo>\u{2192}</mo></mover>", self.argument()),
+      "dot" => format!("<mover>{}<mo>\u{2d9}</mo></mover>", self.argument()),
+      "ddot" => format!("<mover>{}<mo>\u{a8}</mo></mover>", self.argument()),
+      "mathrm" | "mathbf" | "mathit" | "mathbb" | "mathcal" | "mathfrak" | "mathsf" | "mathtt" | "boldsymbol" => {
+        let variant = match name {
+          "mathrm" => "normal",
+          "mathbf" | "boldsymbol" => "bold",
+          "mathit" => "italic",
+          "mathbb" => "double-struck",
+          "mathcal" => "script",
+          "mathfrak" => "fraktur",
+          "mathsf" => "sans-serif",
+          _ => "monospace",
+        };
+        let text = self.text_argument();
+        format!("<mi mathvariant=\"{}\">{}</mi>", variant, escape_html(&text))
+      },
+      "," | ":" | ";" | " " | "quad" | "qquad" | "!" => {
+        let width = match name {
+          "," => "0.167em",
+          ":" => "0.222em",
+          ";" => "0.278em",
+          " " => "0.25em",
+          "quad" => "1em",
+          "qquad" => "2em",
+          _ => "0",
+        };
+        format!("<mspace width=\"{}\"></mspace>", width)
+      },
+      "\\" => String::new(),
+      _ => {
+        if let Some(c) = greek(name) {
+          format!("<mi>{}</mi>", c)
+        } else if let Some((c, limits)) = big_operator(name) {
+          return (format!("<mo>{}</mo>", c), limits && self.display);
+        } else if let Some(c) = operator(name) {
+          format!("<mo>{}</mo>", escape_html(&c.to_string()))
+        } else if FUNCTIONS.contains(&name) {
+          // limits go under these in displayed formulas, like \lim_{x \to 0}
+          let limits = LIMIT_FUNCTIONS.contains(&name) && self.display;
+          return (format!("<mi>{}</mi>", name), limits);
+        } else {
+          error(&format!("\\{}", name))
+        }
+      },
+    };
+
+    (atom, false)
+  }
+
+  /// An environment like `\begin{pmatrix} ... \end{pmatrix}`, after its `\begin`.
+  fn environment(&mut self) -> String {
+    let name = self.text_argument();
+
+    let mut rows = Vec::new();
+    let mut cells = Vec::new();
+    loop {
+      cells.push(format!("<mtd>{}</mtd>", mrow(self.row(Stop::Cell))));
+      match self.next() {
+        Some(Token::Align) => {},
+        Some(Token::Command(c)) if c == "\\" => {
+          rows.push(format!("<mtr>{}</mtr>", cells.concat()));
+          cells.clear();
+        },
+        Some(Token::Command(c)) if c == "end" => {
+          self.text_argument();
+          break;
+        },
+        _ => break,
+      }
+    }
+    // a trailing \\ leaves an empty last row
+    if cells.len() > 1 || cells.first().map(|c| c != "<mtd><mrow></mrow></mtd>").unwrap_or(false) {
+      rows.push(format!("<mtr>{}</mtr>", cells.concat()));
+    }
+
+    let align = match name.as_str() {
+      "cases" | "aligned" | "align" | "align*" | "split" => " columnalign=\"left\"",
+      _ => "",
+    };
+    let table = format!("<mtable{}>{}</mtable>", align, rows.concat());
+
+    let (open, close) = match name.as_str() {
+      "pmatrix" => ("(", ")"),
+      "bmatrix" => ("[", "]"),
+      "Bmatrix" => ("{", "}"),
+      "vmatrix" => ("|", "|"),
+      "Vmatrix" => ("\u{2016}", "\u{2016}"),
+      "cases" => ("{", ""),
+      _ => return table,
+    };
+
+    let fence = |f: &str| if f.is_empty() {
+      String::new()
+    } else {
+      format!("<mo fence=\"true\" stretchy=\"true\">{}</mo>", f)
+    };
+    format!("<mrow>{}{}{}</mrow>", fence(open), table, fence(close))
+  }
+}
+
+fn mrow(items: Vec<String>) -> String {
+  if items.len() == 1 {
+    return items.into_iter().next().unwrap_or_default();
+  }
+
+  format!("<mrow>{}</mrow>", items.concat())
+}
+
+fn error(text: &str) -> String {
+  format!("<merror><mtext>{}</mtext></merror>", escape_html(text))
+}
+
+fn too_deep() -> String {
+  error("formula is nested too deeply")
+}
+
+fn token_text(token: &Token) -> String {
+  match token {
+    Token::Command(c) if c.len() == 1 => c.clone(),
+    Token::Command(c) => format!("\\{}", c),
+    Token::Open => "{".into(),
+    Token::Close => "}".into(),
+    Token::Sup => "^".into(),
+    Token::Sub => "_".into(),
+    Token::Align => "&".into(),
+    Token::Number(n) => n.clone(),
+    Token::Letter(c) | Token::Symbol(c) => c.to_string(),
+  }
+}
+
+/// The text of a delimiter after `\left`, `\right` and the like. `.` means no delimiter.
+fn delimiter(token: &Token) -> String {
+  match token {
+    Token::Symbol('.') => String::new(),
+    Token::Command(c) => match c.as_str() {
+      "{" | "lbrace" => "{".into(),
+      "}" | "rbrace" => "}".into(),
+      "langle" => "\u{27e8}".into(),
+      "rangle" => "\u{27e9}".into(),
+      "lfloor" => "\u{230a}".into(),
+      "rfloor" => "\u{230b}".into(),
+      "lceil" => "\u{2308}".into(),
+      "rceil" => "\u{2309}".into(),
+      "|" | "Vert" => "\u{2016}".into(),
+      "vert" => "|".into(),
+      _ => String::new(),
+    },
+    token => token_text(token),
+  }
+}
+
+fn symbol_char(c: char) -> char {
+  match c {
+    '-' => '\u{2212}',
+    '*' => '\u{2217}',
+    c => c,
+  }
+}
+
+fn greek(name: &str) -> Option<char> {
+  let c = match name {
+    "alpha" => 'α',
+    "beta" => 'β',
+    "gamma" => 'γ',
+    "delta" => 'δ',
+    "epsilon" => 'ϵ',
+    "varepsilon" => 'ε',
+    "zeta" => 'ζ',
+    "eta" => 'η',
+    "theta" => 'θ',
+    "vartheta" => 'ϑ',
+    "iota" => 'ι',
+    "kappa" => 'κ',
+    "lambda" => 'λ',
+    "mu" => 'μ',
+    "nu" => 'ν',
+    "xi" => 'ξ',
+    "pi" => 'π',
+    "varpi" => 'ϖ',
+    "rho" => 'ρ',
+    "varrho" => 'ϱ',
+    "sigma" => 'σ',
+    "varsigma" => 'ς',
+    "tau" => 'τ',
+    "upsilon" => 'υ',
+    "phi" => 'ϕ',
+    "varphi" => 'φ',
+    "chi" => 'χ',
+    "psi" => 'ψ',
+    "omega" => 'ω',
+    "Gamma" => 'Γ',
+    "Delta" => 'Δ',
+    "Theta" => 'Θ',
+    "Lambda" => 'Λ',
+    "Xi" => 'Ξ',
+    "Pi" => 'Π',
+    "Sigma" => 'Σ',
+    "Upsilon" => 'Υ',
+    "Phi" => 'Φ',
+    "Psi" => 'Ψ',
+    "Omega" => 'Ω',
+    "infty" => '∞',
+    "partial" => '∂',
+    "nabla" => '∇',
+    "hbar" => 'ℏ',
+    "ell" => 'ℓ',
+    "emptyset" | "varnothing" => '∅',
+    _ => return None,
+  };
+
+  Some(c)
+}
+
+/// Big operators and whether their limits go under and over them in displayed formulas.
+fn big_operator(name: &str) -> Option<(char, bool)> {
+  let op = match name {
+    "sum" => ('∑', true),
+    "prod" => ('∏', true),
+    "coprod" => ('∐', true),
+    "bigcup" => ('⋃', true),
+    "bigcap" => ('⋂', true),
+    "bigoplus" => ('⨁', true),
+    "bigotimes" => ('⨂', true),
+    "int" => ('∫', false),
+    "iint" => ('∬', false),
+    "iiint" => ('∭', false),
+    "oint" => ('∮', false),
+    _ => return None,
+  };
+
+  Some(op)
+}
+
+fn operator(name: &str) -> Option<char> {
+  let c = match name {
+    "{" | "lbrace" => '{',
+    "}" | "rbrace" => '}',
+    "%" => '%',
+    "$" => '$',
+    "#" => '#',
+    "_" => '_',
+    "&" => '&',
+    "|" => '‖',
+    "pm" => '±',
+    "mp" => '∓',
+    "times" => '×',
+    "div" => '÷',
+    "cdot" => '⋅',
+    "ast" => '∗',
+    "star" => '⋆',
+    "circ" => '∘',
+    "bullet" => '∙',
+    "oplus" => '⊕',
+    "otimes" => '⊗',
+    "cup" => '∪',
+    "cap" => '∩',
+    "setminus" => '∖',
+    "wedge" | "land" => '∧',
+    "vee" | "lor" => '∨',
+    "neg" | "lnot" => '¬',
+    "leq" | "le" => '≤',
+    "geq" | "ge" => '≥',
+    "neq" | "ne" => '≠',
+    "ll" => '≪',
+    "gg" => '≫',
+    "approx" => '≈',
+    "equiv" => '≡',
+    "sim" => '∼',
+    "simeq" => '≃',
+    "cong" => '≅',
+    "propto" => '∝',
+    "in" => '∈',
+    "notin" => '∉',
+    "ni" => '∋',
+    "subset" => '⊂',
+    "supset" => '⊃',
+    "subseteq" => '⊆',
+    "supseteq" => '⊇',
+    "forall" => '∀',
+    "exists" => '∃',
+    "mid" => '∣',
+    "parallel" => '∥',
+    "perp" => '⊥',
+    "angle" => '∠',
+    "to" | "rightarrow" => '→',
+    "leftarrow" | "gets" => '←',
+    "leftrightarrow" => '↔',
+    "Rightarrow" | "implies" => '⇒',
+    "Leftarrow" => '⇐',
+    "Leftrightarrow" | "iff" => '⇔',
+    "mapsto" => '↦',
+    "uparrow" => '↑',
+    "downarrow" => '↓',
+    "langle" => '⟨',
+    "rangle" => '⟩',
+    "lfloor" => '⌊',
+    "rfloor" => '⌋',
+    "lceil" => '⌈',
+    "rceil" => '⌉',
+    "ldots" | "dots" => '…',
+    "cdots" => '⋯',
+    "vdots" => '⋮',
+    "ddots" => '⋱',
+    "prime" => '′',
+    "degree" => '°',
+    _ => return None,
+  };
+
+  Some(c)
+}
+
+/// Functions set upright, as in \sin x.
+const FUNCTIONS: &[&str] = &[
+  "sin", "cos", "tan", "cot", "sec", "csc", "arcsin", "arccos", "arctan", "sinh", "cosh", "tanh",
+  "log", "ln", "lg", "exp", "lim", "liminf", "limsup", "max", "min", "sup", "inf", "det", "dim",
+  "ker", "deg", "gcd", "arg", "Pr", "mod",
+];
+
+/// Functions that take their limits under them in displayed formulas.
+const LIMIT_FUNCTIONS: &[&str] = &["lim", "liminf", "limsup", "max", "min", "sup", "inf", "det", "gcd", "Pr"];
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn deep_nesting() {
+    let inputs = [
+      "x".to_string() + &"^".repeat(10_000),
+      "{".repeat(10_000) + "x" + &"}".repeat(10_000),
+      "\\frac".repeat(10_000),
+      "\\hat".repeat(10_000),
+      "x_{".repeat(10_000),
+    ];
+
+    for input in &inputs {
+      assert!(to_mathml(input, false).contains("<merror>"));
+    }
+  }
+
+  #[test]
+  fn shallow_nesting() {
+    let mathml = to_mathml(&("{".repeat(10) + "x" + &"}".repeat(10)), false);
+    assert!(!mathml.contains("<merror>"));
+  }
+}
@@ -1,4 +1,8 @@
-use crate::models::paste::{Content, Paste, PasteFile};
+use crate::{
+  config::Config,
+  models::paste::{Content, Paste, PasteFile},
+  utils::extract,
+};
 
 use mime::Mime;
 
@@ -6,11 +10,12 @@ use multipart::server::Multipart;
 
 use rocket::{
   Request, Data, Outcome,
+  State,
   http::Status,
   data::{self, FromDataSimple},
 };
 
-use std::io::Read;
+use std::io::{self, Read};
 
 lazy_static! {
   static ref JSON: Mime = "application/json".parse().unwrap();
@@ -67,6 +72,14 @@ impl FromDataSimple for MultipartUpload {
       Err(e) => return Outcome::Failure((Status::BadRequest, e.to_string())),
     };
 
+    let (max_files, max_file_size, max_archive_size) = match request.guard::<State<Config>>() {
+      Outcome::Success(config) => {
+        let config = config.read();
+        (config.pastes.max_files, config.pastes.max_file_size, config.pastes.max_archive_size)
+      },
+      _ => return Outcome::Failure((Status::InternalServerError, "missing config".into())),
+    };
+
     let mut files = Vec::with_capacity(1);
     loop {
       match entry.next_entry_inplace() {
@@ -75,6 +88,35 @@ impl FromDataSimple for MultipartUpload {
         _ => {},
       }
 
+      // archives are extracted into one file per entry
+      if entry.headers.content_type.as_ref().map(extract::is_archive_type).unwrap_or(false) {
+        let mut data = Vec::new();
+        if let Err(e) = (&mut entry.data).take(max_archive_size + 1).read_to_end(&mut data) {
+          return Outcome::Failure((Status::InternalServerError, e.to_string()));
+        }
+        if data.len() as u64 > max_archive_size {
+          return Outcome::Failure((Status::BadRequest, format!("archives can be at most {} bytes", max_archive_size)));
+        }
+
+        // every part shares the paste's allowance, so several archives can't add more files together
+        let remaining = max_files.saturating_sub(paste.files.len() + files.len());
+        let extracted = match extract::extract(&data, remaining, max_file_size) {
+          Ok(e) => e,
+          Err(extract::ExtractError::TooManyFiles(_)) => return Outcome::Failure((
+            Status::BadRequest,
+            format!("pastes can have at most {} files", max_files),
+          )),
+          Err(e) => return Outcome::Failure((Status::BadRequest, e.to_string())),
+        };
+
+        files.extend(extracted.into_iter().map(|x| PasteFile {
+          name: Some(x.name.into()),
+          highlight_language: None,
+          content: x.content,
+        }));
+        continue;
+      }
+
       let mut data = Vec::new();
       if let Err(e) = entry.data.read_to_end(&mut data) {
         return Outcome::Failure((Status::InternalServerError, e.to_string()));
@@ -106,3 +148,99 @@ impl FromDataSimple for MultipartUpload {
     Outcome::Success(MultipartUpload(paste))
   }
 }
+
+/// The file inputs on the web forms. Files sent under any other name are skipped without being read
+/// into memory.
+const FILE_FIELDS: &[&str] = &["archive"];
+
+/// A `multipart/form-data` web form, with its text fields and any uploaded files.
+pub struct MultipartForm {
+  pub fields: Vec<(String, String)>,
+  pub files: Vec<(String, Vec<u8>)>,
+}
+
+impl MultipartForm {
+  /// The text fields encoded as `application/x-www-form-urlencoded`, to be parsed with `FromForm`.
+  pub fn encoded_fields(&self) -> String {
+    url::form_urlencoded::Serializer::new(String::new())
+      .extend_pairs(&self.fields)
+      .finish()
+  }
+
+  /// Removes and returns the contents of the file uploaded with the field `name`.
+  pub fn take_file(&mut self, name: &str) -> Option<Vec<u8>> {
+    let idx = self.files.iter().position(|(n, _)| n == name)?;
+    Some(self.files.remove(idx).1)
+  }
+}
+
+impl FromDataSimple for MultipartForm {
+  type Error = String;
+
+  fn from_data(request: &Request, data: Data) -> data::Outcome<Self, Self::Error> {
+    let boundary = match request.content_type() {
+      Some(ct) if ct.is_form_data() => {
+        match ct.params().find(|&(key, _)| key == "boundary").map(|(_, value)| value) {
+          Some(b) => b,
+          None => return Outcome::Forward(data),
+        }
+      },
+      _ => return Outcome::Forward(data),
+    };
+
+    // every file shares one limit, so sending many files doesn't use any more memory
+    let mut files_left = match request.guard::<State<Config>>() {
+      Outcome::Success(config) => config.read().pastes.max_archive_size,
+      _ => return Outcome::Failure((Status::InternalServerError, "missing config".into())),
+    };
+    // text fields get the same limit as a normal form
+    let mut fields_left = request.limits().get("forms").unwrap_or(32 * 1024);
+
+    let mut mp = Multipart::with_body(data.open(), boundary);
+
+    let mut fields = Vec::new();
+    let mut files = Vec::new();
+    loop {
+      let mut entry = match mp.read_entry() {
+        Ok(Some(e)) => e,
+        Ok(None) => break,
+        Err(e) => return Outcome::Failure((Status::InternalServerError, e.to_string())),
+      };
+
+      let name = entry.headers.name.to_string();
+      let is_file = entry.headers.filename.is_some();
+      if is_file && !FILE_FIELDS.contains(&name.as_str()) {
+        if let Err(e) = io::copy(&mut entry.data, &mut io::sink()) {
+          return Outcome::Failure((Status::InternalServerError, e.to_string()));
+        }
+        continue;
+      }
+      let limit = if is_file { files_left } else { fields_left };
+
+      let mut value = Vec::new();
+      if let Err(e) = entry.data.take(limit + 1).read_to_end(&mut value) {
+        return Outcome::Failure((Status::InternalServerError, e.to_string()));
+      }
+      if value.len() as u64 > limit {
+        return Outcome::Failure((Status::PayloadTooLarge, format!("{} is too large", name)));
+      }
+
+      if is_file {
+        files_left -= value.len() as u64;
+        // browsers send an empty file for file inputs left empty
+        if !value.is_empty() {
+          files.push((name, value));
+        }
+        continue;
+      }
+
+      fields_left -= value.len() as u64;
+      match String::from_utf8(value) {
+        Ok(v) => fields.push((name, v)),
+        Err(_) => return Outcome::Failure((Status::BadRequest, format!("{} is not valid utf-8", name))),
+      }
+    }
+
+    Outcome::Success(MultipartForm { fields, files })
+  }
+}
@@ -0,0 +1,298 @@
+use crate::{
+  config::Oidc,
+  errors::*,
+};
+
+use chrono::Utc;
+
+use data_encoding::BASE64URL_NOPAD;
+
+use hashbrown::HashMap;
+
+use parking_lot::RwLock;
+
+use rand::{Rng, distributions::Alphanumeric};
+
+use reqwest::blocking::Client;
+
+use ring::signature;
+
+use sha2::{Digest, Sha256};
+
+use std::time::{Duration, Instant};
+
+use untrusted::Input;
+
+// refetch discovery documents and keys after an hour
+const CACHE_TIME: Duration = Duration::from_secs(60 * 60);
+
+lazy_static! {
+  static ref PROVIDERS: RwLock<HashMap<String, (Instant, Provider)>> = Default::default();
+}
+
+/// The parts of an OpenID Connect discovery document that are used.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Discovery {
+  pub issuer: String,
+  pub authorization_endpoint: String,
+  pub token_endpoint: String,
+  pub jwks_uri: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Jwks {
+  keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Jwk {
+  kty: String,
+  #[serde(default)]
+  kid: Option<String>,
+  #[serde(default)]
+  n: Option<String>,
+  #[serde(default)]
+  e: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Provider {
+  pub discovery: Discovery,
+  jwks: Jwks,
+}
+
+impl Provider {
+  /// Gets the provider for the issuer in the config, using a cached copy if possible.
+  pub fn get(client: &Client, config: &Oidc) -> Result<Self> {
+    if let Some((fetched, provider)) = PROVIDERS.read().get(&config.issuer) {
+      if fetched.elapsed() < CACHE_TIME {
+        return Ok(provider.clone());
+      }
+    }
+
+    let provider = Provider::fetch(client, &config.issuer)?;
+    PROVIDERS.write().insert(config.issuer.clone(), (Instant::now(), provider.clone()));
+    Ok(provider)
+  }
+
+  fn fetch(client: &Client, issuer: &str) -> Result<Self> {
+    let url = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
+    let discovery: Discovery = client.get(&url).send()?.error_for_status()?.json()?;
+
+    // the discovery document must be for the issuer it was requested from
+    if discovery.issuer.trim_end_matches('/') != issuer.trim_end_matches('/') {
+      anyhow::bail!("oidc discovery document is for a different issuer: {}", discovery.issuer);
+    }
+
+    let jwks: Jwks = client.get(&discovery.jwks_uri).send()?.error_for_status()?.json()?;
+
+    Ok(Provider { discovery, jwks })
+  }
+
+  /// Builds the URL to send the user to in order to authenticate.
+  pub fn authorization_url(&self, config: &Oidc, redirect_uri: &str, auth: &AuthRequest) -> Result<url::Url> {
+    let mut url = url::Url::parse(&self.discovery.authorization_endpoint)?;
+    url.query_pairs_mut()
+      .append_pair("response_type", "code")
+      .append_pair("client_id", &config.client_id)
+      .append_pair("redirect_uri", redirect_uri)
+      .append_pair("scope", &config.scopes.join(" "))
+      .append_pair("state", &auth.state)
+      .append_pair("nonce", &auth.nonce)
+      .append_pair("code_challenge", &auth.code_challenge())
+      .append_pair("code_challenge_method", "S256");
+    Ok(url)
+  }
+
+  /// Exchanges an authorisation code for an ID token and validates it.
+  pub fn exchange(&self, client: &Client, config: &Oidc, redirect_uri: &str, auth: &AuthRequest, code: &str) -> Result<IdClaims> {
+    let mut params = vec![
+      ("grant_type", "authorization_code"),
+      ("code", code),
+      ("redirect_uri", redirect_uri),
+      ("client_id", config.client_id.as_str()),
+      ("code_verifier", auth.code_verifier.as_str()),
+    ];
+    if let Some(ref secret) = config.client_secret {
+      params.push(("client_secret", secret.as_str()));
+    }
+
+    let resp: TokenResponse = client
+      .post(&self.discovery.token_endpoint)
+      .form(&params)
+      .send()?
+      .error_for_status()?
+      .json()?;
+
+    let claims = self.validate(config, &resp.id_token)?;
+
+    if claims.nonce.as_deref() != Some(&auth.nonce) {
+      anyhow::bail!("oidc id token nonce did not match");
+    }
+
+    Ok(claims)
+  }
+
+  fn validate(&self, config: &Oidc, id_token: &str) -> Result<IdClaims> {
+    let parts: Vec<&str> = id_token.split('.').collect();
+    let (raw_header, raw_claims, raw_sig) = match *parts {
+      [header, claims, sig] => (header, claims, sig),
+      _ => anyhow::bail!("malformed oidc id token"),
+    };
+
+    let header: Header = serde_json::from_slice(&BASE64URL_NOPAD.decode(raw_header.as_bytes())?)?;
+
+    // only rsa keys are supported
+    let alg = match header.alg.as_str() {
+      "RS256" => &signature::RSA_PKCS1_2048_8192_SHA256,
+      "RS384" => &signature::RSA_PKCS1_2048_8192_SHA384,
+      "RS512" => &signature::RSA_PKCS1_2048_8192_SHA512,
+      alg => anyhow::bail!("unsupported oidc id token algorithm: {}", alg),
+    };
+
+    let key = self.jwks.keys
+      .iter()
+      .filter(|key| key.kty == "RSA")
+      .find(|key| header.kid.is_none() || key.kid == header.kid)
+      .ok_or_else(|| anyhow::anyhow!("no matching key for oidc id token"))?;
+    let (n, e) = match (&key.n, &key.e) {
+      (Some(n), Some(e)) => (BASE64URL_NOPAD.decode(n.as_bytes())?, BASE64URL_NOPAD.decode(e.as_bytes())?),
+      _ => anyhow::bail!("oidc key is missing its modulus or exponent"),
+    };
+
+    // the signature covers the encoded header and claims
+    let message = &id_token[..raw_header.len() + 1 + raw_claims.len()];
+    let sig = BASE64URL_NOPAD.decode(raw_sig.as_bytes())?;
+    signature::primitive::verify_rsa(
+      alg,
+      (Input::from(&n), Input::from(&e)),
+      Input::from(message.as_bytes()),
+      Input::from(&sig),
+    ).map_err(|_| anyhow::anyhow!("invalid oidc id token signature"))?;
+
+    let claims = BASE64URL_NOPAD.decode(raw_claims.as_bytes())?;
+
+    let registered: RegisteredClaims = serde_json::from_slice(&claims)?;
+    if registered.iss != self.discovery.issuer {
+      anyhow::bail!("oidc id token is for a different issuer: {}", registered.iss);
+    }
+    if !registered.aud.contains(&config.client_id) {
+      anyhow::bail!("oidc id token is for a different client");
+    }
+    if registered.exp < Utc::now().timestamp() {
+      anyhow::bail!("oidc id token has expired");
+    }
+
+    Ok(serde_json::from_slice(&claims)?)
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+  alg: String,
+  #[serde(default)]
+  kid: Option<String>,
+}
+
+/// The claims in an ID token that have to be checked before it can be trusted.
+#[derive(Debug, Deserialize)]
+struct RegisteredClaims {
+  iss: String,
+  aud: Audience,
+  exp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Audience {
+  One(String),
+  Many(Vec<String>),
+}
+
+impl Audience {
+  fn contains(&self, client_id: &str) -> bool {
+    match *self {
+      Audience::One(ref aud) => aud == client_id,
+      Audience::Many(ref auds) => auds.iter().any(|aud| aud == client_id),
+    }
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+  id_token: String,
+}
+
+/// The claims in an ID token that are used.
+#[derive(Debug, Deserialize)]
+pub struct IdClaims {
+  pub sub: String,
+  #[serde(default)]
+  pub nonce: Option<String>,
+  #[serde(default)]
+  pub email: Option<String>,
+  #[serde(default, deserialize_with = "lenient_bool")]
+  pub email_verified: bool,
+  #[serde(default)]
+  pub name: Option<String>,
+  #[serde(default)]
+  pub preferred_username: Option<String>,
+}
+
+impl IdClaims {
+  /// The user's email, if the provider has verified it.
+  pub fn verified_email(&self) -> Option<&str> {
+    if self.email_verified {
+      self.email.as_deref()
+    } else {
+      None
+    }
+  }
+}
+
+// some providers send email_verified as a string
+fn lenient_bool<'de, D>(des: D) -> std::result::Result<bool, D::Error>
+  where D: serde::Deserializer<'de>,
+{
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Lenient {
+    Bool(bool),
+    String(String),
+  }
+
+  Ok(match serde::Deserialize::deserialize(des)? {
+    Lenient::Bool(b) => b,
+    Lenient::String(s) => s == "true",
+  })
+}
+
+/// The state kept in the session between sending the user to the provider and them coming back.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthRequest {
+  pub state: String,
+  nonce: String,
+  code_verifier: String,
+}
+
+impl AuthRequest {
+  pub fn generate() -> Self {
+    fn random(len: usize) -> String {
+      rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+    }
+
+    AuthRequest {
+      state: random(32),
+      nonce: random(32),
+      code_verifier: random(64),
+    }
+  }
+
+  fn code_challenge(&self) -> String {
+    BASE64URL_NOPAD.encode(&Sha256::digest(self.code_verifier.as_bytes()))
+  }
+}
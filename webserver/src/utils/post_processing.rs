@@ -1,4 +1,7 @@
-use crate::config::Config;
+use crate::{
+  config::Config,
+  models::id::FileId,
+};
 
 use data_encoding::HEXLOWER;
 
@@ -12,11 +15,28 @@ use html5ever::{
 
 use markup5ever_rcdom::{NodeData, RcDom, Handle, SerializableHandle};
 
+use hashbrown::HashMap;
+
 use hmac::{Hmac, Mac, NewMac};
 use sha1::Sha1;
 
+use rocket::http::RawStr;
+
 use url::{Url, ParseError as UrlParseError};
 
+/// Where a rendered file is shown in its paste, for fixing up the file's ids and links.
+pub struct FileLinks<'a> {
+  /// Put in front of every id in the file, keeping them apart from the page's and other files' ids.
+  pub id_prefix: String,
+  /// The paste's files by name, with their ids and raw urls.
+  pub files: &'a HashMap<String, (FileId, String)>,
+}
+
+/// The prefix for ids in a rendered file.
+pub fn id_prefix(file: FileId) -> String {
+  format!("user-content-{}-", file)
+}
+
 fn make_parser() -> Parser<RcDom> {
   parse_fragment(
     RcDom::default(),
@@ -31,9 +51,15 @@ struct Context {
   first_in_li: bool,
 }
 
-fn walk(config: &Config, handle: Handle, external: &Attribute, ctx: &mut Context) -> bool {
+fn walk(config: &Config, handle: Handle, external: &Attribute, links: &FileLinks, ctx: &mut Context) -> bool {
   let node = handle;
 
+  if let NodeData::Element { ref attrs, .. } = node.data {
+    if let Some(id) = attrs.borrow_mut().iter_mut().find(|x| &*x.name.local == "id") {
+      id.value = format!("{}{}", links.id_prefix, id.value).into();
+    }
+  }
+
   match node.data {
     NodeData::Element { ref name, .. } if &*name.local == "li" => ctx.first_in_li = true,
     NodeData::Text { ref contents } if contents.borrow().trim() == "" => {},
@@ -63,6 +89,14 @@ fn walk(config: &Config, handle: Handle, external: &Attribute, ctx: &mut Context
 
   match node.data {
     NodeData::Element { ref name, ref attrs, .. } if &*name.local == "img" => {
+      // images of other files in the paste are shown straight from the paste
+      if let Some(url_attr) = attrs.borrow_mut().iter_mut().find(|x| &*x.name.local == "src") {
+        if let Some((_, raw, _)) = paste_file(links, &url_attr.value) {
+          url_attr.value = raw.into();
+          return true;
+        }
+      }
+
       let mut new_url = match crate::CAMO_URL.as_ref() {
         Some(u) => u.clone(),
         None => return true,
@@ -78,6 +112,11 @@ fn walk(config: &Config, handle: Handle, external: &Attribute, ctx: &mut Context
         Err(_) => return true,
       };
 
+      // images embedded in the page don't load anything, so there's nothing to proxy
+      if url.scheme() == "data" {
+        return true;
+      }
+
       let mut hmac = Hmac::<Sha1>::new_from_slice(&crate::CAMO_KEY)
         .expect("HMAC can take key of any size");
       hmac.update(url.as_str().as_bytes());
@@ -97,6 +136,22 @@ fn walk(config: &Config, handle: Handle, external: &Attribute, ctx: &mut Context
       url_attr.value = new_url.into();
     },
     NodeData::Element { ref name, ref attrs, .. } if &*name.local == "a" => {
+      if let Some(href) = attrs.borrow_mut().iter_mut().find(|x| &*x.name.local == "href") {
+        // ids were prefixed above, so links to them need to be too
+        let new_href = if let Some(fragment) = href.value.strip_prefix('#') {
+          Some(format!("#{}{}", links.id_prefix, fragment))
+        } else {
+          // links to other files in the paste go to where they're shown on the page
+          paste_file(links, &href.value).map(|(id, _, fragment)| match fragment {
+            Some(fragment) if !fragment.is_empty() => format!("#{}{}", id_prefix(id), fragment),
+            _ => format!("#{}-title", id),
+          })
+        };
+        if let Some(new_href) = new_href {
+          href.value = new_href.into();
+        }
+      }
+
       let url = attrs
         .borrow()
         .iter()
@@ -123,12 +178,35 @@ fn walk(config: &Config, handle: Handle, external: &Attribute, ctx: &mut Context
   node
     .children
     .borrow_mut()
-    .retain(|child| walk(config, child.clone(), external, ctx));
+    .retain(|child| walk(config, child.clone(), external, links, ctx));
 
   true
 }
 
-pub fn process(config: &Config, src: &str) -> String {
+/// Finds the file in the paste that a relative url like `other.md` or `./images/a.png#x` points to,
+/// returning its id, its raw url and the url's fragment.
+fn paste_file<'a>(links: &'a FileLinks, url: &str) -> Option<(FileId, &'a str, Option<String>)> {
+  match Url::parse(url) {
+    Err(UrlParseError::RelativeUrlWithoutBase) => {},
+    _ => return None,
+  }
+  if url.starts_with('/') || url.starts_with('#') {
+    return None;
+  }
+
+  let (path, fragment) = match url.split_once('#') {
+    Some((path, fragment)) => (path, Some(fragment.to_string())),
+    None => (url, None),
+  };
+  let path = path.split('?').next().unwrap_or_default();
+  let path = path.trim_start_matches("./");
+  let name = RawStr::from_str(path).percent_decode().ok()?;
+
+  let (id, raw) = links.files.get(&*name)?;
+  Some((*id, raw.as_str(), fragment))
+}
+
+pub fn process(config: &Config, src: &str, links: &FileLinks) -> String {
   let external = Attribute {
     name: QualName::new(None, ns!(), local_name!("class")),
     value: "external".into(),
@@ -140,7 +218,7 @@ pub fn process(config: &Config, src: &str) -> String {
 
   let mut ctx = Context::default();
 
-  walk(config, dom.get_document(), &external, &mut ctx);
+  walk(config, dom.get_document(), &external, links, &mut ctx);
 
   let handle: SerializableHandle = dom.document.children.borrow()[0].clone().into();
 
@@ -0,0 +1,352 @@
+//! Responding with raw files, with the caching and partial downloads that browsers and download tools
+//! expect.
+//!
+//! Files are identified by the ID git gives their content, which makes a strong ETag: it only changes
+//! when the content does. Conditional requests are answered with `304 Not Modified`, and a single
+//! byte range can be asked for with `Range`.
+
+use crate::{
+  config::Config,
+  database::models::pastes::Paste as DbPaste,
+  errors::*,
+  models::paste::Visibility,
+  utils::{content_encoding::{self, Encoding}, images},
+};
+
+use chrono::{DateTime, Utc};
+
+use git2::{ObjectType, Oid};
+
+use rocket::{
+  Request,
+  http::{ContentType, Status as HttpStatus},
+  response::{self, Responder, Response},
+};
+
+use std::{
+  fs::File,
+  io::{self, Read, Seek, SeekFrom},
+  path::Path,
+};
+
+/// How long shared caches can keep public pastes' files before checking they haven't changed.
+const PUBLIC_CACHE_CONTROL: &str = "public, max-age=300";
+/// Files of other pastes are only for whoever has the link, so only their browser can keep them, and
+/// it has to check they're still allowed to see them each time.
+const PRIVATE_CACHE_CONTROL: &str = "private, no-cache";
+
+/// File signatures and the types of files that start with them, for files that aren't images.
+const SIGNATURES: &[(&[u8], &str)] = &[
+  (b"%PDF-", "application/pdf"),
+  (b"PK\x03\x04", "application/zip"),
+  (b"\x1f\x8b", "application/gzip"),
+  (b"BZh", "application/x-bzip2"),
+  (b"\xfd7zXZ\x00", "application/x-xz"),
+  (b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+  (b"\x28\xb5\x2f\xfd", "application/zstd"),
+  (b"ID3", "audio/mpeg"),
+  (b"\xff\xfb", "audio/mpeg"),
+  (b"OggS", "audio/ogg"),
+  (b"fLaC", "audio/flac"),
+  (b"\x1a\x45\xdf\xa3", "video/webm"),
+  (b"\x00asm", "application/wasm"),
+  (b"SQLite format 3\x00", "application/vnd.sqlite3"),
+];
+
+/// A raw file, ready to be sent in whole or in part.
+pub struct RawFile {
+  file: File,
+  etag: String,
+  last_modified: DateTime<Utc>,
+  encoding: Option<Encoding>,
+  cache_control: &'static str,
+}
+
+impl RawFile {
+  /// Opens the file at `path` from `paste`, compressed with `encoding` if that's worth it.
+  ///
+  /// `id` is the ID of the file's content from the paste's repository. The file is only hashed to
+  /// find it if it isn't given.
+  pub fn open(config: &Config, paste: &DbPaste, path: &Path, id: Option<Oid>, encoding: Option<Encoding>) -> Result<Self> {
+    let id = match id {
+      Some(id) => id,
+      None => Oid::hash_file(ObjectType::Blob, path)?,
+    };
+    // file contents are shared between pastes, so the file itself can be older than the paste
+    let last_modified = paste.updated_at(config).unwrap_or_else(|_| paste.created_at());
+    let (file, encoding) = content_encoding::open(config, path, id, encoding)?;
+
+    // each encoding is a different representation of the file, so needs its own strong etag
+    let etag = match encoding {
+      Some(e) => format!("\"{}-{}\"", id, e.name()),
+      None => format!("\"{}\"", id),
+    };
+
+    let cache_control = match paste.visibility() {
+      Visibility::Public => PUBLIC_CACHE_CONTROL,
+      Visibility::Unlisted | Visibility::Private => PRIVATE_CACHE_CONTROL,
+    };
+
+    Ok(RawFile {
+      file,
+      etag,
+      last_modified,
+      encoding,
+      cache_control,
+    })
+  }
+
+  /// Whether the client's cached copy is still current, going by `If-None-Match` or, failing that,
+  /// `If-Modified-Since`.
+  fn not_modified(&self, request: &Request) -> bool {
+    let mut tags = request.headers().get("If-None-Match").flat_map(|x| x.split(',')).peekable();
+    if tags.peek().is_some() {
+      // weak comparison, as the spec says for If-None-Match
+      return tags.map(str::trim).any(|tag| tag == "*" || tag.trim_start_matches("W/") == self.etag);
+    }
+
+    let since = request.headers().get_one("If-Modified-Since").and_then(parse_http_date);
+    match since {
+      Some(since) => self.last_modified.timestamp() <= since.timestamp(),
+      None => false,
+    }
+  }
+
+  /// The inclusive byte range the client asked for in a file `len` bytes long.
+  ///
+  /// Returns `Some(Err(()))` if the range can't be satisfied. Requests for more than one range get the
+  /// whole file, as do ones with a stale `If-Range`.
+  fn range(&self, request: &Request, len: u64) -> Option<std::result::Result<(u64, u64), ()>> {
+    let header = request.headers().get_one("Range")?;
+
+    if let Some(if_range) = request.headers().get_one("If-Range") {
+      let current = if if_range.starts_with('"') {
+        if_range == self.etag
+      } else {
+        parse_http_date(if_range).map(|x| x.timestamp()) == Some(self.last_modified.timestamp())
+      };
+      if !current {
+        return None;
+      }
+    }
+
+    parse_range(header, len)
+  }
+}
+
+impl<'r> Responder<'r> for RawFile {
+  fn respond_to(self, request: &Request) -> response::Result<'r> {
+    let mut response = Response::build();
+    response
+      .raw_header("ETag", self.etag.clone())
+      .raw_header("Cache-Control", self.cache_control)
+      .raw_header("Vary", "Accept-Encoding")
+      .raw_header("Last-Modified", self.last_modified.format("%a, %d %b %Y %H:%M:%S GMT").to_string());
+    if let Some(encoding) = self.encoding {
+      response.raw_header("Content-Encoding", encoding.name());
+    }
+
+    if self.not_modified(request) {
+      return response.status(HttpStatus::NotModified).ok();
+    }
+
+    let len = self.file.metadata().map_err(|_| HttpStatus::InternalServerError)?.len();
+    response.raw_header("Accept-Ranges", "bytes");
+
+    match self.range(request, len) {
+      None => response.sized_body(self.file).ok(),
+      Some(Ok((start, end))) => {
+        let part = Part::new(self.file, start, end - start + 1).map_err(|_| HttpStatus::InternalServerError)?;
+        response
+          .status(HttpStatus::PartialContent)
+          .raw_header("Content-Range", format!("bytes {}-{}/{}", start, end, len))
+          .sized_body(part)
+          .ok()
+      },
+      Some(Err(())) => response
+        .status(HttpStatus::RangeNotSatisfiable)
+        .raw_header("Content-Range", format!("bytes */{}", len))
+        .ok(),
+    }
+  }
+}
+
+/// The content type of the binary file at `path` called `name`, detected from how the file starts or,
+/// failing that, its extension. Also returns whether the file is safe to show inline.
+///
+/// Only images, audio and video are shown inline. Anything else could be interpreted by the browser
+/// as something else, like html, so is downloaded.
+pub fn content_type(path: &Path, name: &str) -> Result<(String, bool)> {
+  let mut start = Vec::with_capacity(16);
+  File::open(path)?.take(16).read_to_end(&mut start)?;
+
+  if let Some(format) = images::detect(&start) {
+    return Ok((images::content_type(format).into(), true));
+  }
+
+  let signature = SIGNATURES
+    .iter()
+    .find(|(signature, _)| start.starts_with(signature))
+    .map(|&(_, content_type)| content_type)
+    // mp4 and friends have their signature after the box size
+    .or_else(|| if start.get(4..8) == Some(&b"ftyp"[..]) { Some("video/mp4") } else { None });
+  let content_type = match signature {
+    Some(c) => c.to_string(),
+    None => name
+      .rsplit_once('.')
+      .and_then(|(_, ext)| ContentType::from_extension(ext))
+      .map(|c| c.to_string())
+      .unwrap_or_else(|| "application/octet-stream".into()),
+  };
+
+  let inline = content_type.starts_with("audio/") || content_type.starts_with("video/");
+  Ok((content_type, inline))
+}
+
+/// Parses a `Range` header into the inclusive byte range it asks for in a file `len` bytes long.
+///
+/// Returns `None` if the whole file should be sent instead, and `Some(Err(()))` if the range can't be
+/// satisfied.
+fn parse_range(header: &str, len: u64) -> Option<std::result::Result<(u64, u64), ()>> {
+  let spec = header.trim().strip_prefix("bytes=")?;
+  if spec.contains(',') {
+    return None;
+  }
+  let (start, end) = spec.split_once('-')?;
+  let (start, end) = (start.trim(), end.trim());
+
+  if start.is_empty() {
+    // the last `end` bytes
+    let suffix: u64 = end.parse().ok()?;
+    if suffix == 0 || len == 0 {
+      return Some(Err(()));
+    }
+    return Some(Ok((len.saturating_sub(suffix), len - 1)));
+  }
+
+  let start: u64 = start.parse().ok()?;
+  let end = match end {
+    "" => None,
+    end => Some(end.parse::<u64>().ok()?),
+  };
+  if end.map(|end| end < start).unwrap_or(false) {
+    return None;
+  }
+  if start >= len {
+    return Some(Err(()));
+  }
+
+  Some(Ok((start, end.unwrap_or(len - 1).min(len - 1))))
+}
+
+fn parse_http_date(date: &str) -> Option<DateTime<Utc>> {
+  DateTime::parse_from_rfc2822(date.trim()).ok().map(|x| x.with_timezone(&Utc))
+}
+
+/// Part of a file, which reads and seeks as if it were the whole thing.
+struct Part {
+  file: File,
+  start: u64,
+  len: u64,
+  pos: u64,
+}
+
+impl Part {
+  fn new(mut file: File, start: u64, len: u64) -> io::Result<Self> {
+    file.seek(SeekFrom::Start(start))?;
+
+    Ok(Part {
+      file,
+      start,
+      len,
+      pos: 0,
+    })
+  }
+}
+
+impl Read for Part {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let remaining = self.len.saturating_sub(self.pos);
+    let max = (buf.len() as u64).min(remaining) as usize;
+    if max == 0 {
+      return Ok(0);
+    }
+
+    let read = self.file.read(&mut buf[..max])?;
+    self.pos += read as u64;
+    Ok(read)
+  }
+}
+
+impl Seek for Part {
+  fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+    let (base, offset) = match pos {
+      SeekFrom::Start(n) => (n, 0),
+      SeekFrom::End(n) => (self.len, n),
+      SeekFrom::Current(n) => (self.pos, n),
+    };
+    let pos = if offset >= 0 {
+      base.checked_add(offset as u64)
+    } else {
+      base.checked_sub(offset.unsigned_abs())
+    };
+    let pos = pos.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek out of range"))?;
+
+    self.file.seek(SeekFrom::Start(self.start + pos))?;
+    self.pos = pos;
+    Ok(pos)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn single_range() {
+    assert_eq!(parse_range("bytes=0-9", 100), Some(Ok((0, 9))));
+    assert_eq!(parse_range("bytes=10-10", 100), Some(Ok((10, 10))));
+    assert_eq!(parse_range(" bytes=10 - 19 ", 100), Some(Ok((10, 19))));
+  }
+
+  #[test]
+  fn open_ended_range() {
+    assert_eq!(parse_range("bytes=90-", 100), Some(Ok((90, 99))));
+    assert_eq!(parse_range("bytes=0-", 100), Some(Ok((0, 99))));
+  }
+
+  #[test]
+  fn end_past_the_file() {
+    assert_eq!(parse_range("bytes=90-1000", 100), Some(Ok((90, 99))));
+  }
+
+  #[test]
+  fn suffix_range() {
+    assert_eq!(parse_range("bytes=-10", 100), Some(Ok((90, 99))));
+    assert_eq!(parse_range("bytes=-1000", 100), Some(Ok((0, 99))));
+    assert_eq!(parse_range("bytes=-0", 100), Some(Err(())));
+    assert_eq!(parse_range("bytes=-10", 0), Some(Err(())));
+  }
+
+  #[test]
+  fn start_out_of_bounds() {
+    assert_eq!(parse_range("bytes=100-", 100), Some(Err(())));
+    assert_eq!(parse_range("bytes=100-200", 100), Some(Err(())));
+    assert_eq!(parse_range("bytes=0-", 0), Some(Err(())));
+  }
+
+  #[test]
+  fn multiple_ranges() {
+    assert_eq!(parse_range("bytes=0-9,20-29", 100), None);
+    assert_eq!(parse_range("bytes=0-9, -10", 100), None);
+  }
+
+  #[test]
+  fn invalid_range() {
+    assert_eq!(parse_range("bytes=9-0", 100), None);
+    assert_eq!(parse_range("bytes=a-b", 100), None);
+    assert_eq!(parse_range("bytes=-", 100), None);
+    assert_eq!(parse_range("bytes=10", 100), None);
+    assert_eq!(parse_range("items=0-9", 100), None);
+  }
+}
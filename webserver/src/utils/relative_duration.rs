@@ -0,0 +1,177 @@
+use chrono::{DateTime, Duration, Utc};
+
+use serde::{
+  de::{Deserialize, Deserializer, Error as DeError},
+  ser::{Serialize, Serializer},
+};
+
+use std::{
+  fmt::{self, Display, Formatter},
+  str::FromStr,
+};
+
+/// The units a duration may be written in, largest first.
+const UNITS: &[(char, i64)] = &[
+  ('y', 365 * 24 * 60 * 60),
+  ('w', 7 * 24 * 60 * 60),
+  ('d', 24 * 60 * 60),
+  ('h', 60 * 60),
+  ('m', 60),
+  ('s', 1),
+];
+
+/// Keeps durations far enough from chrono's limits that adding them to the current time is safe.
+const MAX_SECONDS: i64 = 1000 * 365 * 24 * 60 * 60;
+
+/// A positive length of time written like `7d`, `12h` or `1w3d`.
+///
+/// Years are always 365 days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelativeDuration(Duration);
+
+impl RelativeDuration {
+  pub fn duration(self) -> Duration {
+    self.0
+  }
+
+  /// The time this long from now.
+  pub fn from_now(self) -> DateTime<Utc> {
+    Utc::now() + self.0
+  }
+}
+
+impl FromStr for RelativeDuration {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || format!("invalid duration (expected something like 7d or 12h): {}", s);
+
+    let mut seconds: i64 = 0;
+    let mut number = String::new();
+    for c in s.trim().chars() {
+      if c.is_ascii_digit() {
+        number.push(c);
+        continue;
+      }
+
+      let unit = UNITS
+        .iter()
+        .find(|(u, _)| *u == c.to_ascii_lowercase())
+        .map(|(_, secs)| *secs)
+        .ok_or_else(invalid)?;
+      let amount: i64 = number.parse().map_err(|_| invalid())?;
+      number.clear();
+
+      seconds = amount
+        .checked_mul(unit)
+        .and_then(|x| x.checked_add(seconds))
+        .filter(|x| *x <= MAX_SECONDS)
+        .ok_or_else(|| format!("duration is too long: {}", s))?;
+    }
+
+    // a trailing number with no unit, or nothing at all
+    if !number.is_empty() || seconds == 0 {
+      return Err(invalid());
+    }
+
+    Ok(RelativeDuration(Duration::seconds(seconds)))
+  }
+}
+
+impl Display for RelativeDuration {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    let mut seconds = self.0.num_seconds();
+    for (unit, secs) in UNITS {
+      if seconds >= *secs {
+        write!(f, "{}{}", seconds / secs, unit)?;
+        seconds %= secs;
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Serialize for RelativeDuration {
+  fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+    where S: Serializer,
+  {
+    ser.collect_str(self)
+  }
+}
+
+impl<'de> Deserialize<'de> for RelativeDuration {
+  fn deserialize<D>(de: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de>,
+  {
+    let s = String::deserialize(de)?;
+    RelativeDuration::from_str(&s).map_err(D::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn seconds(s: &str) -> Result<i64, String> {
+    RelativeDuration::from_str(s).map(|x| x.duration().num_seconds())
+  }
+
+  #[test]
+  fn units() {
+    assert_eq!(seconds("30s"), Ok(30));
+    assert_eq!(seconds("5m"), Ok(5 * 60));
+    assert_eq!(seconds("12h"), Ok(12 * 60 * 60));
+    assert_eq!(seconds("7d"), Ok(7 * 24 * 60 * 60));
+    assert_eq!(seconds("2w"), Ok(14 * 24 * 60 * 60));
+    assert_eq!(seconds("1y"), Ok(365 * 24 * 60 * 60));
+    assert_eq!(seconds("7D"), Ok(7 * 24 * 60 * 60));
+    assert_eq!(seconds(" 7d "), Ok(7 * 24 * 60 * 60));
+  }
+
+  #[test]
+  fn combined() {
+    assert_eq!(seconds("1d12h"), Ok(36 * 60 * 60));
+    assert_eq!(seconds("1w3d"), Ok(10 * 24 * 60 * 60));
+    assert_eq!(seconds("1h1h"), Ok(2 * 60 * 60));
+    assert_eq!(seconds("0d1s"), Ok(1));
+  }
+
+  #[test]
+  fn zero() {
+    assert!(seconds("0s").is_err());
+    assert!(seconds("0d0h").is_err());
+    assert!(seconds("").is_err());
+  }
+
+  #[test]
+  fn negative() {
+    assert!(seconds("-1d").is_err());
+    assert!(seconds("1d-1h").is_err());
+  }
+
+  #[test]
+  fn overflow() {
+    assert_eq!(seconds("1000y"), Ok(MAX_SECONDS));
+    assert!(seconds("1000y1s").is_err());
+    assert!(seconds("9223372036854775807y").is_err());
+    assert!(seconds("99999999999999999999s").is_err());
+  }
+
+  #[test]
+  fn garbage() {
+    assert!(seconds("7").is_err());
+    assert!(seconds("1d2").is_err());
+    assert!(seconds("d").is_err());
+    assert!(seconds("7x").is_err());
+    assert!(seconds("1.5d").is_err());
+    assert!(seconds("seven days").is_err());
+  }
+
+  #[test]
+  fn display() {
+    for s in &["30s", "1d12h", "1y2w3d4h5m6s"] {
+      assert_eq!(RelativeDuration::from_str(s).unwrap().to_string(), *s);
+    }
+  }
+}
@@ -0,0 +1,402 @@
+//! Validating JSON, YAML and TOML files, showing them as trees and pretty-printing them.
+
+use crate::{
+  errors::*,
+  i18n::L10n,
+  utils::Language,
+};
+
+use serde::{
+  de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor},
+  ser::{Serialize, SerializeMap, Serializer},
+};
+
+use tera::escape_html;
+
+use std::fmt;
+
+/// Trees nested deeper than this start collapsed.
+const OPEN_DEPTH: usize = 2;
+
+/// The key toml uses to pass datetimes through serde.
+const TOML_DATETIME: &str = "$__toml_private_datetime";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+  Json,
+  Yaml,
+  Toml,
+}
+
+impl Format {
+  /// The format of a file, going by its highlight language or, failing that, its name.
+  pub fn detect(name: &str, language: Option<Language>) -> Option<Format> {
+    let lower = name.to_lowercase();
+    let by_name = if lower.ends_with(".json") {
+      Some(Format::Json)
+    } else if lower.ends_with(".yaml") || lower.ends_with(".yml") {
+      Some(Format::Yaml)
+    } else if lower.ends_with(".toml") {
+      Some(Format::Toml)
+    } else {
+      None
+    };
+
+    match language {
+      None => by_name,
+      Some(Language::Json) => Some(Format::Json),
+      Some(Language::Yaml) => Some(Format::Yaml),
+      // toml is highlighted as ini
+      Some(Language::Ini) => by_name.filter(|&f| f == Format::Toml),
+      Some(_) => None,
+    }
+  }
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Format::Json => "JSON",
+      Format::Yaml => "YAML",
+      Format::Toml => "TOML",
+    }
+  }
+
+  /// The language to highlight pretty-printed files as.
+  pub fn hljs(self) -> &'static str {
+    match self {
+      Format::Json => Language::Json.hljs(),
+      Format::Yaml => Language::Yaml.hljs(),
+      Format::Toml => Language::Ini.hljs(),
+    }
+  }
+
+  /// Parses `content`, returning each document in it.
+  ///
+  /// Only YAML files can have more than one document.
+  pub fn parse(self, content: &str) -> std::result::Result<Vec<Node>, ParseError> {
+    match self {
+      Format::Json => serde_json::from_str(content)
+        .map(|x| vec![x])
+        .map_err(|e| ParseError::new(e.to_string(), Some((e.line(), e.column())))),
+      Format::Yaml => serde_yaml::Deserializer::from_str(content)
+        .map(Node::deserialize)
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|e| ParseError::new(e.to_string(), e.location().map(|l| (l.line(), l.column())))),
+      Format::Toml => toml::from_str(content)
+        .map(|x| vec![x])
+        // toml counts from zero
+        .map_err(|e| ParseError::new(e.to_string(), e.line_col().map(|(l, c)| (l + 1, c + 1)))),
+    }
+  }
+
+  /// Pretty-prints the documents parsed from a file.
+  pub fn pretty(self, documents: &[Node]) -> Result<String> {
+    let pretty = match self {
+      Format::Json => serde_json::to_string_pretty(&documents[0])?,
+      Format::Yaml => documents
+        .iter()
+        .map(serde_yaml::to_string)
+        .collect::<std::result::Result<Vec<_>, _>>()?
+        .concat(),
+      // toml needs tables after plain values, which its own value type takes care of
+      Format::Toml => toml::to_string_pretty(&documents[0].to_toml())?,
+    };
+
+    Ok(pretty)
+  }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ParseError {
+  pub message: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub line: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub column: Option<usize>,
+}
+
+impl ParseError {
+  fn new(message: String, position: Option<(usize, usize)>) -> Self {
+    // the parsers put the position at the end of the message, but it's given separately here, and
+    // some yaml errors only have it in the message
+    let (message, position) = match Self::split_position(&message) {
+      Some((rest, found)) => (rest.to_string(), position.or(Some(found))),
+      None => (message, position),
+    };
+
+    ParseError {
+      message,
+      line: position.map(|(l, _)| l),
+      column: position.map(|(_, c)| c),
+    }
+  }
+
+  /// Splits a message ending in "at line 1 column 2" into the rest of the message and the position.
+  fn split_position(message: &str) -> Option<(&str, (usize, usize))> {
+    let start = message.rfind(" at line ")?;
+    let mut parts = message[start + " at line ".len()..].split(" column ");
+    let line = parts.next()?.parse().ok()?;
+    let column = parts.next()?.parse().ok()?;
+
+    Some((&message[..start], (line, column)))
+  }
+
+  pub fn explain(&self, format: Format, l10n: &L10n) -> Result<String> {
+    match (self.line, self.column) {
+      (Some(line), Some(column)) => l10n.tr_ex(
+        ("structured-error", "pos"),
+        |req| req
+          .arg("format", format.name())
+          .arg("line", line)
+          .arg("column", column)
+          .arg_str("err", &self.message),
+      ),
+      _ => l10n.tr_ex(
+        ("structured-error", "no-pos"),
+        |req| req
+          .arg("format", format.name())
+          .arg_str("err", &self.message),
+      ),
+    }
+  }
+}
+
+/// Whether a file is valid, for files in a format that can be checked.
+#[derive(Debug, Serialize)]
+pub struct Validation {
+  pub format: Format,
+  pub valid: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<ParseError>,
+}
+
+impl Validation {
+  pub fn check(name: &str, language: Option<Language>, content: &str) -> Option<Validation> {
+    let format = Format::detect(name, language)?;
+    let error = format.parse(content).err();
+
+    Some(Validation {
+      format,
+      valid: error.is_none(),
+      error,
+    })
+  }
+}
+
+/// A parsed value from any of the formats, keeping the order of keys in maps.
+#[derive(Debug)]
+pub enum Node {
+  Null,
+  Bool(bool),
+  Int(i64),
+  UInt(u64),
+  Float(f64),
+  String(String),
+  /// A TOML datetime, as it was written.
+  Datetime(String),
+  Array(Vec<Node>),
+  Object(Vec<(String, Node)>),
+}
+
+impl Node {
+  /// The text of a scalar, for when it's used as a key.
+  fn key_text(&self) -> String {
+    match self {
+      Node::Null => "null".into(),
+      Node::Bool(b) => b.to_string(),
+      Node::Int(i) => i.to_string(),
+      Node::UInt(u) => u.to_string(),
+      Node::Float(f) => f.to_string(),
+      Node::String(s) | Node::Datetime(s) => s.clone(),
+      Node::Array(_) | Node::Object(_) => serde_json::to_string(self).unwrap_or_default(),
+    }
+  }
+
+  /// Converts a node parsed from TOML back into TOML's own value type.
+  fn to_toml(&self) -> toml::Value {
+    match self {
+      // toml has no null, so it never parses one
+      Node::Null => toml::Value::String("null".into()),
+      Node::Bool(b) => toml::Value::Boolean(*b),
+      Node::Int(i) => toml::Value::Integer(*i),
+      Node::UInt(u) => toml::Value::Integer(*u as i64),
+      Node::Float(f) => toml::Value::Float(*f),
+      Node::String(s) => toml::Value::String(s.clone()),
+      Node::Datetime(s) => s.parse().map(toml::Value::Datetime).unwrap_or_else(|_| toml::Value::String(s.clone())),
+      Node::Array(items) => toml::Value::Array(items.iter().map(Node::to_toml).collect()),
+      Node::Object(entries) => toml::Value::Table(entries.iter().map(|(k, v)| (k.clone(), v.to_toml())).collect()),
+    }
+  }
+}
+
+impl<'de> Deserialize<'de> for Node {
+  fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where D: Deserializer<'de>,
+  {
+    deserializer.deserialize_any(NodeVisitor)
+  }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+  type Value = Node;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("any value")
+  }
+
+  fn visit_unit<E: de::Error>(self) -> std::result::Result<Node, E> {
+    Ok(Node::Null)
+  }
+
+  fn visit_none<E: de::Error>(self) -> std::result::Result<Node, E> {
+    Ok(Node::Null)
+  }
+
+  fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<Node, D::Error> {
+    Node::deserialize(deserializer)
+  }
+
+  fn visit_bool<E: de::Error>(self, v: bool) -> std::result::Result<Node, E> {
+    Ok(Node::Bool(v))
+  }
+
+  fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<Node, E> {
+    Ok(Node::Int(v))
+  }
+
+  fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<Node, E> {
+    Ok(Node::UInt(v))
+  }
+
+  fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<Node, E> {
+    Ok(Node::Float(v))
+  }
+
+  fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Node, E> {
+    Ok(Node::String(v.to_string()))
+  }
+
+  fn visit_string<E: de::Error>(self, v: String) -> std::result::Result<Node, E> {
+    Ok(Node::String(v))
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Node, A::Error> {
+    let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+    while let Some(item) = seq.next_element()? {
+      items.push(item);
+    }
+
+    Ok(Node::Array(items))
+  }
+
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Node, A::Error> {
+    let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+    while let Some((key, value)) = map.next_entry::<Node, Node>()? {
+      entries.push((key.key_text(), value));
+    }
+
+    // toml hands datetimes over as a map with a single special key
+    if let [(ref key, Node::String(_))] = *entries {
+      if key == TOML_DATETIME {
+        if let Some((_, Node::String(datetime))) = entries.pop() {
+          return Ok(Node::Datetime(datetime));
+        }
+      }
+    }
+
+    Ok(Node::Object(entries))
+  }
+}
+
+impl Serialize for Node {
+  fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    match self {
+      Node::Null => serializer.serialize_unit(),
+      Node::Bool(b) => serializer.serialize_bool(*b),
+      Node::Int(i) => serializer.serialize_i64(*i),
+      Node::UInt(u) => serializer.serialize_u64(*u),
+      Node::Float(f) => serializer.serialize_f64(*f),
+      Node::String(s) | Node::Datetime(s) => serializer.serialize_str(s),
+      Node::Array(items) => items.serialize(serializer),
+      Node::Object(entries) => {
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (key, value) in entries {
+          map.serialize_entry(key, value)?;
+        }
+        map.end()
+      },
+    }
+  }
+}
+
+/// Renders documents as nested lists that can be expanded and collapsed.
+pub fn to_tree(documents: &[Node]) -> String {
+  let mut html = String::new();
+
+  for (i, document) in documents.iter().enumerate() {
+    if i > 0 {
+      html.push_str("<hr>\n");
+    }
+    html.push_str("<ul class=\"structured-tree\">\n");
+    push_node(&mut html, None, document, 0);
+    html.push_str("</ul>\n");
+  }
+
+  html
+}
+
+fn push_node(html: &mut String, key: Option<&str>, node: &Node, depth: usize) {
+  let key = key
+    .map(|k| format!("<span class=\"tree-key\">{}</span>: ", escape_html(k)))
+    .unwrap_or_default();
+
+  let (children, summary) = match node {
+    Node::Array(items) => (items.len(), format!("[…] {}", items.len())),
+    Node::Object(entries) => (entries.len(), format!("{{…}} {}", entries.len())),
+    _ => {
+      html.push_str(&format!("<li>{}{}</li>\n", key, scalar(node)));
+      return;
+    },
+  };
+
+  if children == 0 {
+    let empty = if let Node::Array(_) = node { "[]" } else { "{}" };
+    html.push_str(&format!("<li>{}<span class=\"tree-empty\">{}</span></li>\n", key, empty));
+    return;
+  }
+
+  let open = if depth < OPEN_DEPTH { " open" } else { "" };
+  html.push_str(&format!(
+    "<li><details{}><summary>{}<span class=\"tree-summary\">{}</span></summary>\n<ul>\n",
+    open,
+    key,
+    summary,
+  ));
+  match node {
+    Node::Array(items) => for (i, item) in items.iter().enumerate() {
+      push_node(html, Some(&i.to_string()), item, depth + 1);
+    },
+    Node::Object(entries) => for (key, value) in entries {
+      push_node(html, Some(key), value, depth + 1);
+    },
+    _ => {},
+  }
+  html.push_str("</ul>\n</details></li>\n");
+}
+
+fn scalar(node: &Node) -> String {
+  let (class, text) = match node {
+    Node::Null => ("tree-null", "null".to_string()),
+    Node::Bool(b) => ("tree-bool", b.to_string()),
+    Node::Int(i) => ("tree-number", i.to_string()),
+    Node::UInt(u) => ("tree-number", u.to_string()),
+    Node::Float(f) => ("tree-number", f.to_string()),
+    Node::String(s) => ("tree-string", serde_json::to_string(s).unwrap_or_default()),
+    Node::Datetime(s) => ("tree-string", s.clone()),
+    Node::Array(_) | Node::Object(_) => unreachable!("scalar called on a collection"),
+  };
+
+  format!("<span class=\"{}\">{}</span>", class, escape_html(&text))
+}
@@ -0,0 +1,337 @@
+use crate::{
+  config::Config,
+  errors::*,
+};
+
+use anyhow::{anyhow, bail};
+
+use data_encoding::BASE64URL_NOPAD;
+
+use ring::signature;
+
+use serde::Deserialize;
+
+use serde_cbor::Value;
+
+use serde_json::{json, Value as JsonValue};
+
+use sha2::{Digest, Sha256};
+
+use sodiumoxide::randombytes;
+
+use std::collections::BTreeMap;
+
+use untrusted::Input;
+
+// how long the browser should wait for the user to use their key, in milliseconds
+const TIMEOUT: u32 = 2 * 60 * 1000;
+
+// https://www.iana.org/assignments/cose/cose.xhtml#algorithms
+const ES256: i128 = -7;
+const EDDSA: i128 = -8;
+const RS256: i128 = -257;
+
+// https://www.w3.org/TR/webauthn-2/#flags
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_ATTESTED_DATA: u8 = 0x40;
+
+/// The site that credentials are scoped to.
+pub struct RelyingParty {
+  pub id: String,
+  pub name: String,
+  pub origin: String,
+}
+
+impl RelyingParty {
+  pub fn from_config(config: &Config) -> Self {
+    let config = config.read();
+    let general = &config.general;
+    // the rp id is the domain without a port
+    let id = general.site_domain.split(':').next().unwrap_or(&general.site_domain);
+
+    RelyingParty {
+      id: id.to_string(),
+      name: general.site_name.clone(),
+      origin: format!("https://{}", general.site_domain),
+    }
+  }
+}
+
+/// Generates a random challenge for a ceremony, encoded as unpadded base64url.
+pub fn challenge() -> String {
+  BASE64URL_NOPAD.encode(&randombytes::randombytes(32))
+}
+
+/// The options passed to `navigator.credentials.create`, with buffers encoded as base64url.
+pub fn creation_options<'a>(rp: &RelyingParty, challenge: &str, user_id: &[u8], username: &str, name: &str, existing: impl Iterator<Item = &'a [u8]>) -> JsonValue {
+  json!({
+    "challenge": challenge,
+    "rp": {
+      "id": rp.id,
+      "name": rp.name,
+    },
+    "user": {
+      "id": BASE64URL_NOPAD.encode(user_id),
+      "name": username,
+      "displayName": name,
+    },
+    "pubKeyCredParams": [ES256, EDDSA, RS256]
+      .iter()
+      .map(|alg| json!({ "type": "public-key", "alg": *alg as i64 }))
+      .collect::<Vec<_>>(),
+    "excludeCredentials": descriptors(existing),
+    "authenticatorSelection": {
+      "userVerification": "discouraged",
+    },
+    "attestation": "none",
+    "timeout": TIMEOUT,
+  })
+}
+
+/// The options passed to `navigator.credentials.get`, with buffers encoded as base64url.
+pub fn request_options<'a>(rp: &RelyingParty, challenge: &str, allowed: impl Iterator<Item = &'a [u8]>) -> JsonValue {
+  json!({
+    "challenge": challenge,
+    "rpId": rp.id,
+    "allowCredentials": descriptors(allowed),
+    "userVerification": "discouraged",
+    "timeout": TIMEOUT,
+  })
+}
+
+fn descriptors<'a>(ids: impl Iterator<Item = &'a [u8]>) -> Vec<JsonValue> {
+  ids
+    .map(|id| json!({ "type": "public-key", "id": BASE64URL_NOPAD.encode(id) }))
+    .collect()
+}
+
+/// The response from `navigator.credentials.create`, as sent by the browser.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationResponse {
+  client_data_json: String,
+  attestation_object: String,
+}
+
+/// The response from `navigator.credentials.get`, as sent by the browser.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResponse {
+  id: String,
+  client_data_json: String,
+  authenticator_data: String,
+  signature: String,
+}
+
+impl AssertionResponse {
+  pub fn credential_id(&self) -> Result<Vec<u8>> {
+    decode(&self.id)
+  }
+}
+
+/// A newly registered credential.
+#[derive(Debug)]
+pub struct Credential {
+  pub id: Vec<u8>,
+  /// The COSE-encoded public key.
+  pub public_key: Vec<u8>,
+  pub sign_count: u32,
+}
+
+/// Checks the response to a registration ceremony, returning the new credential.
+///
+/// Attestation statements are not checked, since "none" attestation is requested.
+pub fn verify_registration(rp: &RelyingParty, challenge: &str, resp: &RegistrationResponse) -> Result<Credential> {
+  let client_data = decode(&resp.client_data_json)?;
+  check_client_data(rp, &client_data, "webauthn.create", challenge)?;
+
+  let attestation: BTreeMap<String, Value> = serde_cbor::from_slice(&decode(&resp.attestation_object)?)?;
+  let auth_data = match attestation.get("authData") {
+    Some(Value::Bytes(b)) => b,
+    _ => bail!("webauthn attestation object has no authenticator data"),
+  };
+
+  let auth_data = AuthenticatorData::parse(auth_data)?;
+  auth_data.check(rp)?;
+
+  let (id, public_key) = match auth_data.attested {
+    Some(x) => x,
+    None => bail!("webauthn registration has no attested credential"),
+  };
+
+  // make sure the key can be used before storing it
+  PublicKey::from_cose(public_key)?;
+
+  Ok(Credential {
+    id: id.to_vec(),
+    public_key: public_key.to_vec(),
+    sign_count: auth_data.sign_count,
+  })
+}
+
+/// Checks the response to an authentication ceremony against a stored credential, returning the
+/// new signature counter.
+pub fn verify_assertion(rp: &RelyingParty, challenge: &str, public_key: &[u8], sign_count: u32, resp: &AssertionResponse) -> Result<u32> {
+  let client_data = decode(&resp.client_data_json)?;
+  check_client_data(rp, &client_data, "webauthn.get", challenge)?;
+
+  let raw_auth_data = decode(&resp.authenticator_data)?;
+  let auth_data = AuthenticatorData::parse(&raw_auth_data)?;
+  auth_data.check(rp)?;
+
+  // the signature is over the authenticator data and the hash of the client data
+  let mut message = raw_auth_data.clone();
+  message.extend_from_slice(&Sha256::digest(&client_data));
+
+  PublicKey::from_cose(public_key)?.verify(&message, &decode(&resp.signature)?)?;
+
+  // a counter that doesn't go up means the key may have been cloned
+  if (auth_data.sign_count != 0 || sign_count != 0) && auth_data.sign_count <= sign_count {
+    bail!("webauthn signature counter did not increase ({} <= {})", auth_data.sign_count, sign_count);
+  }
+
+  Ok(auth_data.sign_count)
+}
+
+fn decode(s: &str) -> Result<Vec<u8>> {
+  Ok(BASE64URL_NOPAD.decode(s.trim_end_matches('=').as_bytes())?)
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+  #[serde(rename = "type")]
+  kind: String,
+  challenge: String,
+  origin: String,
+}
+
+fn check_client_data(rp: &RelyingParty, raw: &[u8], kind: &str, challenge: &str) -> Result<()> {
+  let data: ClientData = serde_json::from_slice(raw)?;
+
+  if data.kind != kind {
+    bail!("unexpected webauthn client data type: {}", data.kind);
+  }
+  if data.challenge.trim_end_matches('=') != challenge {
+    bail!("webauthn challenge did not match");
+  }
+  if data.origin != rp.origin {
+    bail!("unexpected webauthn origin: {}", data.origin);
+  }
+
+  Ok(())
+}
+
+struct AuthenticatorData<'a> {
+  rp_id_hash: &'a [u8],
+  flags: u8,
+  sign_count: u32,
+  /// The credential ID and COSE public key, if present.
+  attested: Option<(&'a [u8], &'a [u8])>,
+}
+
+impl AuthenticatorData<'a> {
+  // https://www.w3.org/TR/webauthn-2/#sctn-authenticator-data
+  fn parse(data: &'a [u8]) -> Result<Self> {
+    if data.len() < 37 {
+      bail!("webauthn authenticator data is too short");
+    }
+
+    let flags = data[32];
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+    let attested = if flags & FLAG_ATTESTED_DATA == 0 {
+      None
+    } else {
+      // skip the 16-byte aaguid
+      let rest = data.get(53..).ok_or_else(|| anyhow!("webauthn attested data is too short"))?;
+      if rest.len() < 2 {
+        bail!("webauthn attested data is too short");
+      }
+      let id_len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+      let id = rest.get(2..2 + id_len).ok_or_else(|| anyhow!("webauthn credential id is too short"))?;
+      let rest = &rest[2 + id_len..];
+
+      // the key is followed by any extensions, so only read one cbor value
+      let mut de = serde_cbor::Deserializer::from_slice(rest);
+      Value::deserialize(&mut de)?;
+      Some((id, &rest[..de.byte_offset()]))
+    };
+
+    Ok(AuthenticatorData {
+      rp_id_hash: &data[..32],
+      flags,
+      sign_count,
+      attested,
+    })
+  }
+
+  fn check(&self, rp: &RelyingParty) -> Result<()> {
+    if self.rp_id_hash != Sha256::digest(rp.id.as_bytes()).as_slice() {
+      bail!("webauthn rp id hash did not match");
+    }
+    if self.flags & FLAG_USER_PRESENT == 0 {
+      bail!("webauthn user was not present");
+    }
+
+    Ok(())
+  }
+}
+
+enum PublicKey {
+  Es256(Vec<u8>),
+  EdDsa(Vec<u8>),
+  Rs256 {
+    n: Vec<u8>,
+    e: Vec<u8>,
+  },
+}
+
+impl PublicKey {
+  // https://www.rfc-editor.org/rfc/rfc8152#section-13
+  fn from_cose(raw: &[u8]) -> Result<Self> {
+    let map: BTreeMap<Value, Value> = serde_cbor::from_slice(raw)?;
+    let int = |key: i128| match map.get(&Value::Integer(key)) {
+      Some(Value::Integer(i)) => Ok(*i),
+      _ => Err(anyhow!("cose key is missing parameter {}", key)),
+    };
+    let bytes = |key: i128| match map.get(&Value::Integer(key)) {
+      Some(Value::Bytes(b)) => Ok(b.clone()),
+      _ => Err(anyhow!("cose key is missing parameter {}", key)),
+    };
+
+    // kty, alg, crv
+    let key = match (int(1)?, int(3)?) {
+      (2, ES256) if int(-1)? == 1 => {
+        // ring expects an uncompressed sec1 point
+        let mut point = vec![0x04];
+        point.extend(bytes(-2)?);
+        point.extend(bytes(-3)?);
+        PublicKey::Es256(point)
+      },
+      (1, EDDSA) if int(-1)? == 6 => PublicKey::EdDsa(bytes(-2)?),
+      (3, RS256) => PublicKey::Rs256 {
+        n: bytes(-1)?,
+        e: bytes(-2)?,
+      },
+      (kty, alg) => bail!("unsupported cose key (kty {}, alg {})", kty, alg),
+    };
+
+    Ok(key)
+  }
+
+  fn verify(&self, message: &[u8], sig: &[u8]) -> Result<()> {
+    let (message, sig) = (Input::from(message), Input::from(sig));
+    let res = match *self {
+      PublicKey::Es256(ref point) => signature::verify(&signature::ECDSA_P256_SHA256_ASN1, Input::from(point.as_slice()), message, sig),
+      PublicKey::EdDsa(ref x) => signature::verify(&signature::ED25519, Input::from(x.as_slice()), message, sig),
+      PublicKey::Rs256 { ref n, ref e } => signature::primitive::verify_rsa(
+        &signature::RSA_PKCS1_2048_8192_SHA256,
+        (Input::from(n.as_slice()), Input::from(e.as_slice())),
+        message,
+        sig,
+      ),
+    };
+
+    res.map_err(|_| anyhow!("invalid webauthn signature"))
+  }
+}
@@ -36,14 +36,15 @@ const pasteEditors = {};
   }
 
   /**
+   * @param {String} prefix The prefix of the IDs of the date picker's inputs
    * @param {boolean} makeDate Whether to turn the ISO String into a Date
-   * @returns {null | DateTime | String} The absolute expiry date set by the user, if set, otherwise
-   * null. Returns an ISO string if makeDate is false, a DateTime if true.
+   * @returns {null | DateTime | String} The date set by the user in the date picker, if set,
+   * otherwise null. Returns an ISO string if makeDate is false, a DateTime if true.
    */
-  function getAbsoluteExpiry(makeDate) {
-    const date = document.getElementById('absolute-date');
-    const time = document.getElementById('absolute-time');
-    const tz = document.getElementById('absolute-timezone');
+  function getAbsoluteDate(prefix, makeDate) {
+    const date = document.getElementById(`${prefix}-date`);
+    const time = document.getElementById(`${prefix}-time`);
+    const tz = document.getElementById(`${prefix}-timezone`);
 
     if (date === null || time === null || tz === null) {
       return null;
@@ -122,14 +123,14 @@ const pasteEditors = {};
       case 'relative':
         return getRelativeExpiry(false);
       case 'absolute':
-        return getAbsoluteExpiry(false);
+        return getAbsoluteDate('absolute', false);
       default:
         return null;
     }
   }
 
-  function setTimezone(tz) {
-    const tzSelect = document.getElementById('absolute-timezone');
+  function setTimezone(prefix, tz) {
+    const tzSelect = document.getElementById(`${prefix}-timezone`);
     if (tzSelect === null) {
       return;
     }
@@ -144,6 +145,27 @@ const pasteEditors = {};
     });
   }
 
+  /**
+   * Fills in a date picker with a date.
+   *
+   * @param {String} prefix The prefix of the IDs of the date picker's inputs
+   * @param {String} iso The date as an ISO string
+   */
+  function setAbsoluteDate(prefix, iso) {
+    const date = DateTime.fromISO(iso);
+
+    const year = date.year;
+    const month = date.month.toString().padStart(2, '0');
+    const day = date.day.toString().padStart(2, '0');
+    document.getElementById(`${prefix}-date`).value = `${year}-${month}-${day}`;
+
+    const hour = date.hour.toString().padStart(2, '0');
+    const minute = date.minute.toString().padStart(2, '0');
+    document.getElementById(`${prefix}-time`).value = `${hour}:${minute}`;
+
+    document.getElementById(`${prefix}-timezone`).value = `${date.offset / 60}`;
+  }
+
   /**
    * Create the upload array for handling multiple files.
    *
@@ -341,6 +363,17 @@ const pasteEditors = {};
 
       e.target.appendChild(expiresInput);
     }
+
+    const scheduled = document.querySelector('select[name=scheduled_visibility]');
+    const visibilityAt = getAbsoluteDate('visibility', false);
+    if (scheduled !== null && scheduled.value !== '' && visibilityAt !== null) {
+      const visibilityAtInput = document.createElement('input');
+      visibilityAtInput.type = 'hidden';
+      visibilityAtInput.value = visibilityAt;
+      visibilityAtInput.name = 'visibility_at';
+
+      e.target.appendChild(visibilityAtInput);
+    }
   });
 
   // create any initial editors
@@ -351,8 +384,9 @@ const pasteEditors = {};
     addFile();
   }
 
-  // set the default timezone
-  setTimezone();
+  // set the default timezones
+  setTimezone('absolute');
+  setTimezone('visibility');
 
   (function() {
     function inputsRequired(el, req) {
@@ -395,22 +429,14 @@ const pasteEditors = {};
   (function() {
     const abs = document.getElementById('absolute-expiry');
     const expirationDate = abs.dataset.expirationDate;
-    if (expirationDate === undefined) {
-      return;
+    if (expirationDate !== undefined) {
+      setAbsoluteDate('absolute', expirationDate);
     }
 
-    const date = DateTime.fromISO(expirationDate);
-
-    const year = date.year;
-    const month = date.month.toString().padStart(2, '0');
-    const day = date.day.toString().padStart(2, '0');
-    document.getElementById('absolute-date').value = `${year}-${month}-${day}`;
-
-    const hour = date.hour.toString().padStart(2, '0');
-    const minute = date.minute.toString().padStart(2, '0');
-    document.getElementById('absolute-time').value = `${hour}:${minute}`;
-
-    document.getElementById('absolute-timezone').value = `${date.offset / 60}`;
+    const change = document.getElementById('visibility-change');
+    if (change !== null && change.dataset.visibilityDate !== undefined) {
+      setAbsoluteDate('visibility', change.dataset.visibilityDate);
+    }
   })();
 
   (function() {
@@ -0,0 +1,32 @@
+(function() {
+  function offsetLines() {
+    for (const pre of document.querySelectorAll('pre[data-ln-offset]')) {
+      const offset = parseInt(pre.dataset.lnOffset, 10);
+      if (isNaN(offset) || offset === 0) {
+        continue;
+      }
+      for (const n of pre.querySelectorAll('.hljs-ln-n')) {
+        const line = parseInt(n.dataset.lineNumber, 10);
+        n.dataset.lineNumber = line + offset;
+      }
+    }
+  }
+
+  function reportHeight() {
+    if (window.parent === window) {
+      return;
+    }
+    window.parent.postMessage({
+      height: document.documentElement.scrollHeight,
+    }, '*');
+  }
+
+  window.addEventListener('load', () => {
+    // line numbers are added asynchronously, so wait for them
+    setTimeout(() => {
+      offsetLines();
+      reportHeight();
+    }, 0);
+  });
+  window.addEventListener('resize', reportHeight);
+})();
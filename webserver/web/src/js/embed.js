@@ -0,0 +1,39 @@
+(function() {
+  // usage:
+  // <script src="https://paste.example.com/static/js/embed.js" data-paste="https://paste.example.com/p/user/id"></script>
+  // optional: data-file="<file id>", data-lines="5-10", data-height="300"
+  const script = document.currentScript;
+  if (script === null || !script.dataset.paste) {
+    return;
+  }
+
+  const pasteUrl = new URL(script.dataset.paste, window.location.href);
+  const url = new URL(`${pasteUrl.pathname.replace(/\/+$/, '')}/embed`, pasteUrl);
+  if (script.dataset.file) {
+    url.searchParams.set('file', script.dataset.file);
+  }
+  if (script.dataset.lines) {
+    url.searchParams.set('lines', script.dataset.lines);
+  }
+
+  const iframe = document.createElement('iframe');
+  iframe.src = url.toString();
+  iframe.title = script.dataset.title || 'paste';
+  iframe.setAttribute('loading', 'lazy');
+  iframe.style.width = '100%';
+  iframe.style.border = '0';
+  iframe.style.height = `${script.dataset.height || 300}px`;
+
+  script.parentNode.insertBefore(iframe, script.nextSibling);
+
+  // the embed reports its height so the frame can be resized to fit
+  window.addEventListener('message', e => {
+    if (e.source !== iframe.contentWindow || e.origin !== url.origin) {
+      return;
+    }
+    if (e.data === null || typeof e.data.height !== 'number') {
+      return;
+    }
+    iframe.style.height = `${Math.ceil(e.data.height)}px`;
+  });
+})();
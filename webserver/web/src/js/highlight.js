@@ -14,6 +14,10 @@
   for (const pre of document.getElementsByTagName('pre')) {
     (function() {
       if (pre.id === '') {
+        // code blocks in rendered files, like notebook cells, say which language they're in
+        if (pre.lang && hljs.getLanguage(pre.lang) !== undefined) {
+          pre.classList.add(`language-${pre.lang}`);
+        }
         return;
       }
       const title = document.getElementById(`${pre.id}-title`);
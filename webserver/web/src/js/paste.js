@@ -1,27 +1,21 @@
 (function() {
-  function swap(current, currentContent, next, nextContent) {
-    current.classList.remove('is-active');
-    next.classList.add('is-active');
-
-    currentContent.classList.add('is-not-displayed');
-    nextContent.classList.remove('is-not-displayed');
+  function show(tabs, contents, shown) {
+    tabs.forEach((tab, i) => {
+      tab.classList.toggle('is-active', i === shown);
+      contents[i].classList.toggle('is-not-displayed', i !== shown);
+    });
   }
 
   for (const tabsContainer of document.getElementsByClassName('paste-tabs-container')) {
     const fileId = tabsContainer.dataset.id;
     const tabLinks = document.getElementById(`${fileId}-tab-links`);
 
-    const rendered = tabLinks.querySelector('.paste-rendered-tab');
-    const renderedA = rendered.firstChild;
-
-    const source = tabLinks.querySelector('.paste-source-tab');
-    const sourceA = source.firstChild;
-
-    const renderedContent = tabsContainer.querySelector('div.paste-rendered-content');
-    const sourceContent = tabsContainer.querySelector('div.paste-source-content');
+    // structured data files have a pretty-printed tab between the rendered and source tabs
+    const names = ['rendered', 'pretty', 'source'].filter(name => tabLinks.querySelector(`.paste-${name}-tab`) !== null);
+    const tabs = names.map(name => tabLinks.querySelector(`.paste-${name}-tab`));
+    const contents = names.map(name => tabsContainer.querySelector(`div.paste-${name}-content`));
 
-    renderedA.addEventListener('click', () => swap(source, sourceContent, rendered, renderedContent));
-    sourceA.addEventListener('click', () => swap(rendered, renderedContent, source, sourceContent));
+    tabs.forEach((tab, i) => tab.firstChild.addEventListener('click', () => show(tabs, contents, i)));
   }
 
   function getDeletionKeys() {
@@ -0,0 +1,97 @@
+(function() {
+  function decode(s) {
+    const b64 = s.replace(/-/g, '+').replace(/_/g, '/');
+    const bin = atob(b64 + '='.repeat((4 - b64.length % 4) % 4));
+    return Uint8Array.from(bin, c => c.charCodeAt(0));
+  }
+
+  function encode(buf) {
+    const bin = String.fromCharCode(...new Uint8Array(buf));
+    return btoa(bin).replace(/\+/g, '-').replace(/\//g, '_').replace(/=+$/, '');
+  }
+
+  function showError(form) {
+    const error = form.querySelector('.webauthn-error');
+    if (error !== null) {
+      error.classList.remove('is-not-displayed');
+    }
+  }
+
+  function creationOptions(json) {
+    const options = JSON.parse(json);
+    options.challenge = decode(options.challenge);
+    options.user.id = decode(options.user.id);
+    for (const cred of options.excludeCredentials) {
+      cred.id = decode(cred.id);
+    }
+    return options;
+  }
+
+  function requestOptions(json) {
+    const options = JSON.parse(json);
+    options.challenge = decode(options.challenge);
+    for (const cred of options.allowCredentials) {
+      cred.id = decode(cred.id);
+    }
+    return options;
+  }
+
+  function register(form) {
+    const publicKey = creationOptions(form.dataset.webauthnCreate);
+    navigator.credentials.create({ publicKey })
+      .then(cred => {
+        form.elements.credential.value = JSON.stringify({
+          id: encode(cred.rawId),
+          clientDataJson: encode(cred.response.clientDataJSON),
+          attestationObject: encode(cred.response.attestationObject),
+        });
+        form.submit();
+      })
+      .catch(() => showError(form));
+  }
+
+  function authenticate(form, json) {
+    const publicKey = requestOptions(json);
+    navigator.credentials.get({ publicKey })
+      .then(cred => {
+        form.elements.webauthn.value = JSON.stringify({
+          id: encode(cred.rawId),
+          clientDataJson: encode(cred.response.clientDataJSON),
+          authenticatorData: encode(cred.response.authenticatorData),
+          signature: encode(cred.response.signature),
+        });
+        form.submit();
+      })
+      .catch(() => showError(form));
+  }
+
+  function supported() {
+    return window.PublicKeyCredential !== undefined && navigator.credentials !== undefined;
+  }
+
+  (function() {
+    for (const form of document.querySelectorAll('form[data-webauthn-create]')) {
+      if (!supported()) {
+        showError(form);
+        continue;
+      }
+      form.addEventListener('submit', e => {
+        e.preventDefault();
+        register(form);
+      });
+    }
+  })();
+
+  (function() {
+    for (const button of document.querySelectorAll('[data-webauthn-get]')) {
+      if (!supported()) {
+        button.setAttribute('disabled', '');
+        continue;
+      }
+      button.addEventListener('click', e => {
+        e.preventDefault();
+        authenticate(button.form, button.dataset.webauthnGet);
+      });
+    }
+  })();
+})();
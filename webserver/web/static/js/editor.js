@@ -38,16 +38,17 @@ var pasteEditors = {};
     return "".concat(pre).concat(hs, ":").concat(ms);
   }
   /**
+   * @param {String} prefix The prefix of the IDs of the date picker's inputs
    * @param {boolean} makeDate Whether to turn the ISO String into a Date
-   * @returns {null | DateTime | String} The absolute expiry date set by the user, if set, otherwise
-   * null. Returns an ISO string if makeDate is false, a DateTime if true.
+   * @returns {null | DateTime | String} The date set by the user in the date picker, if set,
+   * otherwise null. Returns an ISO string if makeDate is false, a DateTime if true.
    */
 
 
-  function getAbsoluteExpiry(makeDate) {
-    var date = document.getElementById('absolute-date');
-    var time = document.getElementById('absolute-time');
-    var tz = document.getElementById('absolute-timezone');
+  function getAbsoluteDate(prefix, makeDate) {
+    var date = document.getElementById("".concat(prefix, "-date"));
+    var time = document.getElementById("".concat(prefix, "-time"));
+    var tz = document.getElementById("".concat(prefix, "-timezone"));
 
     if (date === null || time === null || tz === null) {
       return null;
@@ -126,15 +127,15 @@ var pasteEditors = {};
         return getRelativeExpiry(false);
 
       case 'absolute':
-        return getAbsoluteExpiry(false);
+        return getAbsoluteDate('absolute', false);
 
       default:
         return null;
     }
   }
 
-  function setTimezone(tz) {
-    var tzSelect = document.getElementById('absolute-timezone');
+  function setTimezone(prefix, tz) {
+    var tzSelect = document.getElementById("".concat(prefix, "-timezone"));
 
     if (tzSelect === null) {
       return;
@@ -150,6 +151,25 @@ var pasteEditors = {};
       }
     });
   }
+  /**
+   * Fills in a date picker with a date.
+   *
+   * @param {String} prefix The prefix of the IDs of the date picker's inputs
+   * @param {String} iso The date as an ISO string
+   */
+
+
+  function setAbsoluteDate(prefix, iso) {
+    var date = DateTime.fromISO(iso);
+    var year = date.year;
+    var month = date.month.toString().padStart(2, '0');
+    var day = date.day.toString().padStart(2, '0');
+    document.getElementById("".concat(prefix, "-date")).value = "".concat(year, "-").concat(month, "-").concat(day);
+    var hour = date.hour.toString().padStart(2, '0');
+    var minute = date.minute.toString().padStart(2, '0');
+    document.getElementById("".concat(prefix, "-time")).value = "".concat(hour, ":").concat(minute);
+    document.getElementById("".concat(prefix, "-timezone")).value = "".concat(date.offset / 60);
+  }
   /**
    * Create the upload array for handling multiple files.
    *
@@ -424,16 +444,28 @@ var pasteEditors = {};
       expiresInput.name = 'expires';
       e.target.appendChild(expiresInput);
     }
+
+    var scheduled = document.querySelector('select[name=scheduled_visibility]');
+    var visibilityAt = getAbsoluteDate('visibility', false);
+
+    if (scheduled !== null && scheduled.value !== '' && visibilityAt !== null) {
+      var visibilityAtInput = document.createElement('input');
+      visibilityAtInput.type = 'hidden';
+      visibilityAtInput.value = visibilityAt;
+      visibilityAtInput.name = 'visibility_at';
+      e.target.appendChild(visibilityAtInput);
+    }
   }); // create any initial editors
 
   createEditors(); // add an initial file if necessary
 
   if (Object.keys(pasteEditors).length === 0) {
     addFile();
-  } // set the default timezone
+  } // set the default timezones
 
 
-  setTimezone();
+  setTimezone('absolute');
+  setTimezone('visibility');
 
   (function () {
     function inputsRequired(el, req) {
@@ -481,19 +513,15 @@ var pasteEditors = {};
     var abs = document.getElementById('absolute-expiry');
     var expirationDate = abs.dataset.expirationDate;
 
-    if (expirationDate === undefined) {
-      return;
+    if (expirationDate !== undefined) {
+      setAbsoluteDate('absolute', expirationDate);
     }
 
-    var date = DateTime.fromISO(expirationDate);
-    var year = date.year;
-    var month = date.month.toString().padStart(2, '0');
-    var day = date.day.toString().padStart(2, '0');
-    document.getElementById('absolute-date').value = "".concat(year, "-").concat(month, "-").concat(day);
-    var hour = date.hour.toString().padStart(2, '0');
-    var minute = date.minute.toString().padStart(2, '0');
-    document.getElementById('absolute-time').value = "".concat(hour, ":").concat(minute);
-    document.getElementById('absolute-timezone').value = "".concat(date.offset / 60);
+    var change = document.getElementById('visibility-change');
+
+    if (change !== null && change.dataset.visibilityDate !== undefined) {
+      setAbsoluteDate('visibility', change.dataset.visibilityDate);
+    }
   })();
 
   (function () {
@@ -0,0 +1,43 @@
+"use strict";
+
+(function () {
+  function offsetLines() {
+    var pres = document.querySelectorAll('pre[data-ln-offset]');
+
+    for (var i = 0; i < pres.length; i++) {
+      var pre = pres[i];
+      var offset = parseInt(pre.dataset.lnOffset, 10);
+
+      if (isNaN(offset) || offset === 0) {
+        continue;
+      }
+
+      var ns = pre.querySelectorAll('.hljs-ln-n');
+
+      for (var j = 0; j < ns.length; j++) {
+        var n = ns[j];
+        var line = parseInt(n.dataset.lineNumber, 10);
+        n.dataset.lineNumber = line + offset;
+      }
+    }
+  }
+
+  function reportHeight() {
+    if (window.parent === window) {
+      return;
+    }
+
+    window.parent.postMessage({
+      height: document.documentElement.scrollHeight
+    }, '*');
+  }
+
+  window.addEventListener('load', function () {
+    // line numbers are added asynchronously, so wait for them
+    setTimeout(function () {
+      offsetLines();
+      reportHeight();
+    }, 0);
+  });
+  window.addEventListener('resize', reportHeight);
+})();
@@ -30,6 +30,11 @@ function _arrayWithoutHoles(arr) { if (Array.isArray(arr)) { for (var i = 0, arr
 
       (function () {
         if (pre.id === '') {
+          // code blocks in rendered files, like notebook cells, say which language they're in
+          if (pre.lang && hljs.getLanguage(pre.lang) !== undefined) {
+            pre.classList.add("language-".concat(pre.lang));
+          }
+
           return;
         }
 
@@ -1,11 +1,11 @@
 "use strict";
 
 (function () {
-  function swap(current, currentContent, next, nextContent) {
-    current.classList.remove('is-active');
-    next.classList.add('is-active');
-    currentContent.classList.add('is-not-displayed');
-    nextContent.classList.remove('is-not-displayed');
+  function show(tabs, contents, shown) {
+    tabs.forEach(function (tab, i) {
+      tab.classList.toggle('is-active', i === shown);
+      contents[i].classList.toggle('is-not-displayed', i !== shown);
+    });
   }
 
   var _iteratorNormalCompletion = true;
@@ -17,17 +17,20 @@
       var tabsContainer = _step.value;
       var fileId = tabsContainer.dataset.id;
       var tabLinks = document.getElementById("".concat(fileId, "-tab-links"));
-      var rendered = tabLinks.querySelector('.paste-rendered-tab');
-      var renderedA = rendered.firstChild;
-      var source = tabLinks.querySelector('.paste-source-tab');
-      var sourceA = source.firstChild;
-      var renderedContent = tabsContainer.querySelector('div.paste-rendered-content');
-      var sourceContent = tabsContainer.querySelector('div.paste-source-content');
-      renderedA.addEventListener('click', function () {
-        return swap(source, sourceContent, rendered, renderedContent);
+      // structured data files have a pretty-printed tab between the rendered and source tabs
+      var names = ['rendered', 'pretty', 'source'].filter(function (name) {
+        return tabLinks.querySelector(".paste-".concat(name, "-tab")) !== null;
+      });
+      var tabs = names.map(function (name) {
+        return tabLinks.querySelector(".paste-".concat(name, "-tab"));
+      });
+      var contents = names.map(function (name) {
+        return tabsContainer.querySelector("div.paste-".concat(name, "-content"));
       });
-      sourceA.addEventListener('click', function () {
-        return swap(rendered, renderedContent, source, sourceContent);
+      tabs.forEach(function (tab, i) {
+        return tab.firstChild.addEventListener('click', function () {
+          return show(tabs, contents, i);
+        });
       });
     };
 
@@ -0,0 +1,134 @@
+"use strict";
+
+(function () {
+  function decode(s) {
+    var b64 = s.replace(/-/g, '+').replace(/_/g, '/');
+    var bin = atob(b64 + '='.repeat((4 - b64.length % 4) % 4));
+    return Uint8Array.from(bin, function (c) {
+      return c.charCodeAt(0);
+    });
+  }
+
+  function encode(buf) {
+    var bin = String.fromCharCode.apply(String, new Uint8Array(buf));
+    return btoa(bin).replace(/\+/g, '-').replace(/\//g, '_').replace(/=+$/, '');
+  }
+
+  function showError(form) {
+    var error = form.querySelector('.webauthn-error');
+
+    if (error !== null) {
+      error.classList.remove('is-not-displayed');
+    }
+  }
+
+  function creationOptions(json) {
+    var options = JSON.parse(json);
+    options.challenge = decode(options.challenge);
+    options.user.id = decode(options.user.id);
+
+    for (var i = 0; i < options.excludeCredentials.length; i++) {
+      var cred = options.excludeCredentials[i];
+      cred.id = decode(cred.id);
+    }
+
+    return options;
+  }
+
+  function requestOptions(json) {
+    var options = JSON.parse(json);
+    options.challenge = decode(options.challenge);
+
+    for (var i = 0; i < options.allowCredentials.length; i++) {
+      var cred = options.allowCredentials[i];
+      cred.id = decode(cred.id);
+    }
+
+    return options;
+  }
+
+  function register(form) {
+    var publicKey = creationOptions(form.dataset.webauthnCreate);
+    navigator.credentials.create({
+      publicKey: publicKey
+    }).then(function (cred) {
+      form.elements.credential.value = JSON.stringify({
+        id: encode(cred.rawId),
+        clientDataJson: encode(cred.response.clientDataJSON),
+        attestationObject: encode(cred.response.attestationObject)
+      });
+      form.submit();
+    }).catch(function () {
+      return showError(form);
+    });
+  }
+
+  function authenticate(form, json) {
+    var publicKey = requestOptions(json);
+    navigator.credentials.get({
+      publicKey: publicKey
+    }).then(function (cred) {
+      form.elements.webauthn.value = JSON.stringify({
+        id: encode(cred.rawId),
+        clientDataJson: encode(cred.response.clientDataJSON),
+        authenticatorData: encode(cred.response.authenticatorData),
+        signature: encode(cred.response.signature)
+      });
+      form.submit();
+    }).catch(function () {
+      return showError(form);
+    });
+  }
+
+  function supported() {
+    return window.PublicKeyCredential !== undefined && navigator.credentials !== undefined;
+  }
+
+  (function () {
+    var forms = document.querySelectorAll('form[data-webauthn-create]');
+
+    var _loop = function _loop(i) {
+      var form = forms[i];
+
+      if (!supported()) {
+        showError(form);
+        return "continue";
+      }
+
+      form.addEventListener('submit', function (e) {
+        e.preventDefault();
+        register(form);
+      });
+    };
+
+    for (var i = 0; i < forms.length; i++) {
+      var _ret = _loop(i);
+
+      if (_ret === "continue") continue;
+    }
+  })();
+
+  (function () {
+    var buttons = document.querySelectorAll('[data-webauthn-get]');
+
+    var _loop2 = function _loop2(i) {
+      var button = buttons[i];
+
+      if (!supported()) {
+        button.setAttribute('disabled', '');
+        return "continue";
+      }
+
+      button.addEventListener('click', function (e) {
+        e.preventDefault();
+        authenticate(button.form, button.dataset.webauthnGet);
+      });
+    };
+
+    for (var i = 0; i < buttons.length; i++) {
+      var _ret2 = _loop2(i);
+
+      if (_ret2 === "continue") continue;
+    }
+  })();
+})();
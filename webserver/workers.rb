@@ -4,12 +4,24 @@ require 'ffi'
 class DeleteDirectory
   extend FFI::Library
   ffi_lib 'libworker_delete_directory'
-  attach_function :delete_directory, [ :string ], :void
+  attach_function :delete_directory, [ :string, :string ], :void
 
   include Sidekiq::Worker
 
-  def perform(path)
-    DeleteDirectory.delete_directory(path)
+  def perform(path, blobs = nil)
+    DeleteDirectory.delete_directory(path, blobs)
+  end
+end
+
+class DedupeStore
+  extend FFI::Library
+  ffi_lib 'libworker_dedupe_store'
+  attach_function :dedupe_store, [ :string ], :void
+
+  include Sidekiq::Worker
+
+  def perform(store_path)
+    DedupeStore.dedupe_store(store_path)
   end
 end
 
@@ -45,3 +57,43 @@ class ExpirePaste
     ExpirePaste.expire_paste(timestamp, store_path, user_id, paste_id)
   end
 end
+
+class ExpiryReminder
+  extend FFI::Library
+  ffi_lib 'libworker_expire_paste'
+  attach_function :paste_expires_at, [ :int64, :string ], :bool
+
+  include Sidekiq::Worker
+
+  def perform(timestamp, paste_id, config_path, email, subject, content)
+    if ExpiryReminder.paste_expires_at(timestamp, paste_id)
+      Email.perform_async(config_path, email, subject, content)
+    end
+  end
+end
+
+class ChangeVisibility
+  extend FFI::Library
+  ffi_lib 'libworker_change_visibility'
+  attach_function :change_visibility, [ :int64, :string ], :void
+
+  include Sidekiq::Worker
+
+  def perform(timestamp, paste_id)
+    ChangeVisibility.change_visibility(timestamp, paste_id)
+  end
+end
+
+class ExportData
+  extend FFI::Library
+  ffi_lib 'libworker_export_data'
+  attach_function :export_data, [ :string, :string, :bool ], :bool
+
+  include Sidekiq::Worker
+
+  def perform(directory, user_directory, include_history, config_path, email, subject, content)
+    if ExportData.export_data(directory, user_directory, include_history)
+      Email.perform_async(config_path, email, subject, content)
+    end
+  end
+end
@@ -0,0 +1,94 @@
+#[macro_use]
+extern crate diesel;
+
+use std::{
+  ffi::CStr,
+  os::raw::c_char,
+  str::FromStr,
+};
+
+use chrono::NaiveDateTime;
+
+use diesel::{
+  prelude::*,
+  pg::PgConnection,
+};
+
+use anyhow::Error;
+
+use uuid::Uuid;
+
+mod schema;
+
+use crate::schema::{pastes, visibility_changes};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Identifiable, Queryable)]
+#[primary_key(paste_id)]
+struct VisibilityChange {
+  paste_id: Uuid,
+  visibility: i16,
+  scheduled_for: NaiveDateTime,
+}
+
+#[no_mangle]
+pub unsafe fn change_visibility(timestamp: i64, paste_id: *const c_char) {
+  let paste_id = CStr::from_ptr(paste_id).to_string_lossy();
+
+  let paste_id = match Uuid::from_str(&paste_id) {
+    Ok(u) => u,
+    Err(e) => {
+      eprintln!("could not parse uuid {}: {}", paste_id, e);
+      return;
+    }
+  };
+
+  change(timestamp, paste_id);
+}
+
+fn change(timestamp: i64, paste_id: Uuid) {
+  dotenv::dotenv().ok();
+
+  let conn = match connection() {
+    Ok(c) => c,
+    Err(e) => {
+      eprintln!("could not establish connection to database: {}", e);
+      return;
+    },
+  };
+
+  // the change may have been cancelled, or the paste deleted
+  let change: VisibilityChange = match visibility_changes::table.find(paste_id).get_result(&conn).optional() {
+    Ok(Some(c)) => c,
+    Ok(None) => return,
+    Err(e) => {
+      eprintln!("could not find visibility change for paste {}: {}", paste_id, e);
+      return;
+    },
+  };
+
+  // the change was rescheduled, so another job will make it
+  if change.scheduled_for.timestamp() != timestamp {
+    return;
+  }
+
+  let result = conn.transaction::<_, Error, _>(|| {
+    diesel::update(pastes::table.find(paste_id))
+      .set(pastes::visibility.eq(change.visibility))
+      .execute(&conn)?;
+    diesel::delete(&change).execute(&conn)?;
+    Ok(())
+  });
+
+  if let Err(e) = result {
+    eprintln!("could not change visibility of paste {}: {}", paste_id, e);
+  }
+}
+
+fn connection() -> Result<PgConnection> {
+  let url = std::env::var("DATABASE_URL")?;
+  let conn = PgConnection::establish(&url)?;
+
+  Ok(conn)
+}
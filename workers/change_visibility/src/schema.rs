@@ -0,0 +1 @@
+../../../webserver/src/database/schema.rs
\ No newline at end of file
@@ -0,0 +1,71 @@
+//! Moves the files of pastes made before the shared blob store existed into it, then removes any
+//! blobs nothing uses.
+//!
+//! This can be run again safely at any time.
+
+use std::{
+  ffi::CStr,
+  fs,
+  io,
+  os::raw::c_char,
+  path::Path,
+};
+
+use blob_store::{Blobs, Result};
+
+use git2::Repository;
+
+use uuid::Uuid;
+
+#[no_mangle]
+pub unsafe fn dedupe_store(store_path: *const c_char) {
+  let store_path = CStr::from_ptr(store_path).to_string_lossy();
+
+  if let Err(e) = dedupe(Path::new(&*store_path)) {
+    eprintln!("could not deduplicate store {}: {}", store_path, e);
+  }
+}
+
+/// Whether a directory in the store holds a user's pastes, rather than blobs, exports or anything
+/// else the store keeps.
+fn holds_pastes(name: &str) -> bool {
+  name == "anonymous" || Uuid::parse_str(name).is_ok()
+}
+
+fn dedupe(store: &Path) -> Result<()> {
+  let blobs = Blobs::new(store.join("blobs"));
+
+  for user in fs::read_dir(store)? {
+    let user = user?;
+    if !user.file_type()?.is_dir() || !holds_pastes(&user.file_name().to_string_lossy()) {
+      continue;
+    }
+
+    for paste in fs::read_dir(user.path())? {
+      let files = paste?.path().join("files");
+      // one broken paste shouldn't stop the rest being deduplicated
+      if let Err(e) = dedupe_paste(&blobs, &files) {
+        eprintln!("could not deduplicate {}: {}", files.to_string_lossy(), e);
+      }
+    }
+  }
+
+  // compressed copies used to be cached outside the blob store, where nothing ever removed them
+  match fs::remove_dir_all(store.join("cache").join("encoded")) {
+    Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+    r => r?,
+  }
+
+  blobs.sweep()
+}
+
+fn dedupe_paste(blobs: &Blobs, files: &Path) -> Result<()> {
+  let repo = Repository::open(files)?;
+
+  if let Ok(head) = repo.head() {
+    blobs.share(&repo, &head.peel_to_tree()?)?;
+  }
+
+  // older revisions' blobs are shared too, as long as they're loose
+  blobs.share_objects(&repo)
+}
@@ -2,14 +2,29 @@ use std::{
   ffi::CStr,
   fs,
   os::raw::c_char,
-  path::Path,
+  path::{Path, PathBuf},
 };
 
+use blob_store::Blobs;
+
+/// Deletes a directory and, if `blobs` isn't null, removes any shared blobs in the blob store at
+/// `blobs` that were only used by pastes in the directory.
 #[no_mangle]
-pub unsafe fn delete_directory(path: *const c_char) {
+pub unsafe fn delete_directory(path: *const c_char, blobs: *const c_char) {
   let path = CStr::from_ptr(path).to_string_lossy();
+  let blobs = if blobs.is_null() {
+    None
+  } else {
+    Some(CStr::from_ptr(blobs).to_string_lossy())
+  };
 
   do_the_thing(&path);
+
+  if let Some(blobs) = blobs {
+    if let Err(e) = Blobs::new(PathBuf::from(&*blobs)).sweep() {
+      eprintln!("could not release blobs in {}: {}", blobs, e);
+    }
+  }
 }
 
 fn do_the_thing(path: &str) {
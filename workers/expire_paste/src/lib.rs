@@ -16,6 +16,10 @@ use diesel::{
 
 use anyhow::Error;
 
+use blob_store::Blobs;
+
+use git2::{Oid, Repository};
+
 use uuid::Uuid;
 
 mod paste;
@@ -42,6 +46,48 @@ pub unsafe fn expire_paste(timestamp: i64, store_path: *const c_char, user_id: *
   expire(timestamp, &store_path, &user_id, paste_id);
 }
 
+/// Whether the paste still expires at `timestamp`, for `ExpiryReminder` jobs to check before
+/// sending their email.
+#[no_mangle]
+pub unsafe fn paste_expires_at(timestamp: i64, paste_id: *const c_char) -> bool {
+  let paste_id = CStr::from_ptr(paste_id).to_string_lossy();
+
+  let paste_id = match Uuid::from_str(&paste_id) {
+    Ok(u) => u,
+    Err(e) => {
+      eprintln!("could not parse uuid {}: {}", paste_id, e);
+      return false;
+    }
+  };
+
+  expires_at(timestamp, paste_id)
+}
+
+fn expires_at(timestamp: i64, paste_id: Uuid) -> bool {
+  dotenv::dotenv().ok();
+
+  let conn = match connection() {
+    Ok(c) => c,
+    Err(e) => {
+      eprintln!("could not establish connection to database: {}", e);
+      return false;
+    },
+  };
+
+  // the paste may have been deleted already
+  let paste: Paste = match schema::pastes::table.find(paste_id).get_result(&conn).optional() {
+    Ok(Some(p)) => p,
+    Ok(None) => return false,
+    Err(e) => {
+      eprintln!("could not find paste {}: {}", paste_id, e);
+      return false;
+    },
+  };
+
+  // the expiry was changed or removed after the reminder was scheduled
+  paste.expires.map(|e| e.timestamp()) == Some(timestamp)
+}
+
 fn expire(timestamp: i64, store_path: &str, user_id: &str, paste_id: Uuid) {
   dotenv::dotenv().ok();
 
@@ -77,11 +123,38 @@ fn expire(timestamp: i64, store_path: &str, user_id: &str, paste_id: Uuid) {
   let path = Path::new(store_path)
     .join(user_id)
     .join(paste_id.to_simple().to_string());
+
+  let object_ids = match object_ids(&path.join("files")) {
+    Ok(i) => i,
+    Err(e) => {
+      eprintln!("could not list blobs of paste {}: {}", paste_id, e);
+      Vec::new()
+    },
+  };
+
   if let Err(e) = fs::remove_dir_all(path) {
     eprintln!("could not delete paste {}: {}", paste_id, e);
+    return;
+  }
+
+  if let Err(e) = Blobs::new(Path::new(store_path).join("blobs")).release(object_ids) {
+    eprintln!("could not release blobs of paste {}: {}", paste_id, e);
   }
 }
 
+/// Every object in the paste's repository, which includes every blob it has had.
+fn object_ids(files: &Path) -> Result<Vec<Oid>> {
+  let repo = Repository::open(files)?;
+
+  let mut ids = Vec::new();
+  repo.odb()?.foreach(|id| {
+    ids.push(*id);
+    true
+  })?;
+
+  Ok(ids)
+}
+
 fn connection() -> Result<PgConnection> {
   let url = std::env::var("DATABASE_URL")?;
   let conn = PgConnection::establish(&url)?;
@@ -0,0 +1,139 @@
+use std::{
+  collections::HashSet,
+  ffi::CStr,
+  fs::{self, File},
+  io::{self, Write},
+  os::raw::c_char,
+  path::Path,
+};
+
+use anyhow::Error;
+
+use serde::Deserialize;
+
+use uuid::Uuid;
+
+use zip::{
+  CompressionMethod,
+  ZipWriter,
+  write::FileOptions,
+};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// The parts of the metadata written by the webserver that are needed to find the files.
+#[derive(Deserialize)]
+struct Metadata {
+  pastes: Vec<Paste>,
+}
+
+#[derive(Deserialize)]
+struct Paste {
+  id: Uuid,
+  files: Vec<PasteFile>,
+}
+
+#[derive(Deserialize)]
+struct PasteFile {
+  id: Uuid,
+  name: Option<String>,
+}
+
+#[no_mangle]
+pub unsafe fn export_data(directory: *const c_char, user_directory: *const c_char, include_history: bool) -> bool {
+  let directory = CStr::from_ptr(directory).to_string_lossy();
+  let user_directory = CStr::from_ptr(user_directory).to_string_lossy();
+
+  match export(Path::new(&*directory), Path::new(&*user_directory), include_history) {
+    Ok(()) => true,
+    Err(e) => {
+      eprintln!("could not export data to {}: {}", directory, e);
+      false
+    },
+  }
+}
+
+fn export(directory: &Path, user_directory: &Path, include_history: bool) -> Result<()> {
+  let raw_metadata = fs::read(directory.join("metadata.json"))?;
+  let metadata: Metadata = serde_json::from_slice(&raw_metadata)?;
+
+  // write to a temporary file so a half-finished archive is never offered for download
+  let temp_path = directory.join("export.zip.tmp");
+  let mut zip = ZipWriter::new(File::create(&temp_path)?);
+  let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+  zip.start_file("metadata.json", options)?;
+  zip.write_all(&raw_metadata)?;
+
+  for paste in &metadata.pastes {
+    let paste_id = paste.id.to_simple().to_string();
+    let files_directory = user_directory.join(&paste_id).join("files");
+    let prefix = format!("pastes/{}", paste_id);
+
+    // names aren't unique within a paste, so fall back to the file id on collisions
+    let mut names = HashSet::new();
+    for file in &paste.files {
+      let file_id = file.id.to_simple().to_string();
+      let name = file.name
+        .as_deref()
+        .map(sanitise)
+        .filter(|n| !n.is_empty() && names.insert(n.clone()))
+        .unwrap_or_else(|| file_id.clone());
+
+      let mut f = match File::open(files_directory.join(&file_id)) {
+        Ok(f) => f,
+        Err(e) => {
+          eprintln!("could not read file {} in paste {}: {}", file_id, paste_id, e);
+          continue;
+        },
+      };
+
+      zip.start_file(format!("{}/files/{}", prefix, name), options)?;
+      io::copy(&mut f, &mut zip)?;
+    }
+
+    if include_history {
+      let repo = format!("{}/repository.git", prefix);
+      if let Err(e) = add_directory(&mut zip, options, &files_directory.join(".git"), &repo) {
+        eprintln!("could not add history for paste {}: {}", paste_id, e);
+      }
+    }
+  }
+
+  zip.finish()?;
+
+  fs::rename(&temp_path, directory.join("export.zip"))?;
+
+  Ok(())
+}
+
+fn add_directory(zip: &mut ZipWriter<File>, options: FileOptions, path: &Path, prefix: &str) -> Result<()> {
+  zip.add_directory(prefix, options)?;
+
+  for entry in fs::read_dir(path)? {
+    let entry = entry?;
+    let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+
+    if entry.file_type()?.is_dir() {
+      add_directory(zip, options, &entry.path(), &name)?;
+    } else {
+      zip.start_file(name, options)?;
+      io::copy(&mut File::open(entry.path())?, zip)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Makes a file name safe to use as a single path component in the archive.
+fn sanitise(name: &str) -> String {
+  let name: String = name
+    .chars()
+    .map(|c| if c == '/' || c == '\\' || c.is_control() { '_' } else { c })
+    .collect();
+
+  match name.as_str() {
+    "." | ".." => String::new(),
+    _ => name,
+  }
+}